@@ -0,0 +1,241 @@
+//! `roc bench`, which times the top-level `expect bench(...)`s in a main module and any modules it
+//! imports, and compares them to a baseline saved by an earlier run.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+/// How long to run a benchmark before measuring it, so that caches and branch predictors are warm.
+pub const WARM_UP_TIME: Duration = Duration::from_millis(500);
+
+/// How many times to measure each benchmark.
+pub const SAMPLES: usize = 50;
+
+/// About how long each sample takes. Fast benchmarks run many times per sample, so that the clock
+/// can tell them apart.
+const SAMPLE_TIME: Duration = Duration::from_millis(20);
+
+/// How long one run of a benchmark took, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+}
+
+impl Stats {
+    /// The stats of `samples`, each of which is how long one run took on average.
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let count = samples.len().max(1) as f64;
+        let mean = samples.iter().sum::<f64>() / count;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / (count - 1.0).max(1.0);
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = match sorted.len() {
+            0 => 0.0,
+            len if len % 2 == 0 => (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0,
+            len => sorted[len / 2],
+        };
+
+        Stats {
+            mean,
+            median,
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+/// Warms up a benchmark, then takes [SAMPLES] samples of it. `run(n)` runs it `n` times in a row
+/// and returns how long that took, or the message it crashed with.
+pub fn measure(mut run: impl FnMut(u64) -> Result<Duration, String>) -> Result<Stats, String> {
+    // Warm up with more and more runs at a time, which also tells how long a run takes
+    let warm_up_start = Instant::now();
+    let mut iterations = 1;
+    let mut per_iteration = loop {
+        let elapsed = run(iterations)?;
+
+        if warm_up_start.elapsed() >= WARM_UP_TIME {
+            break elapsed / iterations as u32;
+        }
+
+        iterations = iterations.saturating_mul(2);
+    };
+
+    if per_iteration.is_zero() {
+        per_iteration = Duration::from_nanos(1);
+    }
+
+    let iterations = (SAMPLE_TIME.as_nanos() / per_iteration.as_nanos()).max(1) as u64;
+
+    let samples = (0..SAMPLES)
+        .map(|_| Ok(run(iterations)?.as_nanos() as f64 / iterations as f64))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Stats::from_samples(&samples))
+}
+
+/// The stats of each benchmark, by name, as `roc bench --save-baseline` saves them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline(pub BTreeMap<String, Stats>);
+
+impl Baseline {
+    pub fn to_json(&self) -> String {
+        let benchmarks: serde_json::Map<String, Value> = self
+            .0
+            .iter()
+            .map(|(name, stats)| {
+                let stats = json!({
+                    "mean_ns": stats.mean,
+                    "median_ns": stats.median,
+                    "stddev_ns": stats.stddev,
+                });
+
+                (name.clone(), stats)
+            })
+            .collect();
+
+        let json = json!({ "benchmarks": benchmarks });
+
+        format!("{json:#}\n")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let json: Value = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+        let benchmarks = json
+            .get("benchmarks")
+            .and_then(Value::as_object)
+            .ok_or("there is no \"benchmarks\" object")?;
+
+        let stats = benchmarks
+            .iter()
+            .map(|(name, stats)| {
+                let field = |field: &str| {
+                    stats
+                        .get(field)
+                        .and_then(Value::as_f64)
+                        .ok_or(format!("\"{name}\" has no \"{field}\" number"))
+                };
+
+                let stats = Stats {
+                    mean: field("mean_ns")?,
+                    median: field("median_ns")?,
+                    stddev: field("stddev_ns")?,
+                };
+
+                Ok((name.clone(), stats))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Baseline(stats))
+    }
+}
+
+/// How a benchmark's mean compares to its baseline's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Within the noise of the two, i.e. less than both of their standard deviations apart.
+    None,
+    Faster(f64),
+    Slower(f64),
+}
+
+impl Change {
+    pub fn new(stats: Stats, baseline: Stats) -> Self {
+        let difference = stats.mean - baseline.mean;
+        let percent = difference * 100.0 / baseline.mean;
+
+        if difference.abs() <= stats.stddev + baseline.stddev || !percent.is_finite() {
+            Change::None
+        } else if difference < 0.0 {
+            Change::Faster(-percent)
+        } else {
+            Change::Slower(percent)
+        }
+    }
+}
+
+/// A row of the table `roc bench` prints, e.g.
+/// `Parser.parse_digits  mean 1.25 µs  median 1.24 µs  stddev 30.00 ns  (8.2% faster)`
+pub fn result_line(name: &str, width: usize, stats: Stats, change: Option<Change>) -> String {
+    let change = match change {
+        None => String::new(),
+        Some(Change::None) => "  (no change)".to_string(),
+        Some(Change::Faster(percent)) => format!("  ({percent:.1}% faster)"),
+        Some(Change::Slower(percent)) => format!("  ({percent:.1}% slower)"),
+    };
+
+    format!(
+        "{name:<width$}  mean {:>10}  median {:>10}  stddev {:>10}{change}",
+        format_nanos(stats.mean),
+        format_nanos(stats.median),
+        format_nanos(stats.stddev),
+    )
+}
+
+fn format_nanos(nanos: f64) -> String {
+    if nanos < 1_000.0 {
+        format!("{nanos:.2} ns")
+    } else if nanos < 1_000_000.0 {
+        format!("{:.2} µs", nanos / 1_000.0)
+    } else if nanos < 1_000_000_000.0 {
+        format!("{:.2} ms", nanos / 1_000_000.0)
+    } else {
+        format!("{:.2} s", nanos / 1_000_000_000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_samples() {
+        let stats = Stats::from_samples(&[4.0, 1.0, 3.0, 2.0]);
+
+        assert_eq!(stats.mean, 2.5);
+        assert_eq!(stats.median, 2.5);
+        assert!((stats.stddev - 1.291).abs() < 0.001);
+    }
+
+    #[test]
+    fn baseline_round_trips_through_json() {
+        let stats = Stats {
+            mean: 1250.0,
+            median: 1240.5,
+            stddev: 30.0,
+        };
+        let baseline = Baseline(BTreeMap::from([("Parser.parse".to_string(), stats)]));
+
+        assert_eq!(Baseline::from_json(&baseline.to_json()), Ok(baseline));
+        assert!(Baseline::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn changes_within_the_noise_dont_count() {
+        let baseline = Stats {
+            mean: 100.0,
+            median: 100.0,
+            stddev: 5.0,
+        };
+        let stats = |mean| Stats {
+            mean,
+            median: mean,
+            stddev: 5.0,
+        };
+
+        assert_eq!(Change::new(stats(108.0), baseline), Change::None);
+        assert_eq!(Change::new(stats(80.0), baseline), Change::Faster(20.0));
+        assert_eq!(Change::new(stats(150.0), baseline), Change::Slower(50.0));
+        assert_eq!(
+            result_line("add", 3, stats(1500.0), None),
+            "add  mean    1.50 µs  median    1.50 µs  stddev    5.00 ns"
+        );
+    }
+}
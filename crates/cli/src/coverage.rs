@@ -0,0 +1,322 @@
+//! `roc test --coverage`, which reports the top-level defs, `if` arms and `when` branches that
+//! the expects reached, both as an lcov file for other tools and as a summary for people.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use roc_module::symbol::{Interns, ModuleId};
+use roc_mono::coverage::CoveragePoint;
+use roc_region::all::LineInfo;
+use roc_repl_expect::coverage::CoverageHits;
+
+use crate::expects::toplevel_def_names;
+
+/// Where the lcov report goes when there's no `--coverage-output`.
+pub const DEFAULT_LCOV_PATH: &str = "lcov.info";
+
+/// How often the expects reached each def and branch in one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub path: PathBuf,
+    pub defs: Vec<Counter>,
+    pub branches: Vec<Counter>,
+}
+
+/// A def or branch, by the 1-based line it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter {
+    pub name: String,
+    pub line: u32,
+    pub hits: u64,
+}
+
+impl FileCoverage {
+    /// Every named top-level def in `src` counts, even ones that nothing used and so were never
+    /// compiled. Branches only count if they were compiled, i.e. are listed in `points`.
+    pub fn new<'a>(
+        module_id: ModuleId,
+        path: &Path,
+        src: &str,
+        interns: &Interns,
+        points: impl IntoIterator<Item = &'a CoveragePoint>,
+        hits: &CoverageHits,
+    ) -> Self {
+        let arena = Bump::new();
+        let line_info = LineInfo::new(src);
+        let line_of = |offset| line_info.convert_offset(offset).line + 1;
+
+        let mut def_hits: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut branches = Vec::new();
+
+        for point in points {
+            match point {
+                CoveragePoint::Def(symbol) if symbol.module_id() == module_id => {
+                    *def_hits.entry(symbol.as_str(interns)).or_insert(0) += hits.count(*point);
+                }
+                CoveragePoint::Branch(branch_module, region) if *branch_module == module_id => {
+                    let line = line_of(region.start().offset);
+
+                    branches.push(Counter {
+                        name: format!(
+                            "{line}:{}",
+                            line_info.convert_pos(region.start()).column + 1
+                        ),
+                        line,
+                        hits: hits.count(*point),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // An annotation can be apart from its body, in which case it comes first
+        let mut seen = BTreeSet::new();
+        let defs = toplevel_def_names(&arena, src)
+            .into_iter()
+            .filter(|(_, name)| seen.insert(name.clone()))
+            .map(|(region, name)| Counter {
+                line: line_of(region.start().offset),
+                hits: def_hits.get(name.as_str()).copied().unwrap_or(0),
+                name,
+            })
+            .collect();
+
+        branches.sort_by_key(|branch| branch.line);
+
+        FileCoverage {
+            path: path.to_path_buf(),
+            defs,
+            branches,
+        }
+    }
+
+    /// Adds the hits of `other`, which covers the same file, e.g. when it was tested through a
+    /// second app.
+    pub fn absorb(&mut self, other: FileCoverage) {
+        for (counters, others) in [
+            (&mut self.defs, other.defs),
+            (&mut self.branches, other.branches),
+        ] {
+            for other in others {
+                match counters
+                    .iter_mut()
+                    .find(|counter| (&counter.name, counter.line) == (&other.name, other.line))
+                {
+                    Some(counter) => counter.hits += other.hits,
+                    None => counters.push(other),
+                }
+            }
+        }
+    }
+}
+
+/// An lcov tracefile, as read by `genhtml` and most coverage services.
+pub fn lcov(files: &[FileCoverage]) -> String {
+    let mut lcov = String::new();
+
+    for file in files {
+        let _ = writeln!(lcov, "TN:\nSF:{}", file.path.display());
+
+        for def in file.defs.iter() {
+            let _ = writeln!(lcov, "FN:{},{}", def.line, def.name);
+        }
+        for def in file.defs.iter() {
+            let _ = writeln!(lcov, "FNDA:{},{}", def.hits, def.name);
+        }
+        let _ = writeln!(lcov, "FNF:{}\nFNH:{}", file.defs.len(), covered(&file.defs));
+
+        for (index, branch) in file.branches.iter().enumerate() {
+            let _ = writeln!(lcov, "BRDA:{},0,{index},{}", branch.line, branch.hits);
+        }
+        let _ = writeln!(
+            lcov,
+            "BRF:{}\nBRH:{}",
+            file.branches.len(),
+            covered(&file.branches)
+        );
+
+        // The lines we know about are the ones that defs and branches start on
+        let mut lines: BTreeMap<u32, u64> = BTreeMap::new();
+        for counter in file.defs.iter().chain(file.branches.iter()) {
+            let hits = lines.entry(counter.line).or_insert(0);
+            *hits = (*hits).max(counter.hits);
+        }
+        for (line, hits) in lines.iter() {
+            let _ = writeln!(lcov, "DA:{line},{hits}");
+        }
+        let _ = writeln!(
+            lcov,
+            "LF:{}\nLH:{}",
+            lines.len(),
+            lines.values().filter(|hits| **hits > 0).count()
+        );
+
+        lcov.push_str("end_of_record\n");
+    }
+
+    lcov
+}
+
+/// A table of how many of each file's defs and branches were reached.
+pub fn summary(files: &[FileCoverage]) -> String {
+    let width = files
+        .iter()
+        .map(|file| file.path.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("Total".len());
+
+    let mut summary = String::from("Coverage:\n");
+    let mut row = |name: &str, defs: (usize, usize), branches: (usize, usize)| {
+        let _ = writeln!(
+            summary,
+            "  {name:<width$}  defs {:>9} {:>7}  branches {:>9} {:>7}",
+            format!("{}/{}", defs.0, defs.1),
+            percent(defs),
+            format!("{}/{}", branches.0, branches.1),
+            percent(branches),
+        );
+    };
+
+    let mut total_defs = (0, 0);
+    let mut total_branches = (0, 0);
+
+    for file in files {
+        let defs = (covered(&file.defs), file.defs.len());
+        let branches = (covered(&file.branches), file.branches.len());
+
+        row(&file.path.display().to_string(), defs, branches);
+
+        total_defs = (total_defs.0 + defs.0, total_defs.1 + defs.1);
+        total_branches = (total_branches.0 + branches.0, total_branches.1 + branches.1);
+    }
+
+    if files.len() > 1 {
+        row("Total", total_defs, total_branches);
+    }
+
+    summary
+}
+
+fn covered(counters: &[Counter]) -> usize {
+    counters.iter().filter(|counter| counter.hits > 0).count()
+}
+
+fn percent((covered, total): (usize, usize)) -> String {
+    match total {
+        0 => "-".to_string(),
+        _ => format!("{:.1}%", covered as f64 * 100.0 / total as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn math() -> FileCoverage {
+        FileCoverage {
+            path: PathBuf::from("Math.roc"),
+            defs: vec![
+                Counter {
+                    name: "add".to_string(),
+                    line: 3,
+                    hits: 2,
+                },
+                Counter {
+                    name: "sub".to_string(),
+                    line: 5,
+                    hits: 0,
+                },
+            ],
+            branches: vec![
+                Counter {
+                    name: "4:8".to_string(),
+                    line: 4,
+                    hits: 2,
+                },
+                Counter {
+                    name: "4:20".to_string(),
+                    line: 4,
+                    hits: 0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn every_named_def_counts() {
+        let src = indoc::indoc! {r#"
+            module [add, sub]
+
+            add : I64, I64 -> I64
+            add = \a, b -> a + b
+
+            sub = \a, b -> a - b
+        "#};
+
+        let coverage = FileCoverage::new(
+            ModuleId::NUM,
+            Path::new("Math.roc"),
+            src,
+            &Interns::default(),
+            [],
+            &CoverageHits::default(),
+        );
+
+        assert_eq!(
+            coverage.defs,
+            vec![
+                Counter {
+                    name: "add".to_string(),
+                    line: 3,
+                    hits: 0,
+                },
+                Counter {
+                    name: "sub".to_string(),
+                    line: 6,
+                    hits: 0,
+                },
+            ]
+        );
+        assert!(coverage.branches.is_empty());
+    }
+
+    #[test]
+    fn lcov_has_a_record_per_file() {
+        assert_eq!(
+            lcov(&[math()]),
+            indoc::indoc! {"
+                TN:
+                SF:Math.roc
+                FN:3,add
+                FN:5,sub
+                FNDA:2,add
+                FNDA:0,sub
+                FNF:2
+                FNH:1
+                BRDA:4,0,0,2
+                BRDA:4,0,1,0
+                BRF:2
+                BRH:1
+                DA:3,2
+                DA:4,2
+                DA:5,0
+                LF:3
+                LH:2
+                end_of_record
+            "}
+        );
+    }
+
+    #[test]
+    fn absorbing_adds_hits() {
+        let mut coverage = math();
+        coverage.absorb(math());
+
+        assert_eq!(coverage.defs[0].hits, 4);
+        assert_eq!(coverage.branches[1].hits, 0);
+        assert!(summary(&[coverage]).contains("defs       1/2   50.0%"));
+    }
+}
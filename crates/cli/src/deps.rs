@@ -0,0 +1,620 @@
+//! `roc deps`, which prints the graph of which modules import which, and `roc why`, which tells why
+//! a module is part of that graph at all.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+
+use bumpalo::Bump;
+use clap::ArgMatches;
+use roc_build::program::handle_loading_problem;
+use roc_error_macros::user_error;
+use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadedModule, Threading};
+use roc_module::symbol::ModuleId;
+use roc_packaging::cache::{self, RocCacheDir};
+use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
+use roc_target::Target;
+use serde_json::{json, Value};
+
+use crate::{nearest_match, FLAG_GRAPH_FORMAT, FLAG_MAIN, FLAG_MAX_THREADS, MODULE_NAME, ROC_FILE};
+
+pub fn deps(matches: &ArgMatches) -> io::Result<i32> {
+    let graph = match load_graph(matches) {
+        Ok(graph) => graph,
+        Err(exit_code) => return Ok(exit_code),
+    };
+
+    let format = matches
+        .get_one::<String>(FLAG_GRAPH_FORMAT)
+        .and_then(|flag| GraphFormat::from_flag(flag))
+        .unwrap_or(GraphFormat::Tree);
+
+    match format {
+        GraphFormat::Tree => print!("{}", graph.render_tree()),
+        GraphFormat::Dot => print!("{}", graph.render_dot()),
+        GraphFormat::Json => print!("{}", graph.render_json()),
+    }
+
+    Ok(0)
+}
+
+pub fn why(matches: &ArgMatches) -> io::Result<i32> {
+    let graph = match load_graph(matches) {
+        Ok(graph) => graph,
+        Err(exit_code) => return Ok(exit_code),
+    };
+
+    let name = matches.get_one::<String>(MODULE_NAME).unwrap();
+
+    match graph.find(name) {
+        Some(target) => {
+            print!("{}", graph.render_why(target));
+
+            Ok(0)
+        }
+        None => {
+            let names: Vec<String> = graph
+                .modules
+                .iter()
+                .map(|module| module.name.clone())
+                .collect();
+
+            match nearest_match(name, &names) {
+                Some((nearest, _)) => {
+                    println!("No module named {name} is loaded. Did you mean {nearest}?")
+                }
+                None => println!("No module named {name} is loaded."),
+            }
+
+            Ok(1)
+        }
+    }
+}
+
+/// Loads the modules that the root module leads to, without caring whether they have problems.
+/// Fails with the exit code to use if they couldn't be loaded at all, like when they import each
+/// other in a cycle.
+fn load_graph(matches: &ArgMatches) -> Result<ModuleGraph, i32> {
+    let arena = Bump::new();
+    let path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
+
+    if !path.exists() {
+        user_error!(
+            "\nThis file was not found: {}\n\nYou can run `roc help` for more information on how to provide a .roc file.\n",
+            path.display()
+        );
+    }
+
+    let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
+        None => Threading::AllAvailable,
+        Some(0) => user_error!("cannot build with at most 0 threads"),
+        Some(1) => Threading::Single,
+        Some(n) => Threading::AtMost(*n),
+    };
+
+    let load_config = LoadConfig {
+        // Only the modules matter, not how they'd be compiled
+        target: Target::LinuxX64,
+        function_kind: FunctionKind::from_env(),
+        render: RenderTarget::ColorTerminal,
+        palette: DEFAULT_PALETTE,
+        threading,
+        exec_mode: ExecutionMode::Check,
+    };
+
+    let loaded = roc_load::load_and_typecheck(
+        &arena,
+        path.to_path_buf(),
+        matches.get_one::<PathBuf>(FLAG_MAIN).cloned(),
+        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+        load_config,
+    );
+
+    match loaded {
+        Ok(loaded) => Ok(ModuleGraph::from_loaded(&loaded)),
+        Err(problem) => {
+            Err(handle_loading_problem(problem, RenderTarget::ColorTerminal).unwrap_or(1))
+        }
+    }
+}
+
+/// How `roc deps` prints the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Tree,
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "tree" => Some(GraphFormat::Tree),
+            "dot" => Some(GraphFormat::Dot),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The modules of an app, package or platform, apart from the builtins, and which ones import
+/// which. The root module comes first.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    pub modules: Vec<GraphModule>,
+    pub packages: Vec<GraphPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphModule {
+    /// The name to show, like `Parser`, or `json.Decode` for a module from a package
+    pub name: String,
+    pub path: PathBuf,
+    /// The shorthand of the package the module is from, if it isn't from the root's own package
+    pub package: Option<String>,
+    /// The indices of the modules it imports, sorted by name
+    pub imports: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphPackage {
+    pub shorthand: String,
+    pub root_module: PathBuf,
+    /// The name of the module whose header asked for the package
+    pub declared_by: String,
+    pub is_platform: bool,
+}
+
+impl ModuleGraph {
+    pub fn from_loaded(loaded: &LoadedModule) -> Self {
+        let mut ids: Vec<ModuleId> = loaded
+            .sources
+            .keys()
+            .copied()
+            .filter(|module_id| !module_id.is_builtin())
+            .collect();
+
+        let name_of = |module_id: ModuleId| {
+            let name = loaded.interns.module_name(module_id).as_str();
+
+            match loaded.module_packages.get(&module_id) {
+                Some(shorthand) => format!("{shorthand}.{name}"),
+                None if name.is_empty() => {
+                    // Apps don't have names of their own, so go by the file instead
+                    let (path, _) = &loaded.sources[&module_id];
+                    path.file_name()
+                        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+                }
+                None => name.to_string(),
+            }
+        };
+
+        ids.sort_by_key(|module_id| (*module_id != loaded.module_id, name_of(*module_id)));
+
+        let modules = ids
+            .iter()
+            .map(|module_id| {
+                let mut imports: Vec<usize> = loaded
+                    .imports
+                    .get(module_id)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|imported| ids.iter().position(|id| id == imported))
+                    .filter(|index| ids[*index] != *module_id)
+                    .collect();
+                imports.sort_by_key(|index| name_of(ids[*index]));
+
+                GraphModule {
+                    name: name_of(*module_id),
+                    path: loaded.sources[module_id].0.clone(),
+                    package: loaded.module_packages.get(module_id).cloned(),
+                    imports,
+                }
+            })
+            .collect();
+
+        let packages = loaded
+            .package_dependencies
+            .iter()
+            .map(|dependency| GraphPackage {
+                shorthand: dependency.shorthand.clone(),
+                root_module: dependency.root_module.clone(),
+                declared_by: name_of(dependency.declared_by),
+                is_platform: dependency.is_platform,
+            })
+            .collect();
+
+        ModuleGraph { modules, packages }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+
+    /// The modules that nothing imports, i.e. the root and, for an app, its platform. Everything
+    /// else is in one of their trees.
+    fn roots(&self) -> Vec<usize> {
+        (0..self.modules.len())
+            .filter(|index| {
+                *index == 0
+                    || !self
+                        .modules
+                        .iter()
+                        .any(|module| module.imports.contains(index))
+            })
+            .collect()
+    }
+
+    /// Packages that were asked for but that no module imports anything from. Platforms are
+    /// always used, by the app they're for.
+    pub fn unused_packages(&self) -> Vec<&GraphPackage> {
+        self.packages
+            .iter()
+            .filter(|package| {
+                !package.is_platform
+                    && !self.modules.iter().any(|module| {
+                        module.imports.iter().any(|imported| {
+                            self.modules[*imported].package.as_ref() == Some(&package.shorthand)
+                        })
+                    })
+            })
+            .collect()
+    }
+
+    /// The shortest chain of imports from the root to `target`, both included, if the root leads
+    /// to it at all.
+    pub fn import_chain(&self, target: usize) -> Option<Vec<usize>> {
+        let mut came_from: Vec<Option<usize>> = vec![None; self.modules.len()];
+        let mut queue = VecDeque::from([0]);
+        let mut seen = vec![false; self.modules.len()];
+        seen[0] = true;
+
+        while let Some(index) = queue.pop_front() {
+            if index == target {
+                let mut chain = vec![index];
+                while let Some(previous) = came_from[*chain.last().unwrap()] {
+                    chain.push(previous);
+                }
+                chain.reverse();
+
+                return Some(chain);
+            }
+
+            for imported in self.modules[index].imports.iter() {
+                if !seen[*imported] {
+                    seen[*imported] = true;
+                    came_from[*imported] = Some(index);
+                    queue.push_back(*imported);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The modules that import `target` directly.
+    pub fn importers(&self, target: usize) -> Vec<usize> {
+        (0..self.modules.len())
+            .filter(|index| self.modules[*index].imports.contains(&target))
+            .collect()
+    }
+
+    /// Each root's imports as a tree, like
+    /// ```text
+    /// main.roc
+    /// ├── Parser
+    /// │   └── Lexer
+    /// └── Lexer (*)
+    /// ```
+    /// where `(*)` marks a module whose imports were already shown.
+    pub fn render_tree(&self) -> String {
+        let mut buf = String::new();
+        let mut shown = vec![false; self.modules.len()];
+
+        for root in self.roots() {
+            if !buf.is_empty() {
+                buf.push('\n');
+            }
+            self.render_subtree(&mut buf, root, "", None, &mut shown);
+        }
+
+        let unused = self.unused_packages();
+        if !unused.is_empty() {
+            buf.push_str("\nUnused packages:\n");
+            for package in unused {
+                let _ = writeln!(
+                    buf,
+                    "    {} (in the header of {})",
+                    package.shorthand, package.declared_by
+                );
+            }
+        }
+
+        buf
+    }
+
+    fn render_subtree(
+        &self,
+        buf: &mut String,
+        index: usize,
+        prefix: &str,
+        is_last: Option<bool>,
+        shown: &mut [bool],
+    ) {
+        let module = &self.modules[index];
+        let (branch, child_prefix) = match is_last {
+            None => ("", String::new()),
+            Some(true) => ("└── ", format!("{prefix}    ")),
+            Some(false) => ("├── ", format!("{prefix}│   ")),
+        };
+        let repeated = shown[index] && !module.imports.is_empty();
+
+        let _ = writeln!(
+            buf,
+            "{prefix}{branch}{}{}",
+            module.name,
+            if repeated { " (*)" } else { "" }
+        );
+
+        if repeated {
+            return;
+        }
+        shown[index] = true;
+
+        for (position, imported) in module.imports.iter().enumerate() {
+            let is_last = position + 1 == module.imports.len();
+            self.render_subtree(buf, *imported, &child_prefix, Some(is_last), shown);
+        }
+    }
+
+    /// The graph in Graphviz's DOT language, with each package's modules in a box of their own.
+    pub fn render_dot(&self) -> String {
+        let mut buf = String::from("digraph imports {\n    node [shape=box];\n");
+
+        for package in self.packages.iter() {
+            let _ = writeln!(buf, "\n    subgraph \"cluster_{}\" {{", package.shorthand);
+            let _ = writeln!(buf, "        label = {:?};", package.shorthand);
+            for module in self.modules.iter() {
+                if module.package.as_ref() == Some(&package.shorthand) {
+                    let _ = writeln!(buf, "        {:?};", module.name);
+                }
+            }
+            buf.push_str("    }\n");
+        }
+
+        buf.push('\n');
+        for module in self.modules.iter() {
+            if module.imports.is_empty() {
+                let _ = writeln!(buf, "    {:?};", module.name);
+            }
+            for imported in module.imports.iter() {
+                let _ = writeln!(
+                    buf,
+                    "    {:?} -> {:?};",
+                    module.name, self.modules[*imported].name
+                );
+            }
+        }
+
+        buf.push_str("}\n");
+        buf
+    }
+
+    pub fn render_json(&self) -> String {
+        let unused: Vec<&str> = self
+            .unused_packages()
+            .iter()
+            .map(|package| package.shorthand.as_str())
+            .collect();
+
+        let modules: Vec<Value> = self
+            .modules
+            .iter()
+            .map(|module| {
+                let imports: Vec<&str> = module
+                    .imports
+                    .iter()
+                    .map(|imported| self.modules[*imported].name.as_str())
+                    .collect();
+
+                json!({
+                    "name": module.name,
+                    "path": module.path,
+                    "package": module.package,
+                    "imports": imports,
+                })
+            })
+            .collect();
+
+        let packages: Vec<Value> = self
+            .packages
+            .iter()
+            .map(|package| {
+                json!({
+                    "shorthand": package.shorthand,
+                    "root_module": package.root_module,
+                    "declared_by": package.declared_by,
+                    "platform": package.is_platform,
+                    "used": !unused.contains(&package.shorthand.as_str()),
+                })
+            })
+            .collect();
+
+        let json = json!({
+            "root": self.modules.first().map(|module| &module.name),
+            "modules": modules,
+            "packages": packages,
+        });
+
+        format!("{json:#}\n")
+    }
+
+    /// Why `target` is part of the graph: the modules that import it directly, and the shortest
+    /// chain of imports that leads to it from the root.
+    pub fn render_why(&self, target: usize) -> String {
+        let name = &self.modules[target].name;
+
+        if target == 0 {
+            return format!("{name} is the root module, so nothing needs to import it.\n");
+        }
+
+        let importers = self.importers(target);
+        if importers.is_empty() {
+            let package = self
+                .packages
+                .iter()
+                .find(|package| self.modules[target].package.as_ref() == Some(&package.shorthand));
+
+            return match package {
+                Some(package) => format!(
+                    "Nothing imports {name}; it's the root of the `{}` package in the header of {}.\n",
+                    package.shorthand, package.declared_by
+                ),
+                None => format!("Nothing imports {name}.\n"),
+            };
+        }
+
+        let mut buf = format!("{name} is imported by:\n");
+        for importer in importers {
+            let _ = writeln!(buf, "    {}", self.modules[importer].name);
+        }
+
+        if let Some(chain) = self.import_chain(target) {
+            buf.push_str("\nby way of:\n");
+            for (depth, index) in chain.iter().enumerate() {
+                let branch = if depth == 0 { "" } else { "└── " };
+                let indent = "    ".repeat(depth.saturating_sub(1));
+                let _ = writeln!(buf, "    {indent}{branch}{}", self.modules[*index].name);
+            }
+        }
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> ModuleGraph {
+        ModuleGraph {
+            modules: vec![
+                GraphModule {
+                    name: "main.roc".to_string(),
+                    path: PathBuf::from("main.roc"),
+                    package: None,
+                    imports: vec![2, 3, 4],
+                },
+                GraphModule {
+                    name: "pf.main".to_string(),
+                    path: PathBuf::from("pf/main.roc"),
+                    package: Some("pf".to_string()),
+                    imports: vec![],
+                },
+                GraphModule {
+                    name: "Lexer".to_string(),
+                    path: PathBuf::from("Lexer.roc"),
+                    package: None,
+                    imports: vec![],
+                },
+                GraphModule {
+                    name: "Parser".to_string(),
+                    path: PathBuf::from("Parser.roc"),
+                    package: None,
+                    imports: vec![2],
+                },
+                GraphModule {
+                    name: "pf.Stdout".to_string(),
+                    path: PathBuf::from("pf/Stdout.roc"),
+                    package: Some("pf".to_string()),
+                    imports: vec![],
+                },
+            ],
+            packages: vec![
+                GraphPackage {
+                    shorthand: "pf".to_string(),
+                    root_module: PathBuf::from("pf/main.roc"),
+                    declared_by: "main.roc".to_string(),
+                    is_platform: true,
+                },
+                GraphPackage {
+                    shorthand: "json".to_string(),
+                    root_module: PathBuf::from("json/main.roc"),
+                    declared_by: "main.roc".to_string(),
+                    is_platform: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn tree_marks_repeats_and_unused_packages() {
+        assert_eq!(
+            graph().render_tree(),
+            indoc::indoc! {"
+                main.roc
+                ├── Lexer
+                ├── Parser
+                │   └── Lexer
+                └── pf.Stdout
+
+                pf.main
+
+                Unused packages:
+                    json (in the header of main.roc)
+            "}
+        );
+    }
+
+    #[test]
+    fn why_follows_the_shortest_chain() {
+        let graph = graph();
+
+        assert_eq!(graph.import_chain(2), Some(vec![0, 2]));
+        assert_eq!(graph.importers(2), vec![0, 3]);
+        assert_eq!(graph.import_chain(1), None);
+        assert_eq!(
+            graph.render_why(2),
+            indoc::indoc! {"
+                Lexer is imported by:
+                    main.roc
+                    Parser
+
+                by way of:
+                    main.roc
+                    └── Lexer
+            "}
+        );
+    }
+
+    #[test]
+    fn dot_boxes_packages() {
+        assert_eq!(
+            graph().render_dot(),
+            indoc::indoc! {r#"
+                digraph imports {
+                    node [shape=box];
+
+                    subgraph "cluster_pf" {
+                        label = "pf";
+                        "pf.main";
+                        "pf.Stdout";
+                    }
+
+                    subgraph "cluster_json" {
+                        label = "json";
+                    }
+
+                    "main.roc" -> "Lexer";
+                    "main.roc" -> "Parser";
+                    "main.roc" -> "pf.Stdout";
+                    "pf.main";
+                    "Lexer";
+                    "Parser" -> "Lexer";
+                    "pf.Stdout";
+                }
+            "#}
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Naming and filtering the top-level `expect`s that `roc test` runs.
+
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use regex::Regex;
+use roc_parse::ast::{Pattern, ValueDef};
+use roc_region::all::{LineInfo, Loc, Region};
+
+use crate::format::parse_all;
+
+/// Where a top-level `expect` is, and what it's called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectInfo {
+    pub module_name: String,
+    pub path: PathBuf,
+    /// The 1-based line of the `expect` keyword.
+    pub line: u32,
+    /// Top-level expects don't have names of their own, so they go by the top-level def above
+    /// them, which is usually the one they test.
+    pub def_name: Option<String>,
+}
+
+impl ExpectInfo {
+    /// e.g. `Parser.parse_digits`, or just `Parser` for expects above all of its defs.
+    pub fn name(&self) -> String {
+        match &self.def_name {
+            Some(def_name) => format!("{}.{def_name}", self.module_name),
+            None => self.module_name.clone(),
+        }
+    }
+
+    /// e.g. `src/Parser.roc:12`
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.path.display(), self.line)
+    }
+}
+
+/// Describes the expects at `regions` in a module. Each region starts at the comment above its
+/// expect, if there is one.
+pub fn describe_expects(
+    module_name: &str,
+    path: &Path,
+    src: &str,
+    regions: &[Region],
+) -> Vec<ExpectInfo> {
+    let module_name = if module_name.is_empty() {
+        // The app module has no name of its own
+        path.file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+    } else {
+        module_name.to_string()
+    };
+
+    let arena = Bump::new();
+    let named_defs = toplevel_def_names(&arena, src);
+    let line_info = LineInfo::new(src);
+
+    regions
+        .iter()
+        .map(|region| {
+            let def_name = named_defs
+                .iter()
+                .take_while(|(def_region, _)| def_region.end() <= region.start())
+                .last()
+                .map(|(_, name)| name.clone());
+
+            ExpectInfo {
+                module_name: module_name.clone(),
+                path: path.to_path_buf(),
+                line: keyword_line(src, &line_info, *region) + 1,
+                def_name,
+            }
+        })
+        .collect()
+}
+
+/// `roc test --filter`, which is matched against expects' def and module names, as well as both
+/// together like `Parser.parse_digits`.
+#[derive(Debug, Clone)]
+pub struct ExpectFilter(Regex);
+
+impl ExpectFilter {
+    /// Filters are regexes, except for ones that don't parse, which are matched as they are.
+    pub fn new(pattern: &str) -> Self {
+        let regex = Regex::new(pattern)
+            .or_else(|_| Regex::new(&regex::escape(pattern)))
+            .expect("an escaped pattern is always a valid regex");
+
+        ExpectFilter(regex)
+    }
+
+    pub fn matches(&self, info: &ExpectInfo) -> bool {
+        self.0.is_match(&info.name())
+            || self.0.is_match(&info.module_name)
+            || info
+                .def_name
+                .as_ref()
+                .is_some_and(|def_name| self.0.is_match(def_name))
+    }
+}
+
+/// The regions and names of the top-level value defs in a module, in source order.
+pub(crate) fn toplevel_def_names(arena: &Bump, src: &str) -> Vec<(Region, String)> {
+    // The module has already compiled, so this only fails if the file changed since
+    let Ok(ast) = parse_all(arena, src) else {
+        return vec![];
+    };
+
+    ast.defs
+        .defs()
+        .zip(ast.defs.regions.iter())
+        .filter_map(|(def, region)| {
+            let pattern: &Loc<Pattern> = match def.err()? {
+                ValueDef::Annotation(pattern, _) => pattern,
+                ValueDef::Body(pattern, _) => pattern,
+                ValueDef::AnnotatedBody { body_pattern, .. } => body_pattern,
+                _ => return None,
+            };
+
+            Some((*region, identifier(&pattern.value)?.to_string()))
+        })
+        .collect()
+}
+
+fn identifier<'a>(pattern: &Pattern<'a>) -> Option<&'a str> {
+    match pattern {
+        Pattern::Identifier { ident } => Some(*ident),
+        Pattern::SpaceBefore(pattern, _) | Pattern::SpaceAfter(pattern, _) => identifier(pattern),
+        _ => None,
+    }
+}
+
+/// The 0-based line of the `expect` keyword, skipping the comment above it.
+fn keyword_line(src: &str, line_info: &LineInfo, region: Region) -> u32 {
+    let first_line = line_info.convert_pos(region.start()).line;
+
+    src.lines()
+        .enumerate()
+        .skip(first_line as usize)
+        .find(|(_, text)| !text.trim_start().starts_with('#'))
+        .map_or(first_line, |(line, _)| line as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use roc_region::all::Position;
+
+    const SRC: &str = indoc! {r#"
+        module [add]
+
+        expect 1 == 1
+
+        add = \a, b -> a + b
+
+        # adds small numbers
+        expect add 1 2 == 3
+    "#};
+
+    fn region_from(text: &str) -> Region {
+        let start = SRC.find(text).unwrap() as u32;
+        let end = SRC.trim_end().len() as u32;
+
+        Region::new(Position::new(start), Position::new(end))
+    }
+
+    #[test]
+    fn expects_go_by_the_def_above_them() {
+        let infos = describe_expects(
+            "Math",
+            Path::new("Math.roc"),
+            SRC,
+            &[region_from("expect 1 == 1"), region_from("# adds")],
+        );
+
+        assert_eq!(infos[0].name(), "Math");
+        assert_eq!(infos[0].line, 3);
+        assert_eq!(infos[1].name(), "Math.add");
+        assert_eq!(infos[1].line, 8);
+    }
+
+    #[test]
+    fn filters_match_names_or_fall_back_to_substrings() {
+        let info = ExpectInfo {
+            module_name: "Math".to_string(),
+            path: PathBuf::from("Math.roc"),
+            line: 8,
+            def_name: Some("add".to_string()),
+        };
+
+        assert!(ExpectFilter::new("^add$").matches(&info));
+        assert!(ExpectFilter::new("Math.add").matches(&info));
+        assert!(ExpectFilter::new("Mat").matches(&info));
+        assert!(!ExpectFilter::new("sub").matches(&info));
+        assert!(!ExpectFilter::new("add(").matches(&info));
+    }
+}
@@ -20,7 +20,10 @@ use roc_parse::header::parse_module_defs;
 use roc_parse::normalize::Normalize;
 use roc_parse::{header, parser::SyntaxError, state::State};
 use roc_problem::can::RuntimeError;
-use roc_region::all::{LineColumn, LineInfo};
+use roc_problem::Severity;
+use roc_region::all::{LineColumn, LineColumnRegion, LineInfo};
+use roc_reporting::cli::print_structured;
+use roc_reporting::diagnostic::Diagnostic;
 use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
 use roc_target::Target;
 use roc_types::subs::{Subs, Variable};
@@ -76,13 +79,17 @@ fn is_roc_file(path: &Path) -> bool {
     matches!(path.extension().and_then(OsStr::to_str), Some("roc"))
 }
 
+/// With a structured `render` target, `FormatMode::CheckOnly` also prints a report for each file
+/// that needs formatting.
 pub fn format_files(
     files: std::vec::Vec<PathBuf>,
     mode: FormatMode,
     migrate: bool,
+    render: RenderTarget,
 ) -> Result<(), String> {
     let arena = Bump::new();
     let mut files_to_reformat = Vec::new(); // to track which files failed `roc format --check`
+    let mut reports = Vec::new();
 
     for file in flatten_directories(files) {
         let src = std::fs::read_to_string(&file).unwrap();
@@ -95,6 +102,7 @@ pub fn format_files(
                         // list for reporting afterwards.
                         if buf.as_str() != src {
                             files_to_reformat.push(file.display().to_string());
+                            reports.push(unformatted_report(&file, &src, buf.as_str(), render));
                         }
                     }
                     FormatMode::WriteToFile => {
@@ -177,6 +185,10 @@ pub fn format_files(
             },
         }
     }
+    if matches!(mode, FormatMode::CheckOnly) && render.is_structured() {
+        print_structured(render, &reports);
+    }
+
     // After processing all files, check if any files failed `format --check`
     if !files_to_reformat.is_empty() {
         let file_list = files_to_reformat.join(", ");
@@ -188,6 +200,46 @@ pub fn format_files(
     Ok(())
 }
 
+/// A report for `roc format --check` about a file that needs formatting, pointing at the first
+/// line that formatting would change.
+fn unformatted_report(file: &Path, src: &str, formatted: &str, render: RenderTarget) -> String {
+    let src_lines: Vec<&str> = src.lines().collect();
+    let line = src_lines
+        .iter()
+        .zip(formatted.lines())
+        .position(|(before, after)| before != &after)
+        .unwrap_or(src_lines.len().min(formatted.lines().count()))
+        // Formatting only added lines to the end, so point at the last one there is
+        .min(src_lines.len().saturating_sub(1));
+    let width = src_lines.get(line).map_or(0, |text| text.chars().count());
+
+    let diagnostic = Diagnostic {
+        severity: Severity::RuntimeError,
+        title: "NOT FORMATTED".to_string(),
+        file: file.to_path_buf(),
+        region: Some(LineColumnRegion::new(
+            LineColumn {
+                line: line as u32,
+                column: 0,
+            },
+            LineColumn {
+                line: line as u32,
+                column: width as u32,
+            },
+        )),
+        related: vec![],
+        message: format!(
+            "This file isn't formatted the way `roc format` formats it, starting on this line. You can fix this with `roc format {}`.",
+            file.display()
+        ),
+    };
+
+    match render {
+        RenderTarget::Sarif => diagnostic.to_sarif_result(),
+        _ => diagnostic.to_json(),
+    }
+}
+
 #[derive(Debug)]
 pub enum FormatProblem {
     ParsingFailed {
@@ -268,7 +320,7 @@ pub fn format_src(arena: &Bump, src: &str, migrate: bool) -> Result<String, Form
     Ok(buf.as_str().to_string())
 }
 
-fn parse_all<'a>(arena: &'a Bump, src: &'a str) -> Result<FullAst<'a>, SyntaxError<'a>> {
+pub(crate) fn parse_all<'a>(arena: &'a Bump, src: &'a str) -> Result<FullAst<'a>, SyntaxError<'a>> {
     let (header, state) = header::parse_header(arena, State::new(src.as_bytes()))
         .map_err(|e| SyntaxError::Header(e.problem))?;
 
@@ -502,7 +554,12 @@ main =
     fn test_single_file_needs_reformatting() {
         let dir = tempdir().unwrap();
         let file_path = setup_test_file(dir.path(), "test1.roc", UNFORMATTED_ROC);
-        let result = format_files(vec![file_path.clone()], FormatMode::CheckOnly, false);
+        let result = format_files(
+            vec![file_path.clone()],
+            FormatMode::CheckOnly,
+            false,
+            RenderTarget::ColorTerminal,
+        );
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err(),
@@ -520,7 +577,12 @@ main =
         let dir = tempdir().unwrap();
         let file1 = setup_test_file(dir.path(), "test1.roc", UNFORMATTED_ROC);
         let file2 = setup_test_file(dir.path(), "test2.roc", UNFORMATTED_ROC);
-        let result = format_files(vec![file1, file2], FormatMode::CheckOnly, false);
+        let result = format_files(
+            vec![file1, file2],
+            FormatMode::CheckOnly,
+            false,
+            RenderTarget::ColorTerminal,
+        );
         assert!(result.is_err());
         let error_message = result.unwrap_err();
         assert!(error_message.contains("test1.roc") && error_message.contains("test2.roc"));
@@ -532,7 +594,12 @@ main =
     fn test_no_files_need_reformatting() {
         let dir = tempdir().unwrap();
         let file_path = setup_test_file(dir.path(), "formatted.roc", FORMATTED_ROC);
-        let result = format_files(vec![file_path], FormatMode::CheckOnly, false);
+        let result = format_files(
+            vec![file_path],
+            FormatMode::CheckOnly,
+            false,
+            RenderTarget::ColorTerminal,
+        );
         assert!(result.is_ok());
 
         cleanup_temp_dir(dir);
@@ -548,6 +615,7 @@ main =
             vec![file_formatted, file1_unformated, file2_unformated],
             FormatMode::CheckOnly,
             false,
+            RenderTarget::ColorTerminal,
         );
         assert!(result.is_err());
         let error_message = result.unwrap_err();
@@ -557,6 +625,20 @@ main =
         cleanup_temp_dir(dir);
     }
 
+    #[test]
+    fn test_unformatted_report_points_at_first_changed_line() {
+        let report = unformatted_report(
+            Path::new("test1.roc"),
+            UNFORMATTED_ROC,
+            &format_src(&Bump::new(), UNFORMATTED_ROC, false).unwrap(),
+            RenderTarget::Json,
+        );
+
+        assert!(report.contains(r#""title":"NOT FORMATTED""#));
+        assert!(report
+            .contains(r#""region":{"end":{"column":41,"line":4},"start":{"column":1,"line":4}}"#));
+    }
+
     const HEADER: &str = indoc! {r#"
         interface Test
             exposes []
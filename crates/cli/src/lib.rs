@@ -18,12 +18,13 @@ use roc_collections::MutMap;
 use roc_error_macros::{internal_error, user_error};
 use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
-use roc_load::{ExpectMetadata, Threading};
+use roc_load::{ExpectMetadata, LoadConfig, Threading};
 #[cfg(not(windows))]
 use roc_module::symbol::ModuleId;
 use roc_mono::ir::OptLevel;
 use roc_packaging::cache::RocCacheDir;
 use roc_packaging::tarball::Compression;
+use roc_reporting::report::RenderTarget;
 #[cfg(not(windows))]
 use roc_reporting::report::ANSI_STYLE_CODES;
 use roc_target::{Architecture, Target};
@@ -41,11 +42,29 @@ use strum::IntoEnumIterator;
 #[cfg(not(target_os = "linux"))]
 use tempfile::TempDir;
 
+#[cfg(not(windows))]
+mod bench;
+#[cfg(not(windows))]
+mod coverage;
+mod deps;
+#[cfg(not(windows))]
+mod expects;
 mod format;
+#[cfg(not(windows))]
+mod reporters;
+mod watch;
+#[cfg(not(windows))]
+use coverage::{FileCoverage, DEFAULT_LCOV_PATH};
+pub use deps::{deps, why};
+#[cfg(not(windows))]
+use expects::{describe_expects, ExpectFilter, ExpectInfo};
 pub use format::{
     annotate_file, annotation_edit, annotation_edits, format_files, format_src, AnnotationProblem,
     FormatMode,
 };
+#[cfg(not(windows))]
+use reporters::{CapturedOutput, Reporter, TestCase, TestSuite};
+pub use watch::{report_loaded_modules, report_modules_beside, watch};
 
 pub const CMD_BUILD: &str = "build";
 pub const CMD_RUN: &str = "run";
@@ -57,6 +76,9 @@ pub const CMD_VERSION: &str = "version";
 pub const CMD_FORMAT: &str = "format";
 pub const CMD_FORMAT_ANNOTATE: &str = "annotate";
 pub const CMD_TEST: &str = "test";
+pub const CMD_BENCH: &str = "bench";
+pub const CMD_DEPS: &str = "deps";
+pub const CMD_WHY: &str = "why";
 pub const CMD_GLUE: &str = "glue";
 pub const CMD_PREPROCESS_HOST: &str = "preprocess-host";
 pub const CMD_LICENSES: &str = "licenses";
@@ -72,6 +94,8 @@ pub const FLAG_LIB: &str = "lib";
 pub const FLAG_NO_LINK: &str = "no-link";
 pub const FLAG_TARGET: &str = "target";
 pub const FLAG_TIME: &str = "time";
+pub const FLAG_TIMINGS: &str = "timings";
+pub const FLAG_TIMINGS_OUTPUT: &str = "timings-output";
 pub const FLAG_VERBOSE: &str = "verbose";
 pub const FLAG_NO_COLOR: &str = "no-color";
 pub const FLAG_NO_HEADER: &str = "no-header";
@@ -95,9 +119,24 @@ pub const FLAG_PP_PLATFORM: &str = "platform";
 pub const FLAG_PP_DYLIB: &str = "lib";
 pub const FLAG_MIGRATE: &str = "migrate";
 pub const FLAG_DOCS_ROOT: &str = "root-dir";
+pub const FLAG_WATCH: &str = "watch";
+pub const FLAG_ERROR_FORMAT: &str = "error-format";
+pub const FLAG_FILTER: &str = "filter";
+pub const FLAG_LIST: &str = "list";
+pub const FLAG_FAIL_FAST: &str = "fail-fast";
+pub const FLAG_REPORTER: &str = "reporter";
+pub const FLAG_COVERAGE: &str = "coverage";
+pub const FLAG_COVERAGE_OUTPUT: &str = "coverage-output";
+pub const FLAG_UPDATE_SNAPSHOTS: &str = "update-snapshots";
+pub const FLAG_BASELINE: &str = "baseline";
+pub const FLAG_SAVE_BASELINE: &str = "save-baseline";
+pub const FLAG_GRAPH_FORMAT: &str = "format";
+pub const FLAG_APP: &str = "app";
+pub const MODULE_NAME: &str = "MODULE_NAME";
 
 pub const VERSION: &str = env!("ROC_VERSION");
 const DEFAULT_GENERATED_DOCS_DIR: &str = "generated-docs";
+const DEFAULT_TIMINGS_PATH: &str = "roc-timings.json";
 
 pub fn build_app() -> Command {
     let flag_optimize = Arg::new(FLAG_OPTIMIZE)
@@ -172,6 +211,19 @@ pub fn build_app() -> Command {
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_watch = Arg::new(FLAG_WATCH)
+        .long(FLAG_WATCH)
+        .help("Run again whenever one of the modules that were loaded changes")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_error_format = Arg::new(FLAG_ERROR_FORMAT)
+        .long(FLAG_ERROR_FORMAT)
+        .help("Set how errors and warnings are printed\n(json prints one JSON object per line and sarif prints a SARIF log, for other tools to read.)")
+        .value_parser(["human", "json", "sarif"])
+        .default_value("human")
+        .required(false);
+
     let flag_main = Arg::new(FLAG_MAIN)
         .long(FLAG_MAIN)
         .help("The .roc file of the main app/package module to resolve dependencies from")
@@ -218,10 +270,26 @@ pub fn build_app() -> Command {
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
             .arg(flag_time.clone())
+            .arg(
+                Arg::new(FLAG_TIMINGS)
+                    .long(FLAG_TIMINGS)
+                    .help("Write how long each phase took for each module, and on which thread, as a Chrome trace\n(Open it in https://ui.perfetto.dev or chrome://tracing.)")
+                    .action(ArgAction::SetTrue)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_TIMINGS_OUTPUT)
+                    .long(FLAG_TIMINGS_OUTPUT)
+                    .help("The file to write the --timings trace to\n(Defaults to roc-timings.json.)")
+                    .value_parser(value_parser!(PathBuf))
+                    .requires(FLAG_TIMINGS)
+                    .required(false)
+            )
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_error_format.clone())
             .arg(flag_wasm_stack_size_kb)
             .arg(
                 Arg::new(FLAG_TARGET)
@@ -272,7 +340,11 @@ pub fn build_app() -> Command {
             .about("Run all top-level `expect`s in a main module and any modules it imports")
             .arg(flag_main.clone())
             .arg(flag_optimize.clone())
-            .arg(flag_max_threads.clone())
+            .arg(
+                flag_max_threads
+                    .clone()
+                    .help("Limit the number of threads (and hence cores) used during compilation, and the number of expects that run at once")
+            )
             .arg(flag_opt_size.clone())
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
@@ -282,13 +354,81 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_watch.clone())
+            .arg(
+                flag_error_format
+                    .clone()
+                    .help("Set how errors, warnings and failed expects are printed\n(json prints one JSON object per line and sarif prints a SARIF log once every test has run, for other tools to read.)")
+            )
             .arg(
                 Arg::new(FLAG_VERBOSE)
                     .long(FLAG_VERBOSE)
-                    .help("Print detailed test statistics by module")
+                    .help("Print detailed test statistics by module, and how long each expect took")
                     .action(ArgAction::SetTrue)
                     .required(false)
             )
+            .arg(
+                Arg::new(FLAG_FILTER)
+                    .long(FLAG_FILTER)
+                    .help("Only run the expects whose module or def name matches this regex or substring\n(Top-level expects are named after the def above them, like `Parser.parse_digits`.)")
+                    .value_parser(value_parser!(String))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_LIST)
+                    .long(FLAG_LIST)
+                    .help("List the expects that would run, without running them")
+                    .action(ArgAction::SetTrue)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_FAIL_FAST)
+                    .long(FLAG_FAIL_FAST)
+                    .help("Stop running expects after the first one fails")
+                    .action(ArgAction::SetTrue)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_REPORTER)
+                    .long(FLAG_REPORTER)
+                    .help("Also report the results as JUnit XML or TAP, for CI systems to read\n(The report goes to stdout instead of the usual output, unless there's an --output file for it.)")
+                    .value_parser(["human", "junit", "tap"])
+                    .default_value("human")
+                    .conflicts_with(FLAG_ERROR_FORMAT)
+                    .conflicts_with(FLAG_LIST)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_OUTPUT)
+                    .long(FLAG_OUTPUT)
+                    .help("The file to write the --reporter's report to")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_COVERAGE)
+                    .long(FLAG_COVERAGE)
+                    .help("Record which top-level defs, `if` arms and `when` branches the expects reach, and write them to an lcov file")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with(FLAG_LIST)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_COVERAGE_OUTPUT)
+                    .long(FLAG_COVERAGE_OUTPUT)
+                    .help("The file to write the --coverage report to\n(Defaults to lcov.info.)")
+                    .value_parser(value_parser!(PathBuf))
+                    .requires(FLAG_COVERAGE)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_UPDATE_SNAPSHOTS)
+                    .long(FLAG_UPDATE_SNAPSHOTS)
+                    .help("Record the values of `expect snapshot(...)`s in their snapshot files, instead of failing when they changed")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with(FLAG_LIST)
+                    .required(false)
+            )
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to test")
@@ -299,6 +439,39 @@ pub fn build_app() -> Command {
             )
             .arg(args_for_app.clone().last(true))
         )
+        .subcommand(Command::new(CMD_BENCH)
+            .about("Time the `expect bench(...)`s in a main module and any modules it imports\n(The value given to `bench` is what gets computed each time, so keep it small; it's copied out on every run.)")
+            .arg(flag_main.clone())
+            .arg(flag_max_threads.clone())
+            .arg(
+                Arg::new(FLAG_FILTER)
+                    .long(FLAG_FILTER)
+                    .help("Only run the benchmarks whose module or def name matches this regex or substring")
+                    .value_parser(value_parser!(String))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_BASELINE)
+                    .long(FLAG_BASELINE)
+                    .help("Compare the results to the ones saved in this file by --save-baseline")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_SAVE_BASELINE)
+                    .long(FLAG_SAVE_BASELINE)
+                    .help("Save the results to this file, to compare later runs to with --baseline")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(ROC_FILE)
+                    .help("The .roc file to benchmark")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+                    .default_value(DEFAULT_ROC_FILENAME)
+            )
+        )
         .subcommand(Command::new(CMD_REPL)
             .about("Launch the interactive Read Eval Print Loop (REPL)")
             .arg(
@@ -315,6 +488,13 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false)
             )
+            .arg(
+                Arg::new(FLAG_APP)
+                    .long(FLAG_APP)
+                    .help("The app whose packages, including its platform, modules can be imported from\nIf its platform's host is built as a dynamic library, like linux-x64.so, effects can be run too.\n[default: main.roc, if it's in the current directory]")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
         )
         .subcommand(Command::new(CMD_RUN)
             .about("Run a .roc file even if it has build errors")
@@ -345,6 +525,7 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_watch.clone())
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone().last(true))
         )
@@ -384,6 +565,11 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false),
             )
+            .arg(
+                flag_error_format
+                    .clone()
+                    .help("Set how files that need formatting are reported by --check\n(json prints one JSON object per line and sarif prints a SARIF log, for other tools to read.)")
+            )
             .after_help("If DIRECTORY_OR_FILES is omitted, the .roc files in the current working\ndirectory are formatted.")
             .subcommand(Command::new(CMD_FORMAT_ANNOTATE)
                 .about("Annotate all top level definitions from a .roc file")
@@ -405,6 +591,8 @@ pub fn build_app() -> Command {
             .arg(flag_main.clone())
             .arg(flag_time.clone())
             .arg(flag_max_threads.clone())
+            .arg(flag_watch)
+            .arg(flag_error_format)
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to check")
@@ -413,6 +601,43 @@ pub fn build_app() -> Command {
                     .default_value(DEFAULT_ROC_FILENAME),
             )
             )
+        .subcommand(Command::new(CMD_DEPS)
+            .about("Print which modules import which, including modules from packages")
+            .arg(flag_main.clone())
+            .arg(flag_max_threads.clone())
+            .arg(
+                Arg::new(FLAG_GRAPH_FORMAT)
+                    .long(FLAG_GRAPH_FORMAT)
+                    .help("Print the imports as a tree, as a Graphviz DOT graph, or as JSON")
+                    .value_parser(["tree", "dot", "json"])
+                    .default_value("tree")
+                    .required(false)
+            )
+            .arg(
+                Arg::new(ROC_FILE)
+                    .help("The .roc file to start from")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+                    .default_value(DEFAULT_ROC_FILENAME),
+            )
+        )
+        .subcommand(Command::new(CMD_WHY)
+            .about("Print the chain of imports that leads to a module")
+            .arg(flag_main.clone())
+            .arg(flag_max_threads.clone())
+            .arg(
+                Arg::new(MODULE_NAME)
+                    .help("The module to explain, like `Parser`, or `json.Decode` for a module from a package")
+                    .required(true)
+            )
+            .arg(
+                Arg::new(ROC_FILE)
+                    .help("The .roc file to start from")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+                    .default_value(DEFAULT_ROC_FILENAME),
+            )
+        )
         .subcommand(
             Command::new(CMD_DOCS)
                 .about("Generate documentation for a Roc package")
@@ -527,6 +752,20 @@ fn opt_level_from_flags(matches: &ArgMatches) -> OptLevel {
     }
 }
 
+/// How `--error-format` says to print problems. Subcommands without the flag print them for people.
+pub fn render_target_from_flags(matches: &ArgMatches) -> RenderTarget {
+    match matches
+        .try_get_one::<String>(FLAG_ERROR_FORMAT)
+        .ok()
+        .flatten()
+        .map(AsRef::as_ref)
+    {
+        Some("json") => RenderTarget::Json,
+        Some("sarif") => RenderTarget::Sarif,
+        _ => RenderTarget::ColorTerminal,
+    }
+}
+
 #[cfg(windows)]
 pub fn test(_matches: &ArgMatches, _target: Target) -> io::Result<i32> {
     todo!("running tests does not work on windows right now")
@@ -538,17 +777,54 @@ struct ModuleTestResults {
     failed_count: usize,
     passed_count: usize,
     tests_duration: Duration,
+    cases: Vec<TestCase>,
 }
 
 #[cfg(not(windows))]
 pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
+    use roc_build::program::loading_problem_report;
     use roc_build::program::report_problems_monomorphized;
     use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError};
     use roc_packaging::cache;
+    use roc_repl_expect::coverage::CoverageHits;
+    use roc_repl_expect::run::{run_toplevel_expects_in_children, ExpectFunctions};
+    use roc_repl_expect::snapshot::SnapshotMode;
+    use roc_reporting::cli::{print_structured, render_problems};
+    use std::ops::ControlFlow;
 
     let start_time = Instant::now();
     let arena = Bump::new();
     let opt_level = opt_level_from_flags(matches);
+    let render = render_target_from_flags(matches);
+    let filter = matches
+        .get_one::<String>(FLAG_FILTER)
+        .map(|pattern| ExpectFilter::new(pattern));
+    let fail_fast = matches.get_flag(FLAG_FAIL_FAST);
+    let reporter = matches
+        .get_one::<String>(FLAG_REPORTER)
+        .and_then(|flag| Reporter::from_flag(flag));
+    let report_path = matches.get_one::<PathBuf>(FLAG_OUTPUT);
+    let coverage_path = matches.get_flag(FLAG_COVERAGE).then(|| {
+        matches
+            .get_one::<PathBuf>(FLAG_COVERAGE_OUTPUT)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_LCOV_PATH))
+    });
+    let snapshot_mode = match matches.get_flag(FLAG_UPDATE_SNAPSHOTS) {
+        true => SnapshotMode::Update,
+        false => SnapshotMode::Record,
+    };
+    let exec_mode = match coverage_path {
+        Some(_) => ExecutionMode::TestWithCoverage,
+        None => ExecutionMode::Test,
+    };
+
+    // A SARIF log has to hold every report, so they're gathered here and printed at the end
+    let sarif = matches!(render, RenderTarget::Sarif);
+    let mut sarif_reports: Vec<String> = Vec::new();
+
+    // Whether to leave out the output meant for people, because stdout is for something else
+    let quiet = render.is_structured() || (reporter.is_some() && report_path.is_none());
 
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
         None => Threading::AllAvailable,
@@ -557,6 +833,13 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         Some(n) => Threading::AtMost(*n),
     };
 
+    // Each expect runs in a process of its own, at most this many at a time
+    let max_children = match threading {
+        Threading::Single => 1,
+        Threading::AtMost(n) => n,
+        Threading::AllAvailable => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
     let paths: Vec<_> = matches.get_many::<PathBuf>(ROC_FILE).unwrap().collect();
 
     let paths: Vec<_> = {
@@ -591,6 +874,10 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
 
     let mut all_files_total_failed_count = 0;
     let mut all_files_total_passed_count = 0;
+    let mut listed_count = 0;
+    let mut stopped_early = false;
+    let mut suites = Vec::new();
+    let mut coverage_files: Vec<FileCoverage> = Vec::new();
 
     for path in paths.iter() {
         let arena = &arena;
@@ -602,11 +889,10 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         let load_config = LoadConfig {
             target,
             function_kind,
-            // TODO: expose this from CLI?
-            render: roc_reporting::report::RenderTarget::ColorTerminal,
+            render,
             palette: roc_reporting::report::DEFAULT_PALETTE,
             threading,
-            exec_mode: ExecutionMode::Test,
+            exec_mode,
         };
         let load_result = roc_load::load_and_monomorphize(
             arena,
@@ -619,19 +905,92 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         let mut loaded = match load_result {
             Ok(loaded) => loaded,
             Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
-                return handle_loading_problem(problem);
+                report_modules_beside([path.as_path()]);
+
+                if sarif {
+                    sarif_reports.push(loading_problem_report(problem));
+                    print_structured(render, &sarif_reports);
+
+                    return Ok(1);
+                }
+
+                return handle_loading_problem(problem, render);
             }
-            Err(LoadMonomorphizedError::ErrorModule(module)) => {
-                return handle_error_module(module, start_time.elapsed());
+            Err(LoadMonomorphizedError::ErrorModule(mut module)) => {
+                report_loaded_modules(module.sources.values().map(|(path, _)| path));
+
+                if sarif {
+                    let (problems, errors, warnings) = render_problems(
+                        &module.sources,
+                        &module.interns,
+                        &mut module.can_problems,
+                        &mut module.type_problems,
+                        render,
+                    );
+                    sarif_reports.extend(errors.into_iter().chain(warnings));
+                    print_structured(render, &sarif_reports);
+
+                    return Ok(problems.exit_code());
+                }
+
+                return handle_error_module(module, start_time.elapsed(), render);
             }
         };
-        let problems = report_problems_monomorphized(&mut loaded);
+        report_loaded_modules(loaded.sources.values().map(|(path, _)| path));
+        let problems = if sarif {
+            let (problems, errors, warnings) = render_problems(
+                &loaded.sources,
+                &loaded.interns,
+                &mut loaded.can_problems,
+                &mut loaded.type_problems,
+                render,
+            );
+            sarif_reports.extend(errors.into_iter().chain(warnings));
+
+            problems
+        } else {
+            report_problems_monomorphized(&mut loaded, render)
+        };
+
+        if matches.get_flag(FLAG_LIST) {
+            let mut infos: Vec<ExpectInfo> = loaded
+                .toplevel_expects
+                .iter()
+                .flat_map(|(module_id, expects)| {
+                    let (module_path, src) = &loaded.sources[module_id];
+                    let regions: Vec<_> = expects.pure.values().copied().collect();
+
+                    describe_expects(
+                        loaded.interns.module_name(*module_id).as_str(),
+                        module_path,
+                        src,
+                        &regions,
+                    )
+                })
+                .filter(|info| filter.as_ref().map_or(true, |filter| filter.matches(info)))
+                .collect();
+            infos.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+            for info in infos.iter() {
+                println!("{}  {}", info.location(), info.name());
+            }
+            listed_count += infos.len();
+
+            // Listing doesn't need the expects to be compiled
+            continue;
+        }
 
         let mut expectations = std::mem::take(&mut loaded.expectations);
 
         let interns = loaded.interns.clone();
         let sources = loaded.sources.clone();
 
+        // Compiling consumes the procedures, so find out what there is to cover beforehand
+        let coverage_points = coverage_path
+            .is_some()
+            .then(|| roc_mono::coverage::coverage_points(loaded.procedures.values()));
+        let mut coverage_hits = CoverageHits::default();
+
         let (dyn_lib, expects_by_module, layout_interner) =
             roc_repl_expect::run::expect_mono_module_to_dylib(
                 arena,
@@ -648,7 +1007,7 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
                 problems.errors, 0,
                 "if there were errors, we would have already exited."
             );
-            if problems.warnings > 0 {
+            if problems.warnings > 0 && !quiet {
                 problems.print_error_warning_count(start_time.elapsed());
                 println!(".\n\nRunning tests…\n\n\x1B[36m{}\x1B[39m", "─".repeat(80));
             }
@@ -658,64 +1017,220 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         let arena = &bumpalo::Bump::new();
         let interns = arena.alloc(interns);
 
-        let mut writer = std::io::stdout();
-
-        let mut total_failed_count = 0;
-        let mut total_passed_count = 0;
+        // Reporters need failures as plain text, which we also print ourselves
+        let captured = CapturedOutput::default();
+        let (mut writer, expect_render): (Box<dyn io::Write>, _) = match reporter {
+            Some(_) => (Box::new(captured.clone()), RenderTarget::Generic),
+            None if sarif => (Box::new(captured.clone()), render),
+            None => (Box::new(io::stdout()), render),
+        };
 
-        let mut results_by_module = Vec::new();
         let global_layout_interner = layout_interner.into_global();
 
         let compilation_duration = start_time.elapsed();
 
+        // Gather the expects of every module, so that they can all run at once
+        let mut infos = Vec::new();
+        let mut kept = Vec::new();
+        let mut module_ranges = Vec::new();
+
         for (module_id, expects) in expects_by_module.into_iter() {
-            let test_start_time = Instant::now();
+            let (module_path, src) = &sources[&module_id];
+            let regions: Vec<_> = expects.pure.iter().map(|expect| expect.region).collect();
+            let module_infos = describe_expects(
+                interns.module_name(module_id).as_str(),
+                module_path,
+                src,
+                &regions,
+            );
 
-            let (failed_count, passed_count) = roc_repl_expect::run::run_toplevel_expects(
-                &mut writer,
-                roc_reporting::report::RenderTarget::ColorTerminal,
-                arena,
-                interns,
-                &global_layout_interner,
-                &dyn_lib,
-                &mut expectations,
-                expects,
-            )
-            .unwrap();
+            let start = kept.len();
+            for (info, expect) in module_infos.into_iter().zip(expects.pure) {
+                if filter.as_ref().map_or(true, |filter| filter.matches(&info)) {
+                    infos.push(info);
+                    kept.push(expect);
+                }
+            }
+            module_ranges.push((module_id, start..kept.len()));
+        }
 
-            let tests_duration = test_start_time.elapsed();
+        let mut cases = Vec::with_capacity(infos.len());
 
-            results_by_module.push(ModuleTestResults {
-                module_id,
-                failed_count,
-                passed_count,
-                tests_duration,
-            });
+        let (total_failed_count, total_passed_count) = run_toplevel_expects_in_children(
+            &mut writer,
+            expect_render,
+            arena,
+            interns,
+            &global_layout_interner,
+            &dyn_lib,
+            &mut expectations,
+            ExpectFunctions {
+                pure: bumpalo::collections::Vec::from_iter_in(kept, arena),
+            },
+            max_children,
+            coverage_points.as_ref().map(|_| &mut coverage_hits),
+            snapshot_mode,
+            |_, result| {
+                let failure = match reporter {
+                    Some(_) if !result.passed => Some(captured.take()),
+                    _ => None,
+                };
+                if let (false, Some(failure)) = (quiet, &failure) {
+                    print!("{failure}");
+                }
+
+                // Results are reported in the order the expects were given in
+                let info = infos[cases.len()].clone();
+                cases.push(TestCase {
+                    info,
+                    result,
+                    failure,
+                });
+
+                if fail_fast && !result.passed {
+                    stopped_early = true;
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        )
+        .unwrap();
 
-            total_failed_count += failed_count;
-            total_passed_count += passed_count;
+        if sarif {
+            // Each failure is a SARIF result on a line of its own
+            let failures = captured.take();
+            sarif_reports.extend(
+                failures
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(String::from),
+            );
+        }
+
+        if let Some(points) = &coverage_points {
+            let packages_dir = cache::roc_cache_packages_dir();
+
+            for (module_id, (module_path, src)) in sources.iter() {
+                // Other people's packages aren't ours to cover
+                if module_path.starts_with(&packages_dir) {
+                    continue;
+                }
+
+                let file = FileCoverage::new(
+                    *module_id,
+                    module_path,
+                    src,
+                    interns,
+                    points,
+                    &coverage_hits,
+                );
+
+                match coverage_files
+                    .iter_mut()
+                    .find(|other| other.path == file.path)
+                {
+                    Some(other) => other.absorb(file),
+                    None => coverage_files.push(file),
+                }
+            }
+        }
+
+        let mut cases = cases.into_iter();
+        let results_by_module: Vec<_> = module_ranges
+            .into_iter()
+            .filter_map(|(module_id, range)| {
+                // With --fail-fast, the later modules' expects may not have run
+                let cases: Vec<TestCase> = cases.by_ref().take(range.len()).collect();
+                if cases.is_empty() {
+                    return None;
+                }
+
+                let failed_count = cases.iter().filter(|case| !case.result.passed).count();
+
+                Some(ModuleTestResults {
+                    module_id,
+                    failed_count,
+                    passed_count: cases.len() - failed_count,
+                    // The expects ran alongside other modules' expects, so this is the time they
+                    // took between them rather than how long the module took to test
+                    tests_duration: cases.iter().map(|case| case.result.duration).sum(),
+                    cases,
+                })
+            })
+            .collect();
+
+        if reporter.is_some() {
+            suites.extend(results_by_module.iter().map(|results| TestSuite {
+                // Modules without expects to run were skipped, so there's always a first one
+                module_name: results.cases[0].info.module_name.clone(),
+                path: sources[&results.module_id].0.clone(),
+                duration: results.tests_duration,
+                cases: results.cases.clone(),
+            }));
         }
 
         let total_duration = start_time.elapsed();
         all_files_total_failed_count += total_failed_count;
         all_files_total_passed_count += total_passed_count;
-        if total_failed_count == 0 && total_passed_count == 0 {
-            // Only report no expectations found once.
-            continue;
-        } else if matches.get_flag(FLAG_VERBOSE) {
-            println!("Compiled in {} ms.", compilation_duration.as_millis());
-            for module_test_results in results_by_module {
-                print_test_results(module_test_results, &sources);
+        // Only report no expectations found once. When quiet, failed expects were printed as they
+        // ran, and the exit code says whether any did.
+        let ran_any = total_failed_count > 0 || total_passed_count > 0;
+        if ran_any && !quiet {
+            if matches.get_flag(FLAG_VERBOSE) {
+                println!("Compiled in {} ms.", compilation_duration.as_millis());
+                for module_test_results in results_by_module {
+                    print_test_results(module_test_results, &sources);
+                }
+            } else {
+                let test_summary_str =
+                    test_summary(total_failed_count, total_passed_count, total_duration);
+                println!("{test_summary_str}");
             }
-        } else {
-            let test_summary_str =
-                test_summary(total_failed_count, total_passed_count, total_duration);
-            println!("{test_summary_str}");
+        }
+
+        if stopped_early {
+            if !quiet {
+                println!("\nStopped after the first failure because of --{FLAG_FAIL_FAST}.");
+            }
+            break;
+        }
+    }
+
+    if let Some(path) = &coverage_path {
+        coverage_files.sort_by(|a, b| a.path.cmp(&b.path));
+        std::fs::write(path, coverage::lcov(&coverage_files))?;
+
+        if !quiet {
+            print!("\n{}", coverage::summary(&coverage_files));
+            println!("\nWrote the lcov report to {}.", path.display());
         }
     }
-    if all_files_total_failed_count == 0 && all_files_total_passed_count == 0 {
+
+    if sarif {
+        print_structured(render, &sarif_reports);
+    }
+
+    if let Some(reporter) = reporter {
+        let report = reporter.render(&suites);
+
+        match report_path {
+            Some(path) => std::fs::write(path, report)?,
+            None => print!("{report}"),
+        }
+    }
+
+    if matches.get_flag(FLAG_LIST) && listed_count > 0 {
+        Ok(0)
+    } else if all_files_total_failed_count == 0 && all_files_total_passed_count == 0 {
         // TODO print this in a more nicely formatted way!
-        println!("No expectations were found.");
+        if quiet {
+            // The exit code says it all
+        } else if let Some(pattern) = matches.get_one::<String>(FLAG_FILTER) {
+            println!("No expectations matched the filter `{pattern}`.");
+        } else {
+            println!("No expectations were found.");
+        }
 
         // If no tests ran, treat that as an error. This is perhaps
         // briefly annoying at the very beginning of a project when
@@ -728,6 +1243,182 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
     }
 }
 
+#[cfg(windows)]
+pub fn bench(_matches: &ArgMatches, _target: Target) -> io::Result<i32> {
+    eprintln!("Running benchmarks does not work on windows right now.");
+
+    Ok(1)
+}
+
+#[cfg(not(windows))]
+pub fn bench(matches: &ArgMatches, target: Target) -> io::Result<i32> {
+    use bench::{Baseline, Change};
+    use roc_build::program::report_problems_monomorphized;
+    use roc_load::{ExecutionMode, FunctionKind, LoadMonomorphizedError};
+    use roc_packaging::cache;
+    use roc_repl_expect::run::{is_benchmark, time_benchmark, ExpectMemory};
+    use std::collections::BTreeMap;
+
+    let start_time = Instant::now();
+    let arena = Bump::new();
+    let render = render_target_from_flags(matches);
+    let filter = matches
+        .get_one::<String>(FLAG_FILTER)
+        .map(|pattern| ExpectFilter::new(pattern));
+    let path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
+
+    let baseline = match matches.get_one::<PathBuf>(FLAG_BASELINE) {
+        Some(baseline_path) => {
+            let json = std::fs::read_to_string(baseline_path)?;
+
+            match Baseline::from_json(&json) {
+                Ok(baseline) => Some(baseline),
+                Err(problem) => user_error!(
+                    "The baseline in {} could not be read: {problem}.",
+                    baseline_path.display()
+                ),
+            }
+        }
+        None => None,
+    };
+
+    if !path.exists() {
+        user_error!(
+            "\nThis file was not found: {}\n\nYou can run `roc help` for more information on how to provide a .roc file.\n",
+            path.display()
+        );
+    }
+
+    let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
+        None => Threading::AllAvailable,
+        Some(0) => user_error!("cannot build with at most 0 threads"),
+        Some(1) => Threading::Single,
+        Some(n) => Threading::AtMost(*n),
+    };
+
+    let load_config = LoadConfig {
+        target,
+        function_kind: FunctionKind::from_env(),
+        render,
+        palette: roc_reporting::report::DEFAULT_PALETTE,
+        threading,
+        exec_mode: ExecutionMode::Test,
+    };
+    let load_result = roc_load::load_and_monomorphize(
+        &arena,
+        path.to_path_buf(),
+        matches.get_one::<PathBuf>(FLAG_MAIN).cloned(),
+        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+        load_config,
+    );
+
+    let mut loaded = match load_result {
+        Ok(loaded) => loaded,
+        Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
+            return handle_loading_problem(problem, render);
+        }
+        Err(LoadMonomorphizedError::ErrorModule(module)) => {
+            return handle_error_module(module, start_time.elapsed(), render);
+        }
+    };
+    let problems = report_problems_monomorphized(&mut loaded, render);
+
+    if problems.warnings > 0 {
+        problems.print_error_warning_count(start_time.elapsed());
+        println!(".\n\nRunning benchmarks…\n");
+    }
+
+    let expectations = std::mem::take(&mut loaded.expectations);
+    let interns = loaded.interns.clone();
+    let sources = loaded.sources.clone();
+
+    // Timing unoptimized code would mostly time what the optimizer would have removed
+    let (dyn_lib, expects_by_module, _) = roc_repl_expect::run::expect_mono_module_to_dylib(
+        &arena,
+        target,
+        loaded,
+        OptLevel::Optimize,
+        LlvmBackendMode::CliTest,
+    )
+    .unwrap();
+
+    let mut benchmarks = Vec::new();
+
+    for (module_id, expects) in expects_by_module.into_iter() {
+        let (module_path, src) = &sources[&module_id];
+        let regions: Vec<_> = expects.pure.iter().map(|expect| expect.region).collect();
+        let infos = describe_expects(
+            interns.module_name(module_id).as_str(),
+            module_path,
+            src,
+            &regions,
+        );
+
+        for (info, expect) in infos.into_iter().zip(expects.pure) {
+            if is_benchmark(&expectations, &interns, &expect)
+                && filter.as_ref().map_or(true, |filter| filter.matches(&info))
+            {
+                benchmarks.push((info, expect));
+            }
+        }
+    }
+    benchmarks.sort_by(|(a, _), (b, _)| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+    if benchmarks.is_empty() {
+        match matches.get_one::<String>(FLAG_FILTER) {
+            Some(pattern) => println!("No benchmarks matched the filter `{pattern}`."),
+            None => println!("No benchmarks were found."),
+        }
+
+        return Ok(2);
+    }
+
+    let shm_name = format!("/roc_expect_buffer_{}", process::id());
+    let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
+
+    let width = benchmarks
+        .iter()
+        .map(|(info, _)| info.name().chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut results = BTreeMap::new();
+    let mut crashed_count = 0;
+
+    for (info, expect) in benchmarks {
+        let name = info.name();
+        let measured =
+            bench::measure(|iterations| time_benchmark(&dyn_lib, &mut memory, expect, iterations));
+
+        match measured {
+            Ok(stats) => {
+                let change = baseline
+                    .as_ref()
+                    .and_then(|baseline| baseline.0.get(&name))
+                    .map(|baseline| Change::new(stats, *baseline));
+
+                println!("{}", bench::result_line(&name, width, stats, change));
+                results.insert(name, stats);
+            }
+            Err(message) => {
+                println!(
+                    "{name:<width$}  {}crashed{}: {message}  ({})",
+                    ANSI_STYLE_CODES.red,
+                    ANSI_STYLE_CODES.reset,
+                    info.location()
+                );
+                crashed_count += 1;
+            }
+        }
+    }
+
+    if let Some(baseline_path) = matches.get_one::<PathBuf>(FLAG_SAVE_BASELINE) {
+        std::fs::write(baseline_path, Baseline(results).to_json())?;
+        println!("\nSaved the baseline to {}.", baseline_path.display());
+    }
+
+    Ok((crashed_count > 0) as i32)
+}
+
 fn find_all_roc_files(path: &PathBuf, flatten_paths: &mut Vec<PathBuf>) {
     if path.is_dir() {
         if let Ok(entries) = std::fs::read_dir(path) {
@@ -763,6 +1454,7 @@ fn print_test_results(
         failed_count,
         passed_count,
         tests_duration,
+        cases,
     } = module_test_results;
 
     let test_summary_str = test_summary(failed_count, passed_count, tests_duration);
@@ -771,6 +1463,22 @@ fn print_test_results(
     let module_name = module_path.file_name().unwrap().to_str().unwrap();
 
     println!("\n{module_name}:\n    {test_summary_str}",);
+
+    let reset = ANSI_STYLE_CODES.reset;
+    for TestCase { info, result, .. } in cases {
+        let outcome = if result.passed {
+            format!("{}passed{reset}", ANSI_STYLE_CODES.green)
+        } else {
+            format!("{}failed{reset}", ANSI_STYLE_CODES.red)
+        };
+
+        println!(
+            "    {outcome} {} (line {}) in {:.1?}",
+            info.name(),
+            info.line,
+            result.duration
+        );
+    }
 }
 
 #[cfg(not(windows))]
@@ -792,7 +1500,10 @@ fn test_summary(failed_count: usize, passed_count: usize, tests_duration: Durati
 /// Find the element of `options` with the smallest edit distance to
 /// `reference`. Returns a tuple containing the element and the distance, or
 /// `None` if the `options` `Vec` is empty.
-fn nearest_match<'a>(reference: &str, options: &'a [String]) -> Option<(&'a String, usize)> {
+pub(crate) fn nearest_match<'a>(
+    reference: &str,
+    options: &'a [String],
+) -> Option<(&'a String, usize)> {
     options
         .iter()
         .map(|s| (s, distance::damerau_levenshtein(reference, s)))
@@ -962,6 +1673,18 @@ pub fn build(
     let emit_debug_info = matches.get_flag(FLAG_PROFILING)
         || matches!(opt_level, OptLevel::Development | OptLevel::Normal);
     let emit_timings = matches.get_flag(FLAG_TIME);
+    // Only `roc build` has --timings
+    let timings_path = matches
+        .try_get_one::<bool>(FLAG_TIMINGS)
+        .ok()
+        .flatten()
+        .filter(|timings| **timings)
+        .map(|_| {
+            matches
+                .get_one::<PathBuf>(FLAG_TIMINGS_OUTPUT)
+                .cloned()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_TIMINGS_PATH))
+        });
 
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
         None => Threading::AllAvailable,
@@ -1007,7 +1730,11 @@ pub fn build(
         fuzz,
     };
 
-    let load_config = standard_load_config(target, build_ordering, threading);
+    let render = render_target_from_flags(matches);
+    let load_config = LoadConfig {
+        render,
+        ..standard_load_config(target, build_ordering, threading)
+    };
 
     let res_binary_path = roc_build::program::build_file(
         &arena,
@@ -1015,6 +1742,7 @@ pub fn build(
         path.to_owned(),
         code_gen_options,
         emit_timings,
+        timings_path.as_deref(),
         link_type,
         linking_strategy,
         build_host,
@@ -1032,7 +1760,10 @@ pub fn build(
             problems,
             total_time,
             expect_metadata,
+            module_paths,
         }) => {
+            report_loaded_modules(&module_paths);
+
             match config {
                 BuildOnly => {
                     // If possible, report the generated executable name relative to the current dir.
@@ -1046,8 +1777,14 @@ pub fn build(
                     // since the process is about to exit anyway.
                     // std::mem::forget(arena);
 
-                    problems.print_error_warning_count(total_time);
-                    println!(" while successfully building:\n\n    {generated_filename}");
+                    if !render.is_structured() {
+                        problems.print_error_warning_count(total_time);
+                        println!(" while successfully building:\n\n    {generated_filename}");
+
+                        if let Some(path) = &timings_path {
+                            println!("\nWrote the build timings to {}.", path.display());
+                        }
+                    }
 
                     // Return a nonzero exit code if there were problems
                     Ok(problems.exit_code())
@@ -1136,9 +1873,13 @@ pub fn build(
             }
         }
         Err(BuildFileError::ErrorModule { module, total_time }) => {
-            handle_error_module(module, total_time)
+            report_loaded_modules(module.sources.values().map(|(path, _)| path));
+            handle_error_module(module, total_time, render)
+        }
+        Err(BuildFileError::LoadingProblem(problem)) => {
+            report_modules_beside([path.as_path()]);
+            handle_loading_problem(problem, render)
         }
-        Err(BuildFileError::LoadingProblem(problem)) => handle_loading_problem(problem),
     }
 }
 
@@ -1,15 +1,19 @@
 //! The `roc` binary that brings together all functionality in the Roc toolset.
 use bumpalo::Bump;
 use roc_build::link::LinkType;
-use roc_build::program::{check_file, CodeGenBackend};
+use roc_build::program::{
+    check_file, handle_loading_problem, CodeGenBackend, DEFAULT_ROC_FILENAME,
+};
 use roc_cli::{
-    annotate_file, build_app, default_linking_strategy, format_files, format_src, test,
-    AnnotationProblem, BuildConfig, FormatMode, CMD_BUILD, CMD_CHECK, CMD_DEV, CMD_DOCS,
-    CMD_FORMAT, CMD_FORMAT_ANNOTATE, CMD_GLUE, CMD_LICENSES, CMD_PREPROCESS_HOST, CMD_REPL,
-    CMD_RUN, CMD_TEST, CMD_VERSION, DIRECTORY_OR_FILES, FLAG_CHECK, FLAG_DEV, FLAG_DOCS_ROOT,
-    FLAG_LIB, FLAG_MAIN, FLAG_MIGRATE, FLAG_NO_COLOR, FLAG_NO_HEADER, FLAG_NO_LINK, FLAG_OUTPUT,
-    FLAG_PP_DYLIB, FLAG_PP_HOST, FLAG_PP_PLATFORM, FLAG_STDIN, FLAG_STDOUT, FLAG_TARGET, FLAG_TIME,
-    FLAG_VERBOSE, GLUE_DIR, GLUE_SPEC, ROC_FILE, VERSION,
+    annotate_file, bench, build_app, default_linking_strategy, deps, format_files, format_src,
+    render_target_from_flags, report_loaded_modules, report_modules_beside, test, watch, why,
+    AnnotationProblem, BuildConfig, FormatMode, CMD_BENCH, CMD_BUILD, CMD_CHECK, CMD_DEPS, CMD_DEV,
+    CMD_DOCS, CMD_FORMAT, CMD_FORMAT_ANNOTATE, CMD_GLUE, CMD_LICENSES, CMD_PREPROCESS_HOST,
+    CMD_REPL, CMD_RUN, CMD_TEST, CMD_VERSION, CMD_WHY, DIRECTORY_OR_FILES, FLAG_APP, FLAG_CHECK,
+    FLAG_DEV, FLAG_DOCS_ROOT, FLAG_LIB, FLAG_MAIN, FLAG_MIGRATE, FLAG_NO_COLOR, FLAG_NO_HEADER,
+    FLAG_NO_LINK, FLAG_OUTPUT, FLAG_PP_DYLIB, FLAG_PP_HOST, FLAG_PP_PLATFORM, FLAG_STDIN,
+    FLAG_STDOUT, FLAG_TARGET, FLAG_TIME, FLAG_VERBOSE, FLAG_WATCH, GLUE_DIR, GLUE_SPEC, ROC_FILE,
+    VERSION,
 };
 use roc_docs::generate_docs_html;
 use roc_error_macros::{internal_error, user_error};
@@ -17,6 +21,9 @@ use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
 use roc_load::{LoadingProblem, Threading};
 use roc_packaging::cache::{self, RocCacheDir};
+use roc_parse::ast::{Header, SpacesBefore};
+use roc_parse::header::parse_header;
+use roc_parse::state::State;
 use roc_target::Target;
 use std::fs::{self, FileType};
 use std::io::BufRead;
@@ -80,6 +87,15 @@ fn main() -> io::Result<()> {
                 Ok(1)
             }
         }
+        Some((CMD_TEST, matches)) if matches.get_flag(FLAG_WATCH) => {
+            let roots: Vec<PathBuf> = matches
+                .get_many::<PathBuf>(ROC_FILE)
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+
+            watch(&roots)
+        }
         Some((CMD_TEST, matches)) => {
             if matches.contains_id(ROC_FILE) {
                 test(matches, Triple::host().into())
@@ -89,6 +105,18 @@ fn main() -> io::Result<()> {
                 Ok(1)
             }
         }
+        Some((CMD_BENCH, matches)) => bench(matches, Triple::host().into()),
+        Some((CMD_DEPS, matches)) => deps(matches),
+        Some((CMD_WHY, matches)) => why(matches),
+        Some((CMD_DEV | CMD_CHECK, matches)) if matches.get_flag(FLAG_WATCH) => {
+            let roots: Vec<PathBuf> = matches
+                .get_one::<PathBuf>(ROC_FILE)
+                .cloned()
+                .into_iter()
+                .collect();
+
+            watch(&roots)
+        }
         Some((CMD_DEV, matches)) => {
             if matches.contains_id(ROC_FILE) {
                 build(
@@ -226,6 +254,7 @@ fn main() -> io::Result<()> {
             };
 
             let opt_main_path = matches.get_one::<PathBuf>(FLAG_MAIN);
+            let render = render_target_from_flags(matches);
 
             match roc_file_path.extension().and_then(OsStr::to_str) {
                 Some("md") => {
@@ -264,18 +293,19 @@ fn main() -> io::Result<()> {
                             emit_timings,
                             RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
                             threading,
+                            render,
                         ) {
-                            Ok((problems, total_time)) => {
-                                problems.print_error_warning_count(total_time);
-                                println!(".\n");
+                            Ok((problems, total_time, _)) => {
+                                if !render.is_structured() {
+                                    problems.print_error_warning_count(total_time);
+                                    println!(".\n");
+                                }
 
                                 exit_code = problems.exit_code();
                             }
 
-                            Err(LoadingProblem::FormattedReport(report, _)) => {
-                                print!("{report}");
-
-                                exit_code = 1;
+                            Err(problem @ LoadingProblem::FormattedReport(..)) => {
+                                exit_code = handle_loading_problem(problem, render)?;
                             }
                             Err(other) => {
                                 panic!("build_file failed with error:\n{other:?}");
@@ -297,17 +327,20 @@ fn main() -> io::Result<()> {
                         emit_timings,
                         RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
                         threading,
+                        render,
                     ) {
-                        Ok((problems, total_time)) => {
-                            problems.print_error_warning_count(total_time);
-                            println!(".\n");
+                        Ok((problems, total_time, module_paths)) => {
+                            report_loaded_modules(&module_paths);
+                            if !render.is_structured() {
+                                problems.print_error_warning_count(total_time);
+                                println!(".\n");
+                            }
                             Ok(problems.exit_code())
                         }
 
-                        Err(LoadingProblem::FormattedReport(report, _)) => {
-                            print!("{report}");
-
-                            Ok(1)
+                        Err(problem @ LoadingProblem::FormattedReport(..)) => {
+                            report_modules_beside([roc_file_path.as_path()]);
+                            handle_loading_problem(problem, render)
                         }
                         Err(other) => {
                             panic!("build_file failed with error:\n{other:?}");
@@ -319,8 +352,23 @@ fn main() -> io::Result<()> {
         Some((CMD_REPL, matches)) => {
             let has_color = !matches.get_one::<bool>(FLAG_NO_COLOR).unwrap();
             let has_header = !matches.get_one::<bool>(FLAG_NO_HEADER).unwrap();
+            let opt_app_path = match matches.get_one::<PathBuf>(FLAG_APP) {
+                Some(app_path) if !app_path.is_file() => {
+                    user_error!("The app {} does not exist.", app_path.display())
+                }
+                Some(app_path) if !is_app(app_path) => {
+                    user_error!("{} is not an app.", app_path.display())
+                }
+                Some(app_path) => Some(app_path.clone()),
+                None => {
+                    // A main.roc that's a module, package, or platform has no packages to import
+                    let main_path = PathBuf::from(DEFAULT_ROC_FILENAME);
+
+                    is_app(&main_path).then_some(main_path)
+                }
+            };
 
-            Ok(roc_repl_cli::main(has_color, has_header))
+            Ok(roc_repl_cli::main(has_color, has_header, opt_app_path))
         }
         Some((CMD_DOCS, matches)) => {
             let root_path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
@@ -480,7 +528,9 @@ fn main() -> io::Result<()> {
                     }
                 }
             } else {
-                match format_files(roc_files, format_mode, migrate) {
+                let render = render_target_from_flags(matches);
+
+                match format_files(roc_files, format_mode, migrate, render) {
                     Ok(()) => 0,
                     Err(message) => {
                         eprintln!("{message}");
@@ -505,6 +555,25 @@ fn main() -> io::Result<()> {
     std::process::exit(exit_code);
 }
 
+/// Whether the file at `path` has an app header
+fn is_app(path: &Path) -> bool {
+    let Ok(src) = fs::read_to_string(path) else {
+        return false;
+    };
+    let arena = Bump::new();
+
+    matches!(
+        parse_header(&arena, State::new(src.as_bytes())),
+        Ok((
+            SpacesBefore {
+                item: Header::App(_),
+                ..
+            },
+            _
+        ))
+    )
+}
+
 fn read_all_roc_files(
     dir: &OsString,
     roc_file_paths: &mut Vec<OsString>,
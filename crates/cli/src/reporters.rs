@@ -0,0 +1,298 @@
+//! `roc test --reporter`, for CI systems that read test results in a standard format rather
+//! than the summary we print for people.
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use roc_repl_expect::run::ExpectResult;
+
+use crate::expects::ExpectInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reporter {
+    /// JUnit XML, one `<testsuite>` per module
+    Junit,
+    /// The Test Anything Protocol, version 13
+    Tap,
+}
+
+impl Reporter {
+    /// `None` is the default, human-readable output.
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "junit" => Some(Reporter::Junit),
+            "tap" => Some(Reporter::Tap),
+            _ => None,
+        }
+    }
+
+    pub fn render(self, suites: &[TestSuite]) -> String {
+        match self {
+            Reporter::Junit => junit(suites),
+            Reporter::Tap => tap(suites),
+        }
+    }
+}
+
+/// How one top-level expect went.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub info: ExpectInfo,
+    pub result: ExpectResult,
+    /// What the expect printed when it failed, e.g. the values of the variables it used.
+    pub failure: Option<String>,
+}
+
+/// The expects that ran in one module.
+#[derive(Debug, Clone)]
+pub struct TestSuite {
+    pub module_name: String,
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn failures(&self) -> usize {
+        self.cases.iter().filter(|case| !case.result.passed).count()
+    }
+}
+
+/// A writer for expects to report their failures to, which keeps what they wrote around until
+/// it's [taken](CapturedOutput::take). Clones share the same buffer.
+#[derive(Debug, Clone, Default)]
+pub struct CapturedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl CapturedOutput {
+    /// Everything written since the last call.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl io::Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn junit(suites: &[TestSuite]) -> String {
+    let tests: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    let failures: usize = suites.iter().map(TestSuite::failures).sum();
+    let time: Duration = suites.iter().map(|suite| suite.duration).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="roc test" tests="{tests}" failures="{failures}" time="{}">"#,
+        seconds(time)
+    );
+
+    for suite in suites {
+        let file = escape_xml(&suite.path.to_string_lossy());
+
+        let _ = writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{}" file="{file}">"#,
+            escape_xml(&suite.module_name),
+            suite.cases.len(),
+            suite.failures(),
+            seconds(suite.duration),
+        );
+
+        for case in suite.cases.iter() {
+            let _ = write!(
+                xml,
+                r#"    <testcase name="{}" classname="{}" file="{file}" line="{}" time="{}""#,
+                escape_xml(&case.info.name()),
+                escape_xml(&suite.module_name),
+                case.info.line,
+                seconds(case.result.duration),
+            );
+
+            if case.result.passed {
+                xml.push_str("/>\n");
+            } else {
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"expect failed at {}\">{}</failure>\n    </testcase>",
+                    escape_xml(&case.info.location()),
+                    escape_xml(case.failure.as_deref().unwrap_or_default().trim()),
+                );
+            }
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn tap(suites: &[TestSuite]) -> String {
+    let tests: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+
+    let mut tap = format!("TAP version 13\n1..{tests}\n");
+    let mut number = 0;
+
+    for suite in suites {
+        let _ = writeln!(
+            tap,
+            "# {} ({}) in {} ms",
+            suite.module_name,
+            suite.path.display(),
+            suite.duration.as_millis()
+        );
+
+        for case in suite.cases.iter() {
+            number += 1;
+
+            let status = if case.result.passed { "ok" } else { "not ok" };
+            let _ = writeln!(tap, "{status} {number} - {}", case.info.name());
+
+            if !case.result.passed {
+                tap.push_str("  ---\n  message: |\n");
+                for line in case.failure.as_deref().unwrap_or_default().trim().lines() {
+                    match line {
+                        "" => tap.push('\n'),
+                        line => {
+                            let _ = writeln!(tap, "    {line}");
+                        }
+                    }
+                }
+                let _ = writeln!(tap, "  at: {}", case.info.location());
+                let _ = writeln!(
+                    tap,
+                    "  duration_ms: {:.3}",
+                    case.result.duration.as_secs_f64() * 1000.0
+                );
+                tap.push_str("  ...\n");
+            }
+        }
+    }
+
+    tap
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace aren't allowed in XML 1.0 at all
+            ch if ch.is_control() && !matches!(ch, '\n' | '\r' | '\t') => {}
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite() -> TestSuite {
+        TestSuite {
+            module_name: "Math".to_string(),
+            path: PathBuf::from("Math.roc"),
+            duration: Duration::from_millis(12),
+            cases: vec![
+                TestCase {
+                    info: ExpectInfo {
+                        module_name: "Math".to_string(),
+                        path: PathBuf::from("Math.roc"),
+                        line: 3,
+                        def_name: Some("add".to_string()),
+                    },
+                    result: ExpectResult {
+                        passed: true,
+                        duration: Duration::from_millis(2),
+                    },
+                    failure: None,
+                },
+                TestCase {
+                    info: ExpectInfo {
+                        module_name: "Math".to_string(),
+                        path: PathBuf::from("Math.roc"),
+                        line: 8,
+                        def_name: Some("lt".to_string()),
+                    },
+                    result: ExpectResult {
+                        passed: false,
+                        duration: Duration::from_millis(2),
+                    },
+                    failure: Some(
+                        "This expectation failed:\n\nexpect lt 2 1 == (1 < 2)\n".to_string(),
+                    ),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn junit_has_a_testcase_per_expect() {
+        let xml = Reporter::Junit.render(&[suite()]);
+
+        assert_eq!(
+            xml,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="roc test" tests="2" failures="1" time="0.012">
+  <testsuite name="Math" tests="2" failures="1" time="0.012" file="Math.roc">
+    <testcase name="Math.add" classname="Math" file="Math.roc" line="3" time="0.002"/>
+    <testcase name="Math.lt" classname="Math" file="Math.roc" line="8" time="0.002">
+      <failure message="expect failed at Math.roc:8">This expectation failed:
+
+expect lt 2 1 == (1 &lt; 2)</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn tap_numbers_every_expect() {
+        let tap = Reporter::Tap.render(&[suite()]);
+
+        assert_eq!(
+            tap,
+            indoc::indoc! {"
+                TAP version 13
+                1..2
+                # Math (Math.roc) in 12 ms
+                ok 1 - Math.add
+                not ok 2 - Math.lt
+                  ---
+                  message: |
+                    This expectation failed:
+
+                    expect lt 2 1 == (1 < 2)
+                  at: Math.roc:8
+                  duration_ms: 2.000
+                  ...
+            "}
+        );
+    }
+}
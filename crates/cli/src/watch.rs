@@ -0,0 +1,425 @@
+//! Re-running `roc check`, `roc test` and `roc dev` whenever a module they load changes.
+//!
+//! Each run happens in a child `roc` process, which reports the modules it loaded back through a
+//! file. That way `roc dev` can be stopped mid-run, and modules that get newly imported are
+//! watched from the next run on.
+//!
+//! This isn't incremental: each run loads and checks every module from scratch, just like running
+//! the command again by hand would.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::FLAG_WATCH;
+
+/// Set on the child process to the path of the file it should list its loaded modules in.
+const ROC_WATCH_MODULES: &str = "ROC_WATCH_MODULES";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long files have to stay unchanged before we re-run, so that saving several files at once
+/// only causes one run.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// The time each watched file was last modified and its length, or `None` if it doesn't exist.
+/// The length catches saves that the file system's timestamps are too coarse to tell apart.
+type Snapshot = BTreeMap<PathBuf, Option<(SystemTime, u64)>>;
+
+/// Runs the current `roc` command without `--watch` in a loop, starting again each time one of
+/// `roots` or the modules they import change. Only returns once interrupted, or if running `roc`
+/// fails.
+pub fn watch(roots: &[PathBuf]) -> io::Result<i32> {
+    let roc = env::current_exe()?;
+    let args = args_without_watch_flag(env::args_os().skip(1));
+    let modules_file = tempfile::NamedTempFile::new()?;
+
+    // Runs are in their own process group, which Ctrl-C doesn't reach, so we stop them ourselves
+    let interrupted = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&interrupted))?;
+    }
+
+    let mut files: Vec<PathBuf> = roots.to_vec();
+
+    loop {
+        // Take the snapshot before starting, so edits made during the run aren't missed
+        let mut modified_times = snapshot(&files);
+        fs::write(modules_file.path(), "")?;
+
+        clear_screen()?;
+        let mut command = Command::new(&roc);
+        command
+            .args(&args)
+            .env(ROC_WATCH_MODULES, modules_file.path());
+        let mut child = spawn_in_own_group(&mut command)?;
+
+        let mut running = true;
+        let mut reported = String::new();
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            if interrupted.load(Ordering::Relaxed) {
+                stop(&mut child, running)?;
+                return Ok(1);
+            }
+
+            if running {
+                if let Some(status) = child.try_wait()? {
+                    running = false;
+                    print_finished(status)?;
+                }
+            }
+
+            // Runs that load several root modules report each one's imports as they go
+            let contents = fs::read_to_string(modules_file.path()).unwrap_or_default();
+            if contents != reported {
+                files = watched_files(roots, &contents);
+                reported = contents;
+
+                // Keep the times from before the run for the files we were already watching
+                let before = std::mem::take(&mut modified_times);
+                modified_times = snapshot(&files)
+                    .into_iter()
+                    .map(|(path, now)| {
+                        let then = before.get(&path).copied().unwrap_or(now);
+                        (path, then)
+                    })
+                    .collect();
+            }
+
+            if snapshot(&files) != modified_times {
+                wait_until_settled(&files, thread::sleep);
+                stop(&mut child, running)?;
+                break;
+            }
+        }
+    }
+}
+
+/// Lists the modules that were loaded for `roc ... --watch`, so it knows which files to watch.
+/// Does nothing when `roc` isn't being watched.
+pub fn report_loaded_modules<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) {
+    let Some(modules_file) = env::var_os(ROC_WATCH_MODULES) else {
+        return;
+    };
+
+    // If this fails, the root modules are still watched, which is the best we can do anyway.
+    let _ = append_modules(Path::new(&modules_file), paths);
+}
+
+/// Lists every module in the directories of `roots` for `roc ... --watch`, for when loading
+/// failed before it was known which modules they import. That way fixing a module that failed to
+/// load starts a new run, even if it isn't one of the roots.
+pub fn report_modules_beside<'a>(roots: impl IntoIterator<Item = &'a Path>) {
+    if env::var_os(ROC_WATCH_MODULES).is_none() {
+        return;
+    }
+
+    let mut modules = vec![];
+    for root in roots {
+        let dir = match root.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        find_modules(dir, &mut modules);
+    }
+
+    report_loaded_modules(&modules);
+}
+
+fn find_modules(dir: &Path, modules: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+        if path.is_dir() && !is_hidden {
+            find_modules(&path, modules);
+        } else if path.extension().is_some_and(|extension| extension == "roc") {
+            modules.push(path);
+        }
+    }
+}
+
+fn append_modules<'a>(
+    modules_file: &Path,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+) -> io::Result<()> {
+    let mut contents = String::new();
+    for path in paths {
+        contents.push_str(&path.to_string_lossy());
+        contents.push('\n');
+    }
+
+    OpenOptions::new()
+        .append(true)
+        .open(modules_file)?
+        .write_all(contents.as_bytes())
+}
+
+/// The roots, along with every module that runs reported loading.
+fn watched_files(roots: &[PathBuf], reported: &str) -> Vec<PathBuf> {
+    let modules = reported.lines().map(PathBuf::from);
+    let all_files: BTreeSet<PathBuf> = roots.iter().cloned().chain(modules).collect();
+
+    all_files.into_iter().collect()
+}
+
+fn args_without_watch_flag(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let watch_flag = OsString::from(format!("--{FLAG_WATCH}"));
+    let mut args: Vec<OsString> = args.into_iter().collect();
+
+    // Only the first one before `--` is ours; any others are arguments for the app
+    let index = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .position(|arg| *arg == watch_flag);
+    if let Some(index) = index {
+        args.remove(index);
+    }
+
+    args
+}
+
+fn snapshot(files: &[PathBuf]) -> Snapshot {
+    files
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).ok().and_then(|metadata| {
+                let time = metadata.modified().ok()?;
+
+                Some((time, metadata.len()))
+            });
+
+            (path.clone(), modified)
+        })
+        .collect()
+}
+
+/// Waits with `sleep` until `files` have stayed unchanged for [DEBOUNCE].
+fn wait_until_settled(files: &[PathBuf], mut sleep: impl FnMut(Duration)) {
+    let mut previous = snapshot(files);
+
+    loop {
+        sleep(DEBOUNCE);
+
+        let current = snapshot(files);
+        if current == previous {
+            return;
+        }
+
+        previous = current;
+    }
+}
+
+/// Starts a run in a process group of its own, so that stopping it also stops the app that
+/// `roc dev` forks off.
+#[cfg(unix)]
+fn spawn_in_own_group(command: &mut Command) -> io::Result<Child> {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0).spawn()
+}
+
+#[cfg(not(unix))]
+fn spawn_in_own_group(command: &mut Command) -> io::Result<Child> {
+    command.spawn()
+}
+
+fn stop(child: &mut Child, running: bool) -> io::Result<()> {
+    if !running {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+
+    // The child may have exited since we last checked, in which case this fails harmlessly.
+    #[cfg(not(unix))]
+    let _ = child.kill();
+
+    child.wait()?;
+
+    Ok(())
+}
+
+fn clear_screen() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1B[2J\x1B[H")?;
+    stdout.flush()
+}
+
+fn print_finished(status: ExitStatus) -> io::Result<()> {
+    let outcome = match status.code() {
+        Some(0) => "\x1B[32mFinished\x1B[39m".to_string(),
+        Some(code) => format!("\x1B[31mExited with code {code}\x1B[39m"),
+        None => "\x1B[31mStopped by a signal\x1B[39m".to_string(),
+    };
+
+    let mut stdout = io::stdout();
+    writeln!(
+        stdout,
+        "\n{outcome}. Watching for changes… (Press Ctrl-C to stop.)"
+    )?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_strings(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn removes_only_our_watch_flag() {
+        let args = args_without_watch_flag(os_strings(&[
+            "dev",
+            "--watch",
+            "main.roc",
+            "--",
+            "--watch",
+            "input.txt",
+        ]));
+
+        assert_eq!(
+            args,
+            os_strings(&["dev", "main.roc", "--", "--watch", "input.txt"])
+        );
+    }
+
+    #[test]
+    fn keeps_watch_flag_meant_for_the_app() {
+        let args = args_without_watch_flag(os_strings(&["dev", "main.roc", "--", "--watch"]));
+
+        assert_eq!(args, os_strings(&["dev", "main.roc", "--", "--watch"]));
+    }
+
+    #[test]
+    fn snapshot_notices_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let resized = dir.path().join("Main.roc");
+        let touched = dir.path().join("Parser.roc");
+        let missing = dir.path().join("Missing.roc");
+        fs::write(&resized, "module []\n").unwrap();
+        fs::write(&touched, "module []\n").unwrap();
+
+        let files = vec![resized.clone(), touched.clone(), missing.clone()];
+        let before = snapshot(&files);
+        assert!(before[&resized].is_some());
+        assert_eq!(before[&missing], None);
+
+        let set_modified = |path: &Path, time: SystemTime| {
+            let file = OpenOptions::new().write(true).open(path).unwrap();
+            file.set_modified(time).unwrap();
+        };
+        let (modified, _) = before[&touched].unwrap();
+
+        // A save within the same timestamp still changes the length
+        fs::write(&resized, "module [x]\n\nx = 1\n").unwrap();
+        set_modified(&resized, before[&resized].unwrap().0);
+        // A save that keeps the length still changes the timestamp
+        fs::write(&touched, "module []\n").unwrap();
+        set_modified(&touched, modified + Duration::from_secs(1));
+        fs::write(&missing, "module []\n").unwrap();
+
+        let after = snapshot(&files);
+        assert_ne!(after[&resized], before[&resized]);
+        assert_ne!(after[&touched], before[&touched]);
+        assert!(after[&missing].is_some());
+    }
+
+    #[test]
+    fn waits_until_files_stop_changing() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("Main.roc");
+        fs::write(&module, "module []\n").unwrap();
+
+        // The file is saved again during each wait, until the saves stop
+        let saves = 4;
+        let mut waits = 0;
+        wait_until_settled(&[module.clone()], |duration| {
+            assert_eq!(duration, DEBOUNCE);
+
+            if waits < saves {
+                let contents = format!("module []\n{}\n", "#".repeat(waits + 1));
+                fs::write(&module, contents).unwrap();
+            }
+
+            waits += 1;
+        });
+
+        // One more wait, to see that the last save was the last one
+        assert_eq!(waits, saves + 1);
+    }
+
+    #[test]
+    fn watches_reported_modules() {
+        let modules_file = tempfile::NamedTempFile::new().unwrap();
+        let roots = vec![PathBuf::from("main.roc"), PathBuf::from("test.roc")];
+
+        // Each root reports its own imports, which may overlap
+        append_modules(
+            modules_file.path(),
+            &[PathBuf::from("main.roc"), PathBuf::from("Parser.roc")],
+        )
+        .unwrap();
+        append_modules(
+            modules_file.path(),
+            &[PathBuf::from("test.roc"), PathBuf::from("Parser.roc")],
+        )
+        .unwrap();
+
+        let reported = fs::read_to_string(modules_file.path()).unwrap();
+
+        assert_eq!(
+            watched_files(&roots, &reported),
+            vec![
+                PathBuf::from("Parser.roc"),
+                PathBuf::from("main.roc"),
+                PathBuf::from("test.roc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_modules_beside_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("Parser")).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join("main.roc"), "").unwrap();
+        fs::write(dir.path().join("Parser").join("Json.roc"), "").unwrap();
+        fs::write(dir.path().join(".git").join("Stale.roc"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+
+        let mut modules = vec![];
+        find_modules(dir.path(), &mut modules);
+        modules.sort();
+
+        assert_eq!(
+            modules,
+            vec![
+                dir.path().join("Parser").join("Json.roc"),
+                dir.path().join("main.roc"),
+            ]
+        );
+    }
+}
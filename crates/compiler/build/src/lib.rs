@@ -6,3 +6,4 @@ pub mod link;
 pub mod llvm_passes;
 pub mod program;
 pub mod target;
+pub mod timings;
@@ -1,4 +1,5 @@
 use crate::link::{link, preprocess_host_wasm32, rebuild_host, LinkType, LinkingStrategy};
+use crate::timings::Trace;
 use bumpalo::collections::CollectIn;
 use bumpalo::Bump;
 use inkwell::memory_buffer::MemoryBuffer;
@@ -13,7 +14,7 @@ use roc_load::{
 use roc_mono::ir::{OptLevel, SingleEntryPoint};
 use roc_packaging::cache::RocCacheDir;
 use roc_reporting::{
-    cli::{report_problems, Problems},
+    cli::{print_structured, report_problems, Problems},
     report::{RenderTarget, DEFAULT_PALETTE},
 };
 use roc_target::{Architecture, Target};
@@ -36,21 +37,26 @@ pub struct CodeGenTiming {
     pub total: Duration,
 }
 
-pub fn report_problems_monomorphized(loaded: &mut MonomorphizedModule) -> Problems {
+pub fn report_problems_monomorphized(
+    loaded: &mut MonomorphizedModule,
+    render: RenderTarget,
+) -> Problems {
     report_problems(
         &loaded.sources,
         &loaded.interns,
         &mut loaded.can_problems,
         &mut loaded.type_problems,
+        render,
     )
 }
 
-pub fn report_problems_typechecked(loaded: &mut LoadedModule) -> Problems {
+pub fn report_problems_typechecked(loaded: &mut LoadedModule, render: RenderTarget) -> Problems {
     report_problems(
         &loaded.sources,
         &loaded.interns,
         &mut loaded.can_problems,
         &mut loaded.type_problems,
+        render,
     )
 }
 
@@ -614,6 +620,8 @@ pub struct BuiltFile<'a> {
     pub problems: Problems,
     pub total_time: Duration,
     pub expect_metadata: ExpectMetadata<'a>,
+    /// The paths of all the modules that were loaded
+    pub module_paths: Vec<PathBuf>,
 }
 
 pub enum BuildOrdering {
@@ -650,27 +658,42 @@ impl<'a> BuildFileError<'a> {
 pub fn handle_error_module(
     mut module: roc_load::LoadedModule,
     total_time: std::time::Duration,
+    render: RenderTarget,
 ) -> std::io::Result<i32> {
     debug_assert!(module.total_problems() > 0);
 
-    let problems = report_problems_typechecked(&mut module);
+    let problems = report_problems_typechecked(&mut module, render);
 
-    problems.print_error_warning_count(total_time);
+    if !render.is_structured() {
+        problems.print_error_warning_count(total_time);
+    }
 
     Ok(problems.exit_code())
 }
 
-pub fn handle_loading_problem(problem: LoadingProblem) -> std::io::Result<i32> {
+pub fn handle_loading_problem(
+    problem: LoadingProblem,
+    render: RenderTarget,
+) -> std::io::Result<i32> {
+    let report = loading_problem_report(problem);
+
+    if render.is_structured() {
+        print_structured(render, [&report]);
+    } else {
+        print!("{report}");
+    }
+
+    Ok(1)
+}
+
+/// The report for a problem that stopped loading, as load rendered it.
+pub fn loading_problem_report(problem: LoadingProblem) -> String {
     match problem {
-        LoadingProblem::FormattedReport(report, _) => {
-            print!("{report}");
-            Ok(1)
-        }
+        LoadingProblem::FormattedReport(report, _) => report,
         _ => {
             // TODO: tighten up the types here, we should always end up with a
             // formatted report from load.
-            println!("Failed with error: {problem:?}");
-            Ok(1)
+            format!("Failed with error: {problem:?}\n")
         }
     }
 }
@@ -702,6 +725,7 @@ pub fn build_file<'a>(
     app_module_path: PathBuf,
     code_gen_options: CodeGenOptions,
     emit_timings: bool,
+    timings_path: Option<&Path>,
     link_type: LinkType,
     linking_strategy: LinkingStrategy,
     build_host: bool,
@@ -713,6 +737,7 @@ pub fn build_file<'a>(
     verbose: bool,
 ) -> Result<BuiltFile<'a>, BuildFileError<'a>> {
     let compilation_start = Instant::now();
+    let render = load_config.render;
 
     let loaded = roc_load::load_and_monomorphize(
         arena,
@@ -729,12 +754,14 @@ pub fn build_file<'a>(
         app_module_path,
         code_gen_options,
         emit_timings,
+        timings_path,
         link_type,
         linking_strategy,
         build_host,
         suppress_build_host_warning,
         wasm_dev_stack_bytes,
         loaded,
+        render,
         compilation_start,
         out_path,
         verbose,
@@ -806,12 +833,14 @@ fn build_loaded_file<'a>(
     app_module_path: PathBuf,
     code_gen_options: CodeGenOptions,
     emit_timings: bool,
+    timings_path: Option<&Path>,
     link_type: LinkType,
     linking_strategy: LinkingStrategy,
     build_host_requested: bool,
     suppress_build_host_warning: bool,
     wasm_dev_stack_bytes: Option<u32>,
     loaded: roc_load::MonomorphizedModule<'a>,
+    render: RenderTarget,
     compilation_start: Instant,
     out_path: Option<&Path>,
     verbose: bool,
@@ -862,6 +891,12 @@ fn build_loaded_file<'a>(
             }
         };
 
+    let mut trace = timings_path.map(|_| {
+        let mut trace = Trace::new(compilation_start);
+        trace.add_modules(&loaded.timings, &loaded.interns);
+        trace
+    });
+
     let buf = &mut String::with_capacity(1024);
 
     let mut it = loaded.timings.iter().peekable();
@@ -890,9 +925,16 @@ fn build_loaded_file<'a>(
     // This only needs to be mutable for report_problems. This can't be done
     // inside a nested scope without causing a borrow error!
     let mut loaded = loaded;
-    let problems = report_problems_monomorphized(&mut loaded);
+    let problems = report_problems_monomorphized(&mut loaded, render);
     let loaded = loaded;
 
+    let module_paths = loaded
+        .sources
+        .values()
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    let code_gen_start = Instant::now();
     let (roc_app_bytes, code_gen_timing, expect_metadata) = gen_from_mono_module(
         arena,
         loaded,
@@ -903,6 +945,10 @@ fn build_loaded_file<'a>(
         wasm_dev_stack_bytes,
     );
 
+    if let Some(trace) = &mut trace {
+        trace.add("Code generation", code_gen_start, Instant::now());
+    }
+
     buf.push('\n');
     buf.push_str("    ");
     buf.push_str("Code Generation");
@@ -1030,6 +1076,17 @@ fn build_loaded_file<'a>(
         println!("Finished linking in {} ms\n", linking_time.as_millis());
     }
 
+    if let (Some(mut trace), Some(path)) = (trace, timings_path) {
+        trace.add("Link", link_start, Instant::now());
+
+        if let Err(error) = std::fs::write(path, trace.to_json()) {
+            eprintln!(
+                "Could not write the build timings to {}: {error}",
+                path.display()
+            );
+        }
+    }
+
     let total_time = compilation_start.elapsed();
 
     Ok(BuiltFile {
@@ -1037,6 +1094,7 @@ fn build_loaded_file<'a>(
         problems,
         total_time,
         expect_metadata,
+        module_paths,
     })
 }
 
@@ -1327,6 +1385,7 @@ fn spawn_legacy_host_build_thread(
     })
 }
 
+/// Typechecks a file, returning its problems along with the paths of all the modules it loaded.
 #[allow(clippy::too_many_arguments)]
 pub fn check_file<'a>(
     arena: &'a Bump,
@@ -1335,7 +1394,8 @@ pub fn check_file<'a>(
     emit_timings: bool,
     roc_cache_dir: RocCacheDir<'_>,
     threading: Threading,
-) -> Result<(Problems, Duration), LoadingProblem<'a>> {
+    render: RenderTarget,
+) -> Result<(Problems, Duration, Vec<PathBuf>), LoadingProblem<'a>> {
     let compilation_start = Instant::now();
 
     // only used for generating errors. We don't do code generation, so hardcoding should be fine
@@ -1347,8 +1407,7 @@ pub fn check_file<'a>(
     let load_config = LoadConfig {
         target,
         function_kind: FunctionKind::from_env(),
-        // TODO: expose this from CLI?
-        render: RenderTarget::ColorTerminal,
+        render,
         palette: DEFAULT_PALETTE,
         threading,
         exec_mode: ExecutionMode::Check,
@@ -1403,7 +1462,17 @@ pub fn check_file<'a>(
         println!("Finished checking in {} ms\n", compilation_end.as_millis(),);
     }
 
-    Ok((report_problems_typechecked(&mut loaded), compilation_end))
+    let module_paths = loaded
+        .sources
+        .values()
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    Ok((
+        report_problems_typechecked(&mut loaded, render),
+        compilation_end,
+        module_paths,
+    ))
 }
 
 pub fn build_str_test<'a>(
@@ -1432,6 +1501,7 @@ pub fn build_str_test<'a>(
     let threading = Threading::AtMost(2);
 
     let load_config = standard_load_config(target, build_ordering, threading);
+    let render = load_config.render;
 
     let compilation_start = std::time::Instant::now();
 
@@ -1462,6 +1532,7 @@ pub fn build_str_test<'a>(
         suppress_build_host_warning,
         wasm_dev_stack_bytes,
         loaded,
+        render,
         compilation_start,
         None,
         false,
@@ -0,0 +1,188 @@
+//! `roc build --timings`, which writes how long each phase of the build took for each module, and
+//! on which thread, in the Chrome trace event format. Trace viewers like Perfetto
+//! (https://ui.perfetto.dev) and chrome://tracing show it as a timeline per thread.
+
+use roc_collections::MutMap;
+use roc_load::{current_thread_index, ModuleTiming};
+use roc_module::symbol::{Interns, ModuleId};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+/// All the events of a trace happen in this one process.
+const PID: u32 = 1;
+
+/// The phases of a build, relative to when it started.
+pub struct Trace {
+    start: Instant,
+    events: Vec<Value>,
+    threads: BTreeSet<usize>,
+}
+
+impl Trace {
+    pub fn new(start: Instant) -> Self {
+        Trace {
+            start,
+            events: Vec::new(),
+            threads: BTreeSet::new(),
+        }
+    }
+
+    /// Adds the phases that each module went through while loading.
+    pub fn add_modules(&mut self, timings: &MutMap<ModuleId, ModuleTiming>, interns: &Interns) {
+        for (module_id, module_timing) in timings.iter() {
+            let module_name = match interns.module_name(*module_id).as_str() {
+                "" => "Application Module",
+                module_name => module_name,
+            };
+
+            for span in module_timing.spans.iter() {
+                self.add_span(
+                    &format!("{} {module_name}", span.phase.name()),
+                    span.phase.name(),
+                    span.start,
+                    span.end,
+                    span.thread,
+                    json!({ "module": module_name }),
+                );
+            }
+        }
+    }
+
+    /// Adds a phase that ran on the current thread, like code generation or linking, which is
+    /// done for all modules at once.
+    pub fn add(&mut self, name: &str, start: Instant, end: Instant) {
+        self.add_span(name, name, start, end, current_thread_index(), json!({}));
+    }
+
+    fn add_span(
+        &mut self,
+        name: &str,
+        category: &str,
+        start: Instant,
+        end: Instant,
+        thread: usize,
+        args: Value,
+    ) {
+        self.threads.insert(thread);
+        self.events.push(json!({
+            "name": name,
+            "cat": category,
+            "ph": "X",
+            "ts": micros_between(self.start, start),
+            "dur": micros_between(start, end),
+            "pid": PID,
+            "tid": thread,
+            "args": args,
+        }));
+    }
+
+    pub fn to_json(&self) -> String {
+        let main_thread = current_thread_index();
+
+        // Name the threads, so the viewer can tell the main thread apart from the workers
+        let thread_names = self.threads.iter().map(|thread| {
+            let name = if *thread == main_thread {
+                "main".to_string()
+            } else {
+                format!("worker {thread}")
+            };
+
+            json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": PID,
+                "tid": thread,
+                "args": { "name": name },
+            })
+        });
+
+        let events: Vec<Value> = thread_names.chain(self.events.iter().cloned()).collect();
+
+        format!("{}\n", json!({ "traceEvents": events }))
+    }
+}
+
+fn micros_between(start: Instant, end: Instant) -> f64 {
+    end.saturating_duration_since(start).as_secs_f64() * 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roc_load::{PhaseSpan, TimedPhase};
+    use std::time::Duration;
+
+    #[test]
+    fn trace_events() {
+        let start = Instant::now();
+
+        let mut interns = Interns::default();
+        let module_id = interns.module_id(&"Parser".into());
+
+        let main_thread = current_thread_index();
+        let worker_thread = main_thread + 1;
+
+        let mut module_timing = ModuleTiming::new(start);
+        module_timing.spans.push(PhaseSpan {
+            phase: TimedPhase::Solve,
+            start: start + Duration::from_millis(250),
+            end: start + Duration::from_millis(750),
+            thread: worker_thread,
+        });
+        let mut timings = MutMap::default();
+        timings.insert(module_id, module_timing);
+
+        let mut trace = Trace::new(start);
+        trace.add_modules(&timings, &interns);
+        trace.add(
+            "Link",
+            start + Duration::from_millis(1000),
+            start + Duration::from_millis(1500),
+        );
+
+        let json: Value = serde_json::from_str(&trace.to_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "traceEvents": [
+                    {
+                        "name": "thread_name",
+                        "ph": "M",
+                        "pid": PID,
+                        "tid": main_thread,
+                        "args": { "name": "main" },
+                    },
+                    {
+                        "name": "thread_name",
+                        "ph": "M",
+                        "pid": PID,
+                        "tid": worker_thread,
+                        "args": { "name": format!("worker {worker_thread}") },
+                    },
+                    {
+                        "name": "Solve Parser",
+                        "cat": "Solve",
+                        "ph": "X",
+                        "ts": 250_000.0,
+                        "dur": 500_000.0,
+                        "pid": PID,
+                        "tid": worker_thread,
+                        "args": { "module": "Parser" },
+                    },
+                    {
+                        "name": "Link",
+                        "cat": "Link",
+                        "ph": "X",
+                        "ts": 1_000_000.0,
+                        "dur": 500_000.0,
+                        "pid": PID,
+                        "tid": main_thread,
+                        "args": {},
+                    },
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,382 @@
+module [
+    Arbitrary,
+    Source,
+    arbitrary,
+    draw,
+    bool,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    f32,
+    f64,
+    dec,
+    str,
+    list,
+    check,
+    counterexample,
+]
+
+import Bool exposing [Bool]
+import List
+import Str
+import Result exposing [Result]
+import Num exposing [
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Dec,
+]
+
+## The choices that values are made from.
+##
+## Every value is made by [draw]ing a sequence of numbers, where smaller
+## numbers make simpler values. That way, [check] can shrink a value that
+## breaks a property by trying smaller choices, without having to know
+## anything about the value's type.
+Source := {
+    prefix : List U64,
+    choices : List U64,
+    seed : U64,
+    random : Bool,
+}
+
+## A value that can be made up for a property-based test.
+##
+## Records, tuples and non-recursive tag unions made of [Arbitrary] values
+## are [Arbitrary] themselves.
+Arbitrary implements
+    ## Makes a value from the choices in a [Source], returning what's left of it.
+    arbitrary : Source -> (a, Source) where a implements Arbitrary
+
+## How many values [check] tries before deciding that a property holds.
+cases : U64
+cases = 100
+
+## Past this many choices, a [Source] only has zeros left, so that every
+## value eventually ends.
+max_choices : U64
+max_choices = 8192
+
+## Draws a number from `0` to `max`, both inclusive.
+##
+## This is the building block for custom [Arbitrary] implementations; draw
+## `0` for the simplest value.
+draw : Source, U64 -> (U64, Source)
+draw = |@Source(source), max|
+    index = List.len(source.choices)
+
+    (choice, seed) =
+        when List.get(source.prefix, index) is
+            Ok(prefixed) -> (Num.min(prefixed, max), source.seed)
+            Err(OutOfBounds) if source.random && index < max_choices ->
+                next_seed = Num.add_wrap(source.seed, 0x9E37_79B9_7F4A_7C15)
+
+                (below(mix(next_seed), max), next_seed)
+
+            Err(OutOfBounds) -> (0, source.seed)
+
+    (choice, @Source({ source & choices: List.append(source.choices, choice), seed }))
+
+below : U64, U64 -> U64
+below = |random, max|
+    if max == Num.max_u64 then
+        random
+    else
+        Num.rem(random, max + 1)
+
+## The SplitMix64 finalizer.
+mix : U64 -> U64
+mix = |seed|
+    a = Num.bitwise_xor(seed, Num.shift_right_zf_by(seed, 30)) |> Num.mul_wrap(0xBF58_476D_1CE4_E5B9)
+    b = Num.bitwise_xor(a, Num.shift_right_zf_by(a, 27)) |> Num.mul_wrap(0x94D0_49BB_1331_11EB)
+
+    Num.bitwise_xor(b, Num.shift_right_zf_by(b, 31))
+
+## Draws a number of at most `max_bits` bits. The width comes first, so small
+## numbers are as likely as large ones.
+unsigned : Source, U64 -> (U64, Source)
+unsigned = |source, max_bits|
+    (bits, source1) = draw(source, max_bits)
+    mask = if bits >= 64 then Num.max_u64 else Num.shift_left_by(1, Num.to_u8(bits)) - 1
+
+    draw(source1, mask)
+
+## Like [unsigned], with a sign that shrinks towards positive.
+signed : Source, U64 -> (I64, Source)
+signed = |source, bits|
+    (magnitude, source1) = unsigned(source, bits - 1)
+    (negative, source2) = bool(source1)
+
+    if negative then
+        (-(Num.to_i64(magnitude)) - 1, source2)
+    else
+        (Num.to_i64(magnitude), source2)
+
+bool : Source -> (Bool, Source)
+bool = |source|
+    (choice, source1) = draw(source, 1)
+
+    (choice == 1, source1)
+
+u8 : Source -> (U8, Source)
+u8 = |source|
+    (n, source1) = unsigned(source, 8)
+
+    (Num.to_u8(n), source1)
+
+u16 : Source -> (U16, Source)
+u16 = |source|
+    (n, source1) = unsigned(source, 16)
+
+    (Num.to_u16(n), source1)
+
+u32 : Source -> (U32, Source)
+u32 = |source|
+    (n, source1) = unsigned(source, 32)
+
+    (Num.to_u32(n), source1)
+
+u64 : Source -> (U64, Source)
+u64 = |source| unsigned(source, 64)
+
+u128 : Source -> (U128, Source)
+u128 = |source|
+    (wide, source1) = bool(source)
+    (high, source2) = if wide then unsigned(source1, 64) else (0, source1)
+    (low, source3) = unsigned(source2, 64)
+
+    (Num.shift_left_by(Num.to_u128(high), 64) |> Num.bitwise_or(Num.to_u128(low)), source3)
+
+i8 : Source -> (I8, Source)
+i8 = |source|
+    (n, source1) = signed(source, 8)
+
+    (Num.to_i8(n), source1)
+
+i16 : Source -> (I16, Source)
+i16 = |source|
+    (n, source1) = signed(source, 16)
+
+    (Num.to_i16(n), source1)
+
+i32 : Source -> (I32, Source)
+i32 = |source|
+    (n, source1) = signed(source, 32)
+
+    (Num.to_i32(n), source1)
+
+i64 : Source -> (I64, Source)
+i64 = |source| signed(source, 64)
+
+i128 : Source -> (I128, Source)
+i128 = |source|
+    (magnitude, source1) = u128(source)
+    (negative, source2) = bool(source1)
+    n = Num.to_i128(Num.shift_right_zf_by(magnitude, 1))
+
+    if negative then
+        (-n - 1, source2)
+    else
+        (n, source2)
+
+## Fractions are always finite, and shrink towards `0`.
+f64 : Source -> (F64, Source)
+f64 = |source|
+    (numerator, source1) = signed(source, 32)
+    (denominator, source2) = unsigned(source1, 16)
+
+    (Num.to_f64(numerator) / Num.to_f64(denominator + 1), source2)
+
+f32 : Source -> (F32, Source)
+f32 = |source|
+    (n, source1) = f64(source)
+
+    (Num.to_f32(n), source1)
+
+dec : Source -> (Dec, Source)
+dec = |source|
+    (numerator, source1) = signed(source, 32)
+    (denominator, source2) = unsigned(source1, 16)
+
+    (Num.to_frac(numerator) / Num.to_frac(denominator + 1), source2)
+
+## Strings are mostly printable ASCII, which shrinks towards `"a"`, with the
+## occasional multi-byte character thrown in.
+str : Source -> (Str, Source)
+str = |source|
+    (bytes, source1) = str_help(source, [])
+
+    when Str.from_utf8(bytes) is
+        Ok(string) -> (string, source1)
+        # Unreachable, since only whole characters are added
+        Err(_) -> ("", source1)
+
+str_help : Source, List U8 -> (List U8, Source)
+str_help = |source, bytes|
+    (more, source1) = draw(source, 3)
+
+    if more == 0 then
+        (bytes, source1)
+    else
+        (char, source2) = draw(source1, 99)
+
+        str_help(source2, List.concat(bytes, char_bytes(char)))
+
+char_bytes : U64 -> List U8
+char_bytes = |char|
+    if char < 95 then
+        [Num.to_u8(Num.rem(char + 65, 95) + 32)]
+    else
+        when char is
+            95 -> Str.to_utf8("\u(e9)")
+            96 -> Str.to_utf8("\u(3bb)")
+            97 -> Str.to_utf8("\u(4e2d)")
+            98 -> Str.to_utf8("\u(1f600)")
+            _ -> [0]
+
+## Lists shrink by dropping elements as well as by shrinking them.
+list : Source -> (List a, Source) where a implements Arbitrary
+list = |source| list_help(source, [])
+
+list_help = |source, items|
+    (more, source1) = draw(source, 3)
+
+    if more == 0 then
+        (items, source1)
+    else
+        (item, source2) = arbitrary(source1)
+
+        list_help(source2, List.append(items, item))
+
+## Checks `property` against [Arbitrary] values, returning the simplest value
+## that it doesn't hold for, if there is one.
+##
+## The values are the same every time, so a failure can always be reproduced.
+## A top-level `expect` that is a function, like
+## ```roc
+## expect |bytes| Decode.from_bytes(Encode.to_bytes(bytes, Json.utf8), Json.utf8) == Ok(bytes)
+## ```
+## is checked this way, and reports the [counterexample] when it fails.
+check : (a -> Bool) -> Result {} a where a implements Arbitrary
+check = |property|
+    when find_failure(property, 0) is
+        Ok(choices) ->
+            (counterexample, _) = arbitrary(replay(shrink(property, choices)))
+
+            Err(counterexample)
+
+        Err(NoFailure) -> Ok({})
+
+## The simplest value that `property` doesn't hold for, like [check] finds,
+## or else the simplest value of all, which `property` holds for.
+counterexample : (a -> Bool) -> a where a implements Arbitrary
+counterexample = |property|
+    when check(property) is
+        Err(found) -> found
+        Ok({}) ->
+            (simplest, _) = arbitrary(replay([]))
+
+            simplest
+
+run : (a -> Bool), Source -> [Passed, Failed (List U64)] where a implements Arbitrary
+run = |property, source|
+    (value, @Source(end)) = arbitrary(source)
+
+    if property(value) then
+        Passed
+    else
+        Failed(end.choices)
+
+find_failure = |property, case|
+    if case >= cases then
+        Err(NoFailure)
+    else
+        when run(property, random(case)) is
+            Failed(choices) -> Ok(choices)
+            Passed -> find_failure(property, case + 1)
+
+## The first case is the simplest value of all.
+random : U64 -> Source
+random = |case|
+    if case == 0 then
+        replay([])
+    else
+        @Source({ prefix: [], choices: [], seed: mix(case), random: Bool.true })
+
+replay : List U64 -> Source
+replay = |choices|
+    @Source({ prefix: choices, choices: [], seed: 0, random: Bool.false })
+
+## Shrinks the choices for a failing value for as long as that finds simpler
+## choices that fail too.
+shrink = |property, choices|
+    shrunk = shrink_at(property, choices, 0)
+
+    if shrunk == choices then
+        choices
+    else
+        shrink(property, shrunk)
+
+shrink_at = |property, choices, index|
+    if index >= List.len(choices) then
+        choices
+    else
+        simpler =
+            List.walk_until(
+                candidates(choices, index),
+                Err(NotFound),
+                |state, candidate|
+                    when run(property, replay(candidate)) is
+                        Failed(found) if shortlex_lt(found, choices) -> Break(Ok(found))
+                        _ -> Continue(state),
+            )
+
+        when simpler is
+            Ok(found) -> shrink_at(property, found, index)
+            Err(NotFound) -> shrink_at(property, choices, index + 1)
+
+## Simpler choices to try at `index`: without the choices there, which drops
+## list elements and such, or with a smaller choice there.
+candidates : List U64, U64 -> List (List U64)
+candidates = |choices, index|
+    deletions =
+        List.map(
+            [8, 4, 3, 2, 1],
+            |size| List.concat(List.take_first(choices, index), List.drop_first(choices, index + size)),
+        )
+    choice = List.get(choices, index) |> Result.with_default(0)
+    smaller = if choice == 0 then [] else [0, choice // 2, choice - 1]
+
+    List.concat(deletions, List.map(smaller, |value| List.set(choices, index, value)))
+
+## Fewer choices are simpler, and otherwise smaller ones are.
+shortlex_lt : List U64, List U64 -> Bool
+shortlex_lt = |left, right|
+    if List.len(left) == List.len(right) then
+        List.map2(left, right, Num.compare)
+        |> List.walk_until(
+            Bool.false,
+            |_, order|
+                when order is
+                    LT -> Break(Bool.true)
+                    GT -> Break(Bool.false)
+                    EQ -> Continue(Bool.false),
+        )
+    else
+        List.len(left) < List.len(right)
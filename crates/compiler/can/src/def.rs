@@ -1252,13 +1252,15 @@ fn canonicalize_value_defs<'a>(
     }
 
     for pending in pending_expects {
-        let (loc_can_condition, can_output) = canonicalize_expr(
-            env,
-            var_store,
-            scope,
-            pending.condition.region,
-            &pending.condition.value,
-        );
+        // `expect |a, b| ...` checks a property of arbitrary `a`s and `b`s,
+        // `expect snapshot(x)` compares `x` to its snapshot, and `expect bench(x)` is timed
+        let condition = crate::desugar::desugar_property_expect(env, scope, pending.condition)
+            .or_else(|| crate::desugar::desugar_snapshot_expect(env, scope, pending.condition))
+            .or_else(|| crate::desugar::desugar_bench_expect(env, scope, pending.condition))
+            .unwrap_or(pending.condition);
+
+        let (loc_can_condition, can_output) =
+            canonicalize_expr(env, var_store, scope, condition.region, &condition.value);
 
         expects.push(loc_can_condition, pending.preceding_comment);
 
@@ -307,6 +307,70 @@ fn to_inspector<'a>(env: &mut Env<'a>, at_opaque: &'a str) -> ast::Expr<'a> {
     )
 }
 
+fn arbitrary<'a>(env: &mut Env<'a>, at_opaque: &'a str) -> ast::Expr<'a> {
+    let alloc_expr = |it| env.arena.alloc(Loc::at(DERIVED_REGION, it));
+
+    let source = "#source";
+    let payload = "#payload";
+    let rest = "#rest";
+
+    // Arbitrary.arbitrary(source)
+    let call_member = alloc_expr(ast::Expr::Apply(
+        alloc_expr(ast::Expr::Var {
+            module_name: "Arbitrary",
+            ident: "arbitrary",
+        }),
+        &*env.arena.alloc([&*alloc_expr(ast::Expr::Var {
+            module_name: "",
+            ident: source,
+        })]),
+        CalledVia::Space,
+    ));
+
+    // (payload, rest)
+    let pair_pattern = ast::Pattern::Tuple(Collection::with_items(env.arena.alloc([
+        Loc::at(DERIVED_REGION, ast::Pattern::Identifier { ident: payload }),
+        Loc::at(DERIVED_REGION, ast::Pattern::Identifier { ident: rest }),
+    ])));
+
+    // @Opaq(payload)
+    let wrap_payload = alloc_expr(ast::Expr::Apply(
+        alloc_expr(ast::Expr::OpaqueRef(at_opaque)),
+        &*env.arena.alloc([&*alloc_expr(ast::Expr::Var {
+            module_name: "",
+            ident: payload,
+        })]),
+        CalledVia::Space,
+    ));
+
+    // (@Opaq(payload), rest)
+    let wrapped_pair = ast::Expr::Tuple(Collection::with_items(env.arena.alloc([
+        &*wrap_payload,
+        alloc_expr(ast::Expr::Var {
+            module_name: "",
+            ident: rest,
+        }),
+    ])));
+
+    // when Arbitrary.arbitrary(source) is
+    //     (payload, rest) -> (@Opaq(payload), rest)
+    let branch = env.arena.alloc(ast::WhenBranch {
+        patterns: env.arena.alloc([Loc::at(DERIVED_REGION, pair_pattern)]),
+        value: Loc::at(DERIVED_REGION, wrapped_pair),
+        guard: None,
+    });
+    let when = alloc_expr(ast::Expr::When(call_member, env.arena.alloc([&*branch])));
+
+    // \source -> when Arbitrary.arbitrary(source) is ...
+    ast::Expr::Closure(
+        env.arena.alloc([Loc::at(
+            DERIVED_REGION,
+            ast::Pattern::Identifier { ident: source },
+        )]),
+        when,
+    )
+}
+
 pub const DERIVED_REGION: Region = Region::zero();
 
 pub(crate) fn synthesize_member_impl<'a>(
@@ -330,6 +394,10 @@ pub(crate) fn synthesize_member_impl<'a>(
             format!("#{opaque_name}_to_inspector"),
             to_inspector(env, at_opaque),
         ),
+        Symbol::ARBITRARY_ARBITRARY => (
+            format!("#{opaque_name}_arbitrary"),
+            arbitrary(env, at_opaque),
+        ),
         other => internal_error!("{:?} is not a derivable ability member!", other),
     };
 
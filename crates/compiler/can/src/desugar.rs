@@ -116,3 +116,240 @@ fn desugar_dbg_stmt<'a>(
         continuation,
     ))
 }
+
+/// Desugars a top-level `expect` of a function, like `expect |a, b| a + b == b + a`, into a
+/// property check against `Arbitrary` values. Essentially:
+/// (
+///     property = |(a, b)| a + b == b + a
+///     (a, b) = Arbitrary.counterexample(property)
+///     a + b == b + a
+/// )
+/// so that a failure reports the simplest values the property doesn't hold for, by the names
+/// they were given. When the property holds, the arguments are the simplest values of all, which
+/// `Arbitrary.check` has already found that it holds for.
+///
+/// Returns `None` for expects that aren't functions.
+pub fn desugar_property_expect<'a>(
+    env: &mut Env<'a>,
+    scope: &mut Scope,
+    condition: &'a Loc<Expr<'a>>,
+) -> Option<&'a Loc<Expr<'a>>> {
+    let region = condition.region;
+
+    let (args, body) = match strip_parens_and_spaces(&condition.value) {
+        Closure(args, body) => (*args, *body),
+        _ => return None,
+    };
+
+    // |(a, b)| body, or just |a| body
+    let arg_pattern: &'a Loc<Pattern<'a>> = match args {
+        [arg] => arg,
+        _ => env.arena.alloc(Loc {
+            value: Pattern::Tuple(Collection::with_items(args)),
+            region,
+        }),
+    };
+    let property = env.arena.alloc(Loc {
+        value: Closure(env.arena.alloc([*arg_pattern]), body),
+        region,
+    });
+
+    // The property is defined outside of where its arguments are bound, so that they don't
+    // shadow each other. Being a function, it isn't reported when the expect fails.
+    let property_ident: &'a str = env.arena.alloc(scope.gen_unique_symbol_name().to_string());
+
+    let property_defs = env.arena.alloc(Defs::default());
+    property_defs.push_value_def(
+        ValueDef::Body(
+            env.arena.alloc(Loc {
+                value: Pattern::Identifier {
+                    ident: property_ident,
+                },
+                region,
+            }),
+            property,
+        ),
+        region,
+        &[],
+        &[],
+    );
+
+    // (a, b) = Arbitrary.counterexample(property)
+    let counterexample_fn = env.arena.alloc(Loc {
+        value: Var {
+            module_name: ModuleName::ARBITRARY,
+            ident: "counterexample",
+        },
+        region,
+    });
+    let property_var = env.arena.alloc(Loc {
+        value: Var {
+            module_name: "",
+            ident: property_ident,
+        },
+        region,
+    });
+    let counterexample = env.arena.alloc(Loc {
+        value: Apply(
+            counterexample_fn,
+            env.arena.alloc([&*property_var]),
+            CalledVia::Space,
+        ),
+        region,
+    });
+
+    let arg_defs = env.arena.alloc(Defs::default());
+    arg_defs.push_value_def(
+        ValueDef::Body(arg_pattern, counterexample),
+        region,
+        &[],
+        &[],
+    );
+
+    // body, now about the bound arguments
+    let checked = env.arena.alloc(Loc {
+        value: Defs(arg_defs, body),
+        region,
+    });
+
+    Some(env.arena.alloc(Loc {
+        value: Defs(property_defs, checked),
+        region,
+    }))
+}
+
+fn strip_parens_and_spaces<'a>(expr: &'a Expr<'a>) -> &'a Expr<'a> {
+    match expr {
+        SpaceBefore(expr, _) | SpaceAfter(expr, _) | ParensAround(expr) => {
+            strip_parens_and_spaces(expr)
+        }
+        _ => expr,
+    }
+}
+
+/// What the value of a snapshot expect is called, which no Roc code can refer to.
+pub const SNAPSHOT_LOOKUP: &str = "#snapshot";
+
+/// What the value of a benchmark is called, which no Roc code can refer to.
+pub const BENCH_LOOKUP: &str = "#bench";
+
+/// Desugars a top-level `expect snapshot(value)` into an expectation that always fails, so that
+/// the `Inspect` rendering of `value` gets reported, and `roc test` can compare it to the
+/// snapshot it recorded before. Essentially:
+/// (
+///     #snapshot = Inspect.to_str(value)
+///     Bool.false
+/// )
+///
+/// Returns `None` for other expects, including ones where `snapshot` is the module's own.
+pub fn desugar_snapshot_expect<'a>(
+    env: &mut Env<'a>,
+    scope: &mut Scope,
+    condition: &'a Loc<Expr<'a>>,
+) -> Option<&'a Loc<Expr<'a>>> {
+    let (function, value) = special_expect_call(scope, condition, "snapshot")?;
+
+    // Inspect.to_str(value)
+    let inspect_fn = env.arena.alloc(Loc {
+        value: Var {
+            module_name: ModuleName::INSPECT,
+            ident: "to_str",
+        },
+        region: function.region,
+    });
+    let rendering = env.arena.alloc(Loc {
+        value: Apply(inspect_fn, env.arena.alloc([value]), CalledVia::Space),
+        region: value.region,
+    });
+
+    Some(report_value_expect(
+        env,
+        SNAPSHOT_LOOKUP,
+        rendering,
+        condition.region,
+    ))
+}
+
+/// Desugars a top-level `expect bench(value)`, which `roc bench` times how long `value` takes to
+/// compute for, into an expectation that always fails. Reporting the value means that it has to
+/// be computed, however much the optimizer would like to skip it. Essentially:
+/// (
+///     #bench = value
+///     Bool.false
+/// )
+///
+/// Returns `None` for other expects, including ones where `bench` is the module's own.
+pub fn desugar_bench_expect<'a>(
+    env: &mut Env<'a>,
+    scope: &mut Scope,
+    condition: &'a Loc<Expr<'a>>,
+) -> Option<&'a Loc<Expr<'a>>> {
+    let (_, value) = special_expect_call(scope, condition, "bench")?;
+
+    Some(report_value_expect(
+        env,
+        BENCH_LOOKUP,
+        value,
+        condition.region,
+    ))
+}
+
+/// The function and argument of an expect like `expect snapshot(value)`, where `name` is
+/// `snapshot`, as long as the module doesn't have a `snapshot` of its own.
+fn special_expect_call<'a>(
+    scope: &Scope,
+    condition: &'a Loc<Expr<'a>>,
+    name: &str,
+) -> Option<(&'a Loc<Expr<'a>>, &'a Loc<Expr<'a>>)> {
+    let (function, value) = match strip_parens_and_spaces(&condition.value) {
+        Apply(function, [value], _) => (*function, *value),
+        PncApply(function, args) if args.len() == 1 => (*function, args.items[0]),
+        _ => return None,
+    };
+
+    match function.value {
+        Var {
+            module_name: "",
+            ident,
+        } if ident == name && scope.lookup_str(name, function.region).is_err() => {
+            Some((function, value))
+        }
+        _ => None,
+    }
+}
+
+/// (
+///     lookup = value
+///     Bool.false
+/// )
+fn report_value_expect<'a>(
+    env: &mut Env<'a>,
+    lookup: &'static str,
+    value: &'a Loc<Expr<'a>>,
+    region: Region,
+) -> &'a Loc<Expr<'a>> {
+    let value_def = ValueDef::Body(
+        env.arena.alloc(Loc {
+            value: Pattern::Identifier { ident: lookup },
+            region: value.region,
+        }),
+        value,
+    );
+
+    let defs = env.arena.alloc(Defs::default());
+    defs.push_value_def(value_def, region, &[], &[]);
+
+    // Bool.false
+    let fail = env.arena.alloc(Loc {
+        value: Var {
+            module_name: ModuleName::BOOL,
+            ident: "false",
+        },
+        region,
+    });
+
+    env.arena.alloc(Loc {
+        value: Defs(defs, fail),
+        region,
+    })
+}
@@ -0,0 +1,578 @@
+//! Derivers for the `Arbitrary` ability.
+
+use std::iter::once;
+
+use roc_can::{
+    expr::{
+        AnnotatedMark, ClosureData, Expr, Field, IntValue, Recursive, WhenBranch, WhenBranchPattern,
+    },
+    num::{IntBound, IntLitWidth},
+    pattern::{Pattern, TupleDestruct},
+};
+use roc_collections::SendMap;
+use roc_derive_key::arbitrary::FlatArbitraryKey;
+use roc_module::{
+    called_via::CalledVia,
+    ident::{Lowercase, TagName},
+    symbol::Symbol,
+};
+use roc_region::all::{Loc, Region};
+use roc_types::{
+    subs::{
+        Content, ExhaustiveMark, FlatType, GetSubsSlice, LambdaSet, OptVariable, RecordFields,
+        RedundantMark, SubsSlice, TagExt, TupleElems, UnionLambdas, UnionTags, Variable,
+    },
+    types::RecordField,
+};
+
+use crate::{
+    synth_var,
+    util::{Env, ExtensionKind},
+    DerivedBody,
+};
+
+pub(crate) fn derive_arbitrary(
+    env: &mut Env<'_>,
+    key: FlatArbitraryKey,
+    def_symbol: Symbol,
+) -> DerivedBody {
+    let (body_type, body) = match key {
+        FlatArbitraryKey::Record(fields) => arbitrary_record(env, def_symbol, fields),
+        FlatArbitraryKey::Tuple(arity) => arbitrary_tuple(env, def_symbol, arity),
+        FlatArbitraryKey::TagUnion(tags) => arbitrary_tag_union(env, def_symbol, tags),
+    };
+
+    let specialization_lambda_sets =
+        env.get_specialization_lambda_sets(body_type, Symbol::ARBITRARY_ARBITRARY);
+
+    DerivedBody {
+        body,
+        body_type,
+        specialization_lambda_sets,
+    }
+}
+
+fn arbitrary_record(
+    env: &mut Env<'_>,
+    fn_name: Symbol,
+    fields: Vec<Lowercase>,
+) -> (Variable, Expr) {
+    // Suppose rcd = { f1, ..., fn }.
+    // Build a generalized type t_rcd = { f1: t1, ..., fn: tn }, with fresh t1, ..., tn,
+    // so that we can re-use the derived impl for many records of the same fields.
+    let (record_var, record_fields) = {
+        let flex_fields = fields
+            .into_iter()
+            .map(|name| {
+                (
+                    name,
+                    RecordField::Required(env.subs.fresh_unnamed_flex_var()),
+                )
+            })
+            .collect::<Vec<(Lowercase, _)>>();
+        let fields = RecordFields::insert_into_subs(env.subs, flex_fields);
+        let record_var = synth_var(
+            env.subs,
+            Content::Structure(FlatType::Record(fields, Variable::EMPTY_RECORD)),
+        );
+
+        (record_var, fields)
+    };
+
+    // Now, a generator for this record is
+    //
+    // arbitrary_rcd : Source -> ({ f1: t1, ..., fn: tn }, Source)
+    // arbitrary_rcd = \source ->
+    //   when Arbitrary.arbitrary source is
+    //     (x1, source1) ->
+    //       ...
+    //         when Arbitrary.arbitrary source_n1 is
+    //           (xn, source_n) -> ({ f1: x1, ..., fn: xn }, source_n)
+    let field_vars: Vec<(Lowercase, Variable)> = record_fields
+        .iter_all()
+        .map(|(field_name, field_var, _)| (env.subs[field_name].clone(), env.subs[field_var]))
+        .collect();
+
+    let source = (env.subs.fresh_unnamed_flex_var(), env.new_symbol("source"));
+    let value_vars = field_vars.iter().map(|(_, var)| *var).collect();
+
+    let body = make_in_turn(env, source, value_vars, |env, values, rest| {
+        let record = if field_vars.is_empty() {
+            Expr::EmptyRecord
+        } else {
+            let mut fields = SendMap::default();
+            for ((field_name, field_var), (_, value_sym)) in field_vars.into_iter().zip(values) {
+                let field = Field {
+                    var: field_var,
+                    region: Region::zero(),
+                    loc_expr: Box::new(Loc::at_zero(Expr::Var(value_sym, field_var))),
+                };
+                fields.insert(field_name, field);
+            }
+
+            Expr::Record { record_var, fields }
+        };
+
+        made_pair(env, (record_var, record), rest)
+    });
+
+    // Finally, build the closure
+    // \source -> body
+    build_outer_derived_closure(env, fn_name, source, body)
+}
+
+fn arbitrary_tuple(env: &mut Env<'_>, fn_name: Symbol, arity: u32) -> (Variable, Expr) {
+    // Suppose tup = (v1, ..., vn).
+    // Build a generalized type t_tup = (t1, ..., tn), with fresh t1, ..., tn,
+    // so that we can re-use the derived impl for many tuples of the same arity.
+    let (tuple_var, tuple_elems) = {
+        // TODO: avoid an allocation here by pre-allocating the indices and variables `TupleElems`
+        // will be instantiated with.
+        let flex_elems: Vec<_> = (0..arity)
+            .map(|i| (i as usize, env.subs.fresh_unnamed_flex_var()))
+            .collect();
+        let elems = TupleElems::insert_into_subs(env.subs, flex_elems);
+        let tuple_var = synth_var(
+            env.subs,
+            Content::Structure(FlatType::Tuple(elems, Variable::EMPTY_TUPLE)),
+        );
+
+        (tuple_var, elems)
+    };
+
+    // Now, a generator for this tuple is
+    //
+    // arbitrary_tup : Source -> ((t1, ..., tn), Source)
+    // arbitrary_tup = \source ->
+    //   when Arbitrary.arbitrary source is
+    //     (x1, source1) ->
+    //       ...
+    //         when Arbitrary.arbitrary source_n1 is
+    //           (xn, source_n) -> ((x1, ..., xn), source_n)
+    let elem_vars: Vec<Variable> = tuple_elems
+        .iter_all()
+        .map(|(_, elem_var)| env.subs[elem_var])
+        .collect();
+
+    let source = (env.subs.fresh_unnamed_flex_var(), env.new_symbol("source"));
+
+    let body = make_in_turn(env, source, elem_vars, |env, values, rest| {
+        let elems = values
+            .into_iter()
+            .map(|(var, sym)| (var, Box::new(Loc::at_zero(Expr::Var(sym, var)))))
+            .collect();
+
+        made_pair(env, (tuple_var, Expr::Tuple { tuple_var, elems }), rest)
+    });
+
+    // Finally, build the closure
+    // \source -> body
+    build_outer_derived_closure(env, fn_name, source, body)
+}
+
+fn arbitrary_tag_union(
+    env: &mut Env<'_>,
+    fn_name: Symbol,
+    tags: Vec<(TagName, u16)>,
+) -> (Variable, Expr) {
+    // Suppose tags = [ A p11 .. p1n, ..., Q pq1 .. pqm ]
+    // Build a generalized type t_tags = [ A t11 .. t1n, ..., Q tq1 .. tqm ],
+    // with fresh t1, ..., tqm, so that we can re-use the derived impl for many
+    // unions of the same tags and payloads.
+    let (union_var, union_tags) = {
+        let flex_tag_labels = tags
+            .into_iter()
+            .map(|(label, arity)| {
+                let variables_slice = env.subs.reserve_into_vars(arity.into());
+                for var_index in variables_slice {
+                    env.subs[var_index] = env.subs.fresh_unnamed_flex_var();
+                }
+                (label, variables_slice)
+            })
+            .collect::<Vec<_>>();
+        let union_tags = UnionTags::insert_slices_into_subs(env.subs, flex_tag_labels);
+        let tag_union_var = synth_var(
+            env.subs,
+            Content::Structure(FlatType::TagUnion(
+                union_tags,
+                TagExt::Any(Variable::EMPTY_TAG_UNION),
+            )),
+        );
+
+        (tag_union_var, union_tags)
+    };
+
+    let tags: Vec<(TagName, Vec<Variable>)> = union_tags
+        .iter_all()
+        .map(|(tag, payloads)| {
+            let tag_name = env.subs[tag].clone();
+            let payload_vars = env.subs.get_subs_slice(env.subs[payloads]).to_vec();
+
+            (tag_name, payload_vars)
+        })
+        .collect();
+
+    // Now, a generator for this tag union is
+    //
+    // arbitrary_union : Source -> ([ A t11 .. t1n, ..., Q tq1 .. tqm ], Source)
+    // arbitrary_union = \source ->
+    //   when Arbitrary.draw source (q - 1) is
+    //     (0, source1) -> <make x11 .. x1n in turn from source1> (A x11 .. x1n, source_1n)
+    //     ...
+    //     (_, sourceq) -> <make xq1 .. xqm in turn from sourceq> (Q xq1 .. xqm, source_qm)
+    //
+    // so that shrinking the drawn number shrinks towards the first tag. A union of one tag
+    // draws nothing, and just makes the payloads.
+    let source = (env.subs.fresh_unnamed_flex_var(), env.new_symbol("source"));
+
+    if tags.len() == 1 {
+        let (tag_name, payload_vars) = tags.into_iter().next().unwrap();
+        let body = make_tag_in_turn(env, union_var, tag_name, payload_vars, source);
+
+        return build_outer_derived_closure(env, fn_name, source, body);
+    }
+
+    let last_index = tags.len() - 1;
+    let whole_var = env.subs.fresh_unnamed_flex_var();
+
+    let branches = tags
+        .into_iter()
+        .enumerate()
+        .map(|(index, (tag_name, payload_vars))| {
+            let rest = (env.subs.fresh_unnamed_flex_var(), env.new_symbol("source"));
+
+            // The last tag takes every number that's left, so the `when` is exhaustive
+            let index_pattern = if index == last_index {
+                Pattern::Underscore
+            } else {
+                Pattern::IntLiteral(
+                    Variable::U64,
+                    Variable::UNSIGNED64,
+                    format!("{index}").into_boxed_str(),
+                    IntValue::I128((index as i128).to_ne_bytes()),
+                    IntBound::Exact(IntLitWidth::U64),
+                )
+            };
+
+            let (branch_var, branch_body) =
+                make_tag_in_turn(env, union_var, tag_name, payload_vars, rest);
+            env.unify(whole_var, branch_var);
+
+            let pattern = pair_pattern(
+                env,
+                (Variable::U64, index_pattern),
+                (rest.0, Pattern::Identifier(rest.1)),
+            );
+
+            (pattern, branch_body)
+        })
+        .collect::<Vec<_>>();
+
+    // Arbitrary.draw source (q - 1)
+    let max = Expr::Int(
+        Variable::U64,
+        Variable::UNSIGNED64,
+        format!("{last_index}").into_boxed_str(),
+        IntValue::I128((last_index as i128).to_ne_bytes()),
+        IntBound::Exact(IntLitWidth::U64),
+    );
+    let draw = call_arbitrary_fn(
+        env,
+        Symbol::ARBITRARY_DRAW,
+        vec![
+            (source.0, Expr::Var(source.1, source.0)),
+            (Variable::U64, max),
+        ],
+    );
+
+    let when_expr = when_pair(env, draw, whole_var, branches);
+
+    // Finally, build the closure
+    // \source -> body
+    build_outer_derived_closure(env, fn_name, source, (whole_var, when_expr))
+}
+
+/// Makes the payloads of a tag in turn, then the tag itself.
+fn make_tag_in_turn(
+    env: &mut Env<'_>,
+    union_var: Variable,
+    tag_name: TagName,
+    payload_vars: Vec<Variable>,
+    source: (Variable, Symbol),
+) -> (Variable, Expr) {
+    make_in_turn(env, source, payload_vars, |env, values, rest| {
+        let tag = Expr::Tag {
+            tag_union_var: union_var,
+            ext_var: env.new_ext_var(ExtensionKind::TagUnion),
+            name: tag_name,
+            arguments: values
+                .into_iter()
+                .map(|(var, sym)| (var, Loc::at_zero(Expr::Var(sym, var))))
+                .collect(),
+        };
+
+        made_pair(env, (union_var, tag), rest)
+    })
+}
+
+/// Makes a value of each of `value_vars` in turn, each from what's left of the source after the
+/// one before:
+///
+///   when Arbitrary.arbitrary source is
+///     (x1, source1) ->
+///       ...
+///         when Arbitrary.arbitrary source_n1 is
+///           (xn, source_n) -> finish [x1, ..., xn] source_n
+fn make_in_turn(
+    env: &mut Env<'_>,
+    source: (Variable, Symbol),
+    value_vars: Vec<Variable>,
+    finish: impl FnOnce(&mut Env<'_>, Vec<(Variable, Symbol)>, (Variable, Symbol)) -> (Variable, Expr),
+) -> (Variable, Expr) {
+    let mut steps = Vec::with_capacity(value_vars.len());
+    let mut rest = source;
+
+    for value_var in value_vars {
+        let value = (value_var, env.unique_symbol());
+        let next_rest = (env.subs.fresh_unnamed_flex_var(), env.new_symbol("source"));
+
+        steps.push((rest, value, next_rest));
+        rest = next_rest;
+    }
+
+    let values = steps.iter().map(|(_, value, _)| *value).collect();
+    let (body_var, mut body) = finish(env, values, rest);
+
+    for (source, value, rest) in steps.into_iter().rev() {
+        // Arbitrary.arbitrary source
+        let call = call_arbitrary_fn(
+            env,
+            Symbol::ARBITRARY_ARBITRARY,
+            vec![(source.0, Expr::Var(source.1, source.0))],
+        );
+
+        let pattern = pair_pattern(
+            env,
+            (value.0, Pattern::Identifier(value.1)),
+            (rest.0, Pattern::Identifier(rest.1)),
+        );
+
+        body = when_pair(env, call, body_var, vec![(pattern, body)]);
+    }
+
+    (body_var, body)
+}
+
+/// `(value, source)`, which is what every generator returns.
+fn made_pair(
+    env: &mut Env<'_>,
+    value: (Variable, Expr),
+    source: (Variable, Symbol),
+) -> (Variable, Expr) {
+    let (value_var, value_expr) = value;
+    let (source_var, source_sym) = source;
+
+    let tuple_var = pair_var(env, value_var, source_var);
+    let tuple = Expr::Tuple {
+        tuple_var,
+        elems: vec![
+            (value_var, Box::new(Loc::at_zero(value_expr))),
+            (
+                source_var,
+                Box::new(Loc::at_zero(Expr::Var(source_sym, source_var))),
+            ),
+        ],
+    };
+
+    (tuple_var, tuple)
+}
+
+fn pair_var(env: &mut Env<'_>, first: Variable, second: Variable) -> Variable {
+    let elems = TupleElems::insert_into_subs(env.subs, [(0, first), (1, second)]);
+
+    synth_var(
+        env.subs,
+        Content::Structure(FlatType::Tuple(elems, Variable::EMPTY_TUPLE)),
+    )
+}
+
+/// `(first, second)` as a pattern
+fn pair_pattern(
+    env: &mut Env<'_>,
+    first: (Variable, Pattern),
+    second: (Variable, Pattern),
+) -> (Variable, Pattern) {
+    let whole_var = pair_var(env, first.0, second.0);
+
+    let destructs = [first, second]
+        .into_iter()
+        .enumerate()
+        .map(|(destruct_index, (var, pattern))| {
+            Loc::at_zero(TupleDestruct {
+                var,
+                destruct_index,
+                typ: (var, Loc::at_zero(pattern)),
+            })
+        })
+        .collect();
+
+    let pattern = Pattern::TupleDestructure {
+        whole_var,
+        ext_var: Variable::EMPTY_TUPLE,
+        destructs,
+    };
+
+    (whole_var, pattern)
+}
+
+/// `when cond is` with a branch for each of `branches`, all of which match pairs.
+fn when_pair(
+    env: &mut Env<'_>,
+    cond: (Variable, Expr),
+    expr_var: Variable,
+    branches: Vec<((Variable, Pattern), Expr)>,
+) -> Expr {
+    let (cond_var, cond_expr) = cond;
+
+    let branches = branches
+        .into_iter()
+        .map(|((pattern_var, pattern), value)| {
+            env.unify(pattern_var, cond_var);
+
+            WhenBranch {
+                patterns: vec![WhenBranchPattern {
+                    pattern: Loc::at_zero(pattern),
+                    degenerate: false,
+                }],
+                value: Loc::at_zero(value),
+                guard: None,
+                redundant: RedundantMark::known_non_redundant(),
+            }
+        })
+        .collect();
+
+    Expr::When {
+        loc_cond: Box::new(Loc::at_zero(cond_expr)),
+        cond_var,
+        expr_var,
+        region: Region::zero(),
+        branches,
+        branches_cond_var: cond_var,
+        exhaustive: ExhaustiveMark::known_exhaustive(),
+    }
+}
+
+/// Calls `Arbitrary.arbitrary` or another function of the `Arbitrary` module, returning the type
+/// of the call and the call itself.
+fn call_arbitrary_fn(
+    env: &mut Env<'_>,
+    symbol: Symbol,
+    arguments: Vec<(Variable, Expr)>,
+) -> (Variable, Expr) {
+    // build `symbol ...` function type, e.g.
+    //
+    // Source -[uls]-> (a, Source) where a implements Arbitrary
+    let exposed_fn_var = env.import_builtin_symbol_var(symbol);
+
+    // (typeof arg1), ..., (typeof argn) -[clos]-> ret
+    let this_arguments_slice = env
+        .subs
+        .insert_into_vars(arguments.iter().map(|(var, _)| *var));
+    let this_clos_var = env.subs.fresh_unnamed_flex_var();
+    let this_ret_var = env.subs.fresh_unnamed_flex_var();
+    let this_fn_var = synth_var(
+        env.subs,
+        Content::Structure(FlatType::Func(
+            this_arguments_slice,
+            this_clos_var,
+            this_ret_var,
+            Variable::PURE,
+        )),
+    );
+
+    //   Source        -[uls]->  (a, Source) where a implements Arbitrary
+    // ~ (typeof arg1) -[clos]-> ret
+    env.unify(exposed_fn_var, this_fn_var);
+
+    let fn_head = if symbol == Symbol::ARBITRARY_ARBITRARY {
+        Expr::AbilityMember(symbol, None, this_fn_var)
+    } else {
+        Expr::Var(symbol, this_fn_var)
+    };
+    let fn_data = Box::new((
+        this_fn_var,
+        Loc::at_zero(fn_head),
+        this_clos_var,
+        this_ret_var,
+        Variable::PURE,
+    ));
+
+    let arguments = arguments
+        .into_iter()
+        .map(|(var, expr)| (var, Loc::at_zero(expr)))
+        .collect();
+    let call = Expr::Call(fn_data, arguments, CalledVia::Space);
+
+    (this_ret_var, call)
+}
+
+fn build_outer_derived_closure(
+    env: &mut Env<'_>,
+    fn_name: Symbol,
+    source: (Variable, Symbol),
+    body: (Variable, Expr),
+) -> (Variable, Expr) {
+    let (source_var, source_sym) = source;
+    let (body_var, body_expr) = body;
+
+    let (fn_var, fn_clos_var) = {
+        // Create fn_var for ambient capture; we fix it up below.
+        let fn_var = synth_var(env.subs, Content::Error);
+
+        // -[fn_name]->
+        let fn_captures = vec![];
+        let fn_name_labels = UnionLambdas::insert_into_subs(env.subs, once((fn_name, fn_captures)));
+        let fn_clos_var = synth_var(
+            env.subs,
+            Content::LambdaSet(LambdaSet {
+                solved: fn_name_labels,
+                recursion_var: OptVariable::NONE,
+                unspecialized: SubsSlice::default(),
+                ambient_function: fn_var,
+            }),
+        );
+
+        // source_var -[fn_name]-> body_var
+        let args_slice = env.subs.insert_into_vars([source_var]);
+        env.subs.set_content(
+            fn_var,
+            Content::Structure(FlatType::Func(
+                args_slice,
+                fn_clos_var,
+                body_var,
+                Variable::PURE,
+            )),
+        );
+
+        (fn_var, fn_clos_var)
+    };
+
+    let clos_expr = Expr::Closure(ClosureData {
+        function_type: fn_var,
+        closure_type: fn_clos_var,
+        return_type: body_var,
+        fx_type: Variable::PURE,
+        early_returns: vec![],
+        name: fn_name,
+        captured_symbols: vec![],
+        recursive: Recursive::NotRecursive,
+        arguments: vec![(
+            source_var,
+            AnnotatedMark::known_exhaustive(),
+            Loc::at_zero(Pattern::Identifier(source_sym)),
+        )],
+        loc_body: Box::new(Loc::at_zero(body_expr)),
+    });
+
+    (fn_var, clos_expr)
+}
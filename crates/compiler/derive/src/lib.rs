@@ -17,6 +17,7 @@ use roc_types::subs::{
 };
 use util::Env;
 
+mod arbitrary;
 mod decoding;
 mod encoding;
 mod hash;
@@ -83,6 +84,9 @@ fn build_derived_body(
         DeriveKey::ToInspector(to_inspector_key) => {
             inspect::derive_to_inspector(&mut env, to_inspector_key, derived_symbol)
         }
+        DeriveKey::Arbitrary(arbitrary_key) => {
+            arbitrary::derive_arbitrary(&mut env, arbitrary_key, derived_symbol)
+        }
     };
 
     let def = Def {
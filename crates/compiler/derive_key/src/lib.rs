@@ -9,16 +9,20 @@
 //!   between e.g. required and optional record fields.
 //! - `Decoding` is like encoding, but has some differences. For one, it *does* need to distinguish
 //!   between required and default value record fields.
+//! - `Arbitrary` only needs the surface shape of a type, like `Hash`, but can't make up values of
+//!   recursive types, since nothing would stop them from growing forever.
 //!
 //! For these reasons the content keying is based on a strategy as well, which are the variants of
 //! [`DeriveKey`].
 
+pub mod arbitrary;
 pub mod decoding;
 pub mod encoding;
 pub mod hash;
 pub mod inspect;
 mod util;
 
+use arbitrary::{FlatArbitrary, FlatArbitraryKey};
 use decoding::{FlatDecodable, FlatDecodableKey};
 use encoding::{FlatEncodable, FlatEncodableKey};
 use hash::{FlatHash, FlatHashKey};
@@ -43,6 +47,7 @@ pub enum DeriveKey {
     Decoder(FlatDecodableKey),
     Hash(FlatHashKey),
     ToInspector(FlatInspectableKey),
+    Arbitrary(FlatArbitraryKey),
 }
 
 impl DeriveKey {
@@ -52,6 +57,7 @@ impl DeriveKey {
             DeriveKey::Decoder(key) => format!("decoder_{}", key.debug_name()),
             DeriveKey::Hash(key) => format!("hash_{}", key.debug_name()),
             DeriveKey::ToInspector(key) => format!("to_inspector_{}", key.debug_name()),
+            DeriveKey::Arbitrary(key) => format!("arbitrary_{}", key.debug_name()),
         }
     }
 }
@@ -82,6 +88,7 @@ pub enum DeriveBuiltin {
     Hash,
     IsEq,
     ToInspector,
+    Arbitrary,
 }
 
 impl TryFrom<Symbol> for DeriveBuiltin {
@@ -94,6 +101,7 @@ impl TryFrom<Symbol> for DeriveBuiltin {
             Symbol::HASH_HASH => Ok(DeriveBuiltin::Hash),
             Symbol::BOOL_IS_EQ => Ok(DeriveBuiltin::IsEq),
             Symbol::INSPECT_TO_INSPECTOR => Ok(DeriveBuiltin::ToInspector),
+            Symbol::ARBITRARY_ARBITRARY => Ok(DeriveBuiltin::Arbitrary),
             _ => Err(value),
         }
     }
@@ -131,6 +139,12 @@ impl Derived {
                 FlatInspectable::Immediate(imm) => Ok(Derived::Immediate(imm)),
                 FlatInspectable::Key(repr) => Ok(Derived::Key(DeriveKey::ToInspector(repr))),
             },
+            DeriveBuiltin::Arbitrary => match FlatArbitrary::from_var(subs, var)? {
+                FlatArbitrary::SingleLambdaSetImmediate(imm) => {
+                    Ok(Derived::SingleLambdaSetImmediate(imm))
+                }
+                FlatArbitrary::Key(repr) => Ok(Derived::Key(DeriveKey::Arbitrary(repr))),
+            },
         }
     }
 
@@ -167,6 +181,12 @@ impl Derived {
                     FlatInspectable::Key(repr) => Ok(Derived::Key(DeriveKey::ToInspector(repr))),
                 }
             }
+            DeriveBuiltin::Arbitrary => match FlatArbitrary::from_builtin_symbol(symbol)? {
+                FlatArbitrary::SingleLambdaSetImmediate(imm) => {
+                    Ok(Derived::SingleLambdaSetImmediate(imm))
+                }
+                FlatArbitrary::Key(repr) => Ok(Derived::Key(DeriveKey::Arbitrary(repr))),
+            },
         }
     }
 }
@@ -5,7 +5,9 @@ use crate::llvm::build_list::{
 use crate::llvm::convert::{
     argument_type_from_layout, basic_type_from_builtin, basic_type_from_layout, zig_str_type,
 };
-use crate::llvm::expect::{clone_to_shared_memory, SharedMemoryPointer};
+use crate::llvm::expect::{
+    clone_to_shared_memory, sink_lookups, values_wanted, SharedMemoryPointer,
+};
 use crate::llvm::memcpy::build_memcpy;
 use crate::llvm::refcounting::{
     build_reset, decrement_refcount_layout, increment_refcount_layout, PointerToRefcount,
@@ -1108,7 +1110,8 @@ fn promote_to_main_function<'a, 'ctx>(
     mod_solutions: &'a ModSolutions,
     symbol: Symbol,
     top_level: ProcLayout<'a>,
-) -> (&'static str, FunctionValue<'ctx>) {
+    main_fn_name: &str,
+) -> FunctionValue<'ctx> {
     let it = top_level.arguments.iter().copied();
     let bytes = roc_alias_analysis::func_name_bytes_help(symbol, it, Niche::NONE, top_level.result);
     let func_name = FuncName(&bytes);
@@ -1124,10 +1127,8 @@ fn promote_to_main_function<'a, 'ctx>(
     // NOTE fake layout; it is only used for debug prints
     let roc_main_fn = function_value_by_func_spec(env, FuncBorrowSpec::Some(*func_spec), symbol);
 
-    let main_fn_name = "$Test.main";
-
     // Add main to the module.
-    let main_fn = expose_function_to_host_help_c_abi(
+    expose_function_to_host_help_c_abi(
         env,
         layout_interner,
         main_fn_name,
@@ -1135,9 +1136,7 @@ fn promote_to_main_function<'a, 'ctx>(
         top_level.arguments,
         top_level.result,
         main_fn_name,
-    );
-
-    (main_fn_name, main_fn)
+    )
 }
 
 fn promote_to_wasm_test_wrapper<'a, 'ctx>(
@@ -3518,6 +3517,20 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
                     roc_target::PtrWidth::Bytes8 => {
                         let shared_memory = SharedMemoryPointer::get(env);
 
+                        if let LlvmBackendMode::CliTest = env.mode {
+                            let clone_block = context.append_basic_block(parent, "clone_block");
+                            let sink_block = context.append_basic_block(parent, "sink_block");
+
+                            let wanted = values_wanted(env, &shared_memory);
+                            bd.new_build_conditional_branch(wanted, clone_block, sink_block);
+
+                            bd.position_at_end(sink_block);
+                            sink_lookups(env, scope, lookups);
+                            bd.new_build_unconditional_branch(then_block);
+
+                            bd.position_at_end(clone_block);
+                        }
+
                         clone_to_shared_memory(
                             env,
                             layout_interner,
@@ -5457,13 +5470,56 @@ pub fn build_procedures_return_main<'a, 'ctx>(
         Some(&std::env::temp_dir().join("test.ll")),
     );
 
-    promote_to_main_function(
+    let main_fn_name = "$Test.main";
+    let main_fn = promote_to_main_function(
         env,
         layout_interner,
         mod_solutions,
         entry_point.symbol,
         entry_point.layout,
-    )
+        main_fn_name,
+    );
+
+    (main_fn_name, main_fn)
+}
+
+/// Like [build_procedures_return_main], but main is exposed under the name that a platform's host
+/// calls its app by, like `roc__main_for_host_1_exposed_generic` for a platform that provides
+/// `main_for_host!`. Like the host's, it takes a pointer to write its result into, which it
+/// writes a [crate::run_roc::RocCallResult] to, so that crashes can be reported.
+pub fn build_procedures_expose_for_host<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    opt_level: OptLevel,
+    procedures: MutMap<(Symbol, ProcLayout<'a>), roc_mono::ir::Proc<'a>>,
+    host_exposed_lambda_sets: HostExposedLambdaSets<'a>,
+    entry_point: SingleEntryPoint<'a>,
+    for_host_name: &str,
+) -> (&'a str, FunctionValue<'ctx>) {
+    let mod_solutions = build_procedures_help(
+        env,
+        layout_interner,
+        opt_level,
+        procedures,
+        host_exposed_lambda_sets,
+        EntryPoint::Program(env.arena.alloc([entry_point])),
+        Some(&std::env::temp_dir().join("test.ll")),
+    );
+
+    let main_fn_name = env.arena.alloc_str(&format!(
+        "roc__{}_1_exposed_generic",
+        for_host_name.trim_end_matches('!')
+    ));
+    let main_fn = promote_to_main_function(
+        env,
+        layout_interner,
+        mod_solutions,
+        entry_point.symbol,
+        entry_point.layout,
+        main_fn_name,
+    );
+
+    (main_fn_name, main_fn)
 }
 
 pub fn build_procedures_expose_expects<'a>(
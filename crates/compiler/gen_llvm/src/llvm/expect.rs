@@ -9,7 +9,7 @@ use inkwell::builder::Builder;
 use inkwell::module::Linkage;
 use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
-use inkwell::AddressSpace;
+use inkwell::{AddressSpace, IntPredicate};
 use roc_builtins::bitcode;
 use roc_error_macros::{internal_error, todo_lambda_erasure};
 use roc_module::symbol::Symbol;
@@ -139,6 +139,41 @@ fn offset_add<'ctx>(
     builder.new_build_int_add(current, intval, "offset_add")
 }
 
+/// Whether the values of a failed expect should be written to `shared_memory`. `roc bench` sets
+/// the offset to write them at to 0 while it times a benchmark, so that only computing the
+/// benchmark's value gets timed, and not reporting it.
+pub(crate) fn values_wanted<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    shared_memory: &SharedMemoryPointer<'ctx>,
+) -> IntValue<'ctx> {
+    let (_, offset) = read_state(env, shared_memory.0);
+
+    env.builder.new_build_int_compare(
+        IntPredicate::NE,
+        offset,
+        env.ptr_int().const_zero(),
+        "values_wanted",
+    )
+}
+
+/// Keeps the values of `lookups` from being optimized away, without reporting them anywhere, the
+/// way `std::hint::black_box` does.
+pub(crate) fn sink_lookups<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    scope: &Scope<'a, 'ctx>,
+    lookups: &[Symbol],
+) {
+    for lookup in lookups.iter() {
+        let value = scope.load_symbol(lookup);
+        let sink = create_entry_block_alloca(env, value.get_type(), "sink");
+
+        env.builder
+            .new_build_store(sink, value)
+            .set_volatile(true)
+            .unwrap();
+    }
+}
+
 pub(crate) fn notify_parent_expect(env: &Env, shared_memory: &SharedMemoryPointer) {
     let func = env
         .module
@@ -3,10 +3,13 @@ use crate::llvm::bitcode::call_void_bitcode_fn;
 use crate::llvm::build::{add_func, get_panic_msg_ptr, get_panic_tag_ptr, BuilderExt, C_CALL_CONV};
 use crate::llvm::build::{CCReturn, Env, FunctionSpec};
 use crate::llvm::convert::zig_str_type;
+use inkwell::attributes::AttributeLoc;
 use inkwell::module::Linkage;
 use inkwell::types::BasicType;
+use inkwell::values::BasicMetadataValueEnum;
 use inkwell::AddressSpace;
 use roc_builtins::bitcode;
+use roc_mono::coverage::COVERAGE_HIT;
 
 use super::build::get_sjlj_buffer;
 use super::intrinsics::LLVM_LONGJMP;
@@ -210,6 +213,130 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
     }
 }
 
+/// The global that the test runner points at its function for recording coverage hits.
+pub const COVERAGE_CALLBACK: &str = "roc_coverage_callback";
+
+/// Defines the [COVERAGE_HIT] function that code instrumented for `roc test --coverage` calls,
+/// which passes its arguments on to the function that [COVERAGE_CALLBACK] points to, if any.
+///
+/// Must be called after the procedures are built, since they're what declare [COVERAGE_HIT].
+/// Does nothing when none of them were instrumented.
+pub fn add_coverage_hit(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let module = env.module;
+    let builder = env.builder;
+
+    let Some(fn_val) = module.get_function(COVERAGE_HIT) else {
+        return;
+    };
+
+    let ptr_type = ctx.ptr_type(AddressSpace::default());
+    let callback = module.add_global(ptr_type, None, COVERAGE_CALLBACK);
+    callback.set_linkage(Linkage::External);
+    callback.set_initializer(&ptr_type.const_null());
+
+    // Like roc_panic, this must not be provided by anything else
+    fn_val.set_linkage(Linkage::Internal);
+
+    let entry = ctx.append_basic_block(fn_val, "entry");
+    let call_block = ctx.append_basic_block(fn_val, "call_callback");
+    let return_block = ctx.append_basic_block(fn_val, "return");
+
+    builder.position_at_end(entry);
+
+    let callback_ptr = builder
+        .new_build_load(ptr_type, callback.as_pointer_value(), "load_callback")
+        .into_pointer_value();
+    let is_set = builder.new_build_is_not_null(callback_ptr, "callback_is_set");
+    builder.new_build_conditional_branch(is_set, call_block, return_block);
+
+    builder.position_at_end(call_block);
+
+    let arguments: Vec<BasicMetadataValueEnum> =
+        fn_val.get_param_iter().map(|param| param.into()).collect();
+    builder.new_build_indirect_call(fn_val.get_type(), callback_ptr, &arguments, "");
+    builder.new_build_unconditional_branch(return_block);
+
+    builder.position_at_end(return_block);
+
+    match fn_val.get_type().get_return_type() {
+        Some(return_type) => builder.new_build_return(Some(&return_type.const_zero())),
+        None => builder.new_build_return(None),
+    };
+
+    if cfg!(debug_assertions) {
+        crate::llvm::build::verify_fn(fn_val);
+    }
+}
+
+/// The global that [add_host_function_pointers] makes for the host function named `name`, like
+/// `roc_fx_line_host` for `roc_fx_line`, for whoever loads the module to point at the host's.
+pub fn host_function_pointer(name: &str) -> String {
+    format!("{name}_host")
+}
+
+/// Defines each of the host functions named `names`, like `roc_fx_line`, to pass its arguments on
+/// to the function that its [host_function_pointer] points to. That way the module can run
+/// effects against a host that's loaded separately, rather than linked in, without the rest of
+/// the host's symbols (like its `roc_alloc`) replacing the module's own.
+///
+/// Must be called after the procedures are built, since they're what declare the host functions.
+/// Names that none of them call are skipped.
+pub fn add_host_function_pointers<'n>(
+    env: &Env<'_, '_, '_>,
+    names: impl IntoIterator<Item = &'n str>,
+) {
+    let ctx = env.context;
+    let module = env.module;
+    let builder = env.builder;
+
+    let ptr_type = ctx.ptr_type(AddressSpace::default());
+
+    for name in names {
+        let Some(fn_val) = module.get_function(name) else {
+            continue;
+        };
+
+        let host_fn = module.add_global(ptr_type, None, &host_function_pointer(name));
+        host_fn.set_linkage(Linkage::External);
+        host_fn.set_initializer(&ptr_type.const_null());
+
+        fn_val.set_linkage(Linkage::Internal);
+
+        let entry = ctx.append_basic_block(fn_val, "entry");
+
+        builder.position_at_end(entry);
+
+        let host_fn_ptr = builder
+            .new_build_load(ptr_type, host_fn.as_pointer_value(), "load_host_fn")
+            .into_pointer_value();
+        let arguments: Vec<BasicMetadataValueEnum> =
+            fn_val.get_param_iter().map(|param| param.into()).collect();
+        let call =
+            builder.new_build_indirect_call(fn_val.get_type(), host_fn_ptr, &arguments, "call");
+
+        call.set_call_convention(C_CALL_CONV);
+
+        // Like sret, some attributes change where the arguments are passed
+        for index in 0..fn_val.count_params() {
+            let loc = AttributeLoc::Param(index);
+
+            for attribute in fn_val.attributes(loc) {
+                call.add_attribute(loc, attribute);
+            }
+        }
+
+        match call.try_as_basic_value().left() {
+            Some(result) => builder.new_build_return(Some(&result)),
+            None => builder.new_build_return(None),
+        };
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
+    }
+}
+
 fn unreachable_function(env: &Env, name: &str) {
     // The type of this function (but not the implementation) should have
     // already been defined by the builtins, which rely on it.
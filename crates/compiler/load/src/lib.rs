@@ -18,12 +18,14 @@ const SKIP_SUBS_CACHE: bool = {
 
 pub use roc_load_internal::docs;
 pub use roc_load_internal::file::{
-    ExecutionMode, ExpectMetadata, LoadConfig, LoadResult, LoadStart, LoadingProblem, Phase,
-    Threading,
+    report_loading_problem, ExecutionMode, ExpectMetadata, LoadConfig, LoadResult, LoadStart,
+    LoadingProblem, Phase, Threading,
 };
 pub use roc_load_internal::module::{
-    CheckedModule, EntryPoint, Expectations, ExposedToHost, LoadedModule, MonomorphizedModule,
+    current_thread_index, CheckedModule, EntryPoint, Expectations, ExposedToHost, LoadedModule,
+    ModuleTiming, MonomorphizedModule, PackageDependency, PhaseSpan, ReusableModule, TimedPhase,
 };
+pub use roc_load_internal::BUILTIN_MODULES;
 pub use roc_solve::FunctionKind;
 
 #[allow(clippy::too_many_arguments)]
@@ -256,6 +258,7 @@ fn read_cached_types() -> MutMap<ModuleId, TypeState> {
     let mod_decode = include_bytes_align_as!(u128, concat!(env!("OUT_DIR"), "/Decode.dat"));
     let mod_hash = include_bytes_align_as!(u128, concat!(env!("OUT_DIR"), "/Hash.dat"));
     let mod_inspect = include_bytes_align_as!(u128, concat!(env!("OUT_DIR"), "/Inspect.dat"));
+    let mod_arbitrary = include_bytes_align_as!(u128, concat!(env!("OUT_DIR"), "/Arbitrary.dat"));
 
     let mut output = MutMap::default();
 
@@ -279,6 +282,7 @@ fn read_cached_types() -> MutMap<ModuleId, TypeState> {
 
         output.insert(ModuleId::HASH, deserialize_help(mod_hash));
         output.insert(ModuleId::INSPECT, deserialize_help(mod_inspect));
+        output.insert(ModuleId::ARBITRARY, deserialize_help(mod_arbitrary));
     }
 
     output
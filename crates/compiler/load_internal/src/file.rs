@@ -1,12 +1,15 @@
 #![allow(clippy::too_many_arguments)]
 
 use crate::docs::ModuleDocumentation;
+use crate::fingerprint::exposed_types_fingerprint;
 use crate::module::{
     CheckedModule, ConstrainedModule, EntryPoint, Expectations, ExposedToHost,
     FoundSpecializationsModule, LateSpecializationsModule, LoadedModule, ModuleHeader,
-    ModuleTiming, MonomorphizedModule, ParsedModule, ToplevelExpects, TypeCheckedModule,
+    ModuleTiming, MonomorphizedModule, PackageDependency, ParsedModule, ReusableModule, TimedPhase,
+    ToplevelExpects, TypeCheckedModule,
 };
 use crate::module_cache::ModuleCache;
+use crate::BUILTIN_MODULES;
 use bumpalo::{collections::CollectIn, Bump};
 use crossbeam::channel::{bounded, Sender};
 use crossbeam::deque::{Injector, Worker};
@@ -127,6 +130,8 @@ pub enum ExecutionMode {
     /// Test is like [`ExecutionMode::ExecutableIfCheck`], but rather than producing a proper
     /// executable, run tests.
     Test,
+    /// Like [`ExecutionMode::Test`], but also counts which defs and branches the tests reach.
+    TestWithCoverage,
 }
 
 impl ExecutionMode {
@@ -135,12 +140,24 @@ impl ExecutionMode {
 
         match self {
             Executable => Phase::MakeSpecializations,
-            Check | ExecutableIfCheck | Test => Phase::SolveTypes,
+            Check | ExecutableIfCheck | Test | TestWithCoverage => Phase::SolveTypes,
         }
     }
 
     fn build_if_checks(&self) -> bool {
-        matches!(self, Self::ExecutableIfCheck | Self::Test)
+        matches!(
+            self,
+            Self::ExecutableIfCheck | Self::Test | Self::TestWithCoverage
+        )
+    }
+
+    fn is_test(&self) -> bool {
+        matches!(self, Self::Test | Self::TestWithCoverage)
+    }
+
+    /// Whether to instrument `module_id` for `roc test --coverage`. Builtins never are.
+    fn coverage(&self, module_id: ModuleId) -> bool {
+        matches!(self, Self::TestWithCoverage) && !module_id.is_builtin()
     }
 }
 
@@ -315,12 +332,26 @@ fn start_phase<'a>(
                     }
                 }
 
-                let skip_constraint_gen = {
+                let is_cached = {
                     // Give this its own scope to make sure that the Guard from the lock() is dropped
                     // immediately after contains_key returns
                     state.cached_types.lock().contains_key(&module_id)
                 };
 
+                let is_unchanged = match &mut state.module_reuse {
+                    Some(module_reuse) if matches!(state.exec_mode, ExecutionMode::Check) => {
+                        let (_, source) = state.module_cache.sources.get(&module_id).unwrap();
+                        module_reuse.take_unchanged(
+                            module_id,
+                            source,
+                            deps_by_name.values().copied(),
+                        )
+                    }
+                    _ => false,
+                };
+
+                let skip_constraint_gen = is_cached || is_unchanged;
+
                 BuildTask::CanonicalizeAndConstrain {
                     parsed,
                     dep_idents,
@@ -356,6 +387,28 @@ fn start_phase<'a>(
 
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
 
+                let reused = match &mut state.module_reuse {
+                    Some(module_reuse) if module_reuse.taken_unchanged.contains_key(&module_id) => {
+                        module_reuse.taken_unchanged.remove(&module_id)
+                    }
+                    Some(module_reuse) => {
+                        let (_, source) = state.module_cache.sources.get(&module_id).unwrap();
+                        let reusable = module_reuse.take_reusable(
+                            module_id,
+                            source,
+                            &ident_ids,
+                            available_modules.keys().copied(),
+                        );
+
+                        reusable.filter(|_| matches!(state.exec_mode, ExecutionMode::Check))
+                    }
+                    None => None,
+                };
+
+                if let (Some(module_reuse), Some(_)) = (&mut state.module_reuse, &reused) {
+                    module_reuse.reused.insert(module_id);
+                }
+
                 #[cfg(debug_assertions)]
                 let checkmate = if roc_checkmate::is_checkmate_enabled() {
                     Some(roc_checkmate::Collector::new())
@@ -380,6 +433,7 @@ fn start_phase<'a>(
                     dep_idents,
                     declarations,
                     state.cached_types.clone(),
+                    reused,
                     derived_module,
                     state.exec_mode,
                     is_host_exposed,
@@ -431,8 +485,8 @@ fn start_phase<'a>(
 
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
 
-                let build_expects =
-                    matches!(state.exec_mode, ExecutionMode::Test) && expectations.is_some();
+                let build_expects = state.exec_mode.is_test() && expectations.is_some();
+                let coverage = state.exec_mode.coverage(module_id);
 
                 BuildTask::BuildPendingSpecializations {
                     layout_cache,
@@ -449,6 +503,7 @@ fn start_phase<'a>(
                     derived_module,
                     expectations,
                     build_expects,
+                    coverage,
                 }
             }
             Phase::MakeSpecializations => {
@@ -554,6 +609,7 @@ fn start_phase<'a>(
                 }
 
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
+                let coverage = state.exec_mode.coverage(module_id);
 
                 BuildTask::MakeSpecializations {
                     module_id,
@@ -568,6 +624,7 @@ fn start_phase<'a>(
                     exposed_by_module: state.exposed_types.clone(),
                     derived_module,
                     expectations,
+                    coverage,
                 }
             }
         }
@@ -760,6 +817,9 @@ struct State<'a> {
 
     pub timings: MutMap<ModuleId, ModuleTiming>,
 
+    /// The packages that module headers depend on, in the order the headers were loaded
+    pub package_dependencies: std::vec::Vec<PackageDependency>,
+
     // Each thread gets its own layout cache. When one "pending specializations"
     // pass completes, it returns its layout cache so another thread can use it.
     // We don't bother trying to union them all together to maximize cache hits,
@@ -779,11 +839,122 @@ struct State<'a> {
     // cached types (used for builtin modules, could include packages in the future too)
     cached_types: CachedTypeState,
 
+    /// Solved types carried over from an earlier load, if the load was asked to reuse them
+    module_reuse: Option<ModuleReuse>,
+
     layout_interner: GlobalLayoutInterner<'a>,
 }
 
 type CachedTypeState = Arc<Mutex<MutMap<ModuleId, TypeState>>>;
 
+/// Solved types carried over between loads of the same module graph.
+#[derive(Debug, Default)]
+struct ModuleReuse {
+    /// Solved types from the earlier load; each is taken out when its module is about to be solved
+    previous: MutMap<ModuleId, ReusableModule>,
+    /// Solved types from this load, for the next one to reuse
+    solved: MutMap<ModuleId, ReusableModule>,
+    exposed_fingerprints: MutMap<ModuleId, u64>,
+    import_fingerprints: MutMap<ModuleId, VecMap<ModuleId, u64>>,
+    /// Modules whose source, and the source of everything they import, is unchanged
+    unchanged: MutSet<ModuleId>,
+    /// Solved types of unchanged modules, taken out before they were constrained
+    taken_unchanged: MutMap<ModuleId, ReusableModule>,
+    /// Modules whose solved types came from the earlier load, rather than being solved again
+    reused: MutSet<ModuleId>,
+}
+
+impl ModuleReuse {
+    /// Takes the module's solved types from the earlier load before it is constrained, if neither
+    /// its source nor that of any module it imports has changed since. Its types are then known
+    /// to be the same, so its constraints don't need to be generated.
+    ///
+    /// Imports are always canonicalized before the modules that import them, so whether they are
+    /// unchanged is already known.
+    fn take_unchanged(
+        &mut self,
+        module_id: ModuleId,
+        source: &str,
+        mut imports: impl Iterator<Item = ModuleId>,
+    ) -> bool {
+        let is_unchanged = self
+            .previous
+            .get(&module_id)
+            .is_some_and(|previous| &*previous.source == source)
+            && imports.all(|import| import.is_builtin() || self.unchanged.contains(&import));
+
+        if !is_unchanged {
+            return false;
+        }
+
+        let previous = self.previous.remove(&module_id).unwrap();
+        self.import_fingerprints
+            .insert(module_id, previous.import_fingerprints.clone());
+        self.unchanged.insert(module_id);
+        self.taken_unchanged.insert(module_id, previous);
+
+        true
+    }
+
+    /// Takes the module's solved types from the earlier load, if they are still valid.
+    fn take_reusable(
+        &mut self,
+        module_id: ModuleId,
+        source: &str,
+        ident_ids: &IdentIds,
+        imports: impl Iterator<Item = ModuleId>,
+    ) -> Option<ReusableModule> {
+        // Builtins are the same in every load, so they never need to be compared
+        let import_fingerprints = imports
+            .filter(|import| !import.is_builtin())
+            .filter_map(|import| Some((import, *self.exposed_fingerprints.get(&import)?)))
+            .collect::<VecMap<_, _>>();
+
+        let reusable = self.previous.remove(&module_id).filter(|previous| {
+            &*previous.source == source
+                && &previous.ident_ids == ident_ids
+                && previous.import_fingerprints == import_fingerprints
+        });
+
+        self.import_fingerprints
+            .insert(module_id, import_fingerprints);
+
+        reusable
+    }
+
+    /// Keeps the module's solved types so that a later load can reuse them.
+    fn keep_solved(
+        &mut self,
+        module_id: ModuleId,
+        source: &str,
+        ident_ids: &IdentIds,
+        subs: &Subs,
+        solved_module: &SolvedModule,
+        abilities_store: &AbilitiesStore,
+    ) {
+        let fingerprint = exposed_types_fingerprint(
+            &solved_module.exposed_types,
+            &solved_module.solved_implementations,
+        );
+        self.exposed_fingerprints.insert(module_id, fingerprint);
+
+        let reusable = ReusableModule {
+            source: source.into(),
+            ident_ids: ident_ids.clone(),
+            import_fingerprints: self
+                .import_fingerprints
+                .remove(&module_id)
+                .unwrap_or_default(),
+            subs: subs.clone(),
+            exposed_vars_by_symbol: solved_module.exposed_vars_by_symbol.clone(),
+            abilities_store: abilities_store.clone(),
+            solved_implementations: solved_module.solved_implementations.clone(),
+            problems: solved_module.problems.clone(),
+        };
+        self.solved.insert(module_id, reusable);
+    }
+}
+
 impl<'a> State<'a> {
     fn goal_phase(&self) -> Phase {
         self.exec_mode.goal_phase()
@@ -801,6 +972,7 @@ impl<'a> State<'a> {
         ident_ids_by_module: SharedIdentIdsByModule,
         arc_shorthands: Arc<Mutex<MutMap<&'a str, ShorthandPath>>>,
         cached_types: MutMap<ModuleId, TypeState>,
+        reusable_modules: Option<MutMap<ModuleId, ReusableModule>>,
         render: RenderTarget,
         palette: Palette,
         number_of_workers: usize,
@@ -836,8 +1008,13 @@ impl<'a> State<'a> {
             declarations_by_id: MutMap::default(),
             exposed_symbols_by_module: MutMap::default(),
             timings: MutMap::default(),
+            package_dependencies: std::vec::Vec::new(),
             layout_caches: std::vec::Vec::with_capacity(number_of_workers),
             cached_types: Arc::new(Mutex::new(cached_types)),
+            module_reuse: reusable_modules.map(|previous| ModuleReuse {
+                previous,
+                ..Default::default()
+            }),
             render,
             palette,
             exec_mode,
@@ -939,6 +1116,7 @@ enum BuildTask<'a> {
         declarations: Declarations,
         dep_idents: IdentIdsByModule,
         cached_subs: CachedTypeState,
+        reused: Option<ReusableModule>,
         derived_module: SharedDerivedModule,
         exec_mode: ExecutionMode,
         is_host_exposed: bool,
@@ -960,6 +1138,7 @@ enum BuildTask<'a> {
         derived_module: SharedDerivedModule,
         expectations: Option<Expectations>,
         build_expects: bool,
+        coverage: bool,
     },
     MakeSpecializations {
         module_id: ModuleId,
@@ -973,6 +1152,7 @@ enum BuildTask<'a> {
         world_abilities: WorldAbilities,
         derived_module: SharedDerivedModule,
         expectations: Option<Expectations>,
+        coverage: bool,
     },
 }
 
@@ -1175,6 +1355,7 @@ pub struct LoadStart<'a> {
     root_type: RootType,
     opt_platform_shorthand: Option<&'a str>,
     src_dir: PathBuf,
+    reusable_modules: Option<MutMap<ModuleId, ReusableModule>>,
 }
 
 #[derive(Debug, Clone)]
@@ -1255,6 +1436,7 @@ impl<'a> LoadStart<'a> {
             root_msg: header_output.msg,
             root_type,
             opt_platform_shorthand: header_output.opt_platform_shorthand,
+            reusable_modules: None,
         })
     }
 
@@ -1311,8 +1493,18 @@ impl<'a> LoadStart<'a> {
             root_msg,
             root_type,
             opt_platform_shorthand: opt_platform_id,
+            reusable_modules: None,
         })
     }
+
+    /// Skip solving any module whose solved types from an earlier load are still valid, and
+    /// keep the solved types from this load in [`LoadedModule::reusable_modules`].
+    ///
+    /// Solved types are only reused when type checking; they don't carry what later phases need.
+    pub fn reusing_modules(mut self, reusable_modules: MutMap<ModuleId, ReusableModule>) -> Self {
+        self.reusable_modules = Some(reusable_modules);
+        self
+    }
 }
 
 fn handle_root_type<'a>(
@@ -1371,6 +1563,36 @@ fn handle_root_type<'a>(
 
                 Ok((header_output, root_type))
             }
+            App {
+                to_platform: To::NewPackage(_),
+                ..
+            } if opt_main_path.is_some() => {
+                // An app whose platform never gets loaded, like the REPL's, can get its packages
+                // from the app it's standing in for, so that it can import modules from them.
+                if let (Some(main_path), Some(cache_dir)) =
+                    (opt_main_path, roc_cache_dir.as_persistent_path())
+                {
+                    let mut main_dir = main_path.clone();
+                    main_dir.pop();
+
+                    let mut messages = Vec::with_capacity(4);
+                    messages.push(header_output.msg);
+                    load_packages_from_main(
+                        arena,
+                        main_dir,
+                        main_path,
+                        &mut messages,
+                        Arc::clone(&arc_modules),
+                        Arc::clone(&ident_ids_by_module),
+                        Arc::clone(&arc_shorthands),
+                        cache_dir,
+                    )?;
+
+                    header_output.msg = Msg::Many(messages);
+                }
+
+                Ok((header_output, RootType::Main))
+            }
             App { .. } | Platform { .. } => Ok((header_output, RootType::Main)),
         }
     } else {
@@ -1604,6 +1826,7 @@ pub fn load_single_threaded<'a>(
         root_type,
         src_dir,
         opt_platform_shorthand,
+        reusable_modules,
         ..
     } = load_start;
 
@@ -1626,6 +1849,7 @@ pub fn load_single_threaded<'a>(
         ident_ids_by_module,
         arc_shorthands,
         cached_types,
+        reusable_modules,
         render,
         palette,
         number_of_workers,
@@ -1958,7 +2182,15 @@ pub fn report_loading_problem(
             available,
             render,
         ),
-        err => todo!("Loading error: {:?}", err),
+        LoadingProblem::UnexpectedHeader(header) => format!("Unexpected header: {header}"),
+        LoadingProblem::TriedToImportAppModule => "Attempted to import app module".to_string(),
+        LoadingProblem::CouldNotFindCacheDir => format!(
+            "Could not find Roc cache directory {}",
+            roc_packaging::cache::roc_cache_packages_dir().display()
+        ),
+        problem @ (LoadingProblem::ErrJoiningWorkerThreads | LoadingProblem::ChannelProblem(_)) => {
+            internal_error!("Loading error: {:?}", problem)
+        }
     }
 }
 
@@ -1985,6 +2217,7 @@ fn load_multi_threaded<'a>(
         root_type,
         src_dir,
         opt_platform_shorthand,
+        reusable_modules,
         ..
     } = load_start;
 
@@ -2022,6 +2255,7 @@ fn load_multi_threaded<'a>(
         ident_ids_by_module,
         arc_shorthands,
         cached_types,
+        reusable_modules,
         render,
         palette,
         num_workers,
@@ -2269,6 +2503,23 @@ fn update<'a>(
                     &parent_dir,
                     &state.cache_dir,
                 )?;
+
+                let platform_shorthand = match header.header_type {
+                    HeaderType::App {
+                        to_platform: To::ExistingPackage(shorthand),
+                        ..
+                    } => Some(shorthand),
+                    _ => None,
+                };
+
+                for shorthand in header.packages.keys() {
+                    state.package_dependencies.push(PackageDependency {
+                        declared_by: home,
+                        shorthand: shorthand.to_string(),
+                        root_module: shorthands[shorthand].root_module().to_path_buf(),
+                        is_platform: platform_shorthand == Some(*shorthand),
+                    });
+                }
             }
 
             use HeaderType::*;
@@ -2375,20 +2626,9 @@ fn update<'a>(
             let mut parsed = parsed;
 
             if !module_id.is_builtin() {
-                let parsed = &mut parsed;
-
-                extend_module_with_builtin_import(parsed, ModuleId::NUM);
-                extend_module_with_builtin_import(parsed, ModuleId::BOOL);
-                extend_module_with_builtin_import(parsed, ModuleId::STR);
-                extend_module_with_builtin_import(parsed, ModuleId::LIST);
-                extend_module_with_builtin_import(parsed, ModuleId::RESULT);
-                extend_module_with_builtin_import(parsed, ModuleId::DICT);
-                extend_module_with_builtin_import(parsed, ModuleId::SET);
-                extend_module_with_builtin_import(parsed, ModuleId::BOX);
-                extend_module_with_builtin_import(parsed, ModuleId::ENCODE);
-                extend_module_with_builtin_import(parsed, ModuleId::DECODE);
-                extend_module_with_builtin_import(parsed, ModuleId::HASH);
-                extend_module_with_builtin_import(parsed, ModuleId::INSPECT);
+                for (builtin, _) in BUILTIN_MODULES {
+                    extend_module_with_builtin_import(&mut parsed, *builtin);
+                }
             }
             state
                 .module_cache
@@ -2521,6 +2761,18 @@ fn update<'a>(
             log!("solved types for {:?}", module_id);
             module_timing.end_time = Instant::now();
 
+            if let Some(module_reuse) = &mut state.module_reuse {
+                let (_, source) = state.module_cache.sources.get(&module_id).unwrap();
+                module_reuse.keep_solved(
+                    module_id,
+                    source,
+                    &ident_ids,
+                    solved_subs.inner(),
+                    &solved_module,
+                    &abilities_store,
+                );
+            }
+
             state
                 .module_cache
                 .type_problems
@@ -2538,12 +2790,13 @@ fn update<'a>(
             };
 
             let opt_expectations = if should_include_expects {
-                let (path, _) = state.module_cache.sources.get(&module_id).unwrap();
+                let (path, source) = state.module_cache.sources.get(&module_id).unwrap();
 
                 Some(Expectations {
                     expectations: loc_expects,
                     subs: solved_subs.clone().into_inner(),
                     path: path.to_owned(),
+                    source: (*source).into(),
                     ident_ids: ident_ids.clone(),
                 })
             } else {
@@ -2561,7 +2814,7 @@ fn update<'a>(
 
             let add_to_host_exposed = is_host_exposed &&
                 // During testing, we don't need to expose anything to the host.
-                !matches!(state.exec_mode, ExecutionMode::Test);
+                !state.exec_mode.is_test();
 
             if add_to_host_exposed {
                 state.exposed_to_host.top_level_values.extend(
@@ -3239,7 +3492,7 @@ fn finish_specialization<'a>(
     let entry_point = {
         let interns: &mut Interns = &mut interns;
         match state.exec_mode {
-            ExecutionMode::Test => Ok(EntryPoint::Test),
+            ExecutionMode::Test | ExecutionMode::TestWithCoverage => Ok(EntryPoint::Test),
             ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck => {
                 use PlatformPath::*;
 
@@ -3384,10 +3637,14 @@ fn finish(
     //
     #[cfg(debug_assertions)] checkmate: Option<roc_checkmate::Collector>,
 ) -> LoadedModule {
-    let module_ids = Arc::try_unwrap(state.arc_modules)
+    let package_module_ids = Arc::try_unwrap(state.arc_modules)
         .unwrap_or_else(|_| panic!("There were still outstanding Arc references to module_ids"))
-        .into_inner()
-        .into_module_ids();
+        .into_inner();
+    let module_packages = package_module_ids
+        .package_shorthands()
+        .map(|(module_id, shorthand)| (module_id, shorthand.to_string()))
+        .collect();
+    let module_ids = package_module_ids.into_module_ids();
 
     // Associate the ident IDs from the derived synth module
     let (_, derived_synth_ident_ids) = Arc::try_unwrap(state.derived_module)
@@ -3418,6 +3675,11 @@ fn finish(
 
     roc_checkmate::dump_checkmate!(checkmate);
 
+    let (reusable_modules, reused_modules) = match state.module_reuse {
+        Some(module_reuse) => (module_reuse.solved, module_reuse.reused),
+        None => Default::default(),
+    };
+
     LoadedModule {
         module_id: state.root_id,
         filename: state.root_path,
@@ -3441,6 +3703,10 @@ fn finish(
         exposed_imports: state.module_cache.exposed_imports,
         imports: state.module_cache.imports,
         exposes: state.module_cache.exposes,
+        package_dependencies: state.package_dependencies,
+        module_packages,
+        reusable_modules,
+        reused_modules,
     }
 }
 
@@ -3465,13 +3731,14 @@ fn load_package_from_disk<'a>(
             let bytes = arena.alloc(bytes_vec);
             let parse_state = roc_parse::state::State::new(bytes);
             let parsed = roc_parse::header::parse_header(arena, parse_state.clone());
-            let parse_header_duration = parse_start.elapsed();
+            let parse_end = Instant::now();
 
             // Insert the first entries for this module's timings
             let mut pkg_module_timing = ModuleTiming::new(module_start_time);
 
             pkg_module_timing.read_roc_file = file_io_duration;
-            pkg_module_timing.parse_header = parse_header_duration;
+            pkg_module_timing.parse_header = parse_end.duration_since(parse_start);
+            pkg_module_timing.record(TimedPhase::ParseHeader, parse_start, parse_end);
 
             match parsed {
                 Ok((
@@ -3747,6 +4014,7 @@ fn load_module<'a>(
         "Decode", ModuleId::DECODE
         "Hash", ModuleId::HASH
         "Inspect", ModuleId::INSPECT
+        "Arbitrary", ModuleId::ARBITRARY
     }
 
     let (filename, opt_shorthand) = module_name_to_path(src_dir, &module_name, arc_shorthands);
@@ -3900,7 +4168,7 @@ fn parse_header<'a>(
     let parse_start = Instant::now();
     let parse_state = roc_parse::state::State::new(src_bytes);
     let parsed = roc_parse::header::parse_header(arena, parse_state.clone());
-    let parse_header_duration = parse_start.elapsed();
+    let parse_end = Instant::now();
 
     if let Err(problem) = ensure_roc_file(&filename, src_bytes) {
         let problem = match problem {
@@ -3924,7 +4192,8 @@ fn parse_header<'a>(
     let mut module_timing = ModuleTiming::new(start_time);
 
     module_timing.read_roc_file = read_file_duration;
-    module_timing.parse_header = parse_header_duration;
+    module_timing.parse_header = parse_end.duration_since(parse_start);
+    module_timing.record(TimedPhase::ParseHeader, parse_start, parse_end);
 
     match parsed {
         Ok((
@@ -4423,6 +4692,7 @@ impl<'a> BuildTask<'a> {
         dep_idents: IdentIdsByModule,
         declarations: Declarations,
         cached_subs: CachedTypeState,
+        reused: Option<ReusableModule>,
         derived_module: SharedDerivedModule,
         exec_mode: ExecutionMode,
         is_host_exposed: bool,
@@ -4448,6 +4718,7 @@ impl<'a> BuildTask<'a> {
             dep_idents,
             module_timing,
             cached_subs,
+            reused,
             derived_module,
             exec_mode,
             is_host_exposed,
@@ -4902,6 +5173,7 @@ fn run_solve<'a>(
     decls: Declarations,
     dep_idents: IdentIdsByModule,
     cached_types: CachedTypeState,
+    reused: Option<ReusableModule>,
     derived_module: SharedDerivedModule,
     exec_mode: ExecutionMode,
     is_host_exposed: bool,
@@ -4923,8 +5195,23 @@ fn run_solve<'a>(
     let has_dbgs = module.has_dbgs;
     let module = module;
 
+    // Only solving that actually ran shows up in the timings' spans
+    let is_reused = reused.is_some();
+
     let solve_result = {
-        if module_id.is_builtin() {
+        if let Some(reused) = reused {
+            SolveResult {
+                solved: Solved(reused.subs),
+                solved_implementations: reused.solved_implementations,
+                exposed_vars_by_symbol: reused.exposed_vars_by_symbol,
+                problems: reused.problems,
+                abilities_store: reused.abilities_store,
+                imported_modules_with_params: vec![],
+
+                #[cfg(debug_assertions)]
+                checkmate: None,
+            }
+        } else if module_id.is_builtin() {
             match cached_types.lock().remove(&module_id) {
                 None => run_solve_solve(
                     exposed_for_module,
@@ -5002,7 +5289,10 @@ fn run_solve<'a>(
         ExecutionMode::Check => {
             // Params are not lowered in check mode
         }
-        ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test => {
+        ExecutionMode::Executable
+        | ExecutionMode::ExecutableIfCheck
+        | ExecutionMode::Test
+        | ExecutionMode::TestWithCoverage => {
             roc_lower_params::type_error::remove_module_param_arguments(
                 &mut problems,
                 home_has_params,
@@ -5023,6 +5313,9 @@ fn run_solve<'a>(
     // Record the final timings
     let solve_end = Instant::now();
     module_timing.solve = solve_end.duration_since(solve_start);
+    if !is_reused {
+        module_timing.record(TimedPhase::Solve, solve_start, solve_end);
+    }
 
     // Send the subs to the main thread for processing,
     Msg::SolvedTypes {
@@ -5278,6 +5571,11 @@ fn canonicalize_and_constrain<'a>(
     let canonicalize_end = Instant::now();
 
     module_timing.canonicalize = canonicalize_end.duration_since(canonicalize_start);
+    module_timing.record(
+        TimedPhase::Canonicalize,
+        canonicalize_start,
+        canonicalize_end,
+    );
 
     // Generate documentation information
     // TODO: store timing information?
@@ -5307,7 +5605,10 @@ fn canonicalize_and_constrain<'a>(
             // No need to lower params for `roc check` and lang server
             // If we did, we'd have to update the language server to exclude the extra arguments
         }
-        ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test => {
+        ExecutionMode::Executable
+        | ExecutionMode::ExecutableIfCheck
+        | ExecutionMode::Test
+        | ExecutionMode::TestWithCoverage => {
             // We need to lower params only if the current module has any or imports at least one with params
             if module_output.module_params.is_some() || !imported_module_params.is_empty() {
                 roc_lower_params::lower::lower(
@@ -5324,6 +5625,7 @@ fn canonicalize_and_constrain<'a>(
 
     let mut constraints = Constraints::new();
 
+    let constrain_start = Instant::now();
     let constraint = if skip_constraint_gen {
         roc_can::constraint::Constraint::True
     } else {
@@ -5338,6 +5640,12 @@ fn canonicalize_and_constrain<'a>(
         )
     };
 
+    let constrain_end = Instant::now();
+    module_timing.constrain = constrain_end.duration_since(constrain_start);
+    if !skip_constraint_gen {
+        module_timing.record(TimedPhase::Constrain, constrain_start, constrain_end);
+    }
+
     // _after has an underscore because it's unused in --release builds
     let _after = roc_types::types::get_type_clone_count();
 
@@ -5371,6 +5679,7 @@ fn canonicalize_and_constrain<'a>(
                         | ModuleId::SET
                         | ModuleId::HASH
                         | ModuleId::INSPECT
+                        | ModuleId::ARBITRARY
                         | ModuleId::STR
                 );
 
@@ -5450,6 +5759,7 @@ fn parse<'a>(
     let parse_end = Instant::now();
 
     module_timing.parse_body = parse_end.duration_since(parse_start);
+    module_timing.record(TimedPhase::ParseBody, parse_start, parse_end);
 
     let mut available_modules: MutMap<ModuleId, Region> = MutMap::default();
     let exposed_values = header.header_type.exposed_or_provided_values();
@@ -5732,6 +6042,7 @@ fn make_specializations<'a>(
     exposed_by_module: &ExposedByModule,
     derived_module: SharedDerivedModule,
     mut expectations: Option<Expectations>,
+    coverage: bool,
 ) -> Msg<'a> {
     let make_specializations_start = Instant::now();
     let mut update_mode_ids = UpdateModeIds::new();
@@ -5751,6 +6062,7 @@ fn make_specializations<'a>(
         exposed_by_module,
         derived_module: &derived_module,
         struct_indexing: UsageTrackingMap::default(),
+        coverage,
     };
 
     let mut procs = Procs::new_in(arena);
@@ -5789,6 +6101,11 @@ fn make_specializations<'a>(
     module_timing
         .make_specializations
         .push(make_specializations_end.duration_since(make_specializations_start));
+    module_timing.record(
+        TimedPhase::MakeSpecializations,
+        make_specializations_start,
+        make_specializations_end,
+    );
 
     Msg::MadeSpecializations {
         module_id: home,
@@ -5821,6 +6138,7 @@ fn build_pending_specializations<'a>(
     derived_module: SharedDerivedModule,
     mut expectations: Option<Expectations>,
     build_expects: bool,
+    coverage: bool,
 ) -> Msg<'a> {
     let find_specializations_start = Instant::now();
 
@@ -5854,6 +6172,7 @@ fn build_pending_specializations<'a>(
         exposed_by_module,
         derived_module: &derived_module,
         struct_indexing: UsageTrackingMap::default(),
+        coverage,
     };
 
     let layout_cache_snapshot = layout_cache.snapshot();
@@ -6170,6 +6489,11 @@ fn build_pending_specializations<'a>(
     let find_specializations_end = Instant::now();
     module_timing.find_specializations =
         find_specializations_end.duration_since(find_specializations_start);
+    module_timing.record(
+        TimedPhase::FindSpecializations,
+        find_specializations_start,
+        find_specializations_end,
+    );
 
     Msg::FoundSpecializations {
         module_id: home,
@@ -6263,6 +6587,8 @@ fn load_derived_partial_procs<'a>(
             exposed_by_module,
             derived_module,
             struct_indexing: UsageTrackingMap::default(),
+            // Derived implementations aren't in any source file to cover.
+            coverage: false,
         };
 
         let partial_proc = match derived_expr {
@@ -6318,6 +6644,11 @@ fn load_derived_partial_procs<'a>(
 
     module_timing.find_specializations =
         load_derived_procs_end.duration_since(load_derived_procs_start);
+    module_timing.record(
+        TimedPhase::FindSpecializations,
+        load_derived_procs_start,
+        load_derived_procs_end,
+    );
 }
 
 fn run_task<'a>(
@@ -6410,6 +6741,7 @@ fn run_task<'a>(
             declarations,
             dep_idents,
             cached_subs,
+            reused,
             derived_module,
             exec_mode,
             is_host_exposed,
@@ -6430,6 +6762,7 @@ fn run_task<'a>(
             declarations,
             dep_idents,
             cached_subs,
+            reused,
             derived_module,
             exec_mode,
             is_host_exposed,
@@ -6451,6 +6784,7 @@ fn run_task<'a>(
             derived_module,
             expectations,
             build_expects,
+            coverage,
         } => Ok(build_pending_specializations(
             arena,
             solved_subs,
@@ -6467,6 +6801,7 @@ fn run_task<'a>(
             derived_module,
             expectations,
             build_expects,
+            coverage,
         )),
         MakeSpecializations {
             module_id,
@@ -6480,6 +6815,7 @@ fn run_task<'a>(
             exposed_by_module,
             derived_module,
             expectations,
+            coverage,
         } => Ok(make_specializations(
             arena,
             module_id,
@@ -6494,6 +6830,7 @@ fn run_task<'a>(
             &exposed_by_module,
             derived_module,
             expectations,
+            coverage,
         )),
     };
 
@@ -6634,7 +6971,8 @@ fn to_incorrect_module_name_report<'a>(
 
     let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
+    let region = Some(lines.convert_region(found.region));
+    report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
     (buf, found.region)
 }
 
@@ -6682,7 +7020,8 @@ fn to_no_platform_package_report(
 
     let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
+    let region = Some(lines.convert_region(region));
+    report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
     buf
 }
 
@@ -6726,7 +7065,8 @@ fn to_multiple_platform_packages_report(
 
     let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
+    let region = Some(lines.convert_region(region));
+    report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
     buf
 }
 
@@ -6833,7 +7173,8 @@ fn to_unrecognized_package_shorthand_report(
 
     let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
+    let region = Some(lines.convert_region(region));
+    report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
     buf
 }
 
@@ -6865,6 +7206,11 @@ fn to_parse_problem_report<'a>(
     let starting_line = 0;
 
     let lines = LineInfo::new(src);
+    let region = problem
+        .problem
+        .problem
+        .get_region()
+        .map(|region| lines.convert_region(region));
 
     let report = parse_problem(
         &alloc,
@@ -6876,7 +7222,7 @@ fn to_parse_problem_report<'a>(
 
     let mut buf = String::new();
 
-    report.render(render, &mut buf, &alloc, &palette);
+    report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
 
     buf
 }
@@ -0,0 +1,308 @@
+//! Fingerprints of the types a module exposes, so that the modules importing it can tell whether
+//! they've changed between loads without comparing them directly.
+//!
+//! The fingerprint follows the structure of each type rather than the [Variable]s in it, which
+//! are numbered by the order they were made in and change whenever anything else in the module
+//! does. Variables are instead numbered by when the walk first reaches them.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use roc_can::abilities::{ResolvedImpl, ResolvedImplementations};
+use roc_collections::MutMap;
+use roc_types::num::NumericRange;
+use roc_types::subs::{
+    Content, ExposedTypesStorageSubs, FlatType, GetSubsSlice, LambdaSet, Subs, TagExt, Variable,
+};
+
+/// A fingerprint of everything a module exposes to the modules that import it
+pub(crate) fn exposed_types_fingerprint(
+    exposed_types: &ExposedTypesStorageSubs,
+    solved_implementations: &ResolvedImplementations,
+) -> u64 {
+    let mut walk = TypeWalk {
+        subs: exposed_types.storage_subs.as_inner(),
+        numbers: MutMap::default(),
+        hasher: DefaultHasher::new(),
+    };
+
+    let mut exposed: Vec<_> = exposed_types.stored_vars_by_symbol.iter().collect();
+    exposed.sort_by_key(|(symbol, _)| **symbol);
+
+    for (symbol, var) in exposed {
+        walk.write(symbol);
+        walk.var(*var);
+    }
+
+    walk.write(&exposed_types.stored_params_var.is_some());
+    if let Some(params_var) = exposed_types.stored_params_var {
+        walk.var(params_var);
+    }
+
+    let mut implementations: Vec<_> = solved_implementations.iter().collect();
+    implementations.sort_by_key(|(key, _)| (key.opaque, key.ability_member));
+
+    for (key, resolved) in implementations {
+        walk.write(&(key.opaque, key.ability_member));
+
+        match resolved {
+            ResolvedImpl::Impl(specialization) => {
+                walk.write(&specialization.symbol);
+
+                // The lambda sets are keyed by variables in the module's own subs, so only the
+                // exposed copies of them can be compared
+                for (region, lset_var) in specialization.specialization_lambda_sets.iter() {
+                    walk.write(region);
+
+                    let stored = exposed_types
+                        .stored_specialization_lambda_set_vars
+                        .get(lset_var);
+
+                    walk.write(&stored.is_some());
+                    if let Some(stored) = stored {
+                        walk.var(*stored);
+                    }
+                }
+            }
+            ResolvedImpl::Error => walk.write(&"error"),
+        }
+    }
+
+    walk.hasher.finish()
+}
+
+struct TypeWalk<'a> {
+    subs: &'a Subs,
+    /// The number of each variable reached so far, in the order it was reached
+    numbers: MutMap<Variable, usize>,
+    hasher: DefaultHasher,
+}
+
+impl TypeWalk<'_> {
+    fn write<T: Hash + ?Sized>(&mut self, value: &T) {
+        value.hash(&mut self.hasher);
+    }
+
+    fn vars(&mut self, vars: &[Variable]) {
+        self.write(&vars.len());
+
+        for var in vars {
+            self.var(*var);
+        }
+    }
+
+    fn var(&mut self, var: Variable) {
+        let root = self.subs.get_root_key_without_compacting(var);
+
+        // Types can be recursive, so a variable that's been reached before is only referred to
+        if let Some(number) = self.numbers.get(&root) {
+            self.write(&("seen", *number));
+
+            return;
+        }
+
+        self.numbers.insert(root, self.numbers.len());
+
+        let subs = self.subs;
+
+        match subs.get_content_without_compacting(root) {
+            Content::FlexVar(opt_name) => {
+                self.write(&"flex");
+                self.write(&opt_name.map(|name| subs[name].as_str()));
+            }
+            Content::RigidVar(name) => {
+                self.write(&"rigid");
+                self.write(subs[*name].as_str());
+            }
+            Content::FlexAbleVar(opt_name, abilities) => {
+                self.write(&"flex able");
+                self.write(&opt_name.map(|name| subs[name].as_str()));
+                self.write(subs.get_subs_slice(*abilities));
+            }
+            Content::RigidAbleVar(name, abilities) => {
+                self.write(&"rigid able");
+                self.write(subs[*name].as_str());
+                self.write(subs.get_subs_slice(*abilities));
+            }
+            Content::RecursionVar {
+                structure,
+                opt_name,
+            } => {
+                self.write(&"recursion");
+                self.write(&opt_name.map(|name| subs[name].as_str()));
+                self.var(*structure);
+            }
+            Content::LambdaSet(LambdaSet {
+                solved,
+                recursion_var,
+                unspecialized,
+                ambient_function,
+            }) => {
+                self.write(&"lambda set");
+
+                for (symbol, captures) in solved.iter_from_subs(subs) {
+                    self.write(symbol);
+                    self.vars(captures);
+                }
+
+                self.write(&recursion_var.is_some());
+                if let Some(recursion_var) = recursion_var.into_variable() {
+                    self.var(recursion_var);
+                }
+
+                for uls in subs.get_subs_slice(*unspecialized) {
+                    self.write(&(uls.1, uls.2));
+                    self.var(uls.0);
+                }
+
+                self.var(*ambient_function);
+            }
+            Content::ErasedLambda => self.write(&"erased lambda"),
+            Content::Structure(flat_type) => self.flat_type(flat_type),
+            Content::Alias(symbol, variables, real_var, kind) => {
+                self.write(&"alias");
+                self.write(&(symbol, kind));
+                self.vars(subs.get_subs_slice(variables.all_variables()));
+                self.var(*real_var);
+            }
+            Content::RangedNumber(range) => {
+                self.write(&"ranged number");
+
+                let (name, width) = match range {
+                    NumericRange::IntAtLeastSigned(width) => ("int signed", width),
+                    NumericRange::IntAtLeastEitherSign(width) => ("int either sign", width),
+                    NumericRange::NumAtLeastSigned(width) => ("num signed", width),
+                    NumericRange::NumAtLeastEitherSign(width) => ("num either sign", width),
+                };
+
+                self.write(&(name, *width as u8));
+            }
+            Content::Error => self.write(&"error"),
+            Content::Pure => self.write(&"pure"),
+            Content::Effectful => self.write(&"effectful"),
+        }
+    }
+
+    fn flat_type(&mut self, flat_type: &FlatType) {
+        let subs = self.subs;
+
+        match flat_type {
+            FlatType::Apply(symbol, args) => {
+                self.write(&("apply", symbol));
+                self.vars(subs.get_subs_slice(*args));
+            }
+            FlatType::Func(args, closure, ret, fx) => {
+                self.write(&"func");
+                self.vars(subs.get_subs_slice(*args));
+                self.var(*closure);
+                self.var(*ret);
+                self.var(*fx);
+            }
+            FlatType::EffectfulFunc => self.write(&"effectful func"),
+            FlatType::Record(fields, ext) => {
+                self.write(&"record");
+
+                for (name, field) in fields.sorted_iterator(subs, *ext) {
+                    self.write(name.as_str());
+                    self.write(&std::mem::discriminant(&field));
+                    self.var(field.into_inner());
+                }
+            }
+            FlatType::Tuple(elems, ext) => {
+                self.write(&"tuple");
+
+                for (index, elem) in elems.sorted_iterator(subs, *ext) {
+                    self.write(&index);
+                    self.var(elem);
+                }
+            }
+            FlatType::TagUnion(tags, ext) => {
+                self.write(&"tag union");
+                self.tags(tags.iter_from_subs(subs));
+                self.ext(*ext);
+            }
+            FlatType::FunctionOrTagUnion(tag_names, symbols, ext) => {
+                self.write(&"function or tag union");
+                self.write(subs.get_subs_slice(*tag_names));
+                self.write(subs.get_subs_slice(*symbols));
+                self.ext(*ext);
+            }
+            FlatType::RecursiveTagUnion(recursion_var, tags, ext) => {
+                self.write(&"recursive tag union");
+                self.var(*recursion_var);
+                self.tags(tags.iter_from_subs(subs));
+                self.ext(*ext);
+            }
+            FlatType::EmptyRecord => self.write(&"empty record"),
+            FlatType::EmptyTagUnion => self.write(&"empty tag union"),
+        }
+    }
+
+    fn tags<'t, T: Hash + 't>(&mut self, tags: impl Iterator<Item = (&'t T, &'t [Variable])>) {
+        for (tag_name, payloads) in tags {
+            self.write(tag_name);
+            self.vars(payloads);
+        }
+    }
+
+    fn ext(&mut self, ext: TagExt) {
+        self.write(&matches!(ext, TagExt::Openness(_)));
+        self.var(ext.var());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roc_module::symbol::Symbol;
+    use roc_types::subs::{StorageSubs, SubsSlice};
+
+    fn exposed_str(unrelated_vars: usize) -> ExposedTypesStorageSubs {
+        let mut subs = Subs::new();
+
+        for _ in 0..unrelated_vars {
+            subs.fresh_unnamed_flex_var();
+        }
+
+        let str_var = subs.fresh_unnamed_flex_var();
+        subs.set_content(
+            str_var,
+            Content::Structure(FlatType::Apply(Symbol::STR_STR, SubsSlice::default())),
+        );
+
+        ExposedTypesStorageSubs {
+            storage_subs: StorageSubs::new(subs),
+            stored_vars_by_symbol: [(Symbol::STR_STR, str_var)].into_iter().collect(),
+            stored_specialization_lambda_set_vars: Default::default(),
+            stored_ability_member_vars: Default::default(),
+            stored_params_var: None,
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_variable_numbering() {
+        let implementations = ResolvedImplementations::default();
+
+        assert_eq!(
+            exposed_types_fingerprint(&exposed_str(0), &implementations),
+            exposed_types_fingerprint(&exposed_str(5), &implementations),
+        );
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_type() {
+        let implementations = ResolvedImplementations::default();
+        let str_exposed = exposed_str(0);
+        let mut num_exposed = exposed_str(0);
+        let var = num_exposed.stored_vars_by_symbol.iter().next().unwrap().1;
+        num_exposed.storage_subs.as_inner_mut().set_content(
+            *var,
+            Content::Structure(FlatType::Apply(Symbol::NUM_NUM, SubsSlice::default())),
+        );
+
+        assert_ne!(
+            exposed_types_fingerprint(&str_exposed, &implementations),
+            exposed_types_fingerprint(&num_exposed, &implementations),
+        );
+    }
+}
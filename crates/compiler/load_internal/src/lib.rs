@@ -6,12 +6,14 @@
 use roc_module::symbol::ModuleId;
 pub mod docs;
 pub mod file;
+mod fingerprint;
 pub mod module;
 mod module_cache;
 
 #[cfg(target_family = "wasm")]
 mod wasm_instant;
 
+/// The builtin modules, which every other module imports automatically
 pub const BUILTIN_MODULES: &[(ModuleId, &str)] = &[
     (ModuleId::BOOL, "Bool"),
     (ModuleId::RESULT, "Result"),
@@ -25,4 +27,5 @@ pub const BUILTIN_MODULES: &[(ModuleId, &str)] = &[
     (ModuleId::DECODE, "Decode"),
     (ModuleId::HASH, "Hash"),
     (ModuleId::INSPECT, "Inspect"),
+    (ModuleId::ARBITRARY, "Arbitrary"),
 ];
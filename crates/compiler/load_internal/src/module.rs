@@ -21,6 +21,7 @@ use roc_solve_problem::TypeError;
 use roc_types::subs::{ExposedTypesStorageSubs, Subs, VarStore, Variable};
 use roc_types::types::{Alias, Types};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(target_family = "wasm")]
 use crate::wasm_instant::{Duration, Instant};
@@ -52,6 +53,18 @@ pub struct LoadedModule {
     pub imports: MutMap<ModuleId, MutSet<ModuleId>>,
     pub exposed_imports: MutMap<ModuleId, MutMap<Symbol, Region>>,
     pub exposes: MutMap<ModuleId, Vec<(Symbol, Variable)>>,
+
+    pub package_dependencies: Vec<PackageDependency>,
+    /// The shorthand that each module from a package was imported through, like `json` for
+    /// `json.Decode`. Modules of the root's own package aren't in here.
+    pub module_packages: MutMap<ModuleId, String>,
+
+    /// The solved types of every module, if the load was started with
+    /// [`LoadStart::reusing_modules`](crate::file::LoadStart::reusing_modules)
+    pub reusable_modules: MutMap<ModuleId, ReusableModule>,
+    /// The modules whose solved types came from the ones the load was started with, rather than
+    /// being solved again
+    pub reused_modules: MutSet<ModuleId>,
 }
 
 impl LoadedModule {
@@ -91,6 +104,18 @@ impl LoadedModule {
     }
 }
 
+/// A package that a module's header depends on, like `json: "https://..."` in an app header.
+#[derive(Debug, Clone)]
+pub struct PackageDependency {
+    /// The module whose header declared the package
+    pub declared_by: ModuleId,
+    pub shorthand: String,
+    /// The package's `main.roc`, or whatever its root module is
+    pub root_module: PathBuf,
+    /// Whether this is the platform that the app is for
+    pub is_platform: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct ModuleHeader<'a> {
     pub(crate) module_id: ModuleId,
@@ -144,6 +169,26 @@ pub struct CheckedModule {
     pub abilities_store: AbilitiesStore,
 }
 
+/// The solved types of a module, kept from one load so that a later load of the same module
+/// graph can skip solving the module again.
+///
+/// They are only reused if the module's source and ident IDs are unchanged, and if every
+/// module it imports exposes exactly the same types as it did when these were solved. When the
+/// source of everything the module imports is unchanged too, constraint generation is skipped
+/// as well. The module is always parsed and canonicalized again.
+#[derive(Debug, Clone)]
+pub struct ReusableModule {
+    pub(crate) source: Box<str>,
+    pub(crate) ident_ids: IdentIds,
+    /// A fingerprint of the exposed types of each non-builtin import
+    pub(crate) import_fingerprints: VecMap<ModuleId, u64>,
+    pub(crate) subs: Subs,
+    pub(crate) exposed_vars_by_symbol: Vec<(Symbol, Variable)>,
+    pub(crate) abilities_store: AbilitiesStore,
+    pub(crate) solved_implementations: ResolvedImplementations,
+    pub(crate) problems: Vec<TypeError>,
+}
+
 #[derive(Debug)]
 pub(crate) struct FoundSpecializationsModule<'a> {
     pub(crate) ident_ids: IdentIds,
@@ -222,6 +267,8 @@ pub enum EntryPoint<'a> {
 pub struct Expectations {
     pub subs: roc_types::subs::Subs,
     pub path: PathBuf,
+    /// The source the module was loaded from, which may not be what's on disk
+    pub source: Box<str>,
     pub expectations: VecMap<Region, Vec<ExpectLookup>>,
     pub ident_ids: IdentIds,
 }
@@ -250,6 +297,9 @@ pub struct ModuleTiming {
     // indexed by make specializations pass
     pub make_specializations: Vec<Duration>,
     // TODO pub monomorphize: Duration,
+    /// When each phase ran and on which thread, for `roc build --timings`. Phases that were
+    /// skipped because an earlier load's results were reused have no span.
+    pub spans: Vec<PhaseSpan>,
     /// Total duration will always be more than the sum of the other fields, due
     /// to things like state lookups in between phases, waiting on other threads, etc.
     pub start_time: Instant,
@@ -268,6 +318,7 @@ impl ModuleTiming {
             solve: Duration::default(),
             find_specializations: Duration::default(),
             make_specializations: Vec::with_capacity(2),
+            spans: Vec::with_capacity(8),
             start_time,
             end_time: start_time, // just for now; we'll overwrite this at the end
         }
@@ -277,6 +328,16 @@ impl ModuleTiming {
         self.end_time.duration_since(self.start_time)
     }
 
+    /// Records that `phase` ran from `start` to `end` on the current thread.
+    pub fn record(&mut self, phase: TimedPhase, start: Instant, end: Instant) {
+        self.spans.push(PhaseSpan {
+            phase,
+            start,
+            end,
+            thread: current_thread_index(),
+        });
+    }
+
     /// Subtract all the other fields from total_start_to_finish
     pub fn other(&self) -> Duration {
         let Self {
@@ -289,6 +350,7 @@ impl ModuleTiming {
             solve,
             find_specializations,
             make_specializations,
+            spans: _,
             start_time,
             end_time,
         } = self;
@@ -310,3 +372,50 @@ impl ModuleTiming {
         calculate(Some(end_time.duration_since(*start_time))).unwrap_or_default()
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimedPhase {
+    ParseHeader,
+    ParseBody,
+    Canonicalize,
+    Constrain,
+    Solve,
+    FindSpecializations,
+    MakeSpecializations,
+}
+
+impl TimedPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            TimedPhase::ParseHeader => "Parse header",
+            TimedPhase::ParseBody => "Parse body",
+            TimedPhase::Canonicalize => "Canonicalize",
+            TimedPhase::Constrain => "Constrain",
+            TimedPhase::Solve => "Solve",
+            TimedPhase::FindSpecializations => "Find specializations",
+            TimedPhase::MakeSpecializations => "Make specializations",
+        }
+    }
+}
+
+/// A phase of compiling one module, from start to end.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseSpan {
+    pub phase: TimedPhase,
+    pub start: Instant,
+    pub end: Instant,
+    /// Which thread did the work, as numbered by [current_thread_index].
+    pub thread: usize,
+}
+
+/// A small number for the current thread, which stays the same for as long as the thread lives.
+/// Threads are numbered from 0 in the order they first ask.
+pub fn current_thread_index() -> usize {
+    static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static INDEX: usize = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+    }
+
+    INDEX.with(|index| *index)
+}
@@ -17,11 +17,12 @@ mod helpers;
 use crate::helpers::fixtures_dir;
 use bumpalo::Bump;
 use roc_can::module::ExposedByModule;
+use roc_collections::MutMap;
 use roc_load_internal::docs::DocDef;
 use roc_load_internal::file::{
     ExecutionMode, LoadConfig, LoadResult, LoadStart, LoadingProblem, Threading,
 };
-use roc_load_internal::module::LoadedModule;
+use roc_load_internal::module::{LoadedModule, ReusableModule, TimedPhase};
 use roc_module::ident::ModuleName;
 use roc_module::symbol::{Interns, ModuleId};
 use roc_packaging::cache::RocCacheDir;
@@ -672,6 +673,131 @@ fn iface_dep_types() {
     );
 }
 
+fn load_reusing_modules(
+    filename: PathBuf,
+    reusable_modules: MutMap<ModuleId, ReusableModule>,
+) -> LoadedModule {
+    let arena = Bump::new();
+    let load_start = LoadStart::from_path(
+        &arena,
+        filename,
+        None,
+        RenderTarget::Generic,
+        RocCacheDir::Disallowed,
+        DEFAULT_PALETTE,
+    )
+    .unwrap()
+    .reusing_modules(reusable_modules);
+    let load_config = LoadConfig {
+        target: TARGET,
+        function_kind: FunctionKind::LambdaSet,
+        render: RenderTarget::Generic,
+        palette: DEFAULT_PALETTE,
+        threading: Threading::Single,
+        exec_mode: ExecutionMode::Check,
+    };
+
+    match roc_load_internal::file::load(
+        &arena,
+        load_start,
+        Default::default(),
+        Default::default(),
+        RocCacheDir::Disallowed,
+        load_config,
+    )
+    .unwrap()
+    {
+        LoadResult::Monomorphized(_) => unreachable!(""),
+        LoadResult::TypeChecked(module) => module,
+    }
+}
+
+/// Whether the module's constraints were generated or its types solved during the load.
+fn was_checked(loaded_module: &LoadedModule, module_id: ModuleId) -> bool {
+    loaded_module.timings[&module_id]
+        .spans
+        .iter()
+        .any(|span| matches!(span.phase, TimedPhase::Constrain | TimedPhase::Solve))
+}
+
+#[test]
+fn iface_dep_types_reusing_modules() {
+    let filename = fixtures_dir().join("module_with_deps").join("Primary.roc");
+
+    let first = load_reusing_modules(filename.clone(), Default::default());
+    assert!(first.reusable_modules.contains_key(&first.module_id));
+    assert!(was_checked(&first, first.module_id));
+
+    // Nothing has changed, so every module's types come from the first load
+    let second = load_reusing_modules(filename, first.reusable_modules);
+
+    let modules: Vec<ModuleId> = second
+        .timings
+        .keys()
+        .copied()
+        .filter(|module_id| !module_id.is_builtin())
+        .collect();
+    assert!(modules.len() > 1);
+    for module_id in modules {
+        assert!(second.reused_modules.contains(&module_id));
+        assert!(
+            !was_checked(&second, module_id),
+            "{:?} was checked again",
+            second.interns.module_name(module_id)
+        );
+    }
+
+    expect_types(
+        second,
+        hashmap! {
+            "blah2" => "Frac *",
+            "blah3" => "Str",
+            "str" => "Str",
+            "always_three" => "* -> Frac *",
+            "identity" => "a -> a",
+            "z" => "Frac *",
+            "w" => "Dep1.Identity {}",
+            "succeed" => "a -> Dep1.Identity a",
+            "yay" => "Res.Res {} err",
+            "with_default" => "Res.Res a err, a -> a",
+        },
+    );
+}
+
+#[test]
+fn changed_dep_type_invalidates_reused_modules() {
+    let dir = TmpDir::new("tmp/changed_dep_type_invalidates_reused_modules");
+    let dep_path = dir.path().join("Dep.roc");
+    let main_path = dir.path().join("Main.roc");
+
+    std::fs::write(&dep_path, "module [value]\n\nvalue = 1\n").unwrap();
+    std::fs::write(
+        &main_path,
+        indoc!(
+            r#"
+            module [main]
+
+            import Dep
+
+            main = Dep.value
+            "#
+        ),
+    )
+    .unwrap();
+
+    let first = load_reusing_modules(main_path.clone(), Default::default());
+
+    std::fs::write(&dep_path, "module [value]\n\nvalue = \"one\"\n").unwrap();
+
+    let second = load_reusing_modules(main_path, first.reusable_modules);
+
+    // Main's own source didn't change, but the type it imports did
+    assert!(!second.reused_modules.contains(&second.module_id));
+    assert!(was_checked(&second, second.module_id));
+
+    expect_types(second, hashmap! { "main" => "Str" });
+}
+
 #[test]
 fn app_dep_types() {
     let subs_by_module = Default::default();
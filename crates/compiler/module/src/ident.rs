@@ -130,6 +130,7 @@ impl ModuleName {
     pub const DECODE: &'static str = "Decode";
     pub const HASH: &'static str = "Hash";
     pub const INSPECT: &'static str = "Inspect";
+    pub const ARBITRARY: &'static str = "Arbitrary";
 
     pub fn as_str(&self) -> &str {
         self.0.as_str()
@@ -56,6 +56,10 @@ pub const DERIVABLE_ABILITIES: &[(Symbol, &[Symbol])] = &[
         Symbol::INSPECT_INSPECT_ABILITY,
         &[Symbol::INSPECT_TO_INSPECTOR],
     ),
+    (
+        Symbol::ARBITRARY_ARBITRARY_ABILITY,
+        &[Symbol::ARBITRARY_ARBITRARY],
+    ),
 ];
 
 /// In Debug builds only, Symbol has a name() method that lets
@@ -164,6 +168,19 @@ impl Symbol {
         u64::from_ne_bytes(self.to_ne_bytes())
     }
 
+    /// The inverse of [Symbol::as_u64], for any value that it produced, whether that was passed
+    /// to compiled code or stored somewhere else, like the `data` of a language server request.
+    pub fn from_u64(raw: u64) -> Option<Symbol> {
+        let bytes = raw.to_ne_bytes();
+        let ident_id = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let module_id = u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        Some(Self {
+            ident_id,
+            module_id: NonZeroU32::new(module_id)?,
+        })
+    }
+
     pub fn fully_qualified(self, interns: &Interns, home: ModuleId) -> ModuleName {
         let module_id = self.module_id();
 
@@ -364,7 +381,7 @@ static DEBUG_IDENT_IDS_BY_MODULE_ID: std::sync::Mutex<roc_collections::VecMap<u3
     std::sync::Mutex::new(roc_collections::VecMap::new());
 
 /// A globally unique ID that gets assigned to each module as it is loaded.
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ModuleId(NonZeroU32);
 
 impl ModuleId {
@@ -385,6 +402,19 @@ impl ModuleId {
         (self.0.get() - 1) as usize
     }
 
+    /// For passing module ids to compiled code. Never 0.
+    pub const fn to_u32(self) -> u32 {
+        self.0.get()
+    }
+
+    /// The inverse of [ModuleId::to_u32].
+    pub const fn from_u32(raw: u32) -> Option<Self> {
+        match NonZeroU32::new(raw) {
+            Some(id) => Some(ModuleId(id)),
+            None => None,
+        }
+    }
+
     #[cfg(any(debug_assertions, feature = "debug-symbols"))]
     pub fn register_debug_idents(self, ident_ids: &IdentIds) {
         let mut all = DEBUG_IDENT_IDS_BY_MODULE_ID.lock().expect("Failed to acquire lock for Debug interning into DEBUG_MODULE_ID_NAMES, presumably because a thread panicked.");
@@ -559,6 +589,20 @@ impl<'a> PackageModuleIds<'a> {
         self.by_id.iter()
     }
 
+    /// The shorthand of the package that each package module came from, like `json` for
+    /// `json.Decode`.
+    pub fn package_shorthands(&self) -> impl Iterator<Item = (ModuleId, &'a str)> + '_ {
+        self.by_id
+            .iter()
+            .enumerate()
+            .filter_map(|(index, name)| match name {
+                PQModuleName::Qualified(shorthand, _) => {
+                    Some((ModuleId::from_zero_indexed(index), *shorthand))
+                }
+                PQModuleName::Unqualified(_) => None,
+            })
+    }
+
     /// Returns true iff two modules belong to the same package.
     /// Returns [None] if one module is unknown.
     pub fn package_eq(&self, left: ModuleId, right: ModuleId) -> Option<bool> {
@@ -1735,6 +1779,30 @@ define_builtins! {
         32 INSPECT_TO_INSPECTOR: "to_inspector"
         33 INSPECT_TO_STR: "to_str"
     }
-
-    num_modules: 15 // Keep this count up to date by hand! (TODO: see the mut_map! macro for how we could determine this count correctly in the macro)
+    15 ARBITRARY: "Arbitrary" => {
+        0 ARBITRARY_ARBITRARY_ABILITY: "Arbitrary" exposed_type=true
+        1 ARBITRARY_SOURCE: "Source" exposed_type=true
+        2 ARBITRARY_ARBITRARY: "arbitrary"
+        3 ARBITRARY_DRAW: "draw"
+        4 ARBITRARY_BOOL: "bool"
+        5 ARBITRARY_U8: "u8"
+        6 ARBITRARY_U16: "u16"
+        7 ARBITRARY_U32: "u32"
+        8 ARBITRARY_U64: "u64"
+        9 ARBITRARY_U128: "u128"
+        10 ARBITRARY_I8: "i8"
+        11 ARBITRARY_I16: "i16"
+        12 ARBITRARY_I32: "i32"
+        13 ARBITRARY_I64: "i64"
+        14 ARBITRARY_I128: "i128"
+        15 ARBITRARY_F32: "f32"
+        16 ARBITRARY_F64: "f64"
+        17 ARBITRARY_DEC: "dec"
+        18 ARBITRARY_STR: "str"
+        19 ARBITRARY_LIST: "list"
+        20 ARBITRARY_CHECK: "check"
+        21 ARBITRARY_COUNTEREXAMPLE: "counterexample"
+    }
+
+    num_modules: 16 // Keep this count up to date by hand! (TODO: see the mut_map! macro for how we could determine this count correctly in the macro)
 }
@@ -0,0 +1,169 @@
+//! Instrumentation for `roc test --coverage`.
+//!
+//! When [Env::coverage](crate::ir::Env) is set, every top-level def and every `if` arm and `when`
+//! branch counts its hits by calling [COVERAGE_HIT] with its [CoveragePoint], encoded as two
+//! `U64`s. Backends leave it to whoever runs the compiled code to provide that function.
+
+use std::collections::BTreeSet;
+
+use roc_collections::MutMap;
+use roc_module::ident::ForeignSymbol;
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_region::all::{Position, Region};
+
+use crate::foreign_calls::visit_lets;
+use crate::ir::{Call, CallType, Env, Expr, Literal, Proc, Stmt};
+use crate::layout::Layout;
+
+/// The foreign function that instrumented code calls each time it reaches a [CoveragePoint].
+pub const COVERAGE_HIT: &str = "roc_coverage_hit";
+
+/// The second argument to [COVERAGE_HIT] for top-level defs. No region ends before it starts.
+const DEF_MARKER: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CoveragePoint {
+    /// The body of a top-level def, or of a function that's defined in one
+    Def(Symbol),
+    /// An `if` arm or `when` branch, by the region of the expression it evaluates to
+    Branch(ModuleId, Region),
+}
+
+impl CoveragePoint {
+    pub fn encode(self) -> (u64, u64) {
+        match self {
+            CoveragePoint::Def(symbol) => (symbol.as_u64(), DEF_MARKER),
+            CoveragePoint::Branch(module_id, region) => {
+                let start = region.start().offset as u64;
+                let end = region.end().offset as u64;
+
+                (module_id.to_u32() as u64, (start << 32) | end)
+            }
+        }
+    }
+
+    /// The inverse of [CoveragePoint::encode], for the arguments [COVERAGE_HIT] was called with.
+    pub fn decode(first: u64, second: u64) -> Option<Self> {
+        if second == DEF_MARKER {
+            return Symbol::from_u64(first).map(CoveragePoint::Def);
+        }
+
+        let module_id = ModuleId::from_u32(u32::try_from(first).ok()?)?;
+        let start = Position::new((second >> 32) as u32);
+        let end = Position::new(second as u32);
+
+        Some(CoveragePoint::Branch(module_id, Region::new(start, end)))
+    }
+}
+
+/// Prefixes `stmt` with a call to [COVERAGE_HIT] for `point`, if coverage is on.
+pub(crate) fn count_hits<'a>(
+    env: &mut Env<'a, '_>,
+    point: CoveragePoint,
+    stmt: Stmt<'a>,
+) -> Stmt<'a> {
+    if !env.coverage {
+        return stmt;
+    }
+
+    let (first, second) = point.encode();
+    let first_symbol = env.unique_symbol();
+    let second_symbol = env.unique_symbol();
+    let unit_symbol = env.unique_symbol();
+
+    let call = Call {
+        call_type: CallType::Foreign {
+            foreign_symbol: ForeignSymbol::from(COVERAGE_HIT),
+            ret_layout: Layout::UNIT,
+        },
+        arguments: env.arena.alloc([first_symbol, second_symbol]),
+    };
+
+    let stmt = Stmt::Let(
+        unit_symbol,
+        Expr::Call(call),
+        Layout::UNIT,
+        env.arena.alloc(stmt),
+    );
+    let stmt = Stmt::Let(
+        second_symbol,
+        u64_literal(second),
+        Layout::U64,
+        env.arena.alloc(stmt),
+    );
+
+    Stmt::Let(
+        first_symbol,
+        u64_literal(first),
+        Layout::U64,
+        env.arena.alloc(stmt),
+    )
+}
+
+/// Prefixes the statement for an `if` arm or `when` branch with a call to [COVERAGE_HIT].
+pub(crate) fn count_branch_hits<'a>(
+    env: &mut Env<'a, '_>,
+    region: Region,
+    stmt: Stmt<'a>,
+) -> Stmt<'a> {
+    let point = CoveragePoint::Branch(env.home, region);
+
+    count_hits(env, point, stmt)
+}
+
+fn u64_literal<'a>(value: u64) -> Expr<'a> {
+    Expr::Literal(Literal::Int((value as i128).to_ne_bytes()))
+}
+
+/// Every point that the procedures were instrumented with, whether or not it's ever reached.
+pub fn coverage_points<'a, 'p>(
+    procs: impl IntoIterator<Item = &'p Proc<'a>>,
+) -> BTreeSet<CoveragePoint>
+where
+    'a: 'p,
+{
+    let mut points = BTreeSet::new();
+
+    for proc in procs {
+        let mut literals = MutMap::default();
+
+        visit_lets(&proc.body, &mut |symbol, expr| match expr {
+            Expr::Literal(Literal::Int(bytes)) => {
+                literals.insert(symbol, i128::from_ne_bytes(*bytes) as u64);
+            }
+            Expr::Call(Call {
+                call_type: CallType::Foreign { foreign_symbol, .. },
+                arguments: [first, second],
+            }) if foreign_symbol.as_str() == COVERAGE_HIT => {
+                let point = literals
+                    .get(first)
+                    .zip(literals.get(second))
+                    .and_then(|(first, second)| CoveragePoint::decode(*first, *second));
+
+                points.extend(point);
+            }
+            _ => {}
+        });
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_round_trip_through_their_encoding() {
+        let def = CoveragePoint::Def(Symbol::LIST_MAP);
+        let branch = CoveragePoint::Branch(
+            ModuleId::LIST,
+            Region::new(Position::new(12), Position::new(40)),
+        );
+
+        for point in [def, branch] {
+            let (first, second) = point.encode();
+            assert_eq!(CoveragePoint::decode(first, second), Some(point));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Finding the calls that procedures make to foreign functions. Those are either effects, which a
+//! platform's host provides, like `roc_fx_line`, or functions that the compiler generates calls
+//! to, like [COVERAGE_HIT].
+
+use std::collections::BTreeSet;
+
+use roc_module::symbol::Symbol;
+
+use crate::coverage::COVERAGE_HIT;
+use crate::ir::{Call, CallType, Expr, Proc, Stmt};
+
+/// The foreign functions that the compiler generates calls to, which no host provides.
+pub const COMPILER_INTERNAL: &[&str] = &[COVERAGE_HIT];
+
+/// The effects that `procs` call, which their platform's host has to provide.
+pub fn effects_called<'a, 'p>(procs: impl IntoIterator<Item = &'p Proc<'a>>) -> BTreeSet<String>
+where
+    'a: 'p,
+{
+    let mut effects = BTreeSet::new();
+
+    for proc in procs {
+        visit_lets(&proc.body, &mut |_, expr| {
+            if let Expr::Call(Call {
+                call_type: CallType::Foreign { foreign_symbol, .. },
+                ..
+            }) = expr
+            {
+                if !COMPILER_INTERNAL.contains(&foreign_symbol.as_str()) {
+                    effects.insert(foreign_symbol.as_str().to_string());
+                }
+            }
+        });
+    }
+
+    effects
+}
+
+/// Calls `visit` with every symbol that `stmt` binds and the expr that it's bound to, in order,
+/// which includes the foreign calls along with whatever their arguments were bound to.
+pub(crate) fn visit_lets<'a>(stmt: &Stmt<'a>, visit: &mut impl FnMut(Symbol, &Expr<'a>)) {
+    let mut stmt = stmt;
+
+    loop {
+        match stmt {
+            Stmt::Let(symbol, expr, _, continuation) => {
+                visit(*symbol, expr);
+
+                stmt = continuation;
+            }
+            Stmt::Switch {
+                branches,
+                default_branch,
+                ..
+            } => {
+                for (_, _, branch) in branches.iter() {
+                    visit_lets(branch, visit);
+                }
+
+                stmt = default_branch.1;
+            }
+            Stmt::Join {
+                body, remainder, ..
+            } => {
+                visit_lets(body, visit);
+
+                stmt = remainder;
+            }
+            Stmt::Refcounting(_, continuation) => stmt = continuation,
+            Stmt::Expect { remainder, .. } | Stmt::Dbg { remainder, .. } => stmt = remainder,
+            Stmt::Ret(_) | Stmt::Jump(..) | Stmt::Crash(..) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::SelfRecursive;
+    use crate::layout::{LambdaName, Layout};
+    use bumpalo::Bump;
+
+    #[test]
+    fn effects_called_leaves_out_compiler_internal_calls() {
+        let arena = Bump::new();
+        let line = Expr::Call(Call {
+            call_type: CallType::Foreign {
+                foreign_symbol: "roc_fx_line".into(),
+                ret_layout: Layout::UNIT,
+            },
+            arguments: arena.alloc([Symbol::ARG_1]),
+        });
+        let hit = Expr::Call(Call {
+            call_type: CallType::Foreign {
+                foreign_symbol: COVERAGE_HIT.into(),
+                ret_layout: Layout::UNIT,
+            },
+            arguments: arena.alloc([Symbol::ARG_1, Symbol::ARG_1]),
+        });
+        let body = Stmt::Let(
+            Symbol::ARG_4,
+            hit,
+            Layout::UNIT,
+            arena.alloc(Stmt::Let(
+                Symbol::ARG_2,
+                line,
+                Layout::UNIT,
+                arena.alloc(Stmt::Ret(Symbol::ARG_2)),
+            )),
+        );
+        let proc = Proc {
+            name: LambdaName::no_niche(Symbol::ARG_3),
+            args: arena.alloc([(Layout::STR, Symbol::ARG_1)]),
+            body,
+            closure_data_layout: None,
+            ret_layout: Layout::UNIT,
+            is_self_recursive: SelfRecursive::NotSelfRecursive,
+            is_erased: false,
+        };
+
+        let effects = effects_called([&proc]);
+
+        assert_eq!(effects, BTreeSet::from(["roc_fx_line".to_string()]));
+    }
+}
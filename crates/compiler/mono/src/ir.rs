@@ -1,5 +1,6 @@
 #![allow(clippy::manual_map)]
 
+use crate::coverage::{count_branch_hits, count_hits, CoveragePoint};
 use crate::ir::erased::{build_erased_function, ResolvedErasedLambda};
 use crate::ir::literal::{make_num_literal, IntOrFloatValue};
 use crate::layout::{
@@ -1373,6 +1374,8 @@ pub struct Env<'a, 'i> {
     pub exposed_by_module: &'i ExposedByModule,
     pub derived_module: &'i SharedDerivedModule,
     pub struct_indexing: UsageTrackingMap<(Symbol, u64), Symbol>,
+    /// Whether to count how often each def and branch is reached, for `roc test --coverage`.
+    pub coverage: bool,
 }
 
 impl<'a, 'i> Env<'a, 'i> {
@@ -3554,7 +3557,12 @@ fn specialize_proc_help<'a>(
     let body = partial_proc.body.clone();
     let body_var = partial_proc.body_var;
 
-    let mut specialized_body = from_can(env, body_var, body, procs, layout_cache);
+    let specialized_body = from_can(env, body_var, body, procs, layout_cache);
+    let mut specialized_body = count_hits(
+        env,
+        CoveragePoint::Def(lambda_name.name()),
+        specialized_body,
+    );
 
     let specialized_proc = match specialized {
         SpecializedLayout::FunctionPointerBody {
@@ -4671,7 +4679,8 @@ pub fn with_hole<'a>(
                     if is_terminated {
                         let terminator = hole;
 
-                        let mut stmt = with_hole(
+                        let else_region = final_else.region;
+                        let stmt = with_hole(
                             env,
                             final_else.value,
                             branch_var,
@@ -4680,6 +4689,7 @@ pub fn with_hole<'a>(
                             assigned,
                             terminator,
                         );
+                        let mut stmt = count_branch_hits(env, else_region, stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = env.unique_symbol();
@@ -4693,6 +4703,7 @@ pub fn with_hole<'a>(
                                 assigned,
                                 terminator,
                             );
+                            let then = count_branch_hits(env, loc_then.region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -4716,7 +4727,8 @@ pub fn with_hole<'a>(
                             .arena
                             .alloc(Stmt::Jump(id, env.arena.alloc([assigned_in_jump])));
 
-                        let mut stmt = with_hole(
+                        let else_region = final_else.region;
+                        let stmt = with_hole(
                             env,
                             final_else.value,
                             branch_var,
@@ -4725,6 +4737,7 @@ pub fn with_hole<'a>(
                             assigned_in_jump,
                             terminator,
                         );
+                        let mut stmt = count_branch_hits(env, else_region, stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -4744,6 +4757,7 @@ pub fn with_hole<'a>(
                                 assigned_in_jump,
                                 terminator,
                             );
+                            let then = count_branch_hits(env, loc_then.region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -7076,7 +7090,9 @@ pub fn from_can<'a>(
                 "invalid condition type in if expression"
             );
 
-            let mut stmt = from_can(env, branch_var, final_else.value, procs, layout_cache);
+            let else_region = final_else.region;
+            let stmt = from_can(env, branch_var, final_else.value, procs, layout_cache);
+            let mut stmt = count_branch_hits(env, else_region, stmt);
 
             for (loc_cond, loc_then) in branches.into_iter().rev() {
                 let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -7087,6 +7103,7 @@ pub fn from_can<'a>(
                     cond_var,
                 );
                 let then = from_can(env, branch_var, loc_then.value, procs, layout_cache);
+                let then = count_branch_hits(env, loc_then.region, then);
 
                 stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -7256,6 +7273,7 @@ fn to_opt_branches<'a>(
     Pattern<'a>,
     Option<Loc<roc_can::expr::Expr>>,
     roc_can::expr::Expr,
+    Option<Region>,
 )> {
     debug_assert!(!branches.is_empty());
 
@@ -7267,6 +7285,8 @@ fn to_opt_branches<'a>(
             continue;
         }
 
+        let branch_region = Some(when_branch.value.region);
+
         for loc_pattern in when_branch.patterns {
             match from_can_pattern(env, procs, layout_cache, &loc_pattern.pattern.value) {
                 Ok((mono_pattern, assignments)) => {
@@ -7301,7 +7321,12 @@ fn to_opt_branches<'a>(
                     };
 
                     // TODO remove clone?
-                    opt_branches.push((mono_pattern, when_branch.guard.clone(), loc_expr.value));
+                    opt_branches.push((
+                        mono_pattern,
+                        when_branch.guard.clone(),
+                        loc_expr.value,
+                        branch_region,
+                    ));
                 }
                 Err(runtime_error) => {
                     // TODO remove clone?
@@ -7309,6 +7334,7 @@ fn to_opt_branches<'a>(
                         Pattern::Underscore,
                         when_branch.guard.clone(),
                         roc_can::expr::Expr::RuntimeError(runtime_error),
+                        branch_region,
                     ));
                 }
             }
@@ -7322,6 +7348,8 @@ fn to_opt_branches<'a>(
             Pattern::Underscore,
             None,
             roc_can::expr::Expr::RuntimeError(roc_problem::can::RuntimeError::NonExhaustivePattern),
+            // Not a branch anyone wrote
+            None,
         ));
     }
 
@@ -7362,7 +7390,7 @@ fn from_can_when<'a>(
     let arena = env.arena;
     let it = opt_branches
         .into_iter()
-        .filter_map(|(pattern, opt_guard, can_expr)| {
+        .filter_map(|(pattern, opt_guard, can_expr, region)| {
             // If the pattern has a void layout we can drop it; however, we must still perform the
             // work of building the body, because that may contain specializations we must
             // discover for use elsewhere. See
@@ -7389,6 +7417,10 @@ fn from_can_when<'a>(
                     with_hole(env, can_expr, expr_var, procs, layout_cache, symbol, jump)
                 }
             };
+            let branch_stmt = match region {
+                Some(region) => count_branch_hits(env, region, branch_stmt),
+                None => branch_stmt,
+            };
 
             use decision_tree::Guard;
             let result = if let Some(loc_expr) = opt_guard {
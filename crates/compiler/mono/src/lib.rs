@@ -11,7 +11,9 @@
 
 pub mod borrow;
 pub mod code_gen_help;
+pub mod coverage;
 pub mod drop_specialization;
+pub mod foreign_calls;
 pub mod inc_dec;
 pub mod ir;
 pub mod layout;
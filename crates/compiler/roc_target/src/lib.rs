@@ -254,6 +254,32 @@ impl Target {
         }
     }
 
+    /// Search for a prebuilt host that's a dynamic library in the platform main directory, like
+    /// `linux-x64.so`, which the repl loads to run effects.
+    pub fn find_dynamic_host(&self, platform_main_roc: &Path) -> Result<PathBuf, String> {
+        let dynamic_library_path = platform_main_roc.with_file_name(format!(
+            "{}.{}",
+            self,
+            self.dynamic_library_file_ext()
+        ));
+
+        let generic_host_path: PathBuf = platform_main_roc
+            .with_file_name("libhost")
+            .with_extension(self.dynamic_library_file_ext());
+
+        if dynamic_library_path.exists() {
+            Ok(dynamic_library_path)
+        } else if generic_host_path.exists() {
+            Ok(generic_host_path)
+        } else {
+            Err(format!(
+                "Failed to find a dynamic library host; I need one of these two paths to exist:\n    {}\n    {}",
+                dynamic_library_path.display(),
+                generic_host_path.display(),
+            ))
+        }
+    }
+
     /// Search for a prebuilt surgical host in the platform main directory.
     pub fn find_surgical_host(
         &self,
@@ -0,0 +1,123 @@
+#![cfg(test)]
+// Even with #[allow(non_snake_case)] on individual idents, rust-analyzer issues diagnostics.
+// See https://github.com/rust-lang/rust-analyzer/issues/6541.
+// For the `v!` macro we use uppercase variables when constructing tag unions.
+#![allow(non_snake_case)]
+
+use crate::{
+    test_key_eq, test_key_neq,
+    util::{check_derivable, check_single_lset_immediate, check_underivable},
+    v,
+};
+use roc_module::symbol::Symbol;
+use roc_types::subs::Variable;
+
+use roc_derive_key::{
+    arbitrary::FlatArbitraryKey, DeriveBuiltin::Arbitrary, DeriveError, DeriveKey,
+};
+
+test_key_eq! {
+    Arbitrary,
+
+    same_record:
+        v!({ a: v!(U8), }), v!({ a: v!(U8), })
+    same_record_fields_diff_types:
+        v!({ a: v!(U8), }), v!({ a: v!(STR), })
+    same_record_fields_any_order:
+        v!({ a: v!(U8), b: v!(U8), c: v!(U8), }),
+        v!({ c: v!(U8), a: v!(U8), b: v!(U8), })
+    explicit_empty_record_and_implicit_empty_record:
+        v!(EMPTY_RECORD), v!({})
+
+    same_tuple:
+        v!((v!(U8), v!(U16),)), v!((v!(U8), v!(U16),))
+    same_tuple_fields_diff_types:
+        v!((v!(U8), v!(U16),)), v!((v!(U32), v!(U64),))
+
+    same_tag_union:
+        v!([ A v!(U8) v!(STR), B v!(STR) ]), v!([ A v!(U8) v!(STR), B v!(STR) ])
+    same_tag_union_tags_diff_types:
+        v!([ A v!(U8) v!(U8), B v!(U8) ]), v!([ A v!(STR) v!(STR), B v!(STR) ])
+    same_tag_union_tags_any_order:
+        v!([ A v!(U8) v!(U8), B v!(U8), C ]), v!([ C, B v!(STR), A v!(STR) v!(STR) ])
+}
+
+test_key_neq! {
+    Arbitrary,
+
+    different_record_fields:
+        v!({ a: v!(U8), }), v!({ b: v!(U8), })
+    record_empty_vs_nonempty:
+        v!(EMPTY_RECORD), v!({ a: v!(U8), })
+
+    different_tuple_arities:
+        v!((v!(U8), v!(U16),)), v!((v!(U8), v!(U16), v!(U32),))
+
+    different_tag_union_tags:
+        v!([ A v!(U8) ]), v!([ B v!(U8) ])
+    different_tag_union_payload_sizes:
+        v!([ A v!(U8) ]), v!([ A v!(U8) v!(U8) ])
+}
+
+#[test]
+fn immediates() {
+    check_single_lset_immediate(Arbitrary, v!(U8), Symbol::ARBITRARY_U8);
+    check_single_lset_immediate(Arbitrary, v!(U16), Symbol::ARBITRARY_U16);
+    check_single_lset_immediate(Arbitrary, v!(U32), Symbol::ARBITRARY_U32);
+    check_single_lset_immediate(Arbitrary, v!(U64), Symbol::ARBITRARY_U64);
+    check_single_lset_immediate(Arbitrary, v!(U128), Symbol::ARBITRARY_U128);
+    check_single_lset_immediate(Arbitrary, v!(I8), Symbol::ARBITRARY_I8);
+    check_single_lset_immediate(Arbitrary, v!(I16), Symbol::ARBITRARY_I16);
+    check_single_lset_immediate(Arbitrary, v!(I32), Symbol::ARBITRARY_I32);
+    check_single_lset_immediate(Arbitrary, v!(I64), Symbol::ARBITRARY_I64);
+    check_single_lset_immediate(Arbitrary, v!(I128), Symbol::ARBITRARY_I128);
+    check_single_lset_immediate(Arbitrary, v!(F32), Symbol::ARBITRARY_F32);
+    check_single_lset_immediate(Arbitrary, v!(F64), Symbol::ARBITRARY_F64);
+    check_single_lset_immediate(Arbitrary, v!(DEC), Symbol::ARBITRARY_DEC);
+    check_single_lset_immediate(Arbitrary, v!(STR), Symbol::ARBITRARY_STR);
+    check_single_lset_immediate(
+        Arbitrary,
+        v!(Symbol::LIST_LIST v!(U8)),
+        Symbol::ARBITRARY_LIST,
+    );
+}
+
+#[test]
+fn optional_record_field_derive_error() {
+    check_underivable(Arbitrary, v!({ ?a: v!(U8), }), DeriveError::Underivable);
+}
+
+#[test]
+fn recursive_tag_union_derive_error() {
+    check_underivable(
+        Arbitrary,
+        v!([ Nil, Cons v!(^lst) ] as lst),
+        DeriveError::Underivable,
+    );
+}
+
+#[test]
+fn empty_tag_union_derive_error() {
+    check_underivable(Arbitrary, v!(EMPTY_TAG_UNION), DeriveError::Underivable);
+}
+
+#[test]
+fn derivable_record_with_record_ext() {
+    check_derivable(
+        Arbitrary,
+        v!({ b: v!(STR), }{ a: v!(STR), } ),
+        DeriveKey::Arbitrary(FlatArbitraryKey::Record(vec!["a".into(), "b".into()])),
+    );
+}
+
+#[test]
+fn derivable_tag_with_tag_ext() {
+    check_derivable(
+        Arbitrary,
+        v!([ B v!(STR) v!(U8) ][ A v!(STR) ]),
+        DeriveKey::Arbitrary(FlatArbitraryKey::TagUnion(vec![
+            ("A".into(), 1),
+            ("B".into(), 2),
+        ])),
+    );
+}
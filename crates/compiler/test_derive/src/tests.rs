@@ -1,5 +1,6 @@
 #![cfg(test)]
 
+mod arbitrary;
 mod decoding;
 mod encoding;
 mod eq;
@@ -72,6 +72,11 @@ fn module_source_and_path(builtin: DeriveBuiltin) -> (ModuleId, &'static str, Pa
             module_source(ModuleId::INSPECT),
             builtins_path.join("Inspect.roc"),
         ),
+        DeriveBuiltin::Arbitrary => (
+            ModuleId::ARBITRARY,
+            module_source(ModuleId::ARBITRARY),
+            builtins_path.join("Arbitrary.roc"),
+        ),
     }
 }
 
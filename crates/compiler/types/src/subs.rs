@@ -1554,6 +1554,8 @@ impl Subs {
     pub const AB_EQ: SubsSlice<Symbol>              = SubsSlice::new(4, 1);
     #[rustfmt::skip]
     pub const AB_INSPECT: SubsSlice<Symbol>         = SubsSlice::new(5, 1);
+    #[rustfmt::skip]
+    pub const AB_ARBITRARY: SubsSlice<Symbol>       = SubsSlice::new(6, 1);
     // END INIT-SymbolSubsSlice
 
     pub fn new() -> Self {
@@ -1583,6 +1585,7 @@ impl Subs {
         symbol_names.push(Symbol::HASH_HASH_ABILITY);
         symbol_names.push(Symbol::BOOL_EQ);
         symbol_names.push(Symbol::INSPECT_INSPECT_ABILITY);
+        symbol_names.push(Symbol::ARBITRARY_ARBITRARY_ABILITY);
         // END INIT-SymbolSubsSlice
 
         // IFTTT INIT-VariableSubsSlice
@@ -84,6 +84,7 @@ pub fn generate(
                     spec_path.to_path_buf(),
                     code_gen_options,
                     false,
+                    None,
                     link_type,
                     linking_strategy,
                     build_host,
@@ -106,6 +107,7 @@ pub fn generate(
                     problems,
                     total_time,
                     expect_metadata: _,
+                    module_paths: _,
                 }) => {
                     // TODO: Should binary_path be update to deal with extensions?
                     use roc_target::OperatingSystem;
@@ -189,9 +191,11 @@ pub fn generate(
                     Ok(0)
                 }
                 Err(BuildFileError::ErrorModule { module, total_time }) => {
-                    handle_error_module(module, total_time)
+                    handle_error_module(module, total_time, RenderTarget::ColorTerminal)
+                }
+                Err(BuildFileError::LoadingProblem(problem)) => {
+                    handle_loading_problem(problem, RenderTarget::ColorTerminal)
                 }
-                Err(BuildFileError::LoadingProblem(problem)) => handle_loading_problem(problem),
             };
 
             // Extend the lifetime of the tempdir to after we're done with everything,
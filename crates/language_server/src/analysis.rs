@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -9,7 +9,10 @@ use bumpalo::Bump;
 use parking_lot::Mutex;
 use roc_can::{abilities::AbilitiesStore, expr::Declarations};
 use roc_collections::{MutMap, MutSet, VecMap};
-use roc_load::{docs::ModuleDocumentation, CheckedModule, LoadedModule};
+use roc_load::{
+    docs::ModuleDocumentation, CheckedModule, ExecutionMode, LoadResult, LoadStart, LoadedModule,
+    ReusableModule,
+};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_packaging::cache::{self, RocCacheDir};
 use roc_region::all::LineInfo;
@@ -17,20 +20,36 @@ use roc_reporting::report::RocDocAllocator;
 use roc_solve_problem::TypeError;
 use roc_types::subs::{Subs, Variable};
 
-use tower_lsp::lsp_types::{Diagnostic, SemanticTokenType, Url};
+use tower_lsp::lsp_types::{Diagnostic, SemanticTokenType, TextEdit, Url};
 
 mod analysed_doc;
 mod annotation_visitor;
+mod call_hierarchy;
 mod completion;
+mod expects;
+mod inlay_hints;
 mod parse_ast;
+mod quick_fix;
+mod references;
 mod semantic_tokens;
+mod signature_help;
+mod symbols;
+mod syntax_regions;
 mod tokens;
 mod utils;
 
 use crate::convert::diag::{IntoLspDiagnostic, ProblemFmt};
 
 pub(crate) use self::analysed_doc::{AnalyzedDocument, DocInfo};
-use self::{analysed_doc::ModuleIdToUrl, tokens::Token};
+#[cfg(not(windows))]
+pub(crate) use self::expects::{run_requested_expect, RUN_EXPECT_VAR};
+pub(crate) use self::expects::{run_toplevel_expect, ExpectOutcome, RUN_EXPECT_COMMAND};
+pub(crate) use self::references::ReferenceTarget;
+use self::{
+    analysed_doc::ModuleIdToUrl,
+    quick_fix::{FixContext, QuickFix},
+    tokens::Token,
+};
 
 pub const HIGHLIGHT_TOKENS_LEGEND: &[SemanticTokenType] = Token::LEGEND;
 
@@ -99,25 +118,44 @@ pub(super) struct AnalyzedModule {
 pub struct AnalysisResult {
     module: Option<AnalyzedModule>,
     diagnostics: Vec<Diagnostic>,
+    quick_fixes: Vec<QuickFix>,
 }
 
-pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
+/// The solved types of each module from the last analysis of a document.
+pub(crate) type ReusableModules = MutMap<ModuleId, ReusableModule>;
+
+/// Analyzes a document along with every module it depends on.
+///
+/// Modules whose solved types in `reusable_modules` are still valid aren't solved again. The
+/// solved types from this analysis are returned, if it got as far as solving them, so that the
+/// next one can do the same, along with the documents whose modules weren't solved again.
+pub(crate) fn global_analysis(
+    doc_info: DocInfo,
+    reusable_modules: ReusableModules,
+) -> (Vec<AnalyzedDocument>, Option<ReusableModules>, HashSet<Url>) {
     let fi = doc_info.url.to_file_path().unwrap();
     let src_dir = find_src_dir(&fi).to_path_buf();
 
     let arena = Bump::new();
-    let loaded = roc_load::load_and_typecheck_str(
-        &arena,
-        fi,
-        &doc_info.source,
-        src_dir,
-        None,
-        roc_target::Target::LinuxX64,
-        roc_load::FunctionKind::LambdaSet,
-        roc_reporting::report::RenderTarget::LanguageServer,
-        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
-        roc_reporting::report::DEFAULT_PALETTE,
-    );
+    let packages_dir = cache::roc_cache_packages_dir();
+    let roc_cache_dir = RocCacheDir::Persistent(packages_dir.as_path());
+    let loaded = LoadStart::from_str(&arena, fi, None, &doc_info.source, roc_cache_dir, src_dir)
+        .and_then(|load_start| {
+            roc_load::load_single_threaded(
+                &arena,
+                load_start.reusing_modules(reusable_modules),
+                roc_target::Target::LinuxX64,
+                roc_load::FunctionKind::LambdaSet,
+                roc_reporting::report::RenderTarget::LanguageServer,
+                roc_reporting::report::DEFAULT_PALETTE,
+                roc_cache_dir,
+                ExecutionMode::Check,
+            )
+        })
+        .map(|result| match result {
+            LoadResult::TypeChecked(module) => module,
+            LoadResult::Monomorphized(_) => unreachable!("the module is only type checked"),
+        });
 
     let module = match loaded {
         Ok(module) => module,
@@ -132,10 +170,11 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
                 analysis_result: AnalysisResult {
                     module: None,
                     diagnostics: all_problems,
+                    quick_fixes: vec![],
                 },
             };
 
-            return vec![analyzed_document];
+            return (vec![analyzed_document], None, HashSet::new());
         }
     };
 
@@ -154,6 +193,8 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
         mut imports,
         exposes,
         docs_by_module,
+        reusable_modules,
+        reused_modules,
         ..
     } = module;
 
@@ -170,9 +211,15 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
         docs_by_module,
     ));
 
+    let module_id_to_url = module_id_to_url_from_sources(&sources);
+    let reused = reused_modules
+        .iter()
+        .filter_map(|module_id| module_id_to_url.get(module_id).cloned())
+        .collect();
+
     let mut builder = AnalyzedDocumentBuilder {
         interns: &interns,
-        module_id_to_url: module_id_to_url_from_sources(&sources),
+        module_id_to_url,
         can_problems: &mut can_problems,
         type_problems: &mut type_problems,
         declarations_by_id: &mut declarations_by_id,
@@ -188,7 +235,7 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
         documents.push(doc);
     }
 
-    documents
+    (documents, Some(reusable_modules), reused)
 }
 
 /// Take the exposed imports from each module, lookup the symbol within that module's list of
@@ -224,6 +271,67 @@ fn find_src_dir(path: &Path) -> &Path {
     path.parent().unwrap_or(path)
 }
 
+/// Analyzes the modules in the workspace of `module_path` that mention `module_name` and aren't
+/// among the `analyzed` documents, so that renaming something the module exposes also reaches
+/// importers that no open document depends on.
+pub(crate) fn analyze_importers(
+    module_path: &Path,
+    module_name: &str,
+    analyzed: &HashSet<Url>,
+) -> Vec<AnalyzedDocument> {
+    let mut paths = vec![];
+    find_roc_files(find_src_dir(module_path), &mut paths);
+
+    let mut seen = analyzed.clone();
+    let mut documents = vec![];
+
+    for path in paths {
+        let Ok(url) = Url::from_file_path(&path) else {
+            continue;
+        };
+        if seen.contains(&url) {
+            continue;
+        }
+
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let mentions_module = source
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(|word| word == module_name);
+        if !mentions_module {
+            continue;
+        }
+
+        let (analyzed_documents, _, _) =
+            global_analysis(DocInfo::new(url, source, 0), ReusableModules::default());
+        for document in analyzed_documents {
+            if seen.insert(document.url().clone()) {
+                documents.push(document);
+            }
+        }
+    }
+
+    documents
+}
+
+fn find_roc_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+        if path.is_dir() && !is_hidden {
+            find_roc_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "roc") {
+            files.push(path);
+        }
+    }
+}
+
 fn _find_parent_git_repo(path: &Path) -> Option<&Path> {
     let mut path = path;
     loop {
@@ -315,7 +423,13 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
         };
 
         let line_info = LineInfo::new(&source);
-        let diagnostics = self.build_diagnostics(&path, &source, &line_info, module_id);
+        let (diagnostics, quick_fixes) = self.build_diagnostics(
+            &path,
+            &source,
+            &line_info,
+            module_id,
+            &analyzed_module.declarations,
+        );
 
         AnalyzedDocument {
             doc_info: DocInfo {
@@ -327,6 +441,7 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
             analysis_result: AnalysisResult {
                 module: Some(analyzed_module),
                 diagnostics,
+                quick_fixes,
             },
         }
     }
@@ -357,34 +472,56 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
         source: &str,
         line_info: &LineInfo,
         module_id: ModuleId,
-    ) -> Vec<Diagnostic> {
+        declarations: &Declarations,
+    ) -> (Vec<Diagnostic>, Vec<QuickFix>) {
         let lines: Vec<_> = source.lines().collect();
 
         let alloc = RocDocAllocator::new(&lines, module_id, self.interns);
 
         let mut all_problems = Vec::new();
+        let mut quick_fixes = Vec::new();
         let fmt = ProblemFmt {
             alloc: &alloc,
             line_info,
             path: source_path,
         };
+        let fix_context = FixContext {
+            source,
+            line_info,
+            declarations,
+            interns: self.interns,
+            alloc: &alloc,
+        };
+
+        let mut add_problem = |diag: Option<Diagnostic>, fix: Option<(String, Vec<TextEdit>)>| {
+            let Some(diag) = diag else {
+                return;
+            };
+
+            if let Some((title, edits)) = fix {
+                quick_fixes.push(QuickFix {
+                    title,
+                    diagnostic: diag.clone(),
+                    edits,
+                });
+            }
+            all_problems.push(diag);
+        };
 
         let can_problems = self.can_problems.remove(&module_id).unwrap_or_default();
 
         let type_problems = self.type_problems.remove(&module_id).unwrap_or_default();
 
         for can_problem in can_problems {
-            if let Some(diag) = can_problem.into_lsp_diagnostic(&fmt) {
-                all_problems.push(diag);
-            }
+            let fix = fix_context.can_problem_fix(&can_problem);
+            add_problem(can_problem.into_lsp_diagnostic(&fmt), fix);
         }
 
         for type_problem in type_problems {
-            if let Some(diag) = type_problem.into_lsp_diagnostic(&fmt) {
-                all_problems.push(diag);
-            }
+            let fix = fix_context.type_problem_fix(&type_problem);
+            add_problem(type_problem.into_lsp_diagnostic(&fmt), fix);
         }
 
-        all_problems
+        (all_problems, quick_fixes)
     }
 }
@@ -9,11 +9,16 @@ use bumpalo::Bump;
 use roc_module::symbol::{ModuleId, Symbol};
 
 use roc_region::all::{LineInfo, Position as RocPosition, Region};
+use roc_types::subs::Variable;
+use serde_json::json;
 
 use tower_lsp::lsp_types::{
-    CodeAction, CodeActionKind, CompletionItem, Diagnostic, GotoDefinitionResponse, Hover,
-    HoverContents, LanguageString, Location, MarkedString, Position, Range, SemanticTokens,
-    SemanticTokensResult, TextEdit, Url, WorkspaceEdit,
+    CallHierarchyIncomingCall, CallHierarchyItem, CodeAction, CodeActionKind, CodeLens, Command,
+    CompletionItem, Diagnostic, DocumentSymbol, DocumentSymbolResponse, Documentation,
+    FoldingRange, GotoDefinitionResponse, Hover, HoverContents, InlayHint, InlayHintKind,
+    InlayHintLabel, LanguageString, Location, MarkedString, MarkupContent, MarkupKind, Position,
+    PrepareRenameResponse, Range, SelectionRange, SemanticTokens, SemanticTokensResult,
+    SignatureHelp, SymbolInformation, SymbolKind, TextEdit, Url, WorkspaceEdit,
 };
 
 use crate::{
@@ -22,12 +27,22 @@ use crate::{
         get_tag_completion_items,
     },
     convert::{ToRange, ToRegion, ToRocPosition},
+    lsp_ext::TypeHierarchyItem,
 };
 
 use super::{
     annotation_visitor::{find_declaration_at, FoundDeclaration, NotFound},
+    call_hierarchy::outgoing_references,
+    expects::{toplevel_expect_regions, ExpectOutcome, RUN_EXPECT_COMMAND},
+    inlay_hints::inferred_bindings,
     parse_ast::Ast,
+    references::{
+        annotation_symbols_at, find_references, ident_at, Reference, ReferenceKind, ReferenceTarget,
+    },
     semantic_tokens::arrange_semantic_tokens,
+    signature_help::{find_call_at, signature_information, CallAt},
+    symbols::{matches_query, module_outline, OutlineSymbol},
+    syntax_regions::{folding_ranges, selection_range, syntax_nodes},
     utils::{format_var_type, is_roc_identifier_char},
     AnalysisResult, AnalyzedModule,
 };
@@ -131,6 +146,33 @@ impl DocInfo {
             data,
         }))
     }
+
+    pub fn folding_ranges(&self) -> Option<Vec<FoldingRange>> {
+        let source = &self.source;
+        let arena = &Bump::new();
+
+        let ast = Ast::parse(arena, source).ok()?;
+
+        Some(folding_ranges(ast.defs(), source, &self.line_info))
+    }
+
+    pub fn selection_ranges(&self, positions: &[Position]) -> Option<Vec<SelectionRange>> {
+        let source = &self.source;
+        let arena = &Bump::new();
+
+        let ast = Ast::parse(arena, source).ok()?;
+        let nodes = syntax_nodes(ast.defs());
+
+        let ranges = positions
+            .iter()
+            .map(|position| {
+                let position = position.to_roc_position(&self.line_info);
+                selection_range(&nodes, position, &self.line_info)
+            })
+            .collect();
+
+        Some(ranges)
+    }
 }
 
 impl AnalyzedDocument {
@@ -178,6 +220,501 @@ impl AnalyzedDocument {
         Some(found_symbol.implementation_symbol())
     }
 
+    /// Like [Self::symbol_at], but also resolves the names of types and abilities.
+    pub(crate) fn reference_target_at(&self, position: Position) -> Option<ReferenceTarget> {
+        let symbol = self
+            .symbol_at(position)
+            .or_else(|| self.type_symbol_at(position))?;
+
+        self.reference_target(symbol)
+    }
+
+    fn reference_target(&self, symbol: Symbol) -> Option<ReferenceTarget> {
+        let AnalyzedModule { interns, .. } = self.module()?;
+
+        Some(ReferenceTarget {
+            symbol,
+            origin_url: self.url().clone(),
+            origin_version: self.doc_info.version,
+            module_name: interns.module_name(symbol.module_id()).to_string(),
+            module_url: self.module_url(symbol.module_id()),
+            ident: symbol.as_str(interns).to_string(),
+        })
+    }
+
+    fn type_symbol_at(&self, position: Position) -> Option<Symbol> {
+        let AnalyzedModule {
+            declarations,
+            interns,
+            module_id,
+            ..
+        } = self.module()?;
+
+        let position = position.to_roc_position(self.line_info());
+        let (_, name, qualifier) = ident_at(&self.doc_info.source, position)?;
+
+        if !name.starts_with(char::is_uppercase) {
+            return None;
+        }
+
+        let in_annotation = annotation_symbols_at(position, declarations)
+            .into_iter()
+            .find(|symbol| symbol.as_str(interns) == name);
+
+        in_annotation.or_else(|| {
+            // Type definitions and exposes lists are not canonicalized into declarations, but an
+            // unqualified type named in them must be defined in this module.
+            let ident_id = interns.all_ident_ids.get(module_id)?.get_id(name)?;
+            qualifier
+                .is_none()
+                .then(|| Symbol::new(*module_id, ident_id))
+        })
+    }
+
+    /// Whether other modules can use `target`, because its module exposes it.
+    pub(crate) fn is_exposed(&self, target: &ReferenceTarget) -> bool {
+        let (Some(AnalyzedModule { modules_info, .. }), Some(symbol)) =
+            (self.module(), self.resolve_reference_target(target))
+        else {
+            return false;
+        };
+
+        // Only values are kept along with what each module exposes
+        target.is_type()
+            || modules_info
+                .exposed_by_module
+                .get(&symbol.module_id())
+                .is_some_and(|exposed| exposed.iter().any(|(exposed, _)| *exposed == symbol))
+    }
+
+    /// Looks up the target's symbol as it is known in this document's analysis.
+    fn resolve_reference_target(&self, target: &ReferenceTarget) -> Option<Symbol> {
+        if self.url() == &target.origin_url && self.doc_info.version == target.origin_version {
+            return Some(target.symbol);
+        }
+
+        let AnalyzedModule {
+            interns,
+            module_id_to_url,
+            ..
+        } = self.module()?;
+
+        let module_id = match &target.module_url {
+            Some(url) => module_id_to_url
+                .iter()
+                .find_map(|(module_id, module_url)| (module_url == url).then_some(*module_id))?,
+            None => interns
+                .module_ids
+                .get_id(&target.module_name.as_str().into())?,
+        };
+        let ident_id = interns
+            .all_ident_ids
+            .get(&module_id)?
+            .get_id(&target.ident)?;
+
+        Some(Symbol::new(module_id, ident_id))
+    }
+
+    fn references(&self, target: &ReferenceTarget) -> Vec<Reference> {
+        let (Some(module), Some(symbol)) = (self.module(), self.resolve_reference_target(target))
+        else {
+            return vec![];
+        };
+
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, &self.doc_info.source).ok();
+
+        find_references(
+            &self.doc_info.source,
+            &module.declarations,
+            ast.as_ref(),
+            symbol,
+            target,
+            symbol.module_id() == module.module_id,
+        )
+    }
+
+    pub(crate) fn reference_locations(
+        &self,
+        target: &ReferenceTarget,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        self.references(target)
+            .into_iter()
+            .filter(|reference| include_declaration || reference.kind != ReferenceKind::Declaration)
+            .map(|reference| self.location(reference.region.to_range(self.line_info())))
+            .collect()
+    }
+
+    pub(crate) fn rename_edits(&self, target: &ReferenceTarget, new_name: &str) -> Vec<TextEdit> {
+        self.references(target)
+            .into_iter()
+            .map(|reference| {
+                let new_text = if reference.punned {
+                    format!("{}: {}", target.ident, new_name)
+                } else {
+                    new_name.to_string()
+                };
+
+                TextEdit {
+                    range: reference.region.to_range(self.line_info()),
+                    new_text,
+                }
+            })
+            .collect()
+    }
+
+    pub fn prepare_rename(&self, position: Position) -> Option<PrepareRenameResponse> {
+        let target = self.reference_target_at(position)?;
+        if target.symbol.is_builtin() {
+            return None;
+        }
+
+        let position = position.to_roc_position(self.line_info());
+        let (region, name, _) = ident_at(&self.doc_info.source, position)?;
+
+        (name == target.ident).then(|| PrepareRenameResponse::RangeWithPlaceholder {
+            range: region.to_range(self.line_info()),
+            placeholder: target.ident,
+        })
+    }
+
+    fn outline(&self) -> Option<Vec<OutlineSymbol>> {
+        let module = self.module()?;
+
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, &self.doc_info.source).ok();
+
+        Some(module_outline(module, ast.as_ref()))
+    }
+
+    pub fn document_symbols(&self) -> Option<DocumentSymbolResponse> {
+        let mut format_type = self.type_formatter()?;
+
+        let symbols = self
+            .outline()?
+            .into_iter()
+            .map(|symbol| self.document_symbol(symbol, &mut format_type))
+            .collect();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn document_symbol(
+        &self,
+        symbol: OutlineSymbol,
+        format_type: &mut dyn FnMut(Variable) -> String,
+    ) -> DocumentSymbol {
+        let children = symbol
+            .children
+            .into_iter()
+            .map(|child| self.document_symbol(child, format_type))
+            .collect::<Vec<_>>();
+
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name: symbol.name,
+            detail: symbol.var.map(format_type),
+            kind: symbol.kind,
+            tags: None,
+            deprecated: None,
+            range: symbol.region.to_range(self.line_info()),
+            selection_range: symbol.selection_region.to_range(self.line_info()),
+            children: (!children.is_empty()).then_some(children),
+        }
+    }
+
+    /// The symbols defined in this document whose names match `query`.
+    pub(crate) fn workspace_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let (Some(module), Some(outline)) = (self.module(), self.outline()) else {
+            return vec![];
+        };
+        let module_name = module.interns.module_name(module.module_id).to_string();
+
+        let mut symbols = vec![];
+        let mut push_symbol = |symbol: &OutlineSymbol, container_name: &str| {
+            if matches_query(&symbol.name, query) {
+                #[allow(deprecated)]
+                symbols.push(SymbolInformation {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind,
+                    tags: None,
+                    deprecated: None,
+                    location: self.location(symbol.selection_region.to_range(self.line_info())),
+                    container_name: Some(container_name.to_string()),
+                });
+            }
+        };
+
+        for symbol in &outline {
+            push_symbol(symbol, &module_name);
+
+            for child in &symbol.children {
+                push_symbol(child, &symbol.name);
+            }
+        }
+
+        symbols
+    }
+
+    /// The outline entry for a symbol defined at the top level of this document, or in one of
+    /// its abilities.
+    fn outline_symbol(&self, target: &ReferenceTarget) -> Option<OutlineSymbol> {
+        let AnalyzedModule { module_id, .. } = self.module()?;
+        let symbol = self.resolve_reference_target(target)?;
+        if symbol.module_id() != *module_id {
+            return None;
+        }
+
+        self.outline()?
+            .into_iter()
+            .flat_map(|symbol| {
+                let children = symbol.children.clone();
+                std::iter::once(symbol).chain(children)
+            })
+            .find(|symbol| symbol.name == target.ident)
+    }
+
+    fn type_formatter(&self) -> Option<impl FnMut(Variable) -> String + '_> {
+        let AnalyzedModule {
+            subs,
+            module_id,
+            interns,
+            ..
+        } = self.module()?;
+
+        let mut subs = subs.clone();
+        Some(move |var| format_var_type(var, &mut subs, module_id, interns))
+    }
+
+    fn to_call_hierarchy_item(
+        &self,
+        symbol: OutlineSymbol,
+        format_type: &mut dyn FnMut(Variable) -> String,
+    ) -> CallHierarchyItem {
+        CallHierarchyItem {
+            name: symbol.name,
+            kind: symbol.kind,
+            tags: None,
+            detail: symbol.var.map(format_type),
+            uri: self.url().clone(),
+            range: symbol.region.to_range(self.line_info()),
+            selection_range: symbol.selection_region.to_range(self.line_info()),
+            data: None,
+        }
+    }
+
+    /// The call hierarchy item for a value, function or ability member defined in this document.
+    pub(crate) fn call_hierarchy_item(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Option<CallHierarchyItem> {
+        let symbol = self
+            .outline_symbol(target)
+            .filter(|symbol| symbol.var.is_some())?;
+        let mut format_type = self.type_formatter()?;
+
+        Some(self.to_call_hierarchy_item(symbol, &mut format_type))
+    }
+
+    /// The top-level definitions in this document that refer to `target`, along with where they
+    /// refer to it.
+    pub(crate) fn incoming_calls(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Vec<CallHierarchyIncomingCall> {
+        let usages = self
+            .references(target)
+            .into_iter()
+            .filter(|reference| reference.kind == ReferenceKind::Usage)
+            .collect::<Vec<_>>();
+
+        if usages.is_empty() {
+            return vec![];
+        }
+        let (Some(outline), Some(mut format_type)) = (self.outline(), self.type_formatter()) else {
+            return vec![];
+        };
+
+        outline
+            .into_iter()
+            .filter(|caller| caller.var.is_some())
+            .filter_map(|caller| {
+                let from_ranges = usages
+                    .iter()
+                    .filter(|usage| caller.region.contains(&usage.region))
+                    .map(|usage| usage.region.to_range(self.line_info()))
+                    .collect::<Vec<_>>();
+
+                (!from_ranges.is_empty()).then(|| CallHierarchyIncomingCall {
+                    from: self.to_call_hierarchy_item(caller, &mut format_type),
+                    from_ranges,
+                })
+            })
+            .collect()
+    }
+
+    /// The top-level symbols that the definition of `target` refers to, along with where it
+    /// refers to each of them.
+    pub(crate) fn outgoing_calls(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Vec<(ReferenceTarget, Vec<Range>)> {
+        let (Some(module), Some(symbol)) = (self.module(), self.resolve_reference_target(target))
+        else {
+            return vec![];
+        };
+
+        outgoing_references(
+            symbol,
+            module.module_id,
+            &module.declarations,
+            &module.abilities,
+        )
+        .into_iter()
+        .filter_map(|(callee, regions)| {
+            let ranges = regions
+                .into_iter()
+                .map(|region| region.to_range(self.line_info()))
+                .collect();
+
+            Some((self.reference_target(callee)?, ranges))
+        })
+        .collect()
+    }
+
+    /// The type hierarchy item for an ability or opaque type defined in this document.
+    pub(crate) fn type_hierarchy_item(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Option<TypeHierarchyItem> {
+        let symbol = self
+            .outline_symbol(target)
+            .filter(|symbol| matches!(symbol.kind, SymbolKind::INTERFACE | SymbolKind::CLASS))?;
+
+        Some(TypeHierarchyItem {
+            name: symbol.name,
+            kind: symbol.kind,
+            tags: None,
+            detail: None,
+            uri: self.url().clone(),
+            range: symbol.region.to_range(self.line_info()),
+            selection_range: symbol.selection_region.to_range(self.line_info()),
+            data: None,
+        })
+    }
+
+    /// The type hierarchy item for a builtin ability, which isn't defined in any document.
+    pub(crate) fn builtin_type_hierarchy_item(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Option<TypeHierarchyItem> {
+        let AnalyzedModule { abilities, .. } = self.module()?;
+        let symbol = self.resolve_reference_target(target)?;
+
+        let is_builtin_ability =
+            symbol.is_builtin() && (abilities.is_ability(symbol) || symbol.is_derivable_ability());
+        if !is_builtin_ability {
+            return None;
+        }
+
+        let uri = Url::parse(&format!("roc:builtins/{}.roc", target.module_name)).ok()?;
+
+        Some(TypeHierarchyItem {
+            name: target.ident.clone(),
+            kind: SymbolKind::INTERFACE,
+            tags: None,
+            detail: Some(target.module_name.clone()),
+            uri,
+            range: Range::default(),
+            selection_range: Range::default(),
+            data: Some(target.builtin_item_data()),
+        })
+    }
+
+    /// Pairs of an opaque type and an ability it implements, from the abilities store.
+    fn ability_implementations(&self) -> Vec<(Symbol, Symbol)> {
+        let Some(AnalyzedModule { abilities, .. }) = self.module() else {
+            return vec![];
+        };
+
+        let mut implementations = abilities
+            .iter_declared_implementations()
+            .filter_map(|(impl_key, _)| {
+                let member_def = abilities.member_def(impl_key.ability_member)?;
+                Some((impl_key.opaque, member_def.parent_ability))
+            })
+            .collect::<Vec<_>>();
+        implementations.sort();
+        implementations.dedup();
+        implementations
+    }
+
+    /// The opaque types defined in this document that implement the ability `target`.
+    pub(crate) fn implementing_types(&self, target: &ReferenceTarget) -> Vec<TypeHierarchyItem> {
+        let Some(ability) = self.resolve_reference_target(target) else {
+            return vec![];
+        };
+
+        let mut items = self
+            .ability_implementations()
+            .into_iter()
+            .filter(|(_, implemented)| *implemented == ability)
+            .filter_map(|(opaque, _)| self.type_hierarchy_item(&self.reference_target(opaque)?))
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| item.range.start);
+        items
+    }
+
+    /// The abilities that the opaque type `target` implements.
+    pub(crate) fn implemented_abilities(&self, target: &ReferenceTarget) -> Vec<ReferenceTarget> {
+        let Some(opaque) = self.resolve_reference_target(target) else {
+            return vec![];
+        };
+
+        self.ability_implementations()
+            .into_iter()
+            .filter(|(implementing, _)| *implementing == opaque)
+            .filter_map(|(_, ability)| self.reference_target(ability))
+            .collect()
+    }
+
+    /// A lens on each top-level `expect` that runs it, saying how it went if it has been run.
+    pub(crate) fn code_lenses(&self, outcomes: &[ExpectOutcome]) -> Option<Vec<CodeLens>> {
+        let AnalyzedModule { declarations, .. } = self.module()?;
+
+        let lenses = toplevel_expect_regions(&self.doc_info.source, declarations)
+            .into_iter()
+            .map(|region| {
+                let range = region.to_range(self.line_info());
+                // An outcome's range also covers the comment before the expect, so only their
+                // ends line up
+                let outcome = outcomes
+                    .iter()
+                    .find(|outcome| outcome.range.end == range.end);
+                let title = match outcome {
+                    None => "Run expect",
+                    Some(ExpectOutcome { failure: None, .. }) => "Passed, run again",
+                    Some(ExpectOutcome {
+                        failure: Some(_), ..
+                    }) => "Failed, run again",
+                };
+
+                CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: title.to_string(),
+                        command: RUN_EXPECT_COMMAND.to_string(),
+                        arguments: Some(vec![json!(self.url()), json!(range.start)]),
+                    }),
+                    data: None,
+                }
+            })
+            .collect();
+
+        Some(lenses)
+    }
+
     pub fn hover(&self, position: Position) -> Option<Hover> {
         let line_info = self.line_info();
 
@@ -221,6 +758,96 @@ impl AnalyzedDocument {
         })
     }
 
+    pub fn signature_help(&self, position: Position) -> Option<SignatureHelp> {
+        let AnalyzedModule {
+            subs,
+            declarations,
+            module_id,
+            interns,
+            modules_info,
+            ..
+        } = self.module()?;
+
+        let position = position.to_roc_position(self.line_info());
+        let CallAt {
+            fn_var,
+            fn_symbol,
+            active_argument,
+        } = find_call_at(position, &self.doc_info.source, declarations)?;
+
+        // Functions from other modules are shown with the type they were defined with, which
+        // keeps the names of their type variables, rather than the type they have at this call.
+        let imported_type = fn_symbol
+            .filter(|symbol| symbol.module_id() != *module_id)
+            .and_then(|symbol| {
+                let exposed = modules_info.exposed_by_module.get(&symbol.module_id())?;
+                let (_, var) = exposed.iter().find(|(exposed, _)| *exposed == symbol)?;
+
+                modules_info.with_subs(&symbol.module_id(), |subs| {
+                    format_var_type(*var, subs, &symbol.module_id(), interns)
+                })
+            });
+        let type_str = imported_type
+            .unwrap_or_else(|| format_var_type(fn_var, &mut subs.clone(), module_id, interns));
+
+        let documentation = fn_symbol
+            .and_then(|symbol| {
+                modules_info
+                    .get_docs(&symbol.module_id())?
+                    .get_doc_for_symbol(&symbol)
+            })
+            .map(|docs| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: docs,
+                })
+            });
+
+        let signature = signature_information(type_str, documentation, active_argument)?;
+
+        Some(SignatureHelp {
+            signatures: vec![signature],
+            active_signature: Some(0),
+            active_parameter: Some(active_argument as u32),
+        })
+    }
+
+    pub fn inlay_hints(&self, range: Range) -> Option<Vec<InlayHint>> {
+        let AnalyzedModule {
+            subs,
+            declarations,
+            module_id,
+            interns,
+            ..
+        } = self.module()?;
+
+        let region = range.to_region(self.line_info());
+        let mut subs = subs.clone();
+
+        let hints = inferred_bindings(region, declarations)
+            .into_iter()
+            .filter(|(_, var)| !subs.var_contains_error(*var))
+            .map(|(position, var)| {
+                let type_str = format_var_type(var, &mut subs, module_id, interns);
+
+                InlayHint {
+                    position: Region::new(position, position)
+                        .to_range(self.line_info())
+                        .start,
+                    label: InlayHintLabel::String(format!(": {type_str}")),
+                    kind: Some(InlayHintKind::TYPE),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                }
+            })
+            .collect();
+
+        Some(hints)
+    }
+
     pub fn definition(&self, symbol: Symbol) -> Option<GotoDefinitionResponse> {
         let AnalyzedModule { declarations, .. } = self.module()?;
 
@@ -409,4 +1036,27 @@ impl AnalyzedDocument {
             ..Default::default()
         })
     }
+
+    /// Fixes for the problems reported anywhere in `range`.
+    pub fn quick_fixes(&self, range: Range) -> Vec<CodeAction> {
+        self.analysis_result
+            .quick_fixes
+            .iter()
+            .filter(|fix| {
+                let diagnostic_range = fix.diagnostic.range;
+                diagnostic_range.start <= range.end && range.start <= diagnostic_range.end
+            })
+            .map(|fix| CodeAction {
+                title: fix.title.clone(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![fix.diagnostic.clone()]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    self.url().clone(),
+                    fix.edits.clone(),
+                )]))),
+                is_preferred: Some(true),
+                ..Default::default()
+            })
+            .collect()
+    }
 }
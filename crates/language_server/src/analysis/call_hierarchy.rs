@@ -0,0 +1,63 @@
+use roc_can::{
+    abilities::AbilitiesStore,
+    expr::{Declarations, Expr},
+    traverse::{find_declaration, walk_expr, FoundDeclaration, Visitor},
+};
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_region::all::Region;
+use roc_types::subs::Variable;
+
+/// Finds the top-level values, functions and ability members that the definition of `symbol`
+/// refers to, along with the regions of the references, in the order they are first used.
+pub(super) fn outgoing_references(
+    symbol: Symbol,
+    module_id: ModuleId,
+    declarations: &Declarations,
+    abilities: &AbilitiesStore,
+) -> Vec<(Symbol, Vec<Region>)> {
+    let Some(found) = find_declaration(symbol, declarations) else {
+        return vec![];
+    };
+
+    let mut visitor = OutgoingVisitor { references: vec![] };
+    match found {
+        FoundDeclaration::Decl(decl) => visitor.visit_decl(decl),
+        FoundDeclaration::Def(def) => visitor.visit_def(def),
+    }
+
+    // Symbols from this module that aren't declared at the top level are local variables
+    let is_top_level = |symbol: Symbol| {
+        symbol.module_id() != module_id
+            || abilities.is_ability_member_name(symbol)
+            || declarations.symbols.iter().any(|loc| loc.value == symbol)
+    };
+
+    let mut grouped: Vec<(Symbol, Vec<Region>)> = vec![];
+    for (symbol, region) in visitor.references {
+        if !is_top_level(symbol) {
+            continue;
+        }
+
+        match grouped.iter_mut().find(|(callee, _)| *callee == symbol) {
+            Some((_, regions)) => regions.push(region),
+            None => grouped.push((symbol, vec![region])),
+        }
+    }
+
+    grouped
+}
+
+struct OutgoingVisitor {
+    references: Vec<(Symbol, Region)>,
+}
+
+impl Visitor for OutgoingVisitor {
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        match expr {
+            Expr::Var(symbol, _)
+            | Expr::ParamsVar { symbol, .. }
+            | Expr::AbilityMember(symbol, ..) => self.references.push((*symbol, region)),
+            _ => walk_expr(self, expr, var),
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use roc_can::expr::{DeclarationTag, Declarations};
+use roc_region::all::{Position as RocPosition, Region};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+use super::DocInfo;
+
+pub(crate) const RUN_EXPECT_COMMAND: &str = "roc.runExpect";
+
+/// How long a top-level `expect` can run for before it's stopped, once it has compiled
+#[cfg(not(windows))]
+const EXPECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How running a top-level `expect` went.
+#[derive(Debug, Clone)]
+pub(crate) struct ExpectOutcome {
+    pub range: Range,
+    /// The report of the values that made the expect fail, if it did.
+    pub failure: Option<String>,
+}
+
+impl ExpectOutcome {
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        Some(Diagnostic {
+            range: self.range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: None,
+            code_description: None,
+            source: Some("expect".to_owned()),
+            message: self.failure.clone()?,
+            related_information: None,
+            tags: None,
+            data: None,
+        })
+    }
+}
+
+/// The regions of the top-level `expect`s in a module, from the `expect` keyword to the end of
+/// the condition.
+pub(super) fn toplevel_expect_regions(source: &str, declarations: &Declarations) -> Vec<Region> {
+    declarations
+        .declarations
+        .iter()
+        .enumerate()
+        .filter(|(_, tag)| matches!(tag, DeclarationTag::Expectation))
+        .map(|(index, _)| {
+            // Only the condition is kept after canonicalization
+            let condition = declarations.expressions[index].region;
+            let keyword = source[..condition.start().offset as usize]
+                .rfind("expect")
+                .map_or(condition.start(), |offset| RocPosition::new(offset as u32));
+
+            Region::new(keyword, condition.end())
+        })
+        .collect()
+}
+
+/// Set for the process that [run_toplevel_expect] starts, which runs an expect rather than serve.
+#[cfg(not(windows))]
+pub(crate) const RUN_EXPECT_VAR: &str = "ROCLS_RUN_EXPECT";
+
+/// What [run_toplevel_expect] asks the process it starts to run, on its stdin
+#[cfg(not(windows))]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ExpectRequest {
+    url: tower_lsp::lsp_types::Url,
+    source: String,
+    position: Position,
+}
+
+/// What the process that runs an expect tells the server, as a line of JSON on its stdout each
+#[cfg(not(windows))]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum RunnerMessage {
+    /// The expect at this range compiled, and is about to run.
+    Started(Range),
+    /// The expect ran, and failed with this report if it did.
+    Finished(Option<String>),
+    /// The expect couldn't be compiled or run.
+    Error(String),
+}
+
+/// Compiles a document and runs just the top-level `expect` whose keyword is at `position`.
+///
+/// This happens in a process of its own, which is this binary started again, so that an expect
+/// that crashes or never finishes can't take the server down with it.
+#[cfg(not(windows))]
+pub(crate) fn run_toplevel_expect(
+    doc_info: &DocInfo,
+    position: Position,
+) -> Result<ExpectOutcome, String> {
+    use std::process::Stdio;
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let mut runner = expect_runner()
+        .and_then(|mut command| {
+            command
+                .env(RUN_EXPECT_VAR, "1")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+        })
+        .map_err(|err| format!("The expect couldn't be started: {err}"))?;
+
+    let request = ExpectRequest {
+        url: doc_info.url.clone(),
+        source: doc_info.source.clone(),
+        position,
+    };
+    let messages = read_runner_messages(runner.stdout.take().unwrap());
+
+    // Closing stdin once it's written tells the runner that that's all of the request
+    if let Err(err) = serde_json::to_writer(runner.stdin.take().unwrap(), &request) {
+        let _ = runner.kill();
+        let _ = runner.wait();
+
+        return Err(format!("The expect couldn't be started: {err}"));
+    }
+
+    let exited = |runner: &mut std::process::Child| match runner.wait() {
+        Ok(status) => status.to_string(),
+        Err(err) => err.to_string(),
+    };
+
+    let outcome = match messages.recv() {
+        Ok(RunnerMessage::Started(range)) => match messages.recv_timeout(EXPECT_TIMEOUT) {
+            Ok(RunnerMessage::Finished(failure)) => Ok(ExpectOutcome { range, failure }),
+            Ok(RunnerMessage::Error(err)) => Err(err),
+            Ok(RunnerMessage::Started(_)) | Err(RecvTimeoutError::Disconnected) => {
+                Ok(ExpectOutcome {
+                    range,
+                    failure: Some(format!(
+                        "This expect crashed the process it ran in ({}).",
+                        exited(&mut runner)
+                    )),
+                })
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(ExpectOutcome {
+                range,
+                failure: Some(format!(
+                    "This expect didn't finish within {} seconds, so it was stopped.",
+                    EXPECT_TIMEOUT.as_secs()
+                )),
+            }),
+        },
+        Ok(RunnerMessage::Error(err)) => Err(err),
+        Ok(RunnerMessage::Finished(_)) | Err(_) => Err(format!(
+            "The expect couldn't be compiled ({}).",
+            exited(&mut runner)
+        )),
+    };
+
+    // Only an expect that's still running is stopped by this
+    let _ = runner.kill();
+    let _ = runner.wait();
+
+    outcome
+}
+
+/// The command that starts the process [run_toplevel_expect] runs an expect in. In tests, this
+/// binary is the test harness, so just the test that runs the expect is picked out of it.
+#[cfg(not(windows))]
+fn expect_runner() -> std::io::Result<std::process::Command> {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+
+    #[cfg(test)]
+    command.args(["tests::expect_runner", "--exact", "--nocapture", "--quiet"]);
+
+    Ok(command)
+}
+
+/// Sends each message the runner writes to `stdout` as soon as it has, until the runner exits.
+/// Any other lines, like those a test harness writes, are skipped.
+#[cfg(not(windows))]
+fn read_runner_messages(
+    stdout: std::process::ChildStdout,
+) -> std::sync::mpsc::Receiver<RunnerMessage> {
+    use std::io::BufRead;
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+        {
+            if let Ok(message) = serde_json::from_str(&line) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Runs the expect that [run_toplevel_expect] asked for on stdin, in the process it started, and
+/// tells it how that went on stdout.
+#[cfg(not(windows))]
+pub(crate) fn run_requested_expect() {
+    use std::io::Write;
+
+    let send = |message: RunnerMessage| {
+        let mut stdout = std::io::stdout().lock();
+        // Nobody is left to tell if the server has stopped listening
+        let _ = serde_json::to_writer(&mut stdout, &message)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(stdout))
+            .and_then(|()| stdout.flush());
+    };
+
+    let request = match serde_json::from_reader::<_, ExpectRequest>(std::io::stdin()) {
+        Ok(request) => request,
+        Err(err) => return send(RunnerMessage::Error(format!("Bad expect request: {err}"))),
+    };
+    let doc_info = DocInfo::new(request.url, request.source, 0);
+
+    let finished = compile_and_run_expect(&doc_info, request.position, |range| {
+        send(RunnerMessage::Started(range))
+    });
+
+    send(match finished {
+        Ok(failure) => RunnerMessage::Finished(failure),
+        Err(err) => RunnerMessage::Error(err),
+    });
+}
+
+/// Compiles a document and runs the top-level `expect` whose keyword is at `position`, in this
+/// process. `started` is told where the expect is once it has compiled. Returns the report of the
+/// values that made it fail, if it did.
+#[cfg(not(windows))]
+fn compile_and_run_expect(
+    doc_info: &DocInfo,
+    position: Position,
+    started: impl FnOnce(Range),
+) -> Result<Option<String>, String> {
+    use bumpalo::Bump;
+    use roc_gen_llvm::llvm::build::LlvmBackendMode;
+    use roc_load::{ExecutionMode, FunctionKind, LoadConfig, Threading};
+    use roc_mono::ir::OptLevel;
+    use roc_packaging::cache::{self, RocCacheDir};
+    use roc_repl_expect::run::{expect_mono_module_to_dylib, run_one_toplevel_expect};
+    use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
+
+    use crate::convert::{ToRange, ToRocPosition};
+
+    let path = doc_info
+        .url
+        .to_file_path()
+        .map_err(|()| format!("{} is not a file", doc_info.url))?;
+    let src_dir = super::find_src_dir(&path).to_path_buf();
+    let target = target_lexicon::Triple::host().into();
+
+    let arena = Bump::new();
+    let packages_dir = cache::roc_cache_packages_dir();
+    let load_config = LoadConfig {
+        target,
+        function_kind: FunctionKind::LambdaSet,
+        render: RenderTarget::LanguageServer,
+        palette: DEFAULT_PALETTE,
+        threading: Threading::Single,
+        exec_mode: ExecutionMode::Test,
+    };
+    let mut loaded = roc_load::load_and_monomorphize_from_str(
+        &arena,
+        path,
+        &doc_info.source,
+        src_dir,
+        None,
+        RocCacheDir::Persistent(packages_dir.as_path()),
+        load_config,
+    )
+    .map_err(|_| "The expect can't run until the module's errors are fixed.".to_string())?;
+
+    let module_id = loaded.module_id;
+    let mut expectations = std::mem::take(&mut loaded.expectations);
+    let interns = arena.alloc(loaded.interns.clone());
+
+    let (lib, mut expects_by_module, layout_interner) = expect_mono_module_to_dylib(
+        &arena,
+        target,
+        loaded,
+        OptLevel::Normal,
+        LlvmBackendMode::CliTest,
+    )
+    .map_err(|err| format!("The expect couldn't be compiled: {err}"))?;
+
+    let position = position.to_roc_position(&doc_info.line_info);
+    let expect = expects_by_module.remove(&module_id).and_then(|expects| {
+        expects
+            .pure
+            .into_iter()
+            .find(|expect| expect.region.contains_pos(position))
+    });
+    let Some(expect) = expect else {
+        return Err("There is no top-level expect here.".to_string());
+    };
+
+    started(expect.region.to_range(&doc_info.line_info));
+
+    let mut report = vec![];
+    let result = run_one_toplevel_expect(
+        &mut report,
+        RenderTarget::LanguageServer,
+        &arena,
+        interns,
+        &layout_interner.into_global(),
+        &lib,
+        &mut expectations,
+        expect,
+    )
+    .map_err(|err| err.to_string())?;
+
+    Ok(match result.passed {
+        true => None,
+        false => Some(String::from_utf8_lossy(&report).trim().to_string()),
+    })
+}
+
+#[cfg(windows)]
+pub(crate) fn run_toplevel_expect(
+    _doc_info: &DocInfo,
+    _position: Position,
+) -> Result<ExpectOutcome, String> {
+    Err("Running expects does not work on windows right now.".to_string())
+}
@@ -0,0 +1,178 @@
+use roc_can::{
+    def::Def,
+    expr::{AnnotatedMark, ClosureData, Declarations, Expr, WhenBranch},
+    pattern::{DestructType, Pattern, RecordDestruct},
+    traverse::{
+        walk_decl, walk_def, walk_expr, walk_pattern, walk_record_destruct, DeclarationInfo,
+        Visitor,
+    },
+};
+use roc_region::all::{Loc, Position, Region};
+use roc_types::subs::Variable;
+
+/// Finds the bindings in `range` whose types are inferred rather than written down: unannotated
+/// defs, lambda arguments and the bindings in `when` patterns.
+///
+/// Returns the end of each binding's name along with its type.
+pub(super) fn inferred_bindings(
+    range: Region,
+    declarations: &Declarations,
+) -> Vec<(Position, Variable)> {
+    let mut visitor = InlayHintVisitor {
+        range,
+        bindings: vec![],
+    };
+    visitor.visit_decls(declarations);
+
+    visitor.bindings.sort_by_key(|(position, _)| *position);
+    visitor.bindings.dedup_by_key(|(position, _)| *position);
+    visitor.bindings
+}
+
+struct InlayHintVisitor {
+    range: Region,
+    bindings: Vec<(Position, Variable)>,
+}
+
+impl InlayHintVisitor {
+    fn add_pattern(&mut self, pattern: &Pattern, region: Region, opt_var: Option<Variable>) {
+        let mut visitor = PatternBindings {
+            bindings: &mut self.bindings,
+        };
+        visitor.visit_pattern(pattern, region, opt_var);
+    }
+
+    fn add_arguments(&mut self, arguments: &[(Variable, AnnotatedMark, Loc<Pattern>)]) {
+        for (var, _, loc_pattern) in arguments {
+            self.add_pattern(&loc_pattern.value, loc_pattern.region, Some(*var));
+        }
+    }
+
+    fn add_when_branch(&mut self, branch: &WhenBranch, cond_var: Variable) {
+        for loc_pattern in branch.patterns.iter().map(|pattern| &pattern.pattern) {
+            let opt_var = match loc_pattern.value {
+                Pattern::Identifier(_) | Pattern::As(..) => Some(cond_var),
+                _ => loc_pattern.value.opt_var(),
+            };
+            self.add_pattern(&loc_pattern.value, loc_pattern.region, opt_var);
+        }
+    }
+}
+
+impl Visitor for InlayHintVisitor {
+    fn should_visit(&mut self, region: Region) -> bool {
+        region.start() <= self.range.end() && self.range.start() <= region.end()
+    }
+
+    fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+        if !self.should_visit(decl.region()) {
+            return;
+        }
+
+        match &decl {
+            DeclarationInfo::Value {
+                loc_symbol,
+                expr_var,
+                annotation: None,
+                ..
+            } => self.bindings.push((loc_symbol.region.end(), *expr_var)),
+            DeclarationInfo::Function {
+                loc_symbol,
+                expr_var,
+                function,
+                annotation: None,
+                ..
+            } => {
+                self.bindings.push((loc_symbol.region.end(), *expr_var));
+                self.add_arguments(&function.value.arguments);
+            }
+            DeclarationInfo::Destructure {
+                loc_pattern,
+                opt_pattern_var,
+                annotation: None,
+                ..
+            } => self.add_pattern(&loc_pattern.value, loc_pattern.region, *opt_pattern_var),
+            _ => {}
+        }
+
+        walk_decl(self, decl);
+    }
+
+    fn visit_def(&mut self, def: &Def) {
+        if !self.should_visit(def.region()) {
+            return;
+        }
+
+        match (&def.annotation, &def.loc_expr.value) {
+            (
+                Some(_),
+                Expr::Closure(ClosureData {
+                    loc_body,
+                    return_type,
+                    ..
+                }),
+            ) => {
+                // The types of the arguments are in the annotation, so only the body needs hints
+                self.visit_expr(&loc_body.value, loc_body.region, *return_type);
+                return;
+            }
+            // The arguments of a closure get hints when we visit it
+            (None, _) => {
+                let opt_var = match def.loc_pattern.value {
+                    Pattern::Identifier(_) => Some(def.expr_var),
+                    _ => def.loc_pattern.value.opt_var(),
+                };
+                self.add_pattern(&def.loc_pattern.value, def.loc_pattern.region, opt_var);
+            }
+            (Some(_), _) => {}
+        }
+
+        walk_def(self, def);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        if !self.should_visit(region) {
+            return;
+        }
+
+        match expr {
+            Expr::Closure(closure) => self.add_arguments(&closure.arguments),
+            Expr::When {
+                cond_var, branches, ..
+            } => {
+                for branch in branches {
+                    self.add_when_branch(branch, *cond_var);
+                }
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr, var);
+    }
+}
+
+/// Collects the identifiers bound by a pattern whose types are known.
+struct PatternBindings<'a> {
+    bindings: &'a mut Vec<(Position, Variable)>,
+}
+
+impl Visitor for PatternBindings<'_> {
+    fn visit_pattern(&mut self, pattern: &Pattern, region: Region, opt_var: Option<Variable>) {
+        match (pattern, opt_var) {
+            (Pattern::Identifier(_), Some(var)) => self.bindings.push((region.end(), var)),
+            (Pattern::As(subpattern, _), Some(var)) => {
+                self.bindings.push((region.end(), var));
+                self.visit_pattern(&subpattern.value, subpattern.region, Some(var));
+            }
+            _ => walk_pattern(self, pattern),
+        }
+    }
+
+    fn visit_record_destruct(&mut self, destruct: &RecordDestruct, region: Region) {
+        if let DestructType::Required = destruct.typ {
+            self.bindings.push((region.end(), destruct.var));
+        }
+
+        walk_record_destruct(self, destruct);
+    }
+}
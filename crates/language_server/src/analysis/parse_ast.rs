@@ -40,6 +40,14 @@ impl<'a> Ast<'a> {
         })
     }
 
+    pub fn header(&self) -> &Header<'a> {
+        &self.module.item
+    }
+
+    pub fn defs(&self) -> &Defs<'a> {
+        &self.defs
+    }
+
     pub fn fmt(&self, flags: MigrationFlags) -> FormattedAst<'a> {
         let mut buf = Buf::new_in(self.arena, flags);
 
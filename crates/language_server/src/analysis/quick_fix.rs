@@ -0,0 +1,260 @@
+use bumpalo::Bump;
+use roc_can::{
+    expr::Declarations,
+    traverse::{find_declaration, DeclarationInfo, FoundDeclaration},
+};
+use roc_exhaustive::{Context, Pattern, RenderAs};
+use roc_module::symbol::Interns;
+use roc_parse::ast::ValueDef;
+use roc_problem::can::{Problem, RuntimeError};
+use roc_region::all::{LineInfo, Position, Region};
+use roc_reporting::{
+    error::r#type::unhandled_patterns_to_doc_block,
+    report::{CiWrite, RocDocAllocator},
+};
+use roc_solve_problem::TypeError;
+use tower_lsp::lsp_types::{Diagnostic, TextEdit};
+
+use crate::convert::ToRange;
+
+use super::parse_ast::Ast;
+
+/// Wide enough that missing patterns are never wrapped onto several lines.
+const PATTERN_WIDTH: usize = 1000;
+
+/// Edits that resolve a problem reported by the compiler.
+#[derive(Debug, Clone)]
+pub(crate) struct QuickFix {
+    pub title: String,
+    pub diagnostic: Diagnostic,
+    pub edits: Vec<TextEdit>,
+}
+
+/// Everything needed to work out how to fix the problems in a module.
+pub(super) struct FixContext<'a> {
+    pub source: &'a str,
+    pub line_info: &'a LineInfo,
+    pub declarations: &'a Declarations,
+    pub interns: &'a Interns,
+    pub alloc: &'a RocDocAllocator<'a>,
+}
+
+impl FixContext<'_> {
+    pub fn can_problem_fix(&self, problem: &Problem) -> Option<(String, Vec<TextEdit>)> {
+        match problem {
+            Problem::UnusedImport(symbol, region) => {
+                let name = symbol.as_str(self.interns);
+                if self.text(*region) != name {
+                    return None;
+                }
+
+                Some((
+                    format!("Remove unused import `{name}`"),
+                    vec![self.delete(self.exposed_item_extent(*region))],
+                ))
+            }
+            Problem::UnusedModuleImport(module_id, region) => {
+                // Imports in old-style module headers can't be removed a line at a time
+                if !self.text(*region).starts_with("import ") {
+                    return None;
+                }
+
+                Some((
+                    format!(
+                        "Remove unused import of `{}`",
+                        self.interns.module_name(*module_id)
+                    ),
+                    vec![self.delete(self.line_extent(*region))],
+                ))
+            }
+            Problem::UnusedDef(symbol, _) => {
+                let found = find_declaration(*symbol, self.declarations)?;
+                let annotation = match &found {
+                    FoundDeclaration::Decl(
+                        DeclarationInfo::Value { annotation, .. }
+                        | DeclarationInfo::Function { annotation, .. },
+                    ) => annotation.map(|annotation| annotation.region),
+                    _ => None,
+                };
+                let region = with_annotation(found.region(), annotation);
+
+                Some((
+                    format!("Remove unused def `{}`", symbol.as_str(self.interns)),
+                    vec![self.delete(self.line_extent(region))],
+                ))
+            }
+            Problem::UnusedArgument(_, _, argument, region) => {
+                let name = argument.as_str(self.interns);
+                if self.text(*region) != name {
+                    return None;
+                }
+
+                Some((
+                    format!("Prefix `{name}` with an underscore"),
+                    vec![self.insert(region.start(), "_".to_string())],
+                ))
+            }
+            Problem::RuntimeError(RuntimeError::ModuleNotImported {
+                module_name,
+                module_exists: true,
+                ..
+            }) => Some((
+                format!("Add `import {module_name}`"),
+                vec![self.insert(self.import_position()?, format!("import {module_name}\n"))],
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn type_problem_fix(&self, problem: &TypeError) -> Option<(String, Vec<TextEdit>)> {
+        let TypeError::Exhaustive(roc_exhaustive::Error::Incomplete(
+            region,
+            Context::BadCase,
+            missing,
+        )) = problem
+        else {
+            return None;
+        };
+
+        // Branches are indented like the line after `when ... is`
+        let when_text = self.text(*region);
+        let first_branch = &when_text[when_text.find('\n')? + 1..];
+        let indent = &first_branch[..first_branch.len() - first_branch.trim_start().len()];
+
+        let mut new_branches = String::new();
+        for pattern in missing {
+            // Patterns that are only missing an `if` guard can't be written as a branch
+            if matches!(pattern, Pattern::Ctor(union, ..) if union.render_as == RenderAs::Guard) {
+                continue;
+            }
+
+            let mut pattern_text = String::new();
+            unhandled_patterns_to_doc_block(self.alloc, vec![pattern.clone()])
+                .1
+                .render_raw(PATTERN_WIDTH, &mut CiWrite::new(&mut pattern_text))
+                .ok()?;
+
+            new_branches.push_str(&format!(
+                "\n{indent}{} -> crash \"TODO\"",
+                pattern_text.trim()
+            ));
+        }
+
+        if new_branches.is_empty() {
+            return None;
+        }
+
+        Some((
+            "Add missing `when` branches".to_string(),
+            vec![self.insert(region.end(), new_branches)],
+        ))
+    }
+
+    fn text(&self, region: Region) -> &str {
+        &self.source[region.start().offset as usize..region.end().offset as usize]
+    }
+
+    fn delete(&self, (start, end): (usize, usize)) -> TextEdit {
+        let region = Region::new(Position::new(start as u32), Position::new(end as u32));
+
+        TextEdit {
+            range: region.to_range(self.line_info),
+            new_text: String::new(),
+        }
+    }
+
+    fn insert(&self, position: Position, new_text: String) -> TextEdit {
+        TextEdit {
+            range: Region::new(position, position).to_range(self.line_info),
+            new_text,
+        }
+    }
+
+    /// The whole lines that `region` is on, including the final newline.
+    fn line_extent(&self, region: Region) -> (usize, usize) {
+        let start = self.source[..region.start().offset as usize]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let end = self.source[region.end().offset as usize..]
+            .find('\n')
+            .map_or(self.source.len(), |newline| {
+                region.end().offset as usize + newline + 1
+            });
+
+        (start, end)
+    }
+
+    /// The text to remove to take an item out of an `exposing [...]` list, along with the
+    /// separators around it.
+    fn exposed_item_extent(&self, region: Region) -> (usize, usize) {
+        let (start, end) = (region.start().offset as usize, region.end().offset as usize);
+        let before = self.source[..start].trim_end_matches([' ', '\t']);
+        let after = self.source[end..].trim_start_matches([' ', '\t']);
+
+        if let Some(after_comma) = after.strip_prefix(',') {
+            let after_comma = after_comma.trim_start_matches([' ', '\t']);
+
+            if before.ends_with('\n') && after_comma.starts_with('\n') {
+                // The item has a line to itself
+                return (before.len(), self.source.len() - after_comma.len() + 1);
+            }
+
+            return (start, self.source.len() - after_comma.len());
+        }
+
+        let before_item = before.trim_end();
+        if before_item.ends_with(',') {
+            return (before_item.len() - 1, end);
+        }
+
+        // This is the only exposed item, so the whole `exposing [...]` can go
+        let exposing = before_item
+            .strip_suffix('[')
+            .map(str::trim_end)
+            .and_then(|before| before.strip_suffix("exposing"));
+
+        match (exposing, after.trim_start().strip_prefix(']')) {
+            (Some(before_exposing), Some(after_list)) => (
+                before_exposing.trim_end().len(),
+                self.source.len() - after_list.len(),
+            ),
+            _ => (start, end),
+        }
+    }
+
+    /// Where a new import should go: after the last import, or before the first def.
+    fn import_position(&self) -> Option<Position> {
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, self.source).ok()?;
+
+        let defs = ast.defs();
+        let last_import = defs
+            .defs()
+            .zip(defs.regions.iter())
+            .filter(|(def, _)| {
+                matches!(
+                    def,
+                    Err(ValueDef::ModuleImport(_) | ValueDef::IngestedFileImport(_))
+                )
+            })
+            .last();
+
+        let offset = match (last_import, defs.regions.first()) {
+            (Some((_, region)), _) => self.line_extent(*region).1,
+            (None, Some(region)) => self.line_extent(*region).0,
+            (None, None) => self.source.len(),
+        };
+
+        Some(Position::new(offset as u32))
+    }
+}
+
+/// Extends a declaration's region to cover its annotation, which may be on the line before it.
+fn with_annotation(region: Region, annotation: Option<Region>) -> Region {
+    match annotation {
+        Some(annotation) if annotation.start() < region.start() => {
+            Region::span_across(&annotation, &region)
+        }
+        _ => region,
+    }
+}
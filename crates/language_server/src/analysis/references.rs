@@ -0,0 +1,588 @@
+use std::ops::Range;
+
+use roc_can::{
+    def::{Annotation, Def},
+    expr::{Declarations, Expr, OpaqueWrapFunctionData},
+    pattern::{DestructType, Pattern, RecordDestruct},
+    traverse::{
+        walk_decl, walk_def, walk_expr, walk_pattern, walk_record_destruct, DeclarationInfo,
+        Visitor,
+    },
+};
+use roc_module::symbol::Symbol;
+use roc_parse::ast::{Header, TypeDef, ValueDef};
+use roc_region::all::{Position, Region};
+use roc_types::subs::Variable;
+use tower_lsp::lsp_types::Url;
+
+use super::parse_ast::Ast;
+
+/// The symbol we are finding references to, or renaming.
+///
+/// ModuleIds are not stable between compilations, so documents that were analyzed separately
+/// from the one the symbol was found in have to look it up again by module and ident.
+#[derive(Debug, Clone)]
+pub(crate) struct ReferenceTarget {
+    pub symbol: Symbol,
+    pub origin_url: Url,
+    pub origin_version: i32,
+    pub module_name: String,
+    pub module_url: Option<Url>,
+    pub ident: String,
+}
+
+impl ReferenceTarget {
+    pub fn is_type(&self) -> bool {
+        self.ident.starts_with(char::is_uppercase)
+    }
+
+    /// Builtins aren't in any document to look them up in again, so hierarchy items for them
+    /// carry their target in `data` instead.
+    pub fn builtin_item_data(&self) -> serde_json::Value {
+        serde_json::json!({
+            "builtin": self.symbol.as_u64(),
+            "module": self.module_name,
+        })
+    }
+
+    /// The builtin that a hierarchy item made with [Self::builtin_item_data] was made for.
+    pub fn from_builtin_item_data(uri: &Url, name: &str, data: &serde_json::Value) -> Option<Self> {
+        let symbol = Symbol::from_u64(data.get("builtin")?.as_u64()?)?;
+
+        Some(ReferenceTarget {
+            symbol,
+            origin_url: uri.clone(),
+            origin_version: 0,
+            module_name: data.get("module")?.as_str()?.to_string(),
+            module_url: None,
+            ident: name.to_string(),
+        })
+    }
+
+    /// Checks that `new_name` can take the place of the target's name everywhere it is used.
+    pub fn check_new_name(&self, new_name: &str) -> Result<(), String> {
+        if self.symbol.is_builtin() {
+            return Err(format!("Builtin `{}` cannot be renamed", self.ident));
+        }
+
+        // Effectful functions have to keep their `!` suffix, and nothing else may have one.
+        let body = if self.ident.ends_with('!') {
+            new_name.strip_suffix('!')
+        } else {
+            Some(new_name)
+        };
+
+        let is_type = self.is_type();
+        let is_valid = body.is_some_and(|body| {
+            body.starts_with(|first: char| first.is_uppercase() == is_type && first.is_alphabetic())
+                && body.chars().all(is_ident_char)
+        });
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` is not a valid name for `{}`",
+                new_name, self.ident
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReferenceKind {
+    Declaration,
+    Usage,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Reference {
+    /// The region of just the name, without any module qualifier.
+    pub region: Region,
+    pub kind: ReferenceKind,
+    /// A record field like `{ name }` that is punned with the symbol, so renaming has to keep
+    /// the field label.
+    pub punned: bool,
+}
+
+/// Finds every reference to `symbol` in a module, using the canonical declarations for values
+/// and annotations, and the parse AST for the parts canonicalization doesn't keep regions for.
+pub(crate) fn find_references(
+    source: &str,
+    declarations: &Declarations,
+    ast: Option<&Ast>,
+    symbol: Symbol,
+    target: &ReferenceTarget,
+    is_defining_module: bool,
+) -> Vec<Reference> {
+    let mut visitor = ReferenceVisitor {
+        source,
+        symbol,
+        name: &target.ident,
+        is_type: target.is_type(),
+        references: Vec::new(),
+    };
+    visitor.visit_decls(declarations);
+
+    let mut references = visitor.references;
+    if let Some(ast) = ast {
+        references.extend(ast_references(ast, source, target, is_defining_module));
+    }
+
+    // Annotation-only defs report the same name as both their pattern and their annotation.
+    references.sort_by_key(|reference| reference.region);
+    references.dedup_by_key(|reference| reference.region);
+    references
+}
+
+/// Finds the symbols of the types written in any annotation that contains `position`.
+pub(crate) fn annotation_symbols_at(position: Position, decls: &Declarations) -> Vec<Symbol> {
+    let mut visitor = Finder {
+        position,
+        symbols: Vec::new(),
+    };
+    visitor.visit_decls(decls);
+    return visitor.symbols;
+
+    struct Finder {
+        position: Position,
+        symbols: Vec<Symbol>,
+    }
+
+    impl Visitor for Finder {
+        fn visit_annotation(&mut self, annotation: &Annotation) {
+            if annotation.region.contains_pos(self.position) {
+                self.symbols.extend(annotation_type_symbols(annotation));
+            }
+        }
+    }
+}
+
+/// Finds the identifier surrounding `position`, returning its region, name and module
+/// qualifier, e.g. `name` and `Module` for `Module.name`.
+pub(crate) fn ident_at(source: &str, position: Position) -> Option<(Region, &str, Option<&str>)> {
+    let offset = (position.offset as usize).min(source.len());
+    let is_name_char = |c: char| is_ident_char(c) || c == '!';
+
+    let start = source[..offset]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_name_char(*c))
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let end = source[offset..]
+        .find(|c: char| !is_name_char(c))
+        .map_or(source.len(), |index| offset + index);
+
+    if start == end {
+        return None;
+    }
+
+    let region = Region::new(Position::new(start as u32), Position::new(end as u32));
+    let qualifier = qualifier_before(&source[..start]);
+
+    Some((region, &source[start..end], qualifier))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn annotation_type_symbols(annotation: &Annotation) -> impl Iterator<Item = Symbol> + '_ {
+    let abilities = annotation
+        .introduced_variables
+        .able
+        .iter()
+        .flat_map(|able| able.abilities.sorted_iter().copied());
+
+    annotation.signature.symbols().into_iter().chain(abilities)
+}
+
+/// Gets the module qualifier in front of a name, if the text before the name ends with one.
+fn qualifier_before(before: &str) -> Option<&str> {
+    let qualified = before.strip_suffix('.')?;
+    let start = qualified
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(is_ident_char(*c) || *c == '.'))
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let qualifier = &qualified[start..];
+
+    // Lowercase qualifiers are record accesses, not modules.
+    qualifier
+        .starts_with(char::is_uppercase)
+        .then_some(qualifier)
+}
+
+struct Occurrence<'a> {
+    region: Region,
+    qualifier: Option<&'a str>,
+}
+
+/// Finds every occurrence of the identifier `name` within `region`.
+fn name_occurrences<'a>(
+    source: &'a str,
+    region: Region,
+    name: &'a str,
+) -> impl Iterator<Item = Occurrence<'a>> + 'a {
+    let start = (region.start().offset as usize).min(source.len());
+    let end = (region.end().offset as usize).min(source.len());
+    let text = source.get(start..end).unwrap_or_default();
+    let skipped = comment_and_string_ranges(text);
+
+    text.match_indices(name).filter_map(move |(index, _)| {
+        // A name in a comment or a string is just text, not a reference
+        if skipped.iter().any(|range| range.contains(&index)) {
+            return None;
+        }
+
+        let offset = start + index;
+        let before = &source[..offset];
+        let after = &source[offset + name.len()..];
+
+        if before.ends_with(is_ident_char)
+            || after.starts_with(|c: char| is_ident_char(c) || c == '!')
+        {
+            return None;
+        }
+
+        let start = Position::new(offset as u32);
+        Some(Occurrence {
+            region: Region::new(start, start.bump_column(name.len() as u32)),
+            qualifier: qualifier_before(before),
+        })
+    })
+}
+
+/// The byte ranges of the comments and string literals in `text`.
+fn comment_and_string_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let end = match c {
+            '#' => text[start..]
+                .find('\n')
+                .map_or(text.len(), |len| start + len),
+            '"' if text[start..].starts_with("\"\"\"") => text[start + 3..]
+                .find("\"\"\"")
+                .map_or(text.len(), |len| start + 3 + len + 3),
+            '"' | '\'' => closing_quote(text, start, c),
+            _ => continue,
+        };
+
+        ranges.push(start..end);
+
+        while chars.next_if(|(index, _)| *index < end).is_some() {}
+    }
+
+    ranges
+}
+
+/// Where the single-line string or character literal that starts at `start` ends.
+fn closing_quote(text: &str, start: usize, quote: char) -> usize {
+    let mut escaped = false;
+
+    for (index, c) in text[start + 1..].char_indices() {
+        let offset = start + 1 + index;
+
+        match c {
+            '\\' if !escaped => escaped = true,
+            '\n' => return offset,
+            c if c == quote && !escaped => return offset + 1,
+            _ => escaped = false,
+        }
+    }
+
+    text.len()
+}
+
+fn region_text(source: &str, region: Region) -> &str {
+    source
+        .get(region.start().offset as usize..region.end().offset as usize)
+        .unwrap_or_default()
+}
+
+struct ReferenceVisitor<'a> {
+    source: &'a str,
+    symbol: Symbol,
+    name: &'a str,
+    is_type: bool,
+    references: Vec<Reference>,
+}
+
+impl ReferenceVisitor<'_> {
+    fn push(&mut self, region: Region, kind: ReferenceKind, punned: bool) {
+        self.references.push(Reference {
+            region,
+            kind,
+            punned,
+        });
+    }
+
+    /// Records the first occurrence of the name in `region`, for nodes that span more than the
+    /// name itself, e.g. `@Name` or `{ name & field: value }`.
+    fn push_first_in(&mut self, region: Region, kind: ReferenceKind, punned: bool) {
+        if let Some(occurrence) = name_occurrences(self.source, region, self.name).next() {
+            self.push(occurrence.region, kind, punned);
+        }
+    }
+
+    /// Records the last occurrence of the name in `region`, e.g. `Module.name` or `pat as name`.
+    fn push_last_in(&mut self, region: Region, kind: ReferenceKind) {
+        if let Some(occurrence) = name_occurrences(self.source, region, self.name).last() {
+            self.push(occurrence.region, kind, false);
+        }
+    }
+
+    /// Records the name of a `name : Type` annotation, which canonicalization keeps no region for.
+    fn push_annotation_name(&mut self, annotation: &Annotation) {
+        let Some(before) = self.source.get(..annotation.region.start().offset as usize) else {
+            return;
+        };
+        let Some(before) = before.trim_end().strip_suffix(':') else {
+            return;
+        };
+        let before = before.trim_end();
+        let Some(start) = before.strip_suffix(self.name).map(str::len) else {
+            return;
+        };
+
+        if !before[..start].ends_with(is_ident_char) {
+            let start = Position::new(start as u32);
+            let region = Region::new(start, start.bump_column(self.name.len() as u32));
+            self.push(region, ReferenceKind::Declaration, false);
+        }
+    }
+}
+
+impl Visitor for ReferenceVisitor<'_> {
+    fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+        match &decl {
+            DeclarationInfo::Value {
+                loc_symbol,
+                annotation: Some(annotation),
+                ..
+            }
+            | DeclarationInfo::Function {
+                loc_symbol,
+                annotation: Some(annotation),
+                ..
+            } if loc_symbol.value == self.symbol => self.push_annotation_name(annotation),
+            _ => {}
+        }
+
+        walk_decl(self, decl);
+    }
+
+    fn visit_def(&mut self, def: &Def) {
+        if let (Pattern::Identifier(symbol), Some(annotation)) =
+            (&def.loc_pattern.value, &def.annotation)
+        {
+            if *symbol == self.symbol {
+                self.push_annotation_name(annotation);
+            }
+        }
+
+        walk_def(self, def);
+    }
+
+    fn visit_annotation(&mut self, annotation: &Annotation) {
+        if self.is_type && annotation_type_symbols(annotation).any(|s| s == self.symbol) {
+            // Types in annotations have no regions of their own, but the annotation as a whole
+            // was resolved to the target, so every mention of the name in it refers to it.
+            let occurrences = name_occurrences(self.source, annotation.region, self.name)
+                .map(|occurrence| occurrence.region)
+                .collect::<Vec<_>>();
+
+            for region in occurrences {
+                self.push(region, ReferenceKind::Usage, false);
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        match expr {
+            Expr::Var(symbol, _)
+            | Expr::ParamsVar { symbol, .. }
+            | Expr::AbilityMember(symbol, _, _)
+                if *symbol == self.symbol =>
+            {
+                self.push_last_in(region, ReferenceKind::Usage)
+            }
+            Expr::RecordUpdate { symbol, .. } if *symbol == self.symbol => {
+                self.push_first_in(region, ReferenceKind::Usage, false)
+            }
+            Expr::OpaqueRef { name, .. }
+            | Expr::OpaqueWrapFunction(OpaqueWrapFunctionData {
+                opaque_name: name, ..
+            }) if *name == self.symbol => self.push_first_in(region, ReferenceKind::Usage, false),
+            Expr::Record { fields, .. } => {
+                for (_, field) in fields.iter() {
+                    let is_target = matches!(
+                        field.loc_expr.value,
+                        Expr::Var(symbol, _) if symbol == self.symbol
+                    );
+                    let is_punned =
+                        is_target && !region_text(self.source, field.region).contains(':');
+
+                    if is_punned {
+                        self.push_first_in(field.region, ReferenceKind::Usage, true);
+                    } else {
+                        self.visit_expr(&field.loc_expr.value, field.loc_expr.region, field.var);
+                    }
+                }
+
+                return;
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr, var);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern, region: Region, _opt_var: Option<Variable>) {
+        match pattern {
+            Pattern::Identifier(symbol)
+            | Pattern::AbilityMemberSpecialization { ident: symbol, .. }
+                if *symbol == self.symbol =>
+            {
+                self.push(region, ReferenceKind::Declaration, false)
+            }
+            Pattern::As(_, symbol) if *symbol == self.symbol => {
+                self.push_last_in(region, ReferenceKind::Declaration)
+            }
+            Pattern::UnwrappedOpaque { opaque, .. } if *opaque == self.symbol => {
+                self.push_first_in(region, ReferenceKind::Usage, false)
+            }
+            _ => {}
+        }
+
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_record_destruct(&mut self, destruct: &RecordDestruct, region: Region) {
+        if destruct.symbol == self.symbol && !matches!(destruct.typ, DestructType::Guard(..)) {
+            self.push_first_in(region, ReferenceKind::Declaration, true);
+        }
+
+        walk_record_destruct(self, destruct);
+    }
+}
+
+/// Finds the references canonicalization doesn't keep: names in `exposes` and
+/// `import ... exposing` lists, type definitions, and ability members.
+fn ast_references(
+    ast: &Ast,
+    source: &str,
+    target: &ReferenceTarget,
+    is_defining_module: bool,
+) -> Vec<Reference> {
+    let name = target.ident.as_str();
+    let first_occurrence = |region: Region, kind: ReferenceKind| {
+        name_occurrences(source, region, name)
+            .next()
+            .map(|occurrence| Reference {
+                region: occurrence.region,
+                kind,
+                punned: false,
+            })
+    };
+
+    let mut references = Vec::new();
+
+    if is_defining_module {
+        let exposes = match ast.header() {
+            Header::Module(header) => header.exposes.items,
+            Header::Hosted(header) => header.exposes.items,
+            Header::App(header) => header.provides.items,
+            Header::Package(_) | Header::Platform(_) => &[],
+        };
+
+        for exposed in exposes {
+            if exposed.value.item().as_str() == name {
+                references.extend(first_occurrence(exposed.region, ReferenceKind::Usage));
+            }
+        }
+    }
+
+    // How the defining module can be referred to from this one.
+    let mut qualifiers = Vec::new();
+    let mut exposed_by_import = false;
+
+    for value_def in ast.defs().value_defs.iter() {
+        let ValueDef::ModuleImport(import) = value_def else {
+            continue;
+        };
+
+        let module_name = import.name.value.name.as_str();
+        if module_name != target.module_name {
+            continue;
+        }
+
+        match &import.alias {
+            Some(alias) => qualifiers.push(alias.item.value.as_str()),
+            None => {
+                qualifiers.push(module_name);
+                qualifiers.extend(module_name.rsplit('.').next());
+            }
+        }
+
+        for exposed in import.exposed.iter().flat_map(|exposed| exposed.item.items) {
+            if exposed.value.item().as_str() == name {
+                exposed_by_import = true;
+                references.extend(first_occurrence(exposed.region, ReferenceKind::Usage));
+            }
+        }
+    }
+
+    let defs = ast.defs();
+    for (def, def_region) in defs.defs().zip(defs.regions.iter()) {
+        let Ok(type_def) = def else {
+            continue;
+        };
+
+        let (TypeDef::Alias { header, .. }
+        | TypeDef::Opaque { header, .. }
+        | TypeDef::Ability { header, .. }) = type_def;
+
+        if target.is_type() {
+            // Type definitions are not canonicalized into declarations, so we resolve the names
+            // in them the way canonicalization would: by what is in scope.
+            for occurrence in name_occurrences(source, *def_region, name) {
+                let is_header =
+                    is_defining_module && header.name.region.contains(&occurrence.region);
+                let in_scope = match occurrence.qualifier {
+                    None => is_defining_module || exposed_by_import,
+                    Some(qualifier) => qualifiers.contains(&qualifier),
+                };
+
+                if is_header || in_scope {
+                    references.push(Reference {
+                        region: occurrence.region,
+                        kind: if is_header {
+                            ReferenceKind::Declaration
+                        } else {
+                            ReferenceKind::Usage
+                        },
+                        punned: false,
+                    });
+                }
+            }
+        } else if let TypeDef::Ability { members, .. } = type_def {
+            if !is_defining_module {
+                continue;
+            }
+
+            for member in members.iter() {
+                if *member.name.value.item() == name {
+                    references.extend(first_occurrence(
+                        member.name.region,
+                        ReferenceKind::Declaration,
+                    ));
+                }
+            }
+        }
+    }
+
+    references
+}
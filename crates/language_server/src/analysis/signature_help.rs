@@ -0,0 +1,160 @@
+use roc_can::{
+    expr::{Declarations, Expr},
+    traverse::{walk_expr, Visitor},
+};
+use roc_module::{
+    called_via::{BinOp, CalledVia},
+    symbol::Symbol,
+};
+use roc_region::all::{Position, Region};
+use roc_types::subs::Variable;
+use tower_lsp::lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, SignatureInformation,
+};
+
+/// A function application whose argument list contains the cursor.
+pub(super) struct CallAt {
+    pub fn_var: Variable,
+    /// The function being called, if it is called by name.
+    pub fn_symbol: Option<Symbol>,
+    /// The index of the argument the cursor is in, counting any argument piped in with `|>`.
+    pub active_argument: usize,
+}
+
+/// Finds the innermost function application whose arguments the cursor is in.
+///
+/// The cursor also counts as being in an application when it is in the whitespace just after
+/// it, since that is where the next argument is about to be typed.
+pub(super) fn find_call_at(
+    position: Position,
+    source: &str,
+    declarations: &Declarations,
+) -> Option<CallAt> {
+    let mut visitor = CallVisitor {
+        position,
+        source,
+        found: None,
+    };
+    visitor.visit_decls(declarations);
+    visitor.found
+}
+
+struct CallVisitor<'a> {
+    position: Position,
+    source: &'a str,
+    found: Option<CallAt>,
+}
+
+impl CallVisitor<'_> {
+    fn is_within(&self, region: Region) -> bool {
+        let (start, end) = (region.start(), region.end());
+        if start > self.position {
+            return false;
+        }
+        if self.position <= end {
+            return true;
+        }
+
+        self.source
+            .get(end.offset as usize..self.position.offset as usize)
+            .is_some_and(|between| between.chars().all(|c| c == ' ' || c == '\t'))
+    }
+}
+
+impl Visitor for CallVisitor<'_> {
+    fn should_visit(&mut self, region: Region) -> bool {
+        self.is_within(region)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        if !self.is_within(region) {
+            return;
+        }
+
+        if let Expr::Call(f, args, CalledVia::Space | CalledVia::BinOp(BinOp::Pizza)) = expr {
+            let (fn_var, loc_fn, ..) = &**f;
+
+            // The cursor has to be past the function's name to be in its arguments
+            if loc_fn.region.end() < self.position {
+                let fn_symbol = match loc_fn.value {
+                    Expr::Var(symbol, _)
+                    | Expr::ParamsVar { symbol, .. }
+                    | Expr::AbilityMember(symbol, ..) => Some(symbol),
+                    _ => None,
+                };
+                let active_argument = args
+                    .iter()
+                    .filter(|(_, arg)| arg.region.end() < self.position)
+                    .count();
+
+                self.found = Some(CallAt {
+                    fn_var: *fn_var,
+                    fn_symbol,
+                    active_argument,
+                });
+            }
+        }
+
+        walk_expr(self, expr, var);
+    }
+}
+
+/// Describes a function's signature, with each of its arguments labelled by its position in the
+/// printed type.
+pub(super) fn signature_information(
+    type_str: String,
+    documentation: Option<Documentation>,
+    active_argument: usize,
+) -> Option<SignatureInformation> {
+    let parameters = argument_offsets(&type_str)?
+        .into_iter()
+        .map(|(start, end)| ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                utf16_len(&type_str[..start]),
+                utf16_len(&type_str[..end]),
+            ]),
+            documentation: None,
+        })
+        .collect::<Vec<_>>();
+
+    Some(SignatureInformation {
+        label: type_str,
+        documentation,
+        active_parameter: Some(active_argument as u32),
+        parameters: Some(parameters),
+    })
+}
+
+/// Finds the byte ranges of the arguments in a printed function type,
+/// e.g. `List a, (a -> b) -> List b` has arguments `List a` and `(a -> b)`.
+fn argument_offsets(type_str: &str) -> Option<Vec<(usize, usize)>> {
+    let mut depth = 0;
+    let mut arguments = vec![];
+    let mut argument_start = 0;
+
+    for (index, c) in type_str.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push((argument_start, index));
+                argument_start = index + ", ".len();
+            }
+            ' ' if depth == 0 => {
+                let rest = &type_str[index..];
+                if rest.starts_with(" -> ") || rest.starts_with(" => ") {
+                    arguments.push((argument_start, index));
+                    return Some(arguments);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Not a function
+    None
+}
+
+fn utf16_len(str: &str) -> u32 {
+    str.encode_utf16().count() as u32
+}
@@ -0,0 +1,237 @@
+use roc_can::{
+    expr::{DeclarationTag, Declarations},
+    pattern::{DestructType, Pattern, RecordDestruct},
+    traverse::{walk_pattern, walk_record_destruct, Visitor},
+};
+use roc_module::symbol::{Interns, Symbol};
+use roc_parse::ast::TypeDef;
+use roc_region::all::Region;
+use roc_types::subs::Variable;
+use tower_lsp::lsp_types::SymbolKind;
+
+use super::{parse_ast::Ast, AnalyzedModule};
+
+/// A symbol defined at the top level of a module, as it appears in the module's outline.
+#[derive(Debug, Clone)]
+pub(crate) struct OutlineSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The type of the symbol, for values and ability members.
+    pub var: Option<Variable>,
+    /// The whole definition, including its annotation and body.
+    pub region: Region,
+    /// The name of the symbol within its definition.
+    pub selection_region: Region,
+    pub children: Vec<OutlineSymbol>,
+}
+
+/// Builds the outline of a module from its canonical declarations and abilities.
+///
+/// Type aliases and opaque types are not kept after canonicalization, so those are read from
+/// the parsed module when it is available.
+pub(super) fn module_outline(module: &AnalyzedModule, ast: Option<&Ast>) -> Vec<OutlineSymbol> {
+    let AnalyzedModule {
+        module_id,
+        interns,
+        abilities,
+        declarations,
+        ..
+    } = module;
+
+    let mut outline = value_symbols(declarations, interns);
+
+    let type_defs = ast.into_iter().flat_map(|ast| {
+        let defs = ast.defs();
+        defs.defs()
+            .zip(defs.regions.iter())
+            .filter_map(|(def, region)| Some((def.ok()?, *region)))
+    });
+
+    for (type_def, region) in type_defs {
+        let (header, kind) = match type_def {
+            TypeDef::Alias { header, .. } => (header, SymbolKind::STRUCT),
+            TypeDef::Opaque { header, .. } => (header, SymbolKind::CLASS),
+            // Abilities and their members are taken from the abilities store below
+            TypeDef::Ability { .. } => continue,
+        };
+
+        outline.push(OutlineSymbol {
+            name: header.name.value.to_string(),
+            kind,
+            var: None,
+            region,
+            selection_region: header.name.region,
+            children: vec![],
+        });
+    }
+
+    let own_abilities = abilities
+        .iter_abilities()
+        .filter(|(ability, _)| ability.module_id() == *module_id);
+
+    for (ability, members) in own_abilities {
+        let name = ability.as_str(interns);
+
+        let mut children = members
+            .iter()
+            .filter_map(|member| {
+                let member_def = abilities.member_def(*member)?;
+
+                Some(OutlineSymbol {
+                    name: member.as_str(interns).to_string(),
+                    kind: SymbolKind::METHOD,
+                    var: Some(member_def.signature_var()),
+                    region: member_def.region,
+                    selection_region: member_def.region,
+                    children: vec![],
+                })
+            })
+            .collect::<Vec<_>>();
+        children.sort_by_key(|child| child.region);
+
+        let header_region = ast.and_then(|ast| ability_regions(ast, name));
+        let (region, selection_region) = match header_region {
+            Some(regions) => regions,
+            None => {
+                // Without a parse we only know where the members are
+                let region = span(children.iter().map(|child| child.region));
+                (region, Region::from_pos(region.start()))
+            }
+        };
+
+        outline.push(OutlineSymbol {
+            name: name.to_string(),
+            kind: SymbolKind::INTERFACE,
+            var: None,
+            region,
+            selection_region,
+            children,
+        });
+    }
+
+    outline.sort_by_key(|symbol| symbol.region);
+    outline
+}
+
+/// The top-level values and functions declared in a module.
+fn value_symbols(declarations: &Declarations, interns: &Interns) -> Vec<OutlineSymbol> {
+    let mut symbols = vec![];
+
+    for (index, tag) in declarations.declarations.iter().enumerate() {
+        let kind = match tag {
+            DeclarationTag::Value => SymbolKind::CONSTANT,
+            DeclarationTag::Function(_)
+            | DeclarationTag::Recursive(_)
+            | DeclarationTag::TailRecursive(_) => SymbolKind::FUNCTION,
+            DeclarationTag::Destructure(destructure_index) => {
+                let destructure = &declarations.destructs[destructure_index.index()];
+                let loc_pattern = &destructure.loc_pattern;
+
+                let mut visitor = PatternSymbols { symbols: vec![] };
+                visitor.visit_pattern(&loc_pattern.value, loc_pattern.region, None);
+
+                for (symbol, selection_region) in visitor.symbols {
+                    let Some(var) = destructure.pattern_vars.get(&symbol) else {
+                        continue;
+                    };
+
+                    symbols.push(OutlineSymbol {
+                        name: symbol.as_str(interns).to_string(),
+                        kind: SymbolKind::CONSTANT,
+                        var: Some(*var),
+                        region: span([loc_pattern.region, declarations.expressions[index].region]),
+                        selection_region,
+                        children: vec![],
+                    });
+                }
+
+                continue;
+            }
+            DeclarationTag::Expectation | DeclarationTag::MutualRecursion { .. } => continue,
+        };
+
+        let loc_symbol = declarations.symbols[index];
+        let loc_expr = &declarations.expressions[index];
+        let annotation_region = declarations.annotations[index]
+            .as_ref()
+            .map(|annotation| annotation.region);
+
+        symbols.push(OutlineSymbol {
+            name: loc_symbol.value.as_str(interns).to_string(),
+            kind,
+            var: Some(declarations.variables[index]),
+            region: span(
+                [loc_symbol.region, loc_expr.region]
+                    .into_iter()
+                    .chain(annotation_region),
+            ),
+            selection_region: loc_symbol.region,
+            children: vec![],
+        });
+    }
+
+    symbols
+}
+
+/// Finds the region of an ability's definition and of its name.
+fn ability_regions(ast: &Ast, name: &str) -> Option<(Region, Region)> {
+    let defs = ast.defs();
+
+    defs.defs()
+        .zip(defs.regions.iter())
+        .find_map(|(def, region)| match def {
+            Ok(TypeDef::Ability { header, .. }) if header.name.value == name => {
+                Some((*region, header.name.region))
+            }
+            _ => None,
+        })
+}
+
+/// Checks whether `name` contains the characters of `query` in order, ignoring case.
+pub(super) fn matches_query(name: &str, query: &str) -> bool {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|query_char| name_chars.any(|name_char| name_char == query_char))
+}
+
+/// The smallest region that covers all of `regions`.
+fn span(regions: impl IntoIterator<Item = Region>) -> Region {
+    let regions = regions.into_iter().collect::<Vec<_>>();
+
+    match (
+        regions.iter().min_by_key(|region| region.start()),
+        regions.iter().max_by_key(|region| region.end()),
+    ) {
+        (Some(first), Some(last)) => Region::span_across(first, last),
+        _ => Region::zero(),
+    }
+}
+
+/// Collects the symbols bound by a pattern, along with the region of each binding.
+struct PatternSymbols {
+    symbols: Vec<(Symbol, Region)>,
+}
+
+impl Visitor for PatternSymbols {
+    fn visit_pattern(&mut self, pattern: &Pattern, region: Region, _opt_var: Option<Variable>) {
+        match pattern {
+            Pattern::Identifier(symbol) => self.symbols.push((*symbol, region)),
+            Pattern::As(_, symbol) => {
+                self.symbols.push((*symbol, region));
+                walk_pattern(self, pattern);
+            }
+            _ => walk_pattern(self, pattern),
+        }
+    }
+
+    fn visit_record_destruct(&mut self, destruct: &RecordDestruct, region: Region) {
+        if !matches!(destruct.typ, DestructType::Guard(..)) {
+            self.symbols.push((destruct.symbol, region));
+        }
+
+        walk_record_destruct(self, destruct);
+    }
+}
@@ -0,0 +1,378 @@
+use roc_parse::ast::{
+    AssignedField, Defs, Expr, Pattern, TypeAnnotation, TypeDef, ValueDef, WhenBranch,
+};
+use roc_region::all::{LineInfo, Loc, Position, Region};
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind, SelectionRange};
+
+use crate::convert::ToRange;
+
+/// A node of the parse AST.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SyntaxNode {
+    pub region: Region,
+    /// Whether the node is worth folding when it spans several lines.
+    pub folds: bool,
+}
+
+/// Collects the regions of the defs, expressions and patterns in a module. The AST is all that's
+/// needed, so this works in modules that don't type check.
+pub(super) fn syntax_nodes(defs: &Defs) -> Vec<SyntaxNode> {
+    let mut collector = NodeCollector { nodes: vec![] };
+    collector.defs(defs);
+    collector.nodes
+}
+
+/// Folds multi-line defs, `when` expressions and branches, collections, runs of imports and
+/// blocks of comments.
+pub(super) fn folding_ranges(defs: &Defs, source: &str, line_info: &LineInfo) -> Vec<FoldingRange> {
+    let mut ranges = vec![];
+    let mut fold = |region: Region, kind: Option<FoldingRangeKind>| {
+        let range = region.to_range(line_info);
+        if range.end.line > range.start.line {
+            ranges.push(FoldingRange {
+                start_line: range.start.line,
+                end_line: range.end.line,
+                kind,
+                ..Default::default()
+            });
+        }
+    };
+
+    let mut imports: Option<Region> = None;
+    for (def, region) in defs.defs().zip(defs.regions.iter()) {
+        if matches!(
+            def,
+            Err(ValueDef::ModuleImport(_) | ValueDef::IngestedFileImport(_))
+        ) {
+            imports =
+                Some(imports.map_or(*region, |imports| Region::span_across(&imports, region)));
+        } else if let Some(imports) = imports.take() {
+            fold(imports, Some(FoldingRangeKind::Imports));
+        }
+    }
+    if let Some(imports) = imports {
+        fold(imports, Some(FoldingRangeKind::Imports));
+    }
+
+    for node in syntax_nodes(defs) {
+        if node.folds {
+            fold(node.region, None);
+        }
+    }
+
+    for (start_line, end_line) in comment_blocks(source) {
+        ranges.push(FoldingRange {
+            start_line,
+            end_line,
+            kind: Some(FoldingRangeKind::Comment),
+            ..Default::default()
+        });
+    }
+
+    // A def and the collection it's defined as are often on the same lines
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges.dedup_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+/// The nodes around `position`, from the innermost outwards.
+pub(super) fn selection_range(
+    nodes: &[SyntaxNode],
+    position: Position,
+    line_info: &LineInfo,
+) -> SelectionRange {
+    let mut regions: Vec<Region> = nodes
+        .iter()
+        .map(|node| node.region)
+        .filter(|region| region.contains_pos(position))
+        .collect();
+    regions.sort_by_key(|region| (region.end().offset - region.start().offset, region.start()));
+    regions.dedup();
+
+    regions
+        .into_iter()
+        .rev()
+        .fold(None, |parent, region| {
+            Some(SelectionRange {
+                range: region.to_range(line_info),
+                parent: parent.map(Box::new),
+            })
+        })
+        .unwrap_or_else(|| SelectionRange {
+            range: Region::new(position, position).to_range(line_info),
+            parent: None,
+        })
+}
+
+/// The first and last lines of each run of two or more comment lines.
+fn comment_blocks(source: &str) -> Vec<(u32, u32)> {
+    let mut blocks = vec![];
+    let mut start = None;
+
+    for (line, text) in source.lines().chain(std::iter::once("")).enumerate() {
+        let line = line as u32;
+        match (text.trim_start().starts_with('#'), start) {
+            (true, None) => start = Some(line),
+            (false, Some(first)) => {
+                if line - first > 1 {
+                    blocks.push((first, line - 1));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+struct NodeCollector {
+    nodes: Vec<SyntaxNode>,
+}
+
+impl NodeCollector {
+    fn push(&mut self, region: Region, folds: bool) {
+        self.nodes.push(SyntaxNode { region, folds });
+    }
+
+    fn defs(&mut self, defs: &Defs) {
+        for (def, region) in defs.defs().zip(defs.regions.iter()) {
+            self.push(*region, true);
+
+            match def {
+                Ok(type_def) => self.type_def(type_def),
+                Err(value_def) => self.value_def(value_def),
+            }
+        }
+    }
+
+    fn type_def(&mut self, type_def: &TypeDef) {
+        match type_def {
+            TypeDef::Alias { ann, .. } => self.annotation(ann),
+            TypeDef::Opaque { typ, .. } => self.annotation(typ),
+            TypeDef::Ability { members, .. } => {
+                for member in members.iter() {
+                    self.push(member.region(), false);
+                    self.annotation(&member.typ);
+                }
+            }
+        }
+    }
+
+    fn value_def(&mut self, value_def: &ValueDef) {
+        match value_def {
+            ValueDef::Annotation(pattern, annotation) => {
+                self.pattern(pattern);
+                self.annotation(annotation);
+            }
+            ValueDef::Body(pattern, body) => {
+                self.pattern(pattern);
+                self.expr(body);
+            }
+            ValueDef::AnnotatedBody {
+                ann_pattern,
+                ann_type,
+                body_pattern,
+                body_expr,
+                ..
+            } => {
+                self.pattern(ann_pattern);
+                self.annotation(ann_type);
+                self.pattern(body_pattern);
+                self.expr(body_expr);
+            }
+            ValueDef::Dbg { condition, .. } | ValueDef::Expect { condition, .. } => {
+                self.expr(condition)
+            }
+            ValueDef::Stmt(expr) => self.expr(expr),
+            ValueDef::ModuleImport(_)
+            | ValueDef::IngestedFileImport(_)
+            | ValueDef::StmtAfterExpr => {}
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Loc<TypeAnnotation>) {
+        let folds = matches!(
+            annotation.value,
+            TypeAnnotation::Record { .. } | TypeAnnotation::TagUnion { .. }
+        );
+        self.push(annotation.region, folds);
+    }
+
+    fn expr(&mut self, loc_expr: &Loc<Expr>) {
+        self.expr_at(&loc_expr.value, loc_expr.region);
+    }
+
+    fn expr_at(&mut self, expr: &Expr, region: Region) {
+        let folds = match expr {
+            // These don't have regions of their own
+            Expr::SpaceBefore(expr, _) | Expr::SpaceAfter(expr, _) | Expr::ParensAround(expr) => {
+                return self.expr_at(expr, region);
+            }
+            Expr::When(..)
+            | Expr::List(_)
+            | Expr::Record(_)
+            | Expr::Tuple(_)
+            | Expr::RecordUpdate { .. }
+            | Expr::RecordBuilder { .. } => true,
+            _ => false,
+        };
+        self.push(region, folds);
+
+        match expr {
+            Expr::List(items) | Expr::Tuple(items) => {
+                for item in items.iter() {
+                    self.expr(item);
+                }
+            }
+            Expr::Record(fields) => self.fields(fields.items),
+            Expr::RecordUpdate { update, fields } => {
+                self.expr(update);
+                self.fields(fields.items);
+            }
+            Expr::RecordBuilder { mapper, fields } => {
+                self.expr(mapper);
+                self.fields(fields.items);
+            }
+            Expr::Closure(arguments, body) => {
+                for argument in arguments.iter() {
+                    self.pattern(argument);
+                }
+                self.expr(body);
+            }
+            Expr::Defs(defs, final_expr) => {
+                self.defs(defs);
+                self.expr(final_expr);
+            }
+            Expr::DbgStmt {
+                first,
+                extra_args,
+                continuation,
+                ..
+            } => {
+                self.expr(first);
+                for arg in extra_args.iter() {
+                    self.expr(arg);
+                }
+                self.expr(continuation);
+            }
+            Expr::LowLevelDbg(_, first, second) => {
+                self.expr(first);
+                self.expr(second);
+            }
+            Expr::Apply(function, args, _) => {
+                self.expr(function);
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Expr::PncApply(function, args) => {
+                self.expr(function);
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Expr::BinOps(operands, last) => {
+                for (operand, _) in operands.iter() {
+                    self.expr(operand);
+                }
+                self.expr(last);
+            }
+            Expr::If {
+                if_thens,
+                final_else,
+                ..
+            } => {
+                for (condition, then) in if_thens.iter() {
+                    self.expr(condition);
+                    self.expr(then);
+                }
+                self.expr(final_else);
+            }
+            Expr::When(condition, branches) => {
+                self.expr(condition);
+                for branch in branches.iter() {
+                    self.when_branch(branch);
+                }
+            }
+            Expr::Return(value, after) => {
+                self.expr(value);
+                if let Some(after) = after {
+                    self.expr(after);
+                }
+            }
+            Expr::UnaryOp(expr, _)
+            | Expr::LowLevelTry(expr, _)
+            | Expr::EmptyRecordBuilder(expr)
+            | Expr::SingleFieldRecordBuilder(expr)
+            | Expr::OptionalFieldInRecordBuilder(_, expr) => self.expr(expr),
+            _ => {}
+        }
+    }
+
+    fn fields(&mut self, fields: &[Loc<AssignedField<Expr>>]) {
+        for field in fields {
+            self.push(field.region, false);
+
+            if let Some(value) = field.value.value() {
+                self.expr(value);
+            }
+        }
+    }
+
+    fn when_branch(&mut self, branch: &WhenBranch) {
+        let start = branch
+            .patterns
+            .first()
+            .map_or(branch.value.region, |pattern| pattern.region);
+        self.push(Region::span_across(&start, &branch.value.region), true);
+
+        for pattern in branch.patterns.iter() {
+            self.pattern(pattern);
+        }
+        if let Some(guard) = &branch.guard {
+            self.expr(guard);
+        }
+        self.expr(&branch.value);
+    }
+
+    fn pattern(&mut self, loc_pattern: &Loc<Pattern>) {
+        self.pattern_at(&loc_pattern.value, loc_pattern.region);
+    }
+
+    fn pattern_at(&mut self, pattern: &Pattern, region: Region) {
+        let folds = match pattern {
+            Pattern::SpaceBefore(pattern, _) | Pattern::SpaceAfter(pattern, _) => {
+                return self.pattern_at(pattern, region);
+            }
+            Pattern::RecordDestructure(_) | Pattern::List(_) | Pattern::Tuple(_) => true,
+            _ => false,
+        };
+        self.push(region, folds);
+
+        match pattern {
+            Pattern::Apply(tag, args) => {
+                self.pattern(tag);
+                for arg in args.iter() {
+                    self.pattern(arg);
+                }
+            }
+            Pattern::PncApply(tag, args) => {
+                self.pattern(tag);
+                for arg in args.iter() {
+                    self.pattern(arg);
+                }
+            }
+            Pattern::RecordDestructure(patterns)
+            | Pattern::List(patterns)
+            | Pattern::Tuple(patterns) => {
+                for pattern in patterns.iter() {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::RequiredField(_, pattern) | Pattern::As(pattern, _) => self.pattern(pattern),
+            Pattern::OptionalField(_, default) => self.expr(default),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Requests from newer versions of the LSP spec than `tower_lsp` supports.
+//!
+//! These are registered on the service as custom methods, with their types mirroring the spec.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{
+    Range, Registration, SymbolKind, SymbolTag, TextDocumentPositionParams,
+    TextDocumentRegistrationOptions, Url,
+};
+
+pub const PREPARE_TYPE_HIERARCHY: &str = "textDocument/prepareTypeHierarchy";
+pub const TYPE_HIERARCHY_SUPERTYPES: &str = "typeHierarchy/supertypes";
+pub const TYPE_HIERARCHY_SUBTYPES: &str = "typeHierarchy/subtypes";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeHierarchyItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<SymbolTag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub uri: Url,
+    pub range: Range,
+    pub selection_range: Range,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TypeHierarchyPrepareParams {
+    #[serde(flatten)]
+    pub text_document_position_params: TextDocumentPositionParams,
+}
+
+/// The params of both `typeHierarchy/supertypes` and `typeHierarchy/subtypes`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TypeHierarchyParams {
+    pub item: TypeHierarchyItem,
+}
+
+/// `ServerCapabilities` has no field for the type hierarchy yet, so clients are told about it
+/// through dynamic registration instead.
+pub fn type_hierarchy_registration() -> Registration {
+    let options = TextDocumentRegistrationOptions {
+        document_selector: None,
+    };
+
+    Registration {
+        id: PREPARE_TYPE_HIERARCHY.to_string(),
+        method: PREPARE_TYPE_HIERARCHY.to_string(),
+        register_options: serde_json::to_value(options).ok(),
+    }
+}
@@ -1,7 +1,8 @@
 use log::{debug, info, trace};
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    panic::{catch_unwind, AssertUnwindSafe},
     sync::{Arc, OnceLock},
     time::Duration,
 };
@@ -9,11 +10,20 @@ use std::{
 use tokio::sync::{Mutex, MutexGuard};
 
 use tower_lsp::lsp_types::{
-    CodeActionOrCommand, CodeActionResponse, CompletionResponse, Diagnostic,
-    GotoDefinitionResponse, Hover, Position, Range, SemanticTokensResult, TextEdit, Url,
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, CodeActionOrCommand,
+    CodeActionResponse, CodeLens, CompletionResponse, Diagnostic, DocumentSymbolResponse,
+    FoldingRange, GotoDefinitionResponse, Hover, InlayHint, Location, Position,
+    PrepareRenameResponse, Range, SelectionRange, SemanticTokensResult, SignatureHelp,
+    SymbolInformation, TextEdit, Url, WorkspaceEdit,
 };
 
-use crate::analysis::{AnalyzedDocument, DocInfo};
+use crate::{
+    analysis::{
+        analyze_importers, AnalyzedDocument, DocInfo, ExpectOutcome, ReferenceTarget,
+        ReusableModules,
+    },
+    lsp_ext::TypeHierarchyItem,
+};
 
 #[derive(Debug)]
 pub(crate) struct DocumentPair {
@@ -51,6 +61,11 @@ impl Default for RegistryConfig {
 #[derive(Debug, Default)]
 pub(crate) struct Registry {
     documents: Mutex<HashMap<Url, DocumentPair>>,
+    /// The solved types of every module from the last analysis of each document.
+    /// ModuleIds are only consistent between analyses of the same document.
+    reusable_modules: Mutex<HashMap<Url, ReusableModules>>,
+    /// The expects that have been run in each document since it last changed.
+    expect_outcomes: Mutex<HashMap<Url, Vec<ExpectOutcome>>>,
     config: RegistryConfig,
 }
 
@@ -58,6 +73,8 @@ impl Registry {
     pub(crate) fn new(config: RegistryConfig) -> Self {
         Self {
             documents: Default::default(),
+            reusable_modules: Default::default(),
+            expect_outcomes: Default::default(),
             config,
         }
     }
@@ -121,6 +138,9 @@ impl Registry {
     }
 
     pub async fn apply_doc_info_changes(&self, url: Url, info: DocInfo) {
+        // The expects will need to run again to see how the change affects them
+        self.expect_outcomes.lock().await.remove(&url);
+
         let mut documents_lock = self.documents.lock().await;
         let doc = documents_lock.get_mut(&url);
         match doc {
@@ -140,7 +160,37 @@ impl Registry {
         }
     }
 
-    async fn document_info_by_url(&self, url: &Url) -> Option<DocInfo> {
+    /// Takes the solved types kept from the last analysis of this document, for the next one to
+    /// reuse. The next analysis keeps its own solved types in their place; if it fails before
+    /// solving, the one after it solves every module again.
+    pub async fn take_reusable_modules(&self, url: &Url) -> ReusableModules {
+        self.reusable_modules
+            .lock()
+            .await
+            .remove(url)
+            .unwrap_or_default()
+    }
+
+    pub async fn keep_reusable_modules(&self, url: Url, reusable_modules: ReusableModules) {
+        self.reusable_modules
+            .lock()
+            .await
+            .insert(url, reusable_modules);
+    }
+
+    /// Keeps the outcome of running an expect, unless the document has changed since it ran.
+    pub async fn keep_expect_outcome(&self, url: &Url, version: i32, outcome: ExpectOutcome) {
+        if self.get_latest_version(url).await != Some(version) {
+            return;
+        }
+
+        let mut expect_outcomes = self.expect_outcomes.lock().await;
+        let outcomes = expect_outcomes.entry(url.clone()).or_default();
+        outcomes.retain(|kept| kept.range != outcome.range);
+        outcomes.push(outcome);
+    }
+
+    pub(crate) async fn document_info_by_url(&self, url: &Url) -> Option<DocInfo> {
         self.documents.lock().await.get(url).map(|a| a.info.clone())
     }
 
@@ -164,11 +214,31 @@ impl Registry {
         .ok()
     }
 
+    /// Gets the most recent analysis of every document, without waiting for pending analyses.
+    async fn all_documents(&self) -> Vec<Arc<AnalyzedDocument>> {
+        self.documents
+            .lock()
+            .await
+            .values()
+            .map(|pair| {
+                pair.latest_document
+                    .get()
+                    .unwrap_or(&pair.last_good_document)
+                    .clone()
+            })
+            .collect()
+    }
+
     pub async fn diagnostics(&self, url: &Url) -> Vec<Diagnostic> {
         let Some(document) = self.latest_document_by_url(url).await else {
             return vec![];
         };
-        document.diagnostics()
+
+        let mut diagnostics = document.diagnostics();
+        if let Some(outcomes) = self.expect_outcomes.lock().await.get(url) {
+            diagnostics.extend(outcomes.iter().filter_map(ExpectOutcome::diagnostic));
+        }
+        diagnostics
     }
 
     pub async fn hover(&self, url: &Url, position: Position) -> Option<Hover> {
@@ -187,6 +257,262 @@ impl Registry {
         def_document.definition(symbol)
     }
 
+    pub async fn signature_help(&self, url: &Url, position: Position) -> Option<SignatureHelp> {
+        self.latest_document_by_url(url)
+            .await?
+            .signature_help(position)
+    }
+
+    pub async fn inlay_hints(&self, url: &Url, range: Range) -> Option<Vec<InlayHint>> {
+        self.latest_document_by_url(url).await?.inlay_hints(range)
+    }
+
+    pub async fn references(
+        &self,
+        url: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let document = self.latest_document_by_url(url).await?;
+        let target = document.reference_target_at(position)?;
+
+        let locations = self
+            .all_documents()
+            .await
+            .iter()
+            .flat_map(|document| document.reference_locations(&target, include_declaration))
+            .collect();
+
+        Some(locations)
+    }
+
+    pub async fn prepare_rename(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<PrepareRenameResponse> {
+        self.latest_document_by_url(url)
+            .await?
+            .prepare_rename(position)
+    }
+
+    pub async fn rename(
+        &self,
+        url: &Url,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, String> {
+        let Some(document) = self.latest_document_by_url(url).await else {
+            return Ok(None);
+        };
+        let Some(target) = document.reference_target_at(position) else {
+            return Ok(None);
+        };
+
+        target.check_new_name(new_name)?;
+
+        let mut documents = self.all_documents().await;
+        if document.is_exposed(&target) {
+            documents.extend(self.unanalyzed_importers(&target).await?);
+        }
+
+        let changes = documents
+            .iter()
+            .filter_map(|document| {
+                let edits = document.rename_edits(&target, new_name);
+                (!edits.is_empty()).then(|| (document.url().clone(), edits))
+            })
+            .collect::<HashMap<_, _>>();
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    /// Analyzes the modules in the workspace that may use the exposed `target`, but that no
+    /// analyzed document depends on. Renaming it without them would leave them using the old name.
+    async fn unanalyzed_importers(
+        &self,
+        target: &ReferenceTarget,
+    ) -> Result<Vec<Arc<AnalyzedDocument>>, String> {
+        let Some(module_path) = target
+            .module_url
+            .as_ref()
+            .and_then(|url| url.to_file_path().ok())
+        else {
+            return Ok(vec![]);
+        };
+
+        let analyzed = self
+            .documents
+            .lock()
+            .await
+            .keys()
+            .cloned()
+            .collect::<HashSet<_>>();
+        let module_name = target.module_name.clone();
+        let analysis = move || analyze_importers(&module_path, &module_name, &analyzed);
+
+        match tokio::task::spawn_blocking(|| catch_unwind(AssertUnwindSafe(analysis))).await {
+            Ok(Ok(documents)) => Ok(documents.into_iter().map(Arc::new).collect()),
+            _ => Err(format!(
+                "`{}` can't be renamed, because the modules that import `{}` couldn't be checked for uses of it",
+                target.ident, target.module_name
+            )),
+        }
+    }
+
+    /// The latest analysis of the document that defines `target`.
+    async fn defining_document(&self, target: &ReferenceTarget) -> Option<Arc<AnalyzedDocument>> {
+        self.latest_document_by_url(target.module_url.as_ref()?)
+            .await
+    }
+
+    /// The symbol that an item from an earlier hierarchy request was made for.
+    async fn hierarchy_item_target(&self, url: &Url, range: Range) -> Option<ReferenceTarget> {
+        self.latest_document_by_url(url)
+            .await?
+            .reference_target_at(range.start)
+    }
+
+    pub async fn prepare_call_hierarchy(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<CallHierarchyItem>> {
+        let target = self
+            .latest_document_by_url(url)
+            .await?
+            .reference_target_at(position)?;
+        let item = self
+            .defining_document(&target)
+            .await?
+            .call_hierarchy_item(&target)?;
+
+        Some(vec![item])
+    }
+
+    pub async fn incoming_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Option<Vec<CallHierarchyIncomingCall>> {
+        let target = self
+            .hierarchy_item_target(&item.uri, item.selection_range)
+            .await?;
+
+        let calls = self
+            .all_documents()
+            .await
+            .iter()
+            .flat_map(|document| document.incoming_calls(&target))
+            .collect();
+
+        Some(calls)
+    }
+
+    pub async fn outgoing_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Option<Vec<CallHierarchyOutgoingCall>> {
+        let target = self
+            .hierarchy_item_target(&item.uri, item.selection_range)
+            .await?;
+        let callees = self
+            .defining_document(&target)
+            .await?
+            .outgoing_calls(&target);
+
+        let mut calls = vec![];
+        for (callee, from_ranges) in callees {
+            // Builtins aren't open in the editor, so there's nowhere to show them
+            let Some(document) = self.defining_document(&callee).await else {
+                continue;
+            };
+
+            if let Some(to) = document.call_hierarchy_item(&callee) {
+                calls.push(CallHierarchyOutgoingCall { to, from_ranges });
+            }
+        }
+
+        Some(calls)
+    }
+
+    /// Like [Self::hierarchy_item_target], but also for the builtin abilities that items can be
+    /// made for.
+    async fn type_hierarchy_item_target(
+        &self,
+        item: &TypeHierarchyItem,
+    ) -> Option<ReferenceTarget> {
+        match &item.data {
+            Some(data) => ReferenceTarget::from_builtin_item_data(&item.uri, &item.name, data),
+            None => {
+                self.hierarchy_item_target(&item.uri, item.selection_range)
+                    .await
+            }
+        }
+    }
+
+    pub async fn prepare_type_hierarchy(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        let document = self.latest_document_by_url(url).await?;
+        let target = document.reference_target_at(position)?;
+        let item = match self.defining_document(&target).await {
+            Some(defining) => defining.type_hierarchy_item(&target)?,
+            // Builtin abilities aren't open in the editor, but are known to every document
+            None => document.builtin_type_hierarchy_item(&target)?,
+        };
+
+        Some(vec![item])
+    }
+
+    /// The abilities that an opaque type implements.
+    pub async fn supertypes(&self, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+        let target = self.type_hierarchy_item_target(item).await?;
+        let document = self.defining_document(&target).await?;
+
+        let mut supertypes = vec![];
+        for ability in document.implemented_abilities(&target) {
+            let item = match self.defining_document(&ability).await {
+                Some(defining) => defining.type_hierarchy_item(&ability),
+                None => document.builtin_type_hierarchy_item(&ability),
+            };
+
+            supertypes.extend(item);
+        }
+
+        Some(supertypes)
+    }
+
+    /// The opaque types that implement an ability.
+    pub async fn subtypes(&self, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+        let target = self.type_hierarchy_item_target(item).await?;
+
+        let subtypes = self
+            .all_documents()
+            .await
+            .iter()
+            .flat_map(|document| document.implementing_types(&target))
+            .collect();
+
+        Some(subtypes)
+    }
+
+    pub async fn document_symbols(&self, url: &Url) -> Option<DocumentSymbolResponse> {
+        self.latest_document_by_url(url).await?.document_symbols()
+    }
+
+    pub async fn workspace_symbols(&self, query: &str) -> Option<Vec<SymbolInformation>> {
+        let symbols = self
+            .all_documents()
+            .await
+            .iter()
+            .flat_map(|document| document.workspace_symbols(query))
+            .collect();
+
+        Some(symbols)
+    }
+
     pub async fn formatting(&self, url: &Url) -> Option<Vec<TextEdit>> {
         let document = self.document_info_by_url(url).await?;
         document.format()
@@ -196,6 +522,21 @@ impl Registry {
         let document = self.document_info_by_url(url).await?;
         document.semantic_tokens()
     }
+
+    pub async fn folding_ranges(&self, url: &Url) -> Option<Vec<FoldingRange>> {
+        let document = self.document_info_by_url(url).await?;
+        document.folding_ranges()
+    }
+
+    pub async fn selection_ranges(
+        &self,
+        url: &Url,
+        positions: &[Position],
+    ) -> Option<Vec<SelectionRange>> {
+        let document = self.document_info_by_url(url).await?;
+        document.selection_ranges(positions)
+    }
+
     pub async fn completion_items(
         &self,
         url: &Url,
@@ -218,6 +559,14 @@ impl Registry {
         Some(CompletionResponse::Array(completions))
     }
 
+    pub async fn code_lenses(&self, url: &Url) -> Option<Vec<CodeLens>> {
+        let document = self.latest_document_by_url(url).await?;
+        let expect_outcomes = self.expect_outcomes.lock().await;
+        let outcomes = expect_outcomes.get(url).map_or(&[][..], Vec::as_slice);
+
+        document.code_lenses(outcomes)
+    }
+
     pub async fn code_actions(&self, url: &Url, range: Range) -> Option<CodeActionResponse> {
         let document = self.latest_document_by_url(url).await?;
 
@@ -225,6 +574,12 @@ impl Registry {
         if let Some(edit) = document.annotate(range) {
             responses.push(CodeActionOrCommand::CodeAction(edit));
         }
+        responses.extend(
+            document
+                .quick_fixes(range)
+                .into_iter()
+                .map(CodeActionOrCommand::CodeAction),
+        );
         Some(responses)
     }
 }
@@ -10,10 +10,15 @@ use tower_lsp::jsonrpc::{self, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use crate::analysis::{global_analysis, DocInfo};
+use crate::analysis::{global_analysis, run_toplevel_expect, DocInfo, RUN_EXPECT_COMMAND};
+use crate::lsp_ext::{
+    TypeHierarchyItem, TypeHierarchyParams, TypeHierarchyPrepareParams, PREPARE_TYPE_HIERARCHY,
+    TYPE_HIERARCHY_SUBTYPES, TYPE_HIERARCHY_SUPERTYPES,
+};
 
 mod analysis;
 mod convert;
+mod lsp_ext;
 mod registry;
 
 struct RocServer {
@@ -104,6 +109,25 @@ impl RocServer {
             },
         };
         let code_action_provider = CodeActionProviderCapability::Simple(true);
+        let signature_help_provider = SignatureHelpOptions {
+            trigger_characters: Some(vec![" ".to_string(), "(".to_string(), ",".to_string()]),
+            retrigger_characters: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let rename_provider = RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let execute_command_provider = ExecuteCommandOptions {
+            commands: vec![RUN_EXPECT_COMMAND.to_string()],
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
         ServerCapabilities {
             text_document_sync: Some(text_document_sync),
             hover_provider: Some(hover_provider),
@@ -112,10 +136,77 @@ impl RocServer {
             semantic_tokens_provider: Some(semantic_tokens_provider),
             completion_provider: Some(completion_provider),
             code_action_provider: Some(code_action_provider),
+            references_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Right(rename_provider)),
+            signature_help_provider: Some(signature_help_provider),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(false),
+            }),
+            execute_command_provider: Some(execute_command_provider),
+            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
             ..ServerCapabilities::default()
         }
     }
 
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let TypeHierarchyPrepareParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .prepare_type_hierarchy(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchyParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        unwind_async(self.state.registry.supertypes(&params.item)).await
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchyParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        unwind_async(self.state.registry.subtypes(&params.item)).await
+    }
+
+    /// Runs the top-level expect at `position`, then publishes a diagnostic if it failed.
+    async fn run_expect(&self, url: Url, position: Position) {
+        let version = match self.state.run_expect(&url, position).await {
+            Ok(version) => version,
+            Err(e) => {
+                self.client.show_message(MessageType::ERROR, e).await;
+                return;
+            }
+        };
+
+        let diagnostics = self.state.registry.diagnostics(&url).await;
+        self.client
+            .publish_diagnostics(url, diagnostics, Some(version))
+            .await;
+
+        if let Err(e) = self.client.code_lens_refresh().await {
+            debug!("Client didn't refresh code lenses. Reason:{:?}", e);
+        }
+    }
+
     /// Records a document content change.
     async fn change(&self, fi: Url, text: String, version: i32) {
         let updating_result = self.state.change(&fi, text, version).await;
@@ -143,6 +234,33 @@ impl RocServerState {
 
     async fn close(&self, _fi: Url) {}
 
+    /// Runs a top-level expect, returning the version of the document it ran in.
+    pub async fn run_expect(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> std::result::Result<i32, String> {
+        let doc_info = self
+            .registry
+            .document_info_by_url(url)
+            .await
+            .ok_or_else(|| format!("{} isn't open", url))?;
+        let version = doc_info.version;
+
+        let run = move || run_toplevel_expect(&doc_info, position);
+        let outcome =
+            match tokio::task::spawn_blocking(|| catch_unwind(AssertUnwindSafe(run))).await {
+                Err(e) => return Err(format!("Expect thread failed. reason:{:?}", e)),
+                Ok(res) => res.map_err(|err| format!("Expect panicked with: {:?}", err))??,
+            };
+
+        self.registry
+            .keep_expect_outcome(url, version, outcome)
+            .await;
+
+        Ok(version)
+    }
+
     pub async fn change(
         &self,
         fi: &Url,
@@ -175,9 +293,12 @@ impl RocServerState {
                 return Err("Not latest version skipping analysis".to_string());
             }
 
-            let results = match tokio::time::timeout(
+            let reusable_modules = inner_ref.registry.take_reusable_modules(fi).await;
+            let analysis = || global_analysis(doc_info, reusable_modules);
+
+            let (results, reusable_modules, reused) = match tokio::time::timeout(
                 Duration::from_secs(60),
-                tokio::task::spawn_blocking(|| catch_unwind(|| global_analysis(doc_info))),
+                tokio::task::spawn_blocking(|| catch_unwind(AssertUnwindSafe(analysis))),
             )
             .await
             {
@@ -194,6 +315,21 @@ impl RocServerState {
                     res.map_err(|err| format!("Document analysis panicked with: {:?}", err))?
                 }
             };
+
+            debug!(
+                "V{:?}:reused the solved types of {} documents",
+                version,
+                reused.len()
+            );
+
+            // These are still valid for the next analysis even if this one turns out to be stale
+            if let Some(reusable_modules) = reusable_modules {
+                inner_ref
+                    .registry
+                    .keep_reusable_modules(fi.clone(), reusable_modules)
+                    .await;
+            }
+
             let latest_version = inner_ref.registry.get_latest_version(fi).await;
 
             //if this version is not the latest another change must have come in and this analysis is useless
@@ -230,6 +366,11 @@ impl LanguageServer for RocServer {
     }
 
     async fn initialized(&self, _: InitializedParams) {
+        let registrations = vec![lsp_ext::type_hierarchy_registration()];
+        if let Err(e) = self.client.register_capability(registrations).await {
+            debug!("Client didn't register the type hierarchy. Reason:{:?}", e);
+        }
+
         self.client
             .log_message(MessageType::INFO, "Roc language server initialized.")
             .await;
@@ -306,6 +447,32 @@ impl LanguageServer for RocServer {
         .await
     }
 
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let DocumentSymbolParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.document_symbols(&text_document.uri)).await
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let WorkspaceSymbolParams {
+            query,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.workspace_symbols(&query)).await
+    }
+
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
         let DocumentFormattingParams {
             text_document,
@@ -329,6 +496,35 @@ impl LanguageServer for RocServer {
         unwind_async(self.state.registry.semantic_tokens(&text_document.uri)).await
     }
 
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let FoldingRangeParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.folding_ranges(&text_document.uri)).await
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let SelectionRangeParams {
+            text_document,
+            positions,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .selection_ranges(&text_document.uri, &positions),
+        )
+        .await
+    }
+
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let doc = params.text_document_position;
         trace!("Got completion request.");
@@ -341,6 +537,49 @@ impl LanguageServer for RocServer {
         .await
     }
 
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let CodeLensParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.code_lenses(&text_document.uri)).await
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        let ExecuteCommandParams {
+            command,
+            arguments,
+            work_done_progress_params: _,
+        } = params;
+
+        if command != RUN_EXPECT_COMMAND {
+            return Err(jsonrpc::Error::invalid_params(format!(
+                "Unknown command: {command}"
+            )));
+        }
+
+        let mut arguments = arguments.into_iter();
+        let url = arguments
+            .next()
+            .and_then(|url| serde_json::from_value::<Url>(url).ok());
+        let position = arguments
+            .next()
+            .and_then(|position| serde_json::from_value::<Position>(position).ok());
+        let (Some(url), Some(position)) = (url, position) else {
+            return Err(jsonrpc::Error::invalid_params(
+                "Expected the document and position of an expect",
+            ));
+        };
+
+        unwind_async(self.run_expect(url, position)).await?;
+        Ok(None)
+    }
+
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
         let CodeActionParams {
             text_document,
@@ -352,6 +591,129 @@ impl LanguageServer for RocServer {
 
         unwind_async(self.state.registry.code_actions(&text_document.uri, range)).await
     }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let SignatureHelpParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            context: _,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .signature_help(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let InlayHintParams {
+            text_document,
+            range,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.inlay_hints(&text_document.uri, range)).await
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let ReferenceParams {
+            text_document_position:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            context: ReferenceContext {
+                include_declaration,
+            },
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.references(
+            &text_document.uri,
+            position,
+            include_declaration,
+        ))
+        .await
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .prepare_rename(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let RenameParams {
+            text_document_position:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            new_name,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .rename(&text_document.uri, position, &new_name),
+        )
+        .await?
+        .map_err(jsonrpc::Error::invalid_params)
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let CallHierarchyPrepareParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .prepare_call_hierarchy(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        unwind_async(self.state.registry.incoming_calls(&params.item)).await
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        unwind_async(self.state.registry.outgoing_calls(&params.item)).await
+    }
 }
 
 async fn unwind_async<Fut, T>(future: Fut) -> tower_lsp::jsonrpc::Result<T>
@@ -372,6 +734,13 @@ where
 }
 
 fn main() {
+    // The server runs each expect it's asked to in a process of its own, which is this binary
+    // started again
+    #[cfg(not(windows))]
+    if std::env::var_os(analysis::RUN_EXPECT_VAR).is_some() {
+        return analysis::run_requested_expect();
+    }
+
     env_logger::Builder::from_env("ROCLS_LOG").init();
 
     // Tokio uses a smaller stack size for threads by default,
@@ -393,7 +762,11 @@ async fn async_main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(RocServer::new);
+    let (service, socket) = LspService::build(RocServer::new)
+        .custom_method(PREPARE_TYPE_HIERARCHY, RocServer::prepare_type_hierarchy)
+        .custom_method(TYPE_HIERARCHY_SUPERTYPES, RocServer::supertypes)
+        .custom_method(TYPE_HIERARCHY_SUBTYPES, RocServer::subtypes)
+        .finish();
     use roc_error_macros::set_panic_not_exit;
     set_panic_not_exit(true);
     Server::new(stdin, stdout, socket).serve(service).await;
@@ -408,6 +781,7 @@ mod tests {
     use log::info;
 
     use super::*;
+    use crate::analysis::AnalyzedDocument;
 
     fn completion_resp_to_strings(
         resp: CompletionResponse,
@@ -676,10 +1050,20 @@ mod tests {
 
     async fn code_action_edits(doc: String, position: Position, name: &str) -> Vec<TextEdit> {
         let (inner, url) = test_setup(doc.clone()).await;
+
+        code_action_edits_in(&inner, &url, position, name).await
+    }
+
+    async fn code_action_edits_in(
+        inner: &RocServerState,
+        url: &Url,
+        position: Position,
+        name: &str,
+    ) -> Vec<TextEdit> {
         let registry = &inner.registry;
 
         let actions = registry
-            .code_actions(&url, Range::new(position, position))
+            .code_actions(url, Range::new(position, position))
             .await
             .unwrap();
 
@@ -694,7 +1078,7 @@ mod tests {
             .expect("Code action does not have an associated edit")
             .changes
             .expect("Edit does not have any changes")
-            .get(&url)
+            .get(url)
             .expect("Edit does not have changes for this file")
             .clone()
     }
@@ -816,4 +1200,1030 @@ mod tests {
         "#]]
         .assert_debug_eq(&edit);
     }
+
+    fn range_to_string(range: Range) -> String {
+        format!(
+            "{}:{}-{}:{}",
+            range.start.line, range.start.character, range.end.line, range.end.character
+        )
+    }
+
+    #[tokio::test]
+    async fn test_references_local() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+                value = 1
+                value + value
+            "#};
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let with_declaration = registry
+            .references(&url, Position::new(5, 5), true)
+            .await
+            .unwrap();
+        let without_declaration = registry
+            .references(&url, Position::new(4, 5), false)
+            .await
+            .unwrap();
+
+        let actual = [with_declaration, without_declaration].map(|locations| {
+            locations
+                .into_iter()
+                .map(|location| range_to_string(location.range))
+                .collect::<Vec<_>>()
+        });
+
+        expect![[r#"
+            [
+                [
+                    "4:4-4:9",
+                    "5:4-5:9",
+                    "5:12-5:17",
+                ],
+                [
+                    "5:4-5:9",
+                    "5:12-5:17",
+                ],
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_rename_top_level() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            greet = \name -> name
+
+            main = greet "Roc"
+            "#};
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let prepared = registry.prepare_rename(&url, Position::new(5, 8)).await;
+        let edit = registry
+            .rename(&url, Position::new(5, 8), "welcome")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let edits = edit
+            .changes
+            .unwrap()
+            .remove(&url)
+            .unwrap()
+            .into_iter()
+            .map(|edit| format!("{} {}", range_to_string(edit.range), edit.new_text))
+            .collect::<Vec<_>>();
+
+        let placeholder = match prepared {
+            Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }) => {
+                Some(format!("{} {}", range_to_string(range), placeholder))
+            }
+            _ => None,
+        };
+
+        expect![[r#"
+            (
+                Some(
+                    "5:7-5:12 greet",
+                ),
+                [
+                    "3:0-3:5 welcome",
+                    "5:7-5:12 welcome",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&(placeholder, edits));
+    }
+
+    #[tokio::test]
+    async fn test_rename_skips_comments() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            User : {
+                # the User's name
+                name : Str,
+            }
+
+            main : User
+            main = { name: "Roc" }
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let edit = inner
+            .registry
+            .rename(&url, Position::new(3, 1), "Person")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let edits = edit
+            .changes
+            .unwrap()
+            .remove(&url)
+            .unwrap()
+            .into_iter()
+            .map(|edit| format!("{} {}", range_to_string(edit.range), edit.new_text))
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            [
+                "3:0-3:4 Person",
+                "8:7-8:11 Person",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_rename_rejects_invalid_name() {
+        let doc = DOC_LIT.to_string() + "main = 1\n";
+        let (inner, url) = test_setup(doc).await;
+
+        let result = inner
+            .registry
+            .rename(&url, Position::new(3, 1), "Main")
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_rename_in_unopened_importer() {
+        let dir = tempfile::tempdir().unwrap();
+        let parser_path = dir.path().join("Parser.roc");
+        let main_path = dir.path().join("Main.roc");
+        let parser = indoc! {r#"
+            module [parse]
+
+            parse = \s -> s
+            "#};
+        std::fs::write(&parser_path, parser).unwrap();
+        std::fs::write(
+            &main_path,
+            indoc! {r#"
+            module [main]
+
+            import Parser
+
+            main = Parser.parse "x"
+            "#},
+        )
+        .unwrap();
+
+        // Only the module defining `parse` is open
+        let parser_url = Url::from_file_path(&parser_path).unwrap();
+        let main_url = Url::from_file_path(&main_path).unwrap();
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        inner
+            .change(&parser_url, parser.to_string(), 0)
+            .await
+            .unwrap();
+
+        let mut changes = inner
+            .registry
+            .rename(&parser_url, Position::new(2, 0), "run")
+            .await
+            .unwrap()
+            .unwrap()
+            .changes
+            .unwrap();
+
+        let edits = [&parser_url, &main_url].map(|url| {
+            changes
+                .remove(url)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|edit| format!("{} {}", range_to_string(edit.range), edit.new_text))
+                .collect::<Vec<_>>()
+        });
+
+        expect![[r#"
+            [
+                [
+                    "0:8-0:13 run",
+                    "2:0-2:5 run",
+                ],
+                [
+                    "4:14-4:19 run",
+                ],
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_document_symbols() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            Pair : { first : U8, second : U8 }
+
+            answer = 42
+
+            double = \x -> x * 2
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) =
+            inner.registry.document_symbols(&url).await
+        else {
+            panic!("Expected nested document symbols");
+        };
+
+        let actual = symbols
+            .into_iter()
+            .map(|symbol| {
+                format!(
+                    "{} {} {:?}",
+                    symbol.name,
+                    range_to_string(symbol.selection_range),
+                    symbol.detail
+                )
+            })
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            [
+                "Pair 3:0-3:4 None",
+                "answer 5:0-5:6 Some(\"Num *\")",
+                "double 7:0-7:6 Some(\"Num a -> Num a\")",
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_symbols() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            answer = 42
+
+            double = \x -> x * 2
+            "#};
+        let (inner, _) = test_setup(doc).await;
+
+        let actual = inner
+            .registry
+            .workspace_symbols("dbl")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.container_name))
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            [
+                (
+                    "double",
+                    Some(
+                        "Test",
+                    ),
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_signature_help() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            add : I64, I64 -> I64
+            add = \a, b -> a + b
+
+            main = add 1 2
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let help = inner
+            .registry
+            .signature_help(&url, Position::new(6, 13))
+            .await
+            .unwrap();
+
+        let signature = &help.signatures[0];
+        let parameters = signature
+            .parameters
+            .iter()
+            .flatten()
+            .map(|parameter| parameter.label.clone())
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            (
+                "I64, I64 -> I64",
+                [
+                    LabelOffsets(
+                        [
+                            0,
+                            3,
+                        ],
+                    ),
+                    LabelOffsets(
+                        [
+                            5,
+                            8,
+                        ],
+                    ),
+                ],
+                Some(
+                    1,
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&(&signature.label, parameters, help.active_parameter));
+    }
+
+    #[tokio::test]
+    async fn test_inlay_hints() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+                greeting = "Hello"
+                exclaim = \s -> Str.concat s "!"
+                when greeting is
+                    other -> exclaim other
+
+            shout = \s -> Str.concat s "!"
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let hints = inner
+            .registry
+            .inlay_hints(&url, Range::new(Position::new(0, 0), Position::new(10, 0)))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => format!(
+                    "{}:{} {}",
+                    hint.position.line, hint.position.character, label
+                ),
+                InlayHintLabel::LabelParts(_) => panic!("Expected a plain label"),
+            })
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            [
+                "3:4 : Str",
+                "4:12 : Str",
+                "5:11 : Str -> Str",
+                "5:16 : Str",
+                "7:13 : Str",
+                "9:5 : Str -> Str",
+                "9:10 : Str",
+            ]
+        "#]]
+        .assert_debug_eq(&hints);
+    }
+
+    #[tokio::test]
+    async fn test_reanalysis_reuses_solved_types() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main : Str
+            main = 1
+
+            identity = \x -> x
+            "#};
+        let (inner, url) = test_setup(doc.clone()).await;
+        let reusable_modules = inner.registry.take_reusable_modules(&url).await;
+        assert!(!reusable_modules.is_empty());
+
+        // Nothing changed, so this analysis reuses the solved types from the last one
+        let (documents, reusable_modules, reused) =
+            global_analysis(DocInfo::new(url.clone(), doc, 1), reusable_modules);
+        assert!(reused.contains(&url));
+
+        expect![[r#"
+            (
+                [
+                    "4:7-4:8",
+                ],
+                [
+                    "main Some(\"Str\")",
+                    "identity Some(\"a -> a\")",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&diagnostics_and_symbols(&documents, &url));
+
+        let changed = DOC_LIT.to_string()
+            + indoc! {r#"
+            main : Str
+            main = "one"
+
+            identity = \x -> x
+            "#};
+
+        // The source changed, so its types are solved again rather than reused
+        let (documents, _, reused) = global_analysis(
+            DocInfo::new(url.clone(), changed, 2),
+            reusable_modules.unwrap(),
+        );
+        assert!(!reused.contains(&url));
+
+        expect![[r#"
+            (
+                [],
+                [
+                    "main Some(\"Str\")",
+                    "identity Some(\"a -> a\")",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&diagnostics_and_symbols(&documents, &url));
+    }
+
+    fn diagnostics_and_symbols(
+        documents: &[AnalyzedDocument],
+        url: &Url,
+    ) -> (Vec<String>, Vec<String>) {
+        let document = documents
+            .iter()
+            .find(|document| document.url() == url)
+            .expect("Expected the document to be analyzed");
+
+        let diagnostics = document
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| range_to_string(diagnostic.range))
+            .collect();
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) = document.document_symbols() else {
+            panic!("Expected nested document symbols");
+        };
+        let details = symbols
+            .into_iter()
+            .map(|symbol| format!("{} {:?}", symbol.name, symbol.detail))
+            .collect();
+
+        (diagnostics, details)
+    }
+
+    async fn quick_fix_edits(doc: String, position: Position, name: &str) -> Vec<String> {
+        code_action_edits(doc, position, name)
+            .await
+            .into_iter()
+            .map(|edit| format!("{} {:?}", range_to_string(edit.range), edit.new_text))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_unused_def() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+                x = 1
+                2
+            "#};
+
+        let edits = quick_fix_edits(doc, Position::new(4, 4), "Remove unused def `x`").await;
+
+        expect![[r#"
+            [
+                "4:0-5:0 \"\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_unused_argument() {
+        let doc = DOC_LIT.to_string() + "main = \\x -> 1\n";
+
+        let edits =
+            quick_fix_edits(doc, Position::new(3, 8), "Prefix `x` with an underscore").await;
+
+        expect![[r#"
+            [
+                "3:8-3:8 \"_\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    /// Like [quick_fix_edits], but for a module that imports the `Parser` and `Helper` modules
+    /// next to it. `Helper` imports `Parser` itself.
+    async fn quick_fix_edits_with_imports(
+        doc: &str,
+        position: Position,
+        name: &str,
+    ) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let parser = indoc! {r#"
+            module [parse, check]
+
+            parse = \s -> s
+
+            check = \s -> s
+            "#};
+        let helper = indoc! {r#"
+            module [help]
+
+            import Parser
+
+            help = Parser.parse "help"
+            "#};
+        std::fs::write(dir.path().join("Parser.roc"), parser).unwrap();
+        std::fs::write(dir.path().join("Helper.roc"), helper).unwrap();
+
+        let path = dir.path().join("Test.roc");
+        std::fs::write(&path, doc).unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        inner.change(&url, doc.to_string(), 0).await.unwrap();
+
+        code_action_edits_in(&inner, &url, position, name)
+            .await
+            .into_iter()
+            .map(|edit| format!("{} {:?}", range_to_string(edit.range), edit.new_text))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_unused_exposed_import() {
+        let doc = indoc! {r#"
+            module [main]
+
+            import Parser exposing [parse, check]
+
+            main = parse "main"
+            "#};
+
+        let edits =
+            quick_fix_edits_with_imports(doc, Position::new(2, 32), "Remove unused import `check`")
+                .await;
+
+        // Only `check` and the comma before it go
+        expect![[r#"
+            [
+                "2:29-2:36 \"\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_only_exposed_import() {
+        let doc = indoc! {r#"
+            module [main]
+
+            import Parser exposing [check]
+
+            main = Parser.parse "main"
+            "#};
+
+        let edits =
+            quick_fix_edits_with_imports(doc, Position::new(2, 25), "Remove unused import `check`")
+                .await;
+
+        // The module is still used, so only the `exposing` list goes
+        expect![[r#"
+            [
+                "2:13-2:30 \"\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_missing_import() {
+        let doc = indoc! {r#"
+            module [main]
+
+            import Helper
+
+            main = Parser.parse Helper.help
+            "#};
+
+        let edits =
+            quick_fix_edits_with_imports(doc, Position::new(4, 8), "Add `import Parser`").await;
+
+        // After the last import
+        expect![[r#"
+            [
+                "3:0-3:0 \"import Parser\\n\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_quick_fix_missing_branches() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            color : [Red, Green, Blue] -> U8
+            color = \c ->
+                when c is
+                    Red -> 1
+            "#};
+
+        let edits = quick_fix_edits(doc, Position::new(5, 4), "Add missing `when` branches").await;
+
+        expect![[r#"
+            [
+                "6:16-6:16 \"\\n        Green -> crash \\\"TODO\\\"\\n        Blue -> crash \\\"TODO\\\"\"",
+            ]
+        "#]]
+        .assert_debug_eq(&edits);
+    }
+
+    #[tokio::test]
+    async fn test_call_hierarchy() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            double = \x -> add x x
+
+            add = \a, b -> a + b
+
+            main = double (add 1 2)
+            "#};
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let calls_to_string = |name: &str, from_ranges: Vec<Range>| {
+            let from_ranges = from_ranges
+                .into_iter()
+                .map(range_to_string)
+                .collect::<Vec<_>>();
+            format!("{} {}", name, from_ranges.join(" "))
+        };
+
+        let add = registry
+            .prepare_call_hierarchy(&url, Position::new(5, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        let incoming = registry
+            .incoming_calls(&add)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|call| calls_to_string(&call.from.name, call.from_ranges))
+            .collect::<Vec<_>>();
+
+        let main = registry
+            .prepare_call_hierarchy(&url, Position::new(7, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        let outgoing = registry
+            .outgoing_calls(&main)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|call| calls_to_string(&call.to.name, call.from_ranges))
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            (
+                Some(
+                    "Num a, Num a -> Num a",
+                ),
+                [
+                    "double 3:15-3:18",
+                    "main 7:15-7:18",
+                ],
+                [
+                    "double 7:7-7:13",
+                    "add 7:15-7:18",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&(add.detail, incoming, outgoing));
+    }
+
+    #[tokio::test]
+    async fn test_type_hierarchy() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            Shape implements
+                area : a -> F64 where a implements Shape
+
+            Square := F64 implements [Shape { area: squareArea }]
+
+            squareArea = \@Square side -> side * side
+
+            Circle := F64 implements [Shape { area: circleArea }]
+
+            circleArea = \@Circle radius -> 3.14 * radius * radius
+            "#};
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let items_to_strings = |items: Vec<TypeHierarchyItem>| {
+            items
+                .into_iter()
+                .map(|item| format!("{} {}", item.name, range_to_string(item.selection_range)))
+                .collect::<Vec<_>>()
+        };
+
+        let shape = registry
+            .prepare_type_hierarchy(&url, Position::new(3, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        let subtypes = registry.subtypes(&shape).await.unwrap();
+
+        let square = registry
+            .prepare_type_hierarchy(&url, Position::new(6, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        let supertypes = registry.supertypes(&square).await.unwrap();
+
+        expect![[r#"
+            (
+                [
+                    "Square 6:0-6:6",
+                    "Circle 10:0-10:6",
+                ],
+                [
+                    "Shape 3:0-3:5",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&(items_to_strings(subtypes), items_to_strings(supertypes)));
+    }
+
+    #[tokio::test]
+    async fn test_type_hierarchy_of_builtin_abilities() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            Id := U64 implements [Eq, Hash]
+
+            Name := Str implements [Hash]
+
+            hashOf : a -> U64 where a implements Hash
+            hashOf = \_ -> 0
+            "#};
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let items_to_strings = |items: Vec<TypeHierarchyItem>| {
+            let mut strings = items
+                .into_iter()
+                .map(|item| format!("{} {}", item.name, range_to_string(item.selection_range)))
+                .collect::<Vec<_>>();
+            strings.sort();
+            strings
+        };
+
+        let hash = registry
+            .prepare_type_hierarchy(&url, Position::new(7, 38))
+            .await
+            .unwrap()
+            .remove(0);
+        let subtypes = registry.subtypes(&hash).await.unwrap();
+
+        let id = registry
+            .prepare_type_hierarchy(&url, Position::new(3, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        let supertypes = registry.supertypes(&id).await.unwrap();
+
+        expect![[r#"
+            (
+                "Hash",
+                [
+                    "Id 3:0-3:2",
+                    "Name 5:0-5:4",
+                ],
+                [
+                    "Eq 0:0-0:0",
+                    "Hash 0:0-0:0",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&(
+            &hash.name,
+            items_to_strings(subtypes),
+            items_to_strings(supertypes),
+        ));
+    }
+
+    fn lenses_to_strings(lenses: Vec<CodeLens>) -> Vec<String> {
+        lenses
+            .into_iter()
+            .map(|lens| {
+                let title = lens.command.map(|command| command.title);
+                format!("{} {:?}", range_to_string(lens.range), title)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_expect_code_lenses() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            double = \x -> x * 2
+
+            expect double 1 == 2
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let lenses = lenses_to_strings(inner.registry.code_lenses(&url).await.unwrap());
+
+        expect![[r#"
+            [
+                "5:0-5:20 Some(\"Run expect\")",
+            ]
+        "#]]
+        .assert_debug_eq(&lenses);
+    }
+
+    /// When [run_toplevel_expect] starts this test binary to run an expect, this is the test that
+    /// runs it. Otherwise there's nothing for it to do.
+    #[cfg(not(windows))]
+    #[test]
+    fn expect_runner() {
+        if std::env::var_os(crate::analysis::RUN_EXPECT_VAR).is_some() {
+            crate::analysis::run_requested_expect();
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_run_expects() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Test.roc");
+        let doc = indoc! {r#"
+            module []
+
+            double = \x -> x * 2
+
+            expect double 1 == 2
+
+            expect double 2 == 5
+            "#};
+        std::fs::write(&path, doc).unwrap();
+
+        let url = Url::from_file_path(&path).unwrap();
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        inner.change(&url, doc.to_string(), 0).await.unwrap();
+
+        let before = lenses_to_strings(inner.registry.code_lenses(&url).await.unwrap());
+
+        inner.run_expect(&url, Position::new(4, 0)).await.unwrap();
+        inner.run_expect(&url, Position::new(6, 0)).await.unwrap();
+
+        let after = lenses_to_strings(inner.registry.code_lenses(&url).await.unwrap());
+        let failures = inner
+            .registry
+            .diagnostics(&url)
+            .await
+            .into_iter()
+            .filter(|diagnostic| diagnostic.source.as_deref() == Some("expect"))
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            (
+                [
+                    "4:0-4:20 Some(\"Run expect\")",
+                    "6:0-6:20 Some(\"Run expect\")",
+                ],
+                [
+                    "4:0-4:20 Some(\"Passed, run again\")",
+                    "6:0-6:20 Some(\"Failed, run again\")",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&(before, after));
+
+        // Only the expect that failed has a diagnostic. Like the report, it also covers anything
+        // between the expect and the def before it
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].range.start <= Position::new(6, 0));
+        assert_eq!(failures[0].range.end, Position::new(6, 20));
+        assert!(failures[0].message.contains("This expectation failed"));
+        assert!(failures[0].message.contains("double 2 == 5"));
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_run_expects_with_unsaved_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Test.roc");
+        let saved = indoc! {r#"
+            module []
+
+            expect 1 + 1 == 3
+            "#};
+        std::fs::write(&path, saved).unwrap();
+
+        // The failure is reported from the buffer, where the expect has moved down
+        let doc = indoc! {r#"
+            module []
+
+            double = \x -> x * 2
+
+            triple = \x -> x * 3
+
+            expect double 2 == triple 2
+            "#};
+
+        let url = Url::from_file_path(&path).unwrap();
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        inner.change(&url, doc.to_string(), 0).await.unwrap();
+
+        inner.run_expect(&url, Position::new(6, 0)).await.unwrap();
+
+        let failures = inner
+            .registry
+            .diagnostics(&url)
+            .await
+            .into_iter()
+            .filter(|diagnostic| diagnostic.source.as_deref() == Some("expect"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].range.end, Position::new(6, 27));
+        assert!(failures[0].message.contains("expect double 2 == triple 2"));
+        assert!(!failures[0].message.contains("1 + 1 == 3"));
+    }
+
+    #[tokio::test]
+    async fn test_folding_ranges() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            import Foo
+            import Bar
+
+            ## Doubles a number,
+            ## which is useful.
+            double = \x ->
+                when x is
+                    0 ->
+                        0
+
+                    _ -> x * 2
+
+            point = {
+                x: 1,
+                y: 2,
+            }
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let actual = inner
+            .registry
+            .folding_ranges(&url)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|range| format!("{}-{} {:?}", range.start_line, range.end_line, range.kind))
+            .collect::<Vec<_>>();
+
+        expect![[r#"
+            [
+                "3-4 Some(Imports)",
+                "6-7 Some(Comment)",
+                "8-13 None",
+                "9-13 None",
+                "10-11 None",
+                "15-18 None",
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_selection_ranges() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            point = { x: 1 + 2, y: 3 }
+            "#};
+        let (inner, url) = test_setup(doc).await;
+
+        let selection = inner
+            .registry
+            .selection_ranges(&url, &[Position::new(3, 17)])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let mut actual = vec![];
+        let mut current = Some(Box::new(selection));
+        while let Some(range) = current {
+            actual.push(range_to_string(range.range));
+            current = range.parent;
+        }
+
+        expect![[r#"
+            [
+                "3:17-3:18",
+                "3:13-3:18",
+                "3:10-3:18",
+                "3:8-3:26",
+                "3:0-3:26",
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
 }
@@ -5,9 +5,10 @@ use roc_build::link::llvm_module_to_dylib;
 use roc_collections::all::MutSet;
 
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
-use roc_gen_llvm::llvm::externs::add_default_roc_externs;
+use roc_gen_llvm::llvm::externs::{add_default_roc_externs, add_host_function_pointers};
 use roc_gen_llvm::{run_jit_function, run_jit_function_dynamic_type};
 use roc_load::{EntryPoint, MonomorphizedModule};
+use roc_mono::foreign_calls::effects_called;
 use roc_mono::ir::OptLevel;
 use roc_mono::layout::STLayoutInterner;
 use roc_parse::ast::Expr;
@@ -18,12 +19,18 @@ use roc_std::RocStr;
 use roc_target::Target;
 use roc_types::pretty_print::{name_and_print_var, DebugPrint};
 use roc_types::subs::Subs;
+use std::collections::BTreeSet;
 
+use crate::platform::PlatformHost;
+
+/// Compiles and runs the expr in `loaded`. If there's an `opt_host`, its effects are run by it,
+/// which is an error if it doesn't have all of them.
 pub fn eval_llvm(
     mut loaded: MonomorphizedModule<'_>,
     target: Target,
     opt_level: OptLevel,
-) -> Option<ReplOutput> {
+    opt_host: Option<&PlatformHost>,
+) -> Result<Option<ReplOutput>, String> {
     let arena = Bump::new();
 
     debug_assert_eq!(loaded.exposed_to_host.top_level_values.len(), 1);
@@ -45,28 +52,37 @@ pub fn eval_llvm(
         DebugPrint::NOTHING,
     );
 
-    let (_, main_fn_layout) = *loaded
+    let Some((_, main_fn_layout)) = loaded
         .procedures
         .keys()
-        .find(|(s, _)| *s == main_fn_symbol)?;
+        .find(|(s, _)| *s == main_fn_symbol)
+        .copied()
+    else {
+        return Ok(None);
+    };
 
     let interns = loaded.interns.clone();
 
-    #[cfg(not(all(
-        any(target_os = "linux", target_os = "macos"),
-        any(target_arch = "x86_64", target_arch = "aarch64")
-    )))]
-    let (lib, main_fn_name, subs, layout_interner) =
-        mono_module_to_dylib_llvm(&arena, target, loaded, opt_level)
+    let (lib, main_fn_name, subs, layout_interner) = match opt_host {
+        Some(host) => {
+            // Only the LLVM backend can call the host's functions through pointers
+            let effects = effects_called(loaded.procedures.values());
+            let dylib = mono_module_to_dylib_llvm(
+                &arena,
+                target,
+                loaded,
+                opt_level,
+                Some(&host.main_for_host),
+                &effects,
+            )
             .expect("we produce a valid Dylib");
 
-    #[cfg(all(
-        any(target_os = "linux", target_os = "macos"),
-        any(target_arch = "x86_64", target_arch = "aarch64")
-    ))]
-    let (lib, main_fn_name, subs, layout_interner) =
-        mono_module_to_dylib_asm(&arena, target, loaded, opt_level)
-            .expect("We failed to produce a valid Dylib.\nTIP: if you're on macos, try this:\n\t<https://github.com/roc-lang/roc/issues/5797#issuecomment-1786105269>");
+            host.provide_effects(&dylib.0, &effects)?;
+
+            dylib
+        }
+        None => mono_module_to_dylib(&arena, target, loaded, opt_level),
+    };
 
     let mut app = CliApp { lib };
 
@@ -84,10 +100,38 @@ pub fn eval_llvm(
 
     let expr_str = format_answer(&arena, expr).to_string();
 
-    Some(ReplOutput {
+    Ok(Some(ReplOutput {
         expr: expr_str,
         expr_type: expr_type_str,
-    })
+    }))
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "macos"),
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+fn mono_module_to_dylib<'a>(
+    arena: &'a Bump,
+    target: Target,
+    loaded: MonomorphizedModule<'a>,
+    opt_level: OptLevel,
+) -> (Library, &'a str, Subs, STLayoutInterner<'a>) {
+    mono_module_to_dylib_llvm(arena, target, loaded, opt_level, None, &BTreeSet::new())
+        .expect("we produce a valid Dylib")
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn mono_module_to_dylib<'a>(
+    arena: &'a Bump,
+    target: Target,
+    loaded: MonomorphizedModule<'a>,
+    opt_level: OptLevel,
+) -> (Library, &'a str, Subs, STLayoutInterner<'a>) {
+    mono_module_to_dylib_asm(arena, target, loaded, opt_level)
+        .expect("We failed to produce a valid Dylib.\nTIP: if you're on macos, try this:\n\t<https://github.com/roc-lang/roc/issues/5797#issuecomment-1786105269>")
 }
 
 struct CliApp {
@@ -169,18 +213,15 @@ impl ReplAppMemory for CliMemory {
     }
 }
 
-#[cfg_attr(
-    all(
-        any(target_os = "linux", target_os = "macos"),
-        any(target_arch = "x86_64", target_arch = "aarch64")
-    ),
-    allow(unused)
-)]
+/// With an `opt_main_for_host`, main is exposed under the name the host would call it by, and the
+/// `host_functions` it calls are left for the host to provide once the dylib is loaded.
 fn mono_module_to_dylib_llvm<'a>(
     arena: &'a Bump,
     target: Target,
     loaded: MonomorphizedModule<'a>,
     opt_level: OptLevel,
+    opt_main_for_host: Option<&str>,
+    host_functions: &BTreeSet<String>,
 ) -> Result<(libloading::Library, &'a str, Subs, STLayoutInterner<'a>), libloading::Error> {
     let MonomorphizedModule {
         procedures,
@@ -241,14 +282,27 @@ fn mono_module_to_dylib_llvm<'a>(
         }
     };
 
-    let (main_fn_name, _main_fn) = roc_gen_llvm::llvm::build::build_procedures_return_main(
-        &env,
-        &layout_interner,
-        opt_level,
-        procedures,
-        host_exposed_lambda_sets,
-        entry_point,
-    );
+    let (main_fn_name, _main_fn) = match opt_main_for_host {
+        Some(main_for_host) => roc_gen_llvm::llvm::build::build_procedures_expose_for_host(
+            &env,
+            &layout_interner,
+            opt_level,
+            procedures,
+            host_exposed_lambda_sets,
+            entry_point,
+            main_for_host,
+        ),
+        None => roc_gen_llvm::llvm::build::build_procedures_return_main(
+            &env,
+            &layout_interner,
+            opt_level,
+            procedures,
+            host_exposed_lambda_sets,
+            entry_point,
+        ),
+    };
+
+    add_host_function_pointers(&env, host_functions.iter().map(String::as_str));
 
     let emit_debug_info = true;
     let ll_file_path = std::env::temp_dir().join("repl.ll");
@@ -1,26 +1,39 @@
 //! Command Line Interface (CLI) functionality for the Read-Evaluate-Print-Loop (REPL).
 mod cli_gen;
+mod platform;
+
+pub use platform::PlatformHost;
 
 use bumpalo::Bump;
 use const_format::concatcp;
 use roc_load::MonomorphizedModule;
+use roc_mono::foreign_calls::effects_called;
 use roc_mono::ir::OptLevel;
+use roc_packaging::cache::roc_cache_dir;
 use roc_repl_eval::gen::Problems;
 use roc_repl_ui::colors::{CYAN, END_COL};
 use roc_repl_ui::repl_state::{ReplAction, ReplState};
-use roc_repl_ui::{format_output, is_incomplete, CONT_PROMPT, PROMPT, SHORT_INSTRUCTIONS, TIPS};
+use roc_repl_ui::{
+    format_defs, format_load_problem, format_output, is_incomplete, CONT_PROMPT, PROMPT,
+    SHORT_INSTRUCTIONS, TIPS,
+};
 use roc_reporting::report::{
     strip_colors, to_file_problem_report_string, ANSI_STYLE_CODES, DEFAULT_PALETTE,
 };
 use roc_target::Target;
+use rustyline::completion::Completer;
 use rustyline::highlight::{Highlighter, PromptInfo};
+use rustyline::hint::{Hint, Hinter};
 use rustyline::validate::{self, ValidationContext, ValidationResult, Validator};
-use rustyline::Config;
-use rustyline_derive::{Completer, Helper, Hinter};
+use rustyline::{CompletionType, Config, Context};
+use rustyline_derive::Helper;
 use std::any::Any;
 use std::backtrace::Backtrace;
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::panic::{AssertUnwindSafe, PanicInfo};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex, OnceLock};
 use target_lexicon::Triple;
 
@@ -35,10 +48,17 @@ pub const WELCOME_MESSAGE: &str = concatcp!(
     "\n\n"
 );
 
-#[derive(Completer, Helper, Hinter, Default)]
+/// Dims the type hinted after what's being typed, so it doesn't look like part of the input
+const DIM: &str = "\u{001b}[2m";
+
+#[derive(Helper, Default)]
 pub struct ReplHelper {
     validator: InputValidator,
     state: ReplState,
+    /// The type hinted for each line and cursor position, since hints are asked for on every
+    /// keystroke but only change when the defs do
+    hints: RefCell<HashMap<(String, usize), Option<String>>>,
+    target: Target,
 }
 
 static BACKTRACE: OnceLock<Arc<Mutex<Option<String>>>> = OnceLock::new();
@@ -55,7 +75,8 @@ fn panic_hook(_: &PanicInfo) {
     }
 }
 
-pub fn main(has_color: bool, has_header: bool) -> i32 {
+/// Runs the REPL. If there's an `opt_app_path`, modules can be imported from that app's packages.
+pub fn main(has_color: bool, has_header: bool, opt_app_path: Option<PathBuf>) -> i32 {
     use rustyline::error::ReadlineError;
     use rustyline::Editor;
 
@@ -86,24 +107,50 @@ pub fn main(has_color: bool, has_header: bool) -> i32 {
     } else {
         rustyline::ColorMode::Disabled
     };
-    let mut editor =
-        Editor::<ReplHelper>::with_config(Config::builder().color_mode(editor_color_mode).build());
-    let repl_helper = ReplHelper::default();
-    editor.set_helper(Some(repl_helper));
+    let config = Config::builder()
+        .color_mode(editor_color_mode)
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::<ReplHelper>::with_config(config);
     let target = Triple::host().into();
+    let repl_helper = ReplHelper {
+        state: opt_app_path
+            .clone()
+            .map(ReplState::with_app)
+            .unwrap_or_default(),
+        target,
+        ..Default::default()
+    };
+    editor.set_helper(Some(repl_helper));
+
+    // Effects can only be run if the app's platform has a host that's a dynamic library
+    let opt_host = match opt_app_path.as_deref() {
+        Some(app_path) => PlatformHost::load(app_path, target).unwrap_or_else(|problem| {
+            println!("Effects can't be run in this repl. {problem}\n");
+
+            None
+        }),
+        None => None,
+    };
+
+    // There's no history yet the first time the repl is run
+    let history_path = history_path();
+    let _ = editor.load_history(&history_path);
+
     let mut arena = Bump::new();
 
-    loop {
+    let exit_code = loop {
         match editor.readline(&strip_colors_if_necessary(PROMPT)) {
             Ok(line) => {
                 let line = line.trim();
 
                 editor.add_history_entry(line);
 
-                let repl_state = &mut editor
-                    .helper_mut()
-                    .expect("Editor helper was not set")
-                    .state;
+                let repl_helper = editor.helper_mut().expect("Editor helper was not set");
+                let repl_state = &mut repl_helper.state;
+
+                // The defs may be about to change, and with them the types of what's typed next
+                repl_helper.hints.get_mut().clear();
 
                 arena.reset();
 
@@ -114,15 +161,32 @@ pub fn main(has_color: bool, has_header: bool) -> i32 {
 
                 match action {
                     ReplAction::Eval { opt_mono, problems } => {
-                        let output = evaluate(opt_mono, problems, target);
+                        let output = evaluate(opt_mono, problems, target, opt_host.as_ref());
                         // If there was no output, don't print a blank line!
                         // (This happens for something like a type annotation.)
                         if !output.is_empty() {
                             println!("{}", strip_colors_if_necessary(&output));
                         }
                     }
+                    ReplAction::Type {
+                        opt_output,
+                        problems,
+                    } => {
+                        let output = format_output(ANSI_STYLE_CODES, opt_output, problems);
+
+                        if !output.is_empty() {
+                            println!("{}", strip_colors_if_necessary(&output));
+                        }
+                    }
+                    ReplAction::Defs(defs) => {
+                        let output = format_defs(ANSI_STYLE_CODES, defs);
+
+                        if !output.is_empty() {
+                            println!("{}", strip_colors_if_necessary(&output));
+                        }
+                    }
                     ReplAction::Exit => {
-                        return 0;
+                        break 0;
                     }
                     ReplAction::FileProblem { filename, error } => {
                         println!(
@@ -130,6 +194,9 @@ pub fn main(has_color: bool, has_header: bool) -> i32 {
                             to_file_problem_report_string(filename, error, has_color)
                         );
                     }
+                    ReplAction::LoadProblem { filename, problem } => {
+                        println!("{}", format_load_problem(&filename, &problem));
+                    }
                     ReplAction::Help => {
                         println!("{TIPS}");
                     }
@@ -142,18 +209,33 @@ pub fn main(has_color: bool, has_header: bool) -> i32 {
             }
             Err(ReadlineError::Eof) => {
                 // End of input; we're done!
-                return 0;
+                break 0;
             }
             Err(ReadlineError::Interrupted) => {
                 eprintln!("CTRL-C");
-                return 1;
+                break 1;
             }
             Err(err) => {
                 eprintln!("REPL error: {err:?}");
-                return 1;
+                break 1;
             }
         }
-    }
+    };
+
+    // The history can't be saved if the cache dir is read-only, which shouldn't stop the repl
+    let _ = editor.save_history(&history_path);
+
+    exit_code
+}
+
+/// Where the lines entered into the repl are kept between sessions
+fn history_path() -> PathBuf {
+    let cache_dir = roc_cache_dir();
+
+    // The cache dir is made when the first package is downloaded, which may not have happened yet
+    let _ = std::fs::create_dir_all(&cache_dir);
+
+    cache_dir.join("repl_history")
 }
 
 fn notify_repl_panic(target: Target, e: Box<dyn Any + Send>) -> ReplAction<'static> {
@@ -187,15 +269,45 @@ Machine Target: {:#?}
     ReplAction::Nothing
 }
 
+/// Runs the compiled expr and formats its value. If it runs effects, they're run by `opt_host`.
 pub fn evaluate(
     opt_mono: Option<MonomorphizedModule<'_>>,
-    problems: Problems,
+    mut problems: Problems,
     target: Target,
+    opt_host: Option<&PlatformHost>,
 ) -> String {
-    let opt_output = opt_mono.and_then(|mono| eval_llvm(mono, target, OptLevel::Normal));
+    let opt_output = opt_mono.and_then(|mono| {
+        let effects = effects_called(mono.procedures.values());
+
+        if opt_host.is_none() && !effects.is_empty() {
+            problems.errors.push(no_host_problem(&effects, target));
+
+            return None;
+        }
+
+        eval_llvm(mono, target, OptLevel::Normal, opt_host).unwrap_or_else(|problem| {
+            problems.errors.push(problem);
+
+            None
+        })
+    });
+
     format_output(ANSI_STYLE_CODES, opt_output, problems)
 }
 
+fn no_host_problem(effects: &BTreeSet<String>, target: Target) -> String {
+    let effects: Vec<&str> = effects.iter().map(String::as_str).collect();
+
+    format!(
+        "This runs effects, which only a platform's host can run: {}\n\n\
+        Tip: Start the repl with {CYAN}roc repl --app{END_COL} for an app whose platform has its \
+        host built as a dynamic library, like {}.{}",
+        effects.join(", "),
+        target,
+        target.dynamic_library_file_ext()
+    )
+}
+
 #[derive(Default)]
 struct InputValidator {}
 
@@ -209,11 +321,66 @@ impl Validator for InputValidator {
     }
 }
 
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // Completing type-checks the defs, which must not take the whole repl down if it panics
+        let completions = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.state.complete(&Bump::new(), line, pos, self.target)
+        }))
+        .unwrap_or((pos, Vec::new()));
+
+        Ok(completions)
+    }
+}
+
+/// The type of what's being typed, shown after the cursor. Unlike a completion, accepting it with
+/// → or Ctrl-E must not add it to the input.
+pub struct TypeHint(String);
+
+impl Hint for TypeHint {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = TypeHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<TypeHint> {
+        let mut hints = self.hints.borrow_mut();
+        let opt_type = hints.entry((line.to_string(), pos)).or_insert_with(|| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| {
+                self.state.hint(&Bump::new(), line, pos, self.target)
+            }))
+            .unwrap_or(None)
+        });
+
+        opt_type
+            .as_ref()
+            .map(|expr_type| TypeHint(format!(" : {expr_type}")))
+    }
+}
+
 impl Highlighter for ReplHelper {
     fn has_continuation_prompt(&self) -> bool {
         true
     }
 
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        format!("{DIM}{hint}{END_COL}").into()
+    }
+
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
         &'s self,
         prompt: &'p str,
@@ -0,0 +1,165 @@
+//! Running effects in the repl, like `Stdout.line! "hi"`, against the host of the platform of the
+//! app given to `roc repl --app`. The host has to be built as a dynamic library, like
+//! `linux-x64.so`. Only its `roc_fx_*` functions are used: the repl's generated code calls them
+//! through pointers that are set once it's loaded, and it keeps its own `roc_alloc`, `roc_panic`,
+//! and so on. The repl's expr is exposed to the host the way the platform's `main_for_host!` is.
+
+use std::collections::BTreeSet;
+use std::ffi::c_void;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use libloading::{Library, Symbol};
+use roc_gen_llvm::llvm::externs::host_function_pointer;
+use roc_packaging::cache::{self, RocCacheDir};
+use roc_parse::ast::{Header, SpacesBefore};
+use roc_parse::header::parse_header;
+use roc_parse::state::State;
+use roc_reporting::report::to_https_problem_report_string;
+use roc_target::Target;
+
+/// The name of a package's root module when its URL doesn't give one
+const DEFAULT_MAIN_NAME: &str = "main.roc";
+
+/// A platform's host, which stays loaded for as long as the repl runs
+pub struct PlatformHost {
+    pub path: PathBuf,
+    /// What the platform provides to its host, like `main_for_host!`
+    pub main_for_host: String,
+    lib: Library,
+}
+
+impl PlatformHost {
+    /// Loads the host of the platform of the app at `app_path`, if the platform has one that's
+    /// a dynamic library.
+    pub fn load(app_path: &Path, target: Target) -> Result<Option<Self>, String> {
+        let platform_main_roc = platform_main_roc(app_path)?;
+
+        let Ok(path) = target.find_dynamic_host(&platform_main_roc) else {
+            return Ok(None);
+        };
+
+        let main_for_host = main_for_host(&platform_main_roc)?;
+
+        // Not globally, so that the host's symbols can't replace the ones the repl generates
+        let lib = unsafe { Library::new(&path) }
+            .map_err(|err| format!("I couldn't load {}: {err}", path.display()))?;
+
+        Ok(Some(PlatformHost {
+            path,
+            main_for_host,
+            lib,
+        }))
+    }
+
+    /// Points the host function pointers in `app`, which the repl generated, at the host's
+    /// functions of the same names
+    pub fn provide_effects(&self, app: &Library, effects: &BTreeSet<String>) -> Result<(), String> {
+        for name in effects {
+            let host_fn: Symbol<*const c_void> = unsafe { self.lib.get(name.as_bytes()) }
+                .map_err(|_| format!("The host {} doesn't have {name}.", self.path.display()))?;
+            let host_fn_ptr: Symbol<*mut *const c_void> =
+                unsafe { app.get(host_function_pointer(name).as_bytes()) }
+                    .map_err(|err| format!("I couldn't find where to put {name}: {err}"))?;
+
+            unsafe { host_fn_ptr.write(*host_fn) };
+        }
+
+        Ok(())
+    }
+}
+
+/// What the platform at `platform_main_roc` provides to its host, like `main_for_host!`
+fn main_for_host(platform_main_roc: &Path) -> Result<String, String> {
+    let src = fs::read_to_string(platform_main_roc)
+        .map_err(|err| format!("I couldn't read {}: {err}", platform_main_roc.display()))?;
+    let arena = Bump::new();
+
+    let provides = match parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            SpacesBefore {
+                item: Header::Platform(header),
+                ..
+            },
+            _,
+        )) => header.provides.item.items,
+        _ => return Err(format!("{} isn't a platform.", platform_main_roc.display())),
+    };
+
+    provides
+        .first()
+        .map(|name| name.value.item().as_str().to_string())
+        .ok_or_else(|| {
+            format!(
+                "{} doesn't provide anything to its host.",
+                platform_main_roc.display()
+            )
+        })
+}
+
+/// The platform's main.roc, from the `platform` package in the header of the app at `app_path`
+fn platform_main_roc(app_path: &Path) -> Result<PathBuf, String> {
+    let src = fs::read_to_string(app_path)
+        .map_err(|err| format!("I couldn't read {}: {err}", app_path.display()))?;
+    let arena = Bump::new();
+
+    let packages = match parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            SpacesBefore {
+                item: Header::App(header),
+                ..
+            },
+            _,
+        )) => header.packages.value.items,
+        _ => return Err(format!("{} isn't an app.", app_path.display())),
+    };
+
+    let platform = packages
+        .iter()
+        .map(|entry| entry.value.item())
+        .find(|entry| entry.platform_marker.is_some())
+        .map(|entry| entry.package_name.value.as_str())
+        .ok_or_else(|| format!("{} doesn't have a platform.", app_path.display()))?;
+
+    if platform.starts_with("https://") {
+        // The platform was downloaded when the app was first loaded, so this finds it in the cache
+        let packages_dir = cache::roc_cache_packages_dir();
+        let (package_dir, opt_root_module) =
+            cache::install_package(RocCacheDir::Persistent(&packages_dir), platform).map_err(
+                |problem| to_https_problem_report_string(platform, problem, app_path.to_owned()),
+            )?;
+
+        Ok(package_dir.join(opt_root_module.unwrap_or(DEFAULT_MAIN_NAME)))
+    } else {
+        let app_dir = app_path.parent().unwrap_or_else(|| Path::new("."));
+
+        Ok(app_dir.join(platform))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_dynamic_host_prefers_target_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let platform_main_roc = dir.path().join("main.roc");
+        let target = Target::LinuxX64;
+
+        assert!(target.find_dynamic_host(&platform_main_roc).is_err());
+
+        fs::write(dir.path().join("libhost.so"), "").unwrap();
+        assert_eq!(
+            target.find_dynamic_host(&platform_main_roc),
+            Ok(dir.path().join("libhost.so"))
+        );
+
+        fs::write(dir.path().join("linux-x64.so"), "").unwrap();
+        assert_eq!(
+            target.find_dynamic_host(&platform_main_roc),
+            Ok(dir.path().join("linux-x64.so"))
+        );
+    }
+}
@@ -2,17 +2,25 @@ use bumpalo::Bump;
 use roc_load::{ExecutionMode, LoadConfig, LoadMonomorphizedError, Threading};
 use roc_packaging::cache::{self, RocCacheDir};
 use roc_problem::Severity;
-use roc_reporting::report::Palette;
+use roc_reporting::report::{Palette, RenderTarget};
 use std::path::PathBuf;
 
+use roc_collections::MutMap;
 use roc_fmt::annotation::Formattable;
 use roc_fmt::annotation::{Newlines, Parens};
-use roc_load::{LoadingProblem, MonomorphizedModule};
+use roc_load::{LoadedModule, LoadingProblem, MonomorphizedModule};
+use roc_module::symbol::{Interns, ModuleId, ModuleIds};
 use roc_parse::ast::Expr;
 use roc_region::all::LineInfo;
 use roc_reporting::report::{can_problem, type_problem, RocDocAllocator};
 use roc_solve::FunctionKind;
+use roc_solve_problem::TypeError;
 use roc_target::Target;
+use roc_types::pretty_print::{name_and_print_var, DebugPrint};
+use roc_types::subs::{Content, FlatType, Subs, Variable};
+
+/// The name of the def that the expr entered into the repl becomes
+pub const REPL_OUTPUT: &str = "repl_output";
 
 #[derive(Debug)]
 pub struct ReplOutput {
@@ -57,6 +65,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
     expr: &str,
     target: Target,
     palette: Palette,
+    opt_app_path: Option<PathBuf>,
 ) -> (Option<MonomorphizedModule<'a>>, Problems) {
     let filename = PathBuf::from("replfile.roc");
     let src_dir = PathBuf::from(".");
@@ -66,7 +75,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         filename,
         module_src,
         src_dir,
-        None,
+        opt_app_path,
         RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
         LoadConfig {
             target,
@@ -86,17 +95,8 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
                 (m.can_problems, m.type_problems)
             );
         }
-        Err(LoadMonomorphizedError::LoadingProblem(LoadingProblem::FormattedReport(report, _))) => {
-            return (
-                None,
-                Problems {
-                    errors: vec![report],
-                    warnings: Vec::new(),
-                },
-            );
-        }
-        Err(e) => {
-            todo!("error while loading module: {:?}", e)
+        Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
+            return (None, loading_problems(problem, palette));
         }
     };
 
@@ -108,6 +108,158 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         ..
     } = &mut loaded;
 
+    let problems = report_problems(
+        interns,
+        sources,
+        can_problems,
+        type_problems,
+        module_src,
+        bytes_before_expr,
+        palette,
+    );
+
+    (Some(loaded), problems)
+}
+
+/// Type-checks `expr` along with `defs`, without generating any code for it, so its type and the
+/// types of the defs can be read from the result. Modules can be imported from the packages of the
+/// app at `opt_app_path`, like they can when evaluating.
+pub fn check<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    defs: I,
+    expr: &str,
+    target: Target,
+    palette: Palette,
+    opt_app_path: Option<PathBuf>,
+) -> (Option<LoadedModule>, Problems) {
+    let filename = PathBuf::from("replfile.roc");
+    let src_dir = PathBuf::from(".");
+    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, defs, expr);
+    let loaded = roc_load::load_and_typecheck_str(
+        arena,
+        filename,
+        module_src,
+        src_dir,
+        opt_app_path,
+        target,
+        FunctionKind::LambdaSet,
+        roc_reporting::report::RenderTarget::ColorTerminal,
+        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+        palette,
+    );
+
+    let mut loaded = match loaded {
+        Ok(v) => v,
+        Err(problem) => return (None, loading_problems(problem, palette)),
+    };
+
+    let LoadedModule {
+        interns,
+        sources,
+        can_problems,
+        type_problems,
+        ..
+    } = &mut loaded;
+
+    let problems = report_problems(
+        interns,
+        sources,
+        can_problems,
+        type_problems,
+        module_src,
+        bytes_before_expr,
+        palette,
+    );
+
+    (Some(loaded), problems)
+}
+
+/// The report of the problem that stopped the module from loading, as [Problems]
+fn loading_problems(problem: LoadingProblem<'_>, palette: Palette) -> Problems {
+    let report = roc_load::report_loading_problem(
+        problem,
+        ModuleIds::default(),
+        RenderTarget::ColorTerminal,
+        palette,
+    );
+
+    Problems {
+        errors: vec![report],
+        warnings: Vec::new(),
+    }
+}
+
+/// The solved type of the top-level def named `ident` in a module from [check], like `Num *`.
+/// The expr that was checked is named [REPL_OUTPUT].
+pub fn def_type(loaded: &mut LoadedModule, ident: &str) -> Option<String> {
+    with_def_var(loaded, ident, |var, subs, home, interns| {
+        // Naming the type's variables changes them, so undo that for the next def's type
+        let snapshot = subs.snapshot();
+        let type_str = name_and_print_var(var, subs, home, interns, DebugPrint::NOTHING);
+
+        subs.rollback_to(snapshot);
+
+        type_str
+    })
+}
+
+/// The names of the fields of the top-level def named `ident` in a module from [check], if it's a
+/// record, or of its elements, like `0` and `1`, if it's a tuple.
+pub fn def_fields(loaded: &mut LoadedModule, ident: &str) -> Vec<String> {
+    with_def_var(loaded, ident, |mut var, subs, _, _| loop {
+        match subs.get_content_without_compacting(var) {
+            Content::Alias(_, _, real_var, _) => var = *real_var,
+            Content::Structure(FlatType::Record(fields, ext)) => {
+                break match fields.unsorted_iterator(subs, *ext) {
+                    Ok(fields) => fields.map(|(name, _)| name.to_string()).collect(),
+                    Err(_) => Vec::new(),
+                };
+            }
+            Content::Structure(FlatType::Tuple(elems, ext)) => {
+                break match elems.unsorted_iterator(subs, *ext) {
+                    Ok(elems) => elems.map(|(index, _)| index.to_string()).collect(),
+                    Err(_) => Vec::new(),
+                };
+            }
+            _ => break Vec::new(),
+        }
+    })
+    .unwrap_or_default()
+}
+
+fn with_def_var<T>(
+    loaded: &mut LoadedModule,
+    ident: &str,
+    f: impl FnOnce(Variable, &mut Subs, ModuleId, &Interns) -> T,
+) -> Option<T> {
+    let home = loaded.module_id;
+    let (decls, subs) = if let Some(decls) = loaded.declarations_by_id.get(&home) {
+        (decls, loaded.solved.inner_mut())
+    } else {
+        let checked = loaded.typechecked.get_mut(&home)?;
+
+        (&checked.decls, checked.solved_subs.inner_mut())
+    };
+
+    // If a def was entered more than once, the last one is the one in scope
+    let index = decls
+        .symbols
+        .iter()
+        .rposition(|symbol| symbol.value.as_str(&loaded.interns) == ident)?;
+
+    Some(f(decls.variables[index], subs, home, &loaded.interns))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn report_problems(
+    interns: &Interns,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
+    type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    module_src: &str,
+    bytes_before_expr: usize,
+    palette: Palette,
+) -> Problems {
     let mut problems = Problems::default();
 
     let errors = &mut problems.errors;
@@ -153,6 +305,14 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         }
 
         for problem in type_probs {
+            // The repl runs its expr as soon as it's entered, which is when its effects are meant
+            // to happen, so running them isn't a problem there
+            if let TypeError::FxInTopLevel(region, _) = &problem {
+                if region.start().offset as usize >= bytes_before_expr {
+                    continue;
+                }
+            }
+
             if let Some(report) = type_problem(&alloc, &line_info, module_path.clone(), problem) {
                 let severity = report.severity;
                 let mut buf = String::new();
@@ -171,7 +331,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         }
     }
 
-    (Some(loaded), problems)
+    problems
 }
 
 fn promote_expr_to_module<'a, 'i, I: Iterator<Item = &'i str>>(
@@ -0,0 +1,89 @@
+//! Collecting the hits that code compiled for `roc test --coverage` reports as it runs.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+
+use roc_gen_llvm::llvm::externs::COVERAGE_CALLBACK;
+use roc_mono::coverage::CoveragePoint;
+
+/// How many times each [CoveragePoint] was reached.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageHits {
+    counts: BTreeMap<CoveragePoint, u64>,
+}
+
+impl CoverageHits {
+    pub fn count(&self, point: CoveragePoint) -> u64 {
+        self.counts.get(&point).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CoveragePoint, u64)> + '_ {
+        self.counts.iter().map(|(point, count)| (*point, *count))
+    }
+
+    fn add(&mut self, point: CoveragePoint, count: u64) {
+        *self.counts.entry(point).or_insert(0) += count;
+    }
+
+    /// Adds the hits that a child process [wrote](write_recorded_hits) to `file`.
+    pub(crate) fn read_from(&mut self, file: &mut File) -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(0))?;
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut numbers = line.split(' ').map(str::parse::<u64>);
+
+            if let (Some(Ok(first)), Some(Ok(second)), Some(Ok(count))) =
+                (numbers.next(), numbers.next(), numbers.next())
+            {
+                if let Some(point) = CoveragePoint::decode(first, second) {
+                    self.add(point, count);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The hits recorded in this process, by the arguments they were reported with.
+static RECORDED_HITS: Mutex<BTreeMap<(u64, u64), u64>> = Mutex::new(BTreeMap::new());
+
+extern "C" fn record_hit(first: u64, second: u64) {
+    if let Ok(mut hits) = RECORDED_HITS.lock() {
+        *hits.entry((first, second)).or_insert(0) += 1;
+    }
+}
+
+/// Starts recording the hits of the code in `lib` in this process. Returns whether it was
+/// compiled with coverage at all.
+pub(crate) fn start_recording(lib: &libloading::Library) -> bool {
+    type Callback = Option<extern "C" fn(u64, u64)>;
+
+    let callback: libloading::Symbol<*mut Callback> =
+        match unsafe { lib.get(COVERAGE_CALLBACK.as_bytes()) } {
+            Ok(callback) => callback,
+            Err(_) => return false,
+        };
+
+    unsafe { callback.write(Some(record_hit)) };
+
+    true
+}
+
+/// Writes the hits recorded in this process for [CoverageHits::read_from], one per line.
+pub(crate) fn write_recorded_hits(file: &mut File) -> std::io::Result<()> {
+    let hits = match RECORDED_HITS.lock() {
+        Ok(hits) => hits,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut contents = String::new();
+    for ((first, second), count) in hits.iter() {
+        contents.push_str(&format!("{first} {second} {count}\n"));
+    }
+
+    file.write_all(contents.as_bytes())
+}
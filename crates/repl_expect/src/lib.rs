@@ -15,7 +15,11 @@ use {
 #[cfg(not(windows))]
 mod app;
 #[cfg(not(windows))]
+pub mod coverage;
+#[cfg(not(windows))]
 pub mod run;
+#[cfg(not(windows))]
+pub mod snapshot;
 
 #[cfg(not(windows))]
 use app::{ExpectMemory, ExpectReplApp};
@@ -103,6 +107,10 @@ mod test {
     use crate::run::expect_mono_module_to_dylib;
 
     fn run_expect_test(source: &str, expected: &str) {
+        run_expect_test_help(source, expected, false)
+    }
+
+    fn run_expect_test_help(source: &str, expected: &str, in_children: bool) {
         let arena = bumpalo::Bump::new();
         let arena = &arena;
 
@@ -168,17 +176,36 @@ mod test {
 
         let global_layout_interner = layout_interner.into_global();
         for (_, expect_funcs) in expects_by_module {
-            let (_failed, _passed) = crate::run::run_expects_with_memory(
-                &mut writer,
-                RenderTarget::ColorTerminal,
-                arena,
-                interns,
-                &global_layout_interner,
-                &dy_lib,
-                &mut expectations,
-                expect_funcs,
-                &mut memory,
-            )
+            let (_failed, _passed) = if in_children {
+                crate::run::run_toplevel_expects_in_children(
+                    &mut writer,
+                    RenderTarget::ColorTerminal,
+                    arena,
+                    interns,
+                    &global_layout_interner,
+                    &dy_lib,
+                    &mut expectations,
+                    expect_funcs,
+                    2,
+                    None,
+                    crate::snapshot::SnapshotMode::Record,
+                    |_, _| std::ops::ControlFlow::Continue(()),
+                )
+            } else {
+                crate::run::run_expects_with_memory(
+                    &mut writer,
+                    RenderTarget::ColorTerminal,
+                    arena,
+                    interns,
+                    &global_layout_interner,
+                    &dy_lib,
+                    &mut expectations,
+                    expect_funcs,
+                    &mut memory,
+                    crate::snapshot::SnapshotMode::Record,
+                    |_, _| std::ops::ControlFlow::Continue(()),
+                )
+            }
             .unwrap();
         }
 
@@ -244,6 +271,32 @@ mod test {
         );
     }
 
+    #[test]
+    fn fail_in_child_process() {
+        run_expect_test_help(
+            indoc!(
+                r#"
+                app "test" provides [main] to "./platform"
+
+                main = 0
+
+                expect 1 == 1
+
+                expect 1 == 2
+                "#
+            ),
+            indoc!(
+                r"
+                This expectation failed:
+
+                7│  expect 1 == 2
+                    ^^^^^^^^^^^^^
+                "
+            ),
+            true,
+        );
+    }
+
     #[test]
     fn lookup_integer() {
         run_expect_test(
@@ -1249,4 +1302,101 @@ mod test {
             ),
         );
     }
+
+    #[test]
+    fn property_pass() {
+        run_expect_test(
+            indoc!(
+                r"
+                interface Test exposes [] imports []
+
+                expect |a, b| Str.count_utf8_bytes(Str.concat(a, b)) == Str.count_utf8_bytes(a) + Str.count_utf8_bytes(b)
+                "
+            ),
+            "",
+        );
+    }
+
+    #[test]
+    fn property_shrinks_counterexample() {
+        run_expect_test(
+            indoc!(
+                r"
+                interface Test exposes [] imports []
+
+                expect |n| n < 10u8
+                "
+            ),
+            indoc!(
+                r"
+                This expectation failed:
+
+                3│  expect |n| n < 10u8
+                    ^^^^^^^^^^^^^^^^^^^
+
+                When it failed, these variables had these values:
+
+                n : U8
+                n = 10
+                "
+            ),
+        );
+    }
+
+    #[test]
+    fn property_names_counterexample_arguments() {
+        run_expect_test(
+            indoc!(
+                r"
+                interface Test exposes [] imports []
+
+                expect |a, b| a < 10u8 || b < 3u8
+                "
+            ),
+            indoc!(
+                r"
+                This expectation failed:
+
+                3│  expect |a, b| a < 10u8 || b < 3u8
+                    ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+
+                When it failed, these variables had these values:
+
+                a : U8
+                a = 10
+
+                b : U8
+                b = 3
+                "
+            ),
+        );
+    }
+
+    #[test]
+    fn snapshot_records_first_value() {
+        run_expect_test(
+            indoc!(
+                r#"
+                interface Test exposes [] imports []
+
+                expect snapshot(Str.split_on("a,b", ","))
+                "#
+            ),
+            "",
+        );
+    }
+
+    #[test]
+    fn benchmark_passes_when_it_runs() {
+        run_expect_test(
+            indoc!(
+                r#"
+                interface Test exposes [] imports []
+
+                expect bench(List.sum([1, 2, 3]))
+                "#
+            ),
+            "",
+        );
+    }
 }
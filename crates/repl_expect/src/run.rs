@@ -1,17 +1,27 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
 use std::sync::{
     atomic::{AtomicBool, AtomicU32},
     Arc,
 };
+use std::time::{Duration, Instant};
 
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
 use inkwell::context::Context;
 use roc_build::link::llvm_module_to_dylib;
-use roc_can::expr::ExpectLookup;
+use roc_can::{
+    desugar::{BENCH_LOOKUP, SNAPSHOT_LOOKUP},
+    expr::ExpectLookup,
+};
 use roc_collections::{MutMap, MutSet, VecMap};
 use roc_error_macros::internal_error;
 use roc_gen_llvm::{
-    llvm::{build::LlvmBackendMode, externs::add_default_roc_externs},
+    llvm::{
+        build::LlvmBackendMode,
+        externs::{add_coverage_hit, add_default_roc_externs},
+    },
     run_roc::RocCallResult,
     run_roc_dylib,
 };
@@ -21,11 +31,15 @@ use roc_mono::{
     ir::OptLevel,
     layout::{GlobalLayoutInterner, STLayoutInterner},
 };
+use roc_parse::ast::{Expr, StrLiteral};
 use roc_region::all::Region;
 use roc_reporting::{error::expect::Renderer, report::RenderTarget};
 use roc_target::Target;
 use roc_types::subs::Subs;
 
+use crate::coverage::{self, CoverageHits};
+use crate::snapshot::{check_snapshot, SnapshotMode, SnapshotOutcome};
+
 pub struct ExpectMemory<'a> {
     ptr: *mut u8,
     length: usize,
@@ -35,7 +49,6 @@ pub struct ExpectMemory<'a> {
 impl<'a> ExpectMemory<'a> {
     const SHM_SIZE: usize = 1024;
 
-    #[cfg(test)]
     pub(crate) fn from_slice(slice: &mut [u8]) -> Self {
         Self {
             ptr: slice.as_mut_ptr(),
@@ -113,6 +126,15 @@ impl<'a> ExpectMemory<'a> {
     }
 }
 
+/// How running a single top-level expect went.
+#[derive(Debug, Clone, Copy)]
+pub struct ExpectResult {
+    pub passed: bool,
+    pub duration: Duration,
+}
+
+/// Runs `expects` one after the other, writing a report of each one that fails to `writer`.
+/// Snapshot expects are compared to their snapshots, but never record any.
 #[allow(clippy::too_many_arguments)]
 pub fn run_toplevel_expects<'a, W: std::io::Write>(
     writer: &mut W,
@@ -123,6 +145,36 @@ pub fn run_toplevel_expects<'a, W: std::io::Write>(
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
     expects: ExpectFunctions<'_>,
+) -> std::io::Result<(usize, usize)> {
+    run_toplevel_expects_with(
+        writer,
+        render_target,
+        arena,
+        interns,
+        layout_interner,
+        lib,
+        expectations,
+        expects,
+        SnapshotMode::Verify,
+        |_, _| ControlFlow::Continue(()),
+    )
+}
+
+/// Like [run_toplevel_expects], but tells `on_result` how each expect went as soon as it has run.
+/// The remaining expects are skipped once `on_result` breaks, and snapshot expects record their
+/// snapshots as `snapshot_mode` says.
+#[allow(clippy::too_many_arguments)]
+pub fn run_toplevel_expects_with<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expects: ExpectFunctions<'_>,
+    snapshot_mode: SnapshotMode,
+    on_result: impl FnMut(&ToplevelExpect<'_>, ExpectResult) -> ControlFlow<()>,
 ) -> std::io::Result<(usize, usize)> {
     let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
     let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
@@ -137,9 +189,378 @@ pub fn run_toplevel_expects<'a, W: std::io::Write>(
         expectations,
         expects,
         &mut memory,
+        snapshot_mode,
+        on_result,
     )
 }
 
+/// Like [run_toplevel_expects_with], but runs each expect in a child process of its own, with up
+/// to `max_children` of them running at once. An expect that crashes its process fails without
+/// taking the others down with it. Results are still written and reported in the order the
+/// expects were given in.
+///
+/// When `lib` was compiled for `roc test --coverage`, the hits of each expect are added to
+/// `coverage`, except for those of expects that crashed.
+#[allow(clippy::too_many_arguments)]
+pub fn run_toplevel_expects_in_children<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expects: ExpectFunctions<'_>,
+    max_children: usize,
+    mut coverage: Option<&mut CoverageHits>,
+    snapshot_mode: SnapshotMode,
+    mut on_result: impl FnMut(&ToplevelExpect<'_>, ExpectResult) -> ControlFlow<()>,
+) -> std::io::Result<(usize, usize)> {
+    let expects = expects.pure;
+    let max_children = max_children.max(1);
+    let record_coverage = coverage.is_some();
+
+    let mut failed = 0;
+    let mut passed = 0;
+
+    let mut running: MutMap<libc::pid_t, RunningExpect> = MutMap::default();
+    // Expects that finished before one given earlier than them did, by index
+    let mut finished: MutMap<usize, (ExpectResult, Vec<u8>)> = MutMap::default();
+    let mut next_to_start = 0;
+    let mut next_to_report = 0;
+
+    // Otherwise each child would print whatever we hadn't yet
+    writer.flush()?;
+    std::io::stdout().flush()?;
+
+    while next_to_report < expects.len() {
+        while next_to_start < expects.len() && running.len() < max_children {
+            let (pid, output, hits) = spawn_expect(
+                render_target,
+                arena,
+                interns,
+                layout_interner,
+                lib,
+                expectations,
+                expects[next_to_start],
+                record_coverage,
+                snapshot_mode,
+            )?;
+
+            running.insert(
+                pid,
+                RunningExpect {
+                    index: next_to_start,
+                    start: Instant::now(),
+                    output,
+                    hits,
+                },
+            );
+            next_to_start += 1;
+        }
+
+        let (index, result, output) = wait_for_any_child(
+            render_target,
+            arena,
+            interns,
+            expectations,
+            &expects,
+            &mut running,
+            coverage.as_deref_mut(),
+        )?;
+        finished.insert(index, (result, output));
+
+        while let Some((result, output)) = finished.remove(&next_to_report) {
+            let expect = &expects[next_to_report];
+            next_to_report += 1;
+
+            writer.write_all(&output)?;
+
+            match result.passed {
+                true => passed += 1,
+                false => failed += 1,
+            }
+
+            if on_result(expect, result).is_break() {
+                stop_children(running.into_keys());
+                return Ok((failed, passed));
+            }
+        }
+    }
+
+    Ok((failed, passed))
+}
+
+/// Runs just `expect`, and writes its report to `writer` if it fails. It runs in this process, so
+/// an expect that crashes takes the process down with it; callers that need to survive that run
+/// this in a process of its own.
+#[allow(clippy::too_many_arguments)]
+pub fn run_one_toplevel_expect<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expect: ToplevelExpect<'_>,
+) -> std::io::Result<ExpectResult> {
+    // Nobody else reads this memory, so it needn't be shared
+    let mut buffer = vec![0xAA; ExpectMemory::SHM_SIZE];
+    let mut memory = ExpectMemory::from_slice(&mut buffer);
+    memory.set_shared_buffer(lib);
+
+    let start = Instant::now();
+    let passed = run_expect_pure(
+        writer,
+        render_target,
+        arena,
+        interns,
+        layout_interner,
+        lib,
+        expectations,
+        &mut memory,
+        expect,
+        SnapshotMode::Verify,
+    )?;
+
+    Ok(ExpectResult {
+        passed,
+        duration: start.elapsed(),
+    })
+}
+
+struct RunningExpect {
+    index: usize,
+    start: Instant,
+    /// Where the child writes its report, if the expect fails
+    output: File,
+    /// Where the child writes its coverage hits, if they're being recorded
+    hits: Option<File>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_expect<'a>(
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expect: ToplevelExpect<'_>,
+    record_coverage: bool,
+    snapshot_mode: SnapshotMode,
+) -> std::io::Result<(libc::pid_t, File, Option<File>)> {
+    // A file rather than a pipe, so that children never block on a parent that's waiting for a
+    // different child to finish
+    let mut output = tempfile::tempfile()?;
+    let mut hits = match record_coverage {
+        true => Some(tempfile::tempfile()?),
+        false => None,
+    };
+
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 => {
+            // we are the child
+
+            // Nobody else reads this memory, so it needn't be shared
+            let mut buffer = vec![0xAA; ExpectMemory::SHM_SIZE];
+            let mut memory = ExpectMemory::from_slice(&mut buffer);
+            memory.set_shared_buffer(lib);
+
+            if hits.is_some() {
+                coverage::start_recording(lib);
+            }
+
+            let mut report = Vec::new();
+            let result = run_expect_pure(
+                &mut report,
+                render_target,
+                arena,
+                interns,
+                layout_interner,
+                lib,
+                expectations,
+                &mut memory,
+                expect,
+                snapshot_mode,
+            );
+
+            let exit_code = match result {
+                Ok(true) => 0,
+                Ok(false) => 1,
+                Err(_) => 2,
+            };
+
+            let written = output
+                .write_all(&report)
+                .and_then(|()| match hits.as_mut() {
+                    Some(hits) => coverage::write_recorded_hits(hits),
+                    None => Ok(()),
+                });
+
+            if written.is_err() {
+                unsafe { libc::_exit(2) };
+            }
+
+            // Skip the exit handlers, which are the parent's to run
+            unsafe { libc::_exit(exit_code) }
+        }
+        pid => Ok((pid, output, hits)),
+    }
+}
+
+/// Waits for any of the `running` children to exit, and returns which expect it was running, how
+/// that went, and its report. Its coverage hits are added to `coverage`.
+///
+/// Only the `running` children are waited on, since the process may have others, like the one
+/// `--watch` runs each build in.
+fn wait_for_any_child<'a>(
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expects: &[ToplevelExpect<'_>],
+    running: &mut MutMap<libc::pid_t, RunningExpect>,
+    coverage: Option<&mut CoverageHits>,
+) -> std::io::Result<(usize, ExpectResult, Vec<u8>)> {
+    let (pid, status) = loop {
+        if let Some(exited) = try_wait_for_any(running.keys().copied())? {
+            break exited;
+        }
+
+        std::thread::sleep(CHILD_POLL_INTERVAL);
+    };
+
+    let child = running.remove(&pid).unwrap();
+    let index = child.index;
+
+    let (result, output) = finish_child(
+        render_target,
+        arena,
+        interns,
+        expectations,
+        expects[index],
+        child,
+        status,
+        coverage,
+    )?;
+
+    Ok((index, result, output))
+}
+
+/// How the expect that `child` ran went, now that it has exited with `status`, and its report
+#[allow(clippy::too_many_arguments)]
+fn finish_child<'a>(
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expect: ToplevelExpect<'_>,
+    mut child: RunningExpect,
+    status: libc::c_int,
+    coverage: Option<&mut CoverageHits>,
+) -> std::io::Result<(ExpectResult, Vec<u8>)> {
+    let duration = child.start.elapsed();
+
+    let mut output = Vec::new();
+    child.output.seek(SeekFrom::Start(0))?;
+    child.output.read_to_end(&mut output)?;
+
+    if libc::WIFSIGNALED(status) {
+        render_crash(
+            &mut output,
+            render_target,
+            arena,
+            interns,
+            expectations,
+            expect,
+            libc::WTERMSIG(status),
+        )?;
+    } else if let (Some(coverage), Some(hits)) = (coverage, child.hits.as_mut()) {
+        coverage.read_from(hits)?;
+    }
+
+    let passed = libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0;
+
+    Ok((ExpectResult { passed, duration }, output))
+}
+
+/// How often [wait_for_any_child] checks whether a child has exited
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The first of `pids` that has exited, and its status, without waiting for any of them
+fn try_wait_for_any(
+    pids: impl Iterator<Item = libc::pid_t>,
+) -> std::io::Result<Option<(libc::pid_t, libc::c_int)>> {
+    for pid in pids {
+        let mut status = 0;
+
+        loop {
+            match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+                0 => break,
+                -1 => {
+                    let error = std::io::Error::last_os_error();
+                    match error.kind() {
+                        std::io::ErrorKind::Interrupted => continue,
+                        _ => return Err(error),
+                    }
+                }
+                _ => return Ok(Some((pid, status))),
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn render_crash<'a>(
+    writer: &mut impl std::io::Write,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expect: ToplevelExpect<'_>,
+    signal: libc::c_int,
+) -> std::io::Result<()> {
+    let module_id = expect.symbol.module_id();
+    let data = expectations.get_mut(&module_id).unwrap();
+
+    let filename = data.path.to_owned();
+    let renderer = Renderer::new(
+        arena,
+        interns,
+        render_target,
+        module_id,
+        filename,
+        &data.source,
+    );
+
+    let description = unsafe { std::ffi::CStr::from_ptr(libc::strsignal(signal)) };
+    let message = format!(
+        "Its process was stopped by signal {signal} ({}).",
+        description.to_string_lossy()
+    );
+    renderer.render_panic(writer, &message, expect.region)?;
+
+    // Structured reports already end their lines
+    if !render_target.is_structured() {
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn stop_children(pids: impl IntoIterator<Item = libc::pid_t>) {
+    for pid in pids {
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+            libc::waitpid(pid, std::ptr::null_mut(), 0);
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
     writer: &mut W,
@@ -151,6 +572,8 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
     expectations: &mut VecMap<ModuleId, Expectations>,
     expects: ExpectFunctions<'_>,
     memory: &mut ExpectMemory,
+    snapshot_mode: SnapshotMode,
+    mut on_result: impl FnMut(&ToplevelExpect<'_>, ExpectResult) -> ControlFlow<()>,
 ) -> std::io::Result<(usize, usize)> {
     let mut failed = 0;
     let mut passed = 0;
@@ -158,6 +581,7 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
     memory.set_shared_buffer(lib);
 
     for expect in expects.pure {
+        let start = Instant::now();
         let result = run_expect_pure(
             writer,
             render_target,
@@ -168,12 +592,22 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
             expectations,
             memory,
             expect,
+            snapshot_mode,
         )?;
 
         match result {
             true => passed += 1,
             false => failed += 1,
         }
+
+        let result = ExpectResult {
+            passed: result,
+            duration: start.elapsed(),
+        };
+
+        if on_result(&expect, result).is_break() {
+            break;
+        }
     }
 
     Ok((failed, passed))
@@ -190,6 +624,7 @@ fn run_expect_pure<'a, W: std::io::Write>(
     expectations: &mut VecMap<ModuleId, Expectations>,
     shared_memory: &mut ExpectMemory,
     expect: ToplevelExpect<'_>,
+    snapshot_mode: SnapshotMode,
 ) -> std::io::Result<bool> {
     use roc_gen_llvm::try_run_jit_function;
 
@@ -199,13 +634,38 @@ fn run_expect_pure<'a, W: std::io::Write>(
 
     let shared_memory_ptr: *const u8 = shared_memory.ptr.cast();
 
+    // Benchmarks always fail, so outside of `roc bench` it's enough that they ran
+    if result.is_ok()
+        && sequence.count_failures() == 1
+        && is_benchmark(expectations, interns, &expect)
+    {
+        return Ok(true);
+    }
+
+    if result.is_ok() && sequence.count_failures() > 0 {
+        let snapshot = check_snapshot_expect(
+            writer,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            expectations,
+            shared_memory_ptr,
+            expect,
+            snapshot_mode,
+        )?;
+
+        if let Some(passed) = snapshot {
+            return Ok(passed);
+        }
+    }
+
     if result.is_err() || sequence.count_failures() > 0 {
         let module_id = expect.symbol.module_id();
         let data = expectations.get_mut(&module_id).unwrap();
 
-        let path = &data.path;
         let filename = data.path.to_owned();
-        let source = std::fs::read_to_string(path).unwrap();
+        let source = data.source.clone();
 
         let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
 
@@ -229,7 +689,10 @@ fn run_expect_pure<'a, W: std::io::Write>(
             }
         }
 
-        writeln!(writer)?;
+        // Structured reports already end their lines
+        if !render_target.is_structured() {
+            writeln!(writer)?;
+        }
 
         Ok(false)
     } else {
@@ -237,6 +700,147 @@ fn run_expect_pure<'a, W: std::io::Write>(
     }
 }
 
+/// Whether `expect` is an `expect bench(...)`, which `roc bench` times.
+pub fn is_benchmark(
+    expectations: &VecMap<ModuleId, Expectations>,
+    interns: &Interns,
+    expect: &ToplevelExpect<'_>,
+) -> bool {
+    let Some(data) = expectations.get(&expect.symbol.module_id()) else {
+        return false;
+    };
+
+    data.expectations.iter().any(|(region, lookups)| {
+        expect.region.contains(region)
+            && lookups
+                .iter()
+                .any(|lookup| lookup.symbol.as_str(interns) == BENCH_LOOKUP)
+    })
+}
+
+/// Runs the benchmark `expect` `iterations` times in a row, and returns how long that took all
+/// together. Fails with the message of the crash if it crashed.
+///
+/// Only computing the benchmark's value is timed. It isn't reported, though it's still kept from
+/// being optimized away.
+pub fn time_benchmark(
+    lib: &libloading::Library,
+    memory: &mut ExpectMemory,
+    expect: ToplevelExpect<'_>,
+    iterations: u64,
+) -> Result<Duration, String> {
+    memory.set_shared_buffer(lib);
+
+    // Looked up once, so that the lookup isn't part of the time
+    let function: libloading::Symbol<unsafe extern "C" fn(*mut RocCallResult<()>)> =
+        unsafe { lib.get(expect.name.as_bytes()) }.map_err(|error| error.to_string())?;
+
+    let mut sequence = ExpectSequence { ptr: memory.ptr };
+    sequence.discard_values();
+
+    let start = Instant::now();
+    let mut outcome = Ok(());
+
+    for _ in 0..iterations {
+        let mut result = RocCallResult::default();
+        unsafe { function(&mut result) };
+
+        if let Err((message, _)) = Result::<(), _>::from(result) {
+            outcome = Err(message);
+            break;
+        }
+    }
+
+    let elapsed = start.elapsed();
+
+    sequence.reset();
+
+    outcome.map(|()| elapsed)
+}
+
+/// Snapshot expects always fail, so that the value they take a snapshot of gets reported. That
+/// value is then compared to the snapshot, and the expect only fails for real when they differ.
+///
+/// Returns whether the expect passed, or `None` if it isn't a snapshot expect.
+#[allow(clippy::too_many_arguments)]
+fn check_snapshot_expect<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    start: *const u8,
+    expect: ToplevelExpect<'_>,
+    snapshot_mode: SnapshotMode,
+) -> std::io::Result<Option<bool>> {
+    // we always run programs as the host
+    let target = target_lexicon::Triple::host().into();
+
+    let frame = ExpectFrame::at_offset(start, ExpectSequence::START_OFFSET);
+    let module_id = expect.symbol.module_id();
+
+    if frame.module_id != module_id || !expect.region.contains(&frame.region) {
+        return Ok(None);
+    }
+
+    let data = expectations.get_mut(&module_id).unwrap();
+
+    let Some(lookups) = data.expectations.get(&frame.region) else {
+        return Ok(None);
+    };
+
+    let symbols = split_expect_lookups(&data.subs, lookups);
+
+    match symbols.as_slice() {
+        [symbol] if symbol.as_str(interns) == SNAPSHOT_LOOKUP => {}
+        _ => return Ok(None),
+    }
+
+    let (_, expressions, _) = crate::get_values(
+        target,
+        arena,
+        &data.subs,
+        interns,
+        layout_interner,
+        start,
+        frame.start_offset,
+        1,
+    );
+
+    // It's an `Inspect.to_str`
+    let actual = match expressions[0] {
+        Expr::Str(StrLiteral::PlainLine(actual)) => actual,
+        _ => return Ok(None),
+    };
+
+    let filename = data.path.to_owned();
+    let source = &data.source;
+    let snapshot_range = frame.region.start().offset as usize..frame.region.end().offset as usize;
+    let Some(snapshot_source) = source.get(snapshot_range) else {
+        return Ok(None);
+    };
+
+    match check_snapshot(&data.path, snapshot_source, actual, snapshot_mode)? {
+        SnapshotOutcome::Changed { expected } => {
+            let renderer =
+                Renderer::new(arena, interns, render_target, module_id, filename, source);
+
+            renderer.render_snapshot_mismatch(writer, &expected, actual, expect.region)?;
+
+            // Structured reports already end their lines
+            if !render_target.is_structured() {
+                writeln!(writer)?;
+            }
+
+            Ok(Some(false))
+        }
+        SnapshotOutcome::Matched | SnapshotOutcome::Recorded | SnapshotOutcome::Missing => {
+            Ok(Some(true))
+        }
+    }
+}
+
 pub fn render_expects_in_memory<'a>(
     writer: &mut impl std::io::Write,
     arena: &'a Bump,
@@ -252,7 +856,7 @@ pub fn render_expects_in_memory<'a>(
 
     let data = expectations.get_mut(&module_id).unwrap();
     let filename = data.path.to_owned();
-    let source = std::fs::read_to_string(&data.path).unwrap();
+    let source = data.source.clone();
 
     let renderer = Renderer::new(
         arena,
@@ -406,6 +1010,15 @@ impl ExpectSequence {
             std::ptr::write_unaligned(ptr.add(Self::LOCK_INDEX), 0);
         }
     }
+
+    /// Until the next [Self::reset], failed expects compute the values they would report, but
+    /// don't write them here. Generated code takes an offset of 0 to mean that.
+    fn discard_values(&mut self) {
+        unsafe {
+            let ptr = self.ptr as *mut usize;
+            std::ptr::write_unaligned(ptr.add(Self::OFFSET_INDEX), 0);
+        }
+    }
 }
 
 pub enum ChildProcessMsg {
@@ -520,6 +1133,9 @@ pub fn expect_mono_module_to_dylib<'a>(
         procedures,
     );
 
+    // Only defined when the procedures were instrumented for `roc test --coverage`
+    add_coverage_hit(&env);
+
     let mut modules_expects: MutMap<ModuleId, ExpectFunctions> = MutMap::default();
 
     for (module_id, expects) in toplevel_expects.into_iter() {
@@ -0,0 +1,218 @@
+//! Snapshot expects, like `expect snapshot(parse("1 + 2"))`, which pass as long as the `Inspect`
+//! rendering of their value is the same as the one recorded in their snapshot file.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// What to do with the snapshot files of snapshot expects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotMode {
+    /// Compare values to their snapshots, and record the snapshots that don't exist yet.
+    #[default]
+    Record,
+    /// Record every value, whether or not it changed: `roc test --update-snapshots`.
+    Update,
+    /// Compare values to the snapshots that exist, without ever writing any. For editors, which
+    /// shouldn't create files behind people's backs.
+    Verify,
+}
+
+/// How the value of a snapshot expect compared to its snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    Matched,
+    /// There was no snapshot yet, or it was [updated](SnapshotMode::Update).
+    Recorded,
+    /// There was no snapshot, and none was recorded.
+    Missing,
+    Changed {
+        expected: String,
+    },
+}
+
+/// Where the snapshot of the expect with `source`, i.e. the `snapshot(...)` after `expect`, in the
+/// module at `module_path` lives. For `src/Parser.roc`, that's `src/snapshots/Parser-<hash>.snap`,
+/// where the hash is of the source, so that moving the expect around doesn't lose its snapshot.
+pub fn snapshot_path(module_path: &Path, source: &str) -> PathBuf {
+    let stem = module_path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let dir = module_path.parent().unwrap_or_else(|| Path::new(""));
+
+    dir.join("snapshots")
+        .join(format!("{stem}-{:016x}.snap", fnv1a(&normalize(source))))
+}
+
+/// Compares `actual` to the snapshot of the expect with `source`, recording it as `mode` says.
+pub fn check_snapshot(
+    module_path: &Path,
+    source: &str,
+    actual: &str,
+    mode: SnapshotMode,
+) -> std::io::Result<SnapshotOutcome> {
+    let path = snapshot_path(module_path, source);
+
+    let expected = match std::fs::read_to_string(&path) {
+        Ok(contents) => Some(parse_snapshot(&contents).to_string()),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+
+    match (expected, mode) {
+        (Some(expected), _) if expected == actual => Ok(SnapshotOutcome::Matched),
+        (Some(expected), SnapshotMode::Record | SnapshotMode::Verify) => {
+            Ok(SnapshotOutcome::Changed { expected })
+        }
+        (None, SnapshotMode::Verify) => Ok(SnapshotOutcome::Missing),
+        (_, SnapshotMode::Record | SnapshotMode::Update) => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, render_snapshot(source, actual))?;
+
+            Ok(SnapshotOutcome::Recorded)
+        }
+    }
+}
+
+/// Snapshot files start with the expect they belong to, which is only there for people to read.
+fn render_snapshot(source: &str, value: &str) -> String {
+    format!("# {}\n{value}\n", normalize(source))
+}
+
+fn parse_snapshot(contents: &str) -> &str {
+    let value = match contents.split_once('\n') {
+        Some((header, value)) if header.starts_with("# ") => value,
+        _ => contents,
+    };
+
+    value.strip_suffix('\n').unwrap_or(value)
+}
+
+/// The source of an expect, on one line, so that re-indenting it doesn't lose its snapshot. The
+/// whitespace in string and character literals is part of the value, so it's kept as it is.
+fn normalize(source: &str) -> String {
+    let mut normalized = String::with_capacity(source.len());
+    let mut rest = source.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+
+            if !rest.is_empty() {
+                normalized.push(' ');
+            }
+        } else if c == '"' || c == '\'' {
+            let len = literal_len(rest);
+
+            normalized.push_str(&rest[..len]);
+            rest = &rest[len..];
+        } else {
+            normalized.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    normalized
+}
+
+/// The length of the string or character literal that `source` starts with, including its quotes.
+/// That's all of `source` if the literal is never closed.
+fn literal_len(source: &str) -> usize {
+    let quote = if source.starts_with("\"\"\"") {
+        "\"\"\""
+    } else {
+        &source[..1]
+    };
+    let mut index = quote.len();
+
+    while let Some(c) = source[index..].chars().next() {
+        if c == '\\' {
+            // The escaped character can't close the literal
+            index += 1 + source[index + 1..].chars().next().map_or(0, char::len_utf8);
+        } else if source[index..].starts_with(quote) {
+            return index + quote.len();
+        } else {
+            index += c.len_utf8();
+        }
+    }
+
+    source.len()
+}
+
+/// FNV-1a, which unlike [std::hash::Hasher]s is the same on every machine and Rust version.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reindenting_keeps_the_snapshot() {
+        let path = Path::new("src/Parser.roc");
+
+        assert_eq!(
+            snapshot_path(path, "snapshot (parse \"1 + 2\")"),
+            snapshot_path(path, "snapshot (parse\n    \"1 + 2\")")
+        );
+        assert!(snapshot_path(path, "snapshot(x)")
+            .to_string_lossy()
+            .starts_with("src/snapshots/Parser-"));
+    }
+
+    #[test]
+    fn whitespace_in_strings_changes_the_snapshot() {
+        let path = Path::new("src/Parser.roc");
+
+        assert_ne!(
+            snapshot_path(path, "snapshot(parse(\"a  b\"))"),
+            snapshot_path(path, "snapshot(parse(\"a b\"))")
+        );
+        assert_ne!(
+            snapshot_path(path, "snapshot(parse(\"\"\"a \" \n  b\"\"\"))"),
+            snapshot_path(path, "snapshot(parse(\"\"\"a \" \n b\"\"\"))")
+        );
+        assert_eq!(
+            normalize("snapshot (\n    parse \"a \\\"  b\"  )\n"),
+            "snapshot ( parse \"a \\\"  b\" )"
+        );
+    }
+
+    #[test]
+    fn record_then_compare() {
+        let dir = tempfile::tempdir().unwrap();
+        let module_path = dir.path().join("Parser.roc");
+        let check = |actual, mode| check_snapshot(&module_path, "snapshot(x)", actual, mode);
+
+        assert_eq!(
+            check("1", SnapshotMode::Verify).unwrap(),
+            SnapshotOutcome::Missing
+        );
+        assert_eq!(
+            check("1", SnapshotMode::Record).unwrap(),
+            SnapshotOutcome::Recorded
+        );
+        assert_eq!(
+            check("1", SnapshotMode::Record).unwrap(),
+            SnapshotOutcome::Matched
+        );
+        assert_eq!(
+            check("2", SnapshotMode::Record).unwrap(),
+            SnapshotOutcome::Changed {
+                expected: "1".to_string()
+            }
+        );
+        assert_eq!(
+            check("2", SnapshotMode::Update).unwrap(),
+            SnapshotOutcome::Recorded
+        );
+        assert_eq!(
+            check("2", SnapshotMode::Verify).unwrap(),
+            SnapshotOutcome::Matched
+        );
+    }
+}
@@ -145,6 +145,208 @@ fn standalone_annotation() {
     assert!(matches!(action, ReplAction::Nothing));
 }
 
+#[test]
+fn type_without_evaluating() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, "5 : Num *");
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let action = state.step(&arena, ":type x + 1", target, DEFAULT_PALETTE);
+
+    match action {
+        ReplAction::Type {
+            opt_output: Some(output),
+            problems,
+        } => {
+            assert!(problems.is_empty());
+            assert_eq!(output.expr, "x + 1");
+            assert_eq!(output.expr_type, "Num *");
+        }
+        _ => {
+            panic!("Unexpected action: {:?}", action);
+        }
+    }
+}
+
+#[test]
+fn defs_until_reset() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, "5 : Num *");
+    complete("greeting = \"hi\"", &mut state, "\"hi\" : Str");
+
+    assert_eq!(list_defs(&mut state), ["x : Num *", "greeting : Str"]);
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let action = state.step(&arena, ":reset", target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::Nothing));
+
+    assert!(list_defs(&mut state).is_empty());
+}
+
+#[test]
+fn load_problems() {
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let mut state = ReplState::new();
+
+    let action = state.step(&arena, ":load notes.txt", target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::FileProblem { .. }));
+
+    let action = state.step(&arena, ":load ../Parser.roc", target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::LoadProblem { .. }));
+
+    let action = state.step(&arena, ":load src/Parser.roc", target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::LoadProblem { .. }));
+}
+
+#[test]
+fn load_and_reload() {
+    // Loaded modules have to be inside the current directory
+    let filename = std::path::Path::new("ReplLoadAndReload.roc");
+    let greet = indoc!(
+        r#"
+        module [greet]
+
+        greet = |name| Str.concat("Hi, ", name)
+        "#
+    );
+    std::fs::write(filename, greet).unwrap();
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let mut state = ReplState::new();
+
+    let action = state.step(
+        &arena,
+        ":load ReplLoadAndReload.roc",
+        target,
+        DEFAULT_PALETTE,
+    );
+    assert!(matches!(action, ReplAction::Nothing));
+
+    complete("greet(\"Sam\")", &mut state, "\"Hi, Sam\" : Str");
+
+    let greet_and_farewell = indoc!(
+        r#"
+        module [greet, farewell]
+
+        greet = |name| Str.concat("Hi, ", name)
+
+        farewell = |name| Str.concat("Bye, ", name)
+        "#
+    );
+    std::fs::write(filename, greet_and_farewell).unwrap();
+
+    let action = state.step(&arena, ":reload", target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::Nothing));
+
+    complete("farewell(\"Sam\")", &mut state, "\"Bye, Sam\" : Str");
+
+    std::fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn import_from_unknown_package() {
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let mut state = ReplState::new();
+    let action = state.step(&arena, "import json.Decode", target, DEFAULT_PALETTE);
+
+    match action {
+        ReplAction::Eval {
+            opt_mono: None,
+            problems,
+        } => {
+            assert!(problems.errors[0].contains("UNRECOGNIZED PACKAGE"));
+        }
+        _ => {
+            panic!("Unexpected action: {:?}", action);
+        }
+    }
+
+    // The import that failed isn't kept around to fail again
+    complete("1 + 1", &mut state, "2 : Num *");
+}
+
+#[test]
+fn completions_and_hints() {
+    let mut state = ReplState::new();
+
+    complete(
+        "user = { name: \"Sam\", age: 30 }",
+        &mut state,
+        "{ age: 30, name: \"Sam\" } : { age : Num *, name : Str }",
+    );
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let completions = |line: &str| state.complete(&arena, line, line.len(), target);
+
+    assert_eq!(completions("Li"), (0, vec!["List".to_string()]));
+    assert_eq!(completions("Arb"), (0, vec!["Arbitrary".to_string()]));
+    assert_eq!(completions("us"), (0, vec!["user".to_string()]));
+    assert_eq!(completions("1 + user.n"), (9, vec!["name".to_string()]));
+    assert!(completions("List.ma").1.contains(&"map".to_string()));
+    assert_eq!(completions("nope.n"), (5, Vec::new()));
+
+    assert_eq!(
+        state.hint(&arena, "user", 4, target),
+        Some("{ age : Num *, name : Str }".to_string())
+    );
+    assert_eq!(state.hint(&arena, "use", 3, target), None);
+    assert_eq!(state.hint(&arena, "user", 2, target), None);
+}
+
+#[test]
+fn save_and_open() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, "5 : Num *");
+    complete("greeting = \"hi\"", &mut state, "\"hi\" : Str");
+    complete("x = 6", &mut state, "6 : Num *");
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let filename = std::env::temp_dir().join("ReplSaveAndOpen.roc");
+    let save = format!(":save {}", filename.display());
+    let action = state.step(&arena, &save, target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::Nothing));
+
+    let saved = std::fs::read_to_string(&filename).unwrap();
+    assert_eq!(
+        saved,
+        "module [greeting, x]\n\ngreeting = \"hi\"\n\nx = 6\n"
+    );
+
+    let mut opened = ReplState::new();
+    let open = format!(":open {}", filename.display());
+    let action = opened.step(&arena, &open, target, DEFAULT_PALETTE);
+    assert!(matches!(action, ReplAction::Nothing));
+
+    assert_eq!(list_defs(&mut opened), ["greeting : Str", "x : Num *"]);
+
+    std::fs::remove_file(filename).unwrap();
+}
+
+fn list_defs(state: &mut ReplState) -> Vec<String> {
+    let arena = Bump::new();
+    let target = Triple::host().into();
+
+    match state.step(&arena, ":defs", target, DEFAULT_PALETTE) {
+        ReplAction::Defs(defs) => defs
+            .into_iter()
+            .map(|def| format!("{} : {}", def.expr, def.expr_type))
+            .collect(),
+        action => {
+            panic!("Unexpected action: {:?}", action);
+        }
+    }
+}
+
 /// validate and step the given input, then check the Result vs the output
 /// with ANSI escape codes stripped.
 fn complete(input: &str, state: &mut ReplState, expected_start: &str) {
@@ -158,7 +360,7 @@ fn complete(input: &str, state: &mut ReplState, expected_start: &str) {
 
     match action {
         ReplAction::Eval { opt_mono, problems } => {
-            let string = evaluate(opt_mono, problems, target);
+            let string = evaluate(opt_mono, problems, target, None);
             let escaped =
                 std::string::String::from_utf8(strip_ansi_escapes::strip(string.trim()).unwrap())
                     .unwrap();
@@ -194,7 +396,7 @@ fn error(input: &str, state: &mut ReplState, expected_step_result: String) {
 
     match action {
         ReplAction::Eval { opt_mono, problems } => {
-            let string = evaluate(opt_mono, problems, target);
+            let string = evaluate(opt_mono, problems, target, None);
             let escaped =
                 std::string::String::from_utf8(strip_ansi_escapes::strip(string.trim()).unwrap())
                     .unwrap();
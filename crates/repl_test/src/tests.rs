@@ -1599,6 +1599,11 @@ fn interpolation_with_nested_interpolation() {
                   - ctrl-v + ctrl-j makes a newline
                   - :q quits
                   - :help shows this text again
+                  - :type followed by an expression shows its type without running it
+                  - :load followed by a .roc file brings what it exposes into scope, and :reload picks up changes to it
+                  - :defs lists your definitions, and :reset forgets them
+                  - tab completes names, module members like List.map, and record fields
+                  - :save followed by a .roc file writes your definitions to it as a module, and :open reads them back
             "#
         ),
         // TODO figure out why the tests prints the repl help text at the end, but only after syntax errors or something?
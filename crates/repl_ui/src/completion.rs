@@ -0,0 +1,65 @@
+//! What the REPL suggests for the word before the cursor: builtin modules and their members, defs
+//! entered so far, and the fields of records. Modeled on the language server's completions.
+
+use bumpalo::Bump;
+use roc_load::BUILTIN_MODULES;
+use roc_parse::ast::{Header, SpacesBefore};
+use roc_parse::header::parse_header;
+use roc_parse::state::State;
+
+/// Where the word that ends at `pos` starts, like `List.ma` in `List.ma|p`. Words are names, and
+/// the module names and record fields in front of them.
+pub fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '.' | '!'))
+        .last()
+        .map_or(pos, |(index, _)| index)
+}
+
+pub fn builtin_module_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN_MODULES.iter().map(|(_, name)| *name)
+}
+
+/// What the builtin module named `module_name` exposes, or nothing if there's no such module
+pub fn builtin_members(module_name: &str) -> Vec<String> {
+    let Some((module_id, _)) = BUILTIN_MODULES
+        .iter()
+        .find(|(_, name)| *name == module_name)
+    else {
+        return Vec::new();
+    };
+
+    let arena = Bump::new();
+    let src = roc_builtins::roc::module_source(*module_id);
+
+    match parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            SpacesBefore {
+                item: Header::Module(header),
+                ..
+            },
+            _,
+        )) => header
+            .exposes
+            .items
+            .iter()
+            .map(|name| name.value.item().as_str().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The `candidates` that start with `prefix`, sorted and without duplicates
+pub fn matching<I: IntoIterator<Item = String>>(candidates: I, prefix: &str) -> Vec<String> {
+    let mut matching: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+
+    matching.sort();
+    matching.dedup();
+
+    matching
+}
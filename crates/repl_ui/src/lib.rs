@@ -1,7 +1,9 @@
 //! UI functionality, shared between CLI and web, for the Read-Evaluate-Print-Loop (REPL).
 // We don't do anything here related to the terminal (doesn't exist on the web) or LLVM (too big for the web).
 pub mod colors;
+pub mod completion;
 pub mod repl_state;
+pub mod session;
 
 use bumpalo::Bump;
 use colors::{CYAN, END_COL, GREEN};
@@ -10,6 +12,7 @@ use repl_state::{parse_src, ParseOutcome};
 use roc_parse::ast::{Expr, ExtractSpaces, ValueDef};
 use roc_repl_eval::gen::{Problems, ReplOutput};
 use roc_reporting::report::StyleCodes;
+use std::path::Path;
 
 // TODO add link to repl tutorial (does not yet exist).
 pub const TIPS: &str = concatcp!(
@@ -48,6 +51,53 @@ pub const TIPS: &str = concatcp!(
             ":help",
             END_COL,
             " shows this text again\n",
+            CYAN,
+            "  - ",
+            END_COL,
+            GREEN,
+            ":type",
+            END_COL,
+            " followed by an expression shows its type without running it\n",
+            CYAN,
+            "  - ",
+            END_COL,
+            GREEN,
+            ":load",
+            END_COL,
+            " followed by a .roc file brings what it exposes into scope, and ",
+            GREEN,
+            ":reload",
+            END_COL,
+            " picks up changes to it\n",
+            CYAN,
+            "  - ",
+            END_COL,
+            GREEN,
+            ":defs",
+            END_COL,
+            " lists your definitions, and ",
+            GREEN,
+            ":reset",
+            END_COL,
+            " forgets them\n",
+            CYAN,
+            "  - ",
+            END_COL,
+            GREEN,
+            "tab",
+            END_COL,
+            " completes names, module members like List.map, and record fields\n",
+            CYAN,
+            "  - ",
+            END_COL,
+            GREEN,
+            ":save",
+            END_COL,
+            " followed by a .roc file writes your definitions to it as a module, and ",
+            GREEN,
+            ":open",
+            END_COL,
+            " reads them back\n",
         )
     }
 );
@@ -84,9 +134,17 @@ pub fn is_incomplete(input: &str) -> bool {
                 false
             }
         }
-        ParseOutcome::Empty | ParseOutcome::Help | ParseOutcome::Exit | ParseOutcome::SyntaxErr => {
-            false
-        }
+        ParseOutcome::Empty
+        | ParseOutcome::Help
+        | ParseOutcome::Exit
+        | ParseOutcome::SyntaxErr
+        | ParseOutcome::Type(_)
+        | ParseOutcome::Load(_)
+        | ParseOutcome::Reload
+        | ParseOutcome::Save(_)
+        | ParseOutcome::Open(_)
+        | ParseOutcome::Defs
+        | ParseOutcome::Reset => false,
     }
 }
 
@@ -130,3 +188,27 @@ pub fn format_output(
 
     buf
 }
+
+/// The defs listed by `:defs`, one per line, like `x : Num *`
+pub fn format_defs(style_codes: StyleCodes, defs: Vec<ReplOutput>) -> String {
+    let mut buf = String::new();
+
+    for ReplOutput { expr, expr_type } in defs {
+        buf.push('\n');
+        buf.push_str(&expr);
+
+        // Defs without a type of their own, like type aliases, are listed as they were entered
+        if !expr_type.is_empty() {
+            buf.push_str(style_codes.green);
+            buf.push_str(" : ");
+            buf.push_str(style_codes.reset);
+            buf.push_str(&expr_type);
+        }
+    }
+
+    buf
+}
+
+pub fn format_load_problem(filename: &Path, problem: &str) -> String {
+    format!("\nI can't load {}. {problem}\n", filename.display())
+}
@@ -1,28 +1,46 @@
-use std::path::PathBuf;
-use std::{fs, io};
+use std::path::{Component, Path, PathBuf};
+use std::{env, fs, io};
 
 use bumpalo::Bump;
 use roc_collections::MutSet;
 use roc_load::MonomorphizedModule;
-use roc_parse::ast::{Defs, Expr, Pattern, StrLiteral, TypeDef, TypeHeader, ValueDef};
+use roc_parse::ast::{
+    Defs, Expr, Header, Pattern, SpacesBefore, StrLiteral, TypeDef, TypeHeader, ValueDef,
+};
 use roc_parse::expr::parse_repl_defs_and_optional_expr;
+use roc_parse::header::parse_header;
 use roc_parse::parser::EWhen;
 use roc_parse::parser::{EClosure, EExpr, EPattern};
 use roc_parse::state::State;
 use roc_region::all::Loc;
-use roc_repl_eval::gen::{compile_to_mono, Problems};
-use roc_reporting::report::Palette;
+use roc_repl_eval::gen::{
+    check, compile_to_mono, def_fields, def_type, Problems, ReplOutput, REPL_OUTPUT,
+};
+use roc_reporting::report::{Palette, DEFAULT_PALETTE};
 use roc_target::Target;
 
+use crate::completion::{builtin_members, builtin_module_names, matching, word_start};
+use crate::session::{module_defs, module_src};
+
 #[derive(Debug, Clone, PartialEq)]
 enum PastDef {
-    Def { ident: String, src: String },
+    Def {
+        ident: String,
+        src: String,
+    },
     Import(String),
+    /// A module brought into scope with `:load`, and the import that does it
+    Load {
+        filename: PathBuf,
+        src: String,
+    },
 }
 
 pub struct ReplState {
     past_defs: Vec<PastDef>,
     past_def_idents: MutSet<String>,
+    /// The app whose packages, including its platform, modules can be imported from
+    opt_app_path: Option<PathBuf>,
 }
 
 impl Default for ReplState {
@@ -38,12 +56,25 @@ pub enum ReplAction<'a> {
         opt_mono: Option<MonomorphizedModule<'a>>,
         problems: Problems,
     },
+    /// The type of the expr after `:type`, which doesn't get evaluated
+    Type {
+        opt_output: Option<ReplOutput>,
+        problems: Problems,
+    },
+    /// The defs listed by `:defs`. Ones whose type can't be shown on its own, like type aliases
+    /// and defs with errors, have an empty `expr_type` and their whole source as the `expr`.
+    Defs(Vec<ReplOutput>),
     Exit,
     Help,
     FileProblem {
         filename: PathBuf,
         error: io::ErrorKind,
     },
+    /// A file that `:load` can't bring into scope, and why
+    LoadProblem {
+        filename: PathBuf,
+        problem: String,
+    },
     Nothing,
 }
 
@@ -52,6 +83,16 @@ impl ReplState {
         Self {
             past_defs: Default::default(),
             past_def_idents: Default::default(),
+            opt_app_path: None,
+        }
+    }
+
+    /// A REPL that can import modules from the packages of the app at `app_path`, like
+    /// `import json.Decode`
+    pub fn with_app(app_path: PathBuf) -> Self {
+        Self {
+            opt_app_path: Some(app_path),
+            ..Self::new()
         }
     }
 
@@ -66,6 +107,42 @@ impl ReplState {
         let src: &str = match parse_src(arena, line) {
             ParseOutcome::Empty | ParseOutcome::Help => return ReplAction::Help,
             ParseOutcome::Exit => return ReplAction::Exit,
+            ParseOutcome::Type("")
+            | ParseOutcome::Load("")
+            | ParseOutcome::Save("")
+            | ParseOutcome::Open("") => return ReplAction::Help,
+            ParseOutcome::Type(expr) => {
+                let (opt_loaded, problems) = check(
+                    arena,
+                    self.past_def_srcs(),
+                    expr,
+                    target,
+                    palette,
+                    self.opt_app_path.clone(),
+                );
+                let opt_output = opt_loaded
+                    .and_then(|mut loaded| def_type(&mut loaded, REPL_OUTPUT))
+                    .map(|expr_type| ReplOutput {
+                        expr: expr.to_string(),
+                        expr_type,
+                    });
+
+                return ReplAction::Type {
+                    opt_output,
+                    problems,
+                };
+            }
+            ParseOutcome::Defs => return self.defs(arena, target, palette),
+            ParseOutcome::Load(filename) => return self.load(arena, PathBuf::from(filename)),
+            ParseOutcome::Reload => return self.reload(arena),
+            ParseOutcome::Save(filename) => return self.save(arena, PathBuf::from(filename)),
+            ParseOutcome::Open(filename) => return self.open(arena, PathBuf::from(filename)),
+            ParseOutcome::Reset => {
+                self.past_defs.clear();
+                self.past_def_idents.clear();
+
+                return ReplAction::Nothing;
+            }
             ParseOutcome::Incomplete | ParseOutcome::SyntaxErr => {
                 pending_past_def = None;
 
@@ -198,7 +275,29 @@ impl ReplState {
                                 }
                                 ValueDef::ModuleImport(import) => match import.name.value.package {
                                     Some(_) => {
-                                        todo!("handle importing a module from a package")
+                                        let src = line[vd.byte_range()].to_string();
+
+                                        // Check that the package and module exist before we add the
+                                        // import to past defs, for the same reason as below.
+                                        let (_, problems) = check(
+                                            arena,
+                                            self.past_def_srcs().chain([src.as_str()]),
+                                            "{}",
+                                            target,
+                                            palette,
+                                            self.opt_app_path.clone(),
+                                        );
+
+                                        if !problems.errors.is_empty() {
+                                            return ReplAction::Eval {
+                                                opt_mono: None,
+                                                problems,
+                                            };
+                                        }
+
+                                        self.past_defs.push(PastDef::Import(src));
+
+                                        return ReplAction::Nothing;
                                     }
                                     None => {
                                         let mut filename = PathBuf::new();
@@ -259,13 +358,11 @@ impl ReplState {
 
         let (opt_mono, problems) = compile_to_mono(
             arena,
-            self.past_defs.iter().map(|past_def| match past_def {
-                PastDef::Def { ident: _, src } => src.as_str(),
-                PastDef::Import(src) => src.as_str(),
-            }),
+            self.past_def_srcs(),
             src,
             target,
             palette,
+            self.opt_app_path.clone(),
         );
 
         if let Some((ident, src)) = pending_past_def {
@@ -282,6 +379,422 @@ impl ReplState {
 
         self.past_defs.push(PastDef::Def { ident, src });
     }
+
+    fn past_def_srcs(&self) -> impl Iterator<Item = &str> {
+        self.past_defs.iter().map(|past_def| match past_def {
+            PastDef::Def { ident: _, src } => src.as_str(),
+            PastDef::Import(src) => src.as_str(),
+            PastDef::Load { filename: _, src } => src.as_str(),
+        })
+    }
+
+    /// Lists the defs entered so far, with their types. A def that was entered more than once is
+    /// listed where it was last entered, since that's the one in scope.
+    fn defs<'a>(&self, arena: &'a Bump, target: Target, palette: Palette) -> ReplAction<'a> {
+        // Checking `{}` type-checks all the past defs, without any problems of its own
+        let (mut opt_loaded, _) = check(
+            arena,
+            self.past_def_srcs(),
+            "{}",
+            target,
+            palette,
+            self.opt_app_path.clone(),
+        );
+        let mut listed = MutSet::default();
+        let mut defs = Vec::new();
+
+        for past_def in self.past_defs.iter().rev() {
+            let PastDef::Def { ident, src } = past_def else {
+                continue;
+            };
+
+            if !listed.insert(ident) {
+                continue;
+            }
+
+            let opt_type = opt_loaded
+                .as_mut()
+                .and_then(|loaded| def_type(loaded, ident));
+
+            defs.push(match opt_type {
+                Some(expr_type) => ReplOutput {
+                    expr: ident.clone(),
+                    expr_type,
+                },
+                None => ReplOutput {
+                    expr: src.clone(),
+                    expr_type: String::new(),
+                },
+            });
+        }
+
+        defs.reverse();
+
+        ReplAction::Defs(defs)
+    }
+
+    /// Brings the defs that the module in `filename` exposes into scope, like an `import` that
+    /// exposes all of them would.
+    fn load<'a>(&mut self, arena: &Bump, filename: PathBuf) -> ReplAction<'a> {
+        let src = match load_import(arena, &filename) {
+            Ok(src) => src,
+            Err(action) => return action,
+        };
+
+        // Loading a file again is the same as reloading it
+        self.past_defs.retain(|past_def| {
+            !matches!(past_def, PastDef::Load { filename: loaded, .. } if *loaded == filename)
+        });
+        self.past_defs.push(PastDef::Load { filename, src });
+
+        ReplAction::Nothing
+    }
+
+    /// Loaded files are read again every time something is evaluated, so reloading them only has
+    /// to pick up changes to what they expose. Files that can no longer be loaded are dropped.
+    fn reload<'a>(&mut self, arena: &Bump) -> ReplAction<'a> {
+        let mut problem = None;
+
+        self.past_defs.retain_mut(|past_def| match past_def {
+            PastDef::Load { filename, src } => match load_import(arena, filename) {
+                Ok(new_src) => {
+                    *src = new_src;
+
+                    true
+                }
+                Err(action) => {
+                    problem.get_or_insert(action);
+
+                    false
+                }
+            },
+            PastDef::Def { .. } | PastDef::Import(_) => true,
+        });
+
+        problem.unwrap_or(ReplAction::Nothing)
+    }
+
+    /// Writes the defs entered so far to `filename`, as a module that exposes them. Only the
+    /// last of a def that was entered more than once is written, since that's the one in scope.
+    fn save<'a>(&self, arena: &Bump, filename: PathBuf) -> ReplAction<'a> {
+        if filename.extension().and_then(|ext| ext.to_str()) != Some("roc") {
+            return ReplAction::FileProblem {
+                filename,
+                error: io::ErrorKind::Unsupported,
+            };
+        }
+
+        let mut annotated = MutSet::default();
+        let mut defined = MutSet::default();
+        let mut imports = Vec::new();
+        let mut defs = Vec::new();
+
+        for past_def in self.past_defs.iter().rev() {
+            match past_def {
+                PastDef::Def { ident, src } => {
+                    // A standalone annotation is kept along with the body that comes after it
+                    let is_latest = if is_annotation(arena, src) {
+                        annotated.insert(ident)
+                    } else {
+                        defined.insert(ident)
+                    };
+
+                    if is_latest {
+                        defs.push((ident.as_str(), src.as_str()));
+                    }
+                }
+                PastDef::Import(src) | PastDef::Load { filename: _, src } => {
+                    if !imports.contains(&src.as_str()) {
+                        imports.push(src.as_str());
+                    }
+                }
+            }
+        }
+
+        imports.reverse();
+        defs.reverse();
+
+        let mut exposed = MutSet::default();
+        let exposes: Vec<&str> = defs
+            .iter()
+            .map(|(ident, _)| *ident)
+            .filter(|ident| exposed.insert(*ident))
+            .collect();
+        let srcs: Vec<&str> = defs.iter().map(|(_, src)| *src).collect();
+        let src = module_src(arena, &exposes, &imports, &srcs);
+
+        match fs::write(&filename, src) {
+            Ok(()) => ReplAction::Nothing,
+            Err(err) => ReplAction::FileProblem {
+                filename,
+                error: err.kind(),
+            },
+        }
+    }
+
+    /// Replaces the defs entered so far with the ones in the module in `filename`, like one
+    /// written by `:save`. Top-level `expect`s and `dbg`s aren't defs, so they're left out.
+    fn open<'a>(&mut self, arena: &Bump, filename: PathBuf) -> ReplAction<'a> {
+        if filename.extension().and_then(|ext| ext.to_str()) != Some("roc") {
+            return ReplAction::FileProblem {
+                filename,
+                error: io::ErrorKind::Unsupported,
+            };
+        }
+
+        let src = match fs::read_to_string(&filename) {
+            Ok(src) => arena.alloc_str(&src),
+            Err(err) => {
+                return ReplAction::FileProblem {
+                    filename,
+                    error: err.kind(),
+                }
+            }
+        };
+        let defs = match module_defs(arena, src) {
+            Ok(defs) => defs,
+            Err(problem) => {
+                return ReplAction::LoadProblem {
+                    filename,
+                    problem: problem.to_string(),
+                }
+            }
+        };
+
+        self.past_defs.clear();
+        self.past_def_idents.clear();
+
+        for def in defs.loc_defs() {
+            let (ident, def_src) = match def {
+                Ok(td) => match td.value {
+                    TypeDef::Alias { header, .. }
+                    | TypeDef::Opaque { header, .. }
+                    | TypeDef::Ability { header, .. } => (header.name.value, &src[td.byte_range()]),
+                },
+                Err(vd) => match vd.value {
+                    ValueDef::Annotation(
+                        Loc {
+                            value: Pattern::Identifier { ident },
+                            ..
+                        },
+                        _,
+                    )
+                    | ValueDef::Body(
+                        Loc {
+                            value: Pattern::Identifier { ident },
+                            ..
+                        },
+                        _,
+                    )
+                    | ValueDef::AnnotatedBody {
+                        body_pattern:
+                            Loc {
+                                value: Pattern::Identifier { ident },
+                                ..
+                            },
+                        ..
+                    } => (ident, &src[vd.byte_range()]),
+                    ValueDef::ModuleImport(_) | ValueDef::IngestedFileImport(_) => {
+                        self.past_defs
+                            .push(PastDef::Import(src[vd.byte_range()].to_string()));
+
+                        continue;
+                    }
+                    _ => continue,
+                },
+            };
+
+            self.add_past_def(ident.trim_end().to_string(), def_src.to_string());
+        }
+
+        ReplAction::Nothing
+    }
+
+    /// What the word before `pos` in `line` can be completed to, and where the part of it that
+    /// gets completed starts. Builtin modules and the defs entered so far complete names, and a
+    /// builtin module's members or a record's fields complete what comes after a `.`
+    pub fn complete(
+        &self,
+        arena: &Bump,
+        line: &str,
+        pos: usize,
+        target: Target,
+    ) -> (usize, Vec<String>) {
+        let start = word_start(line, pos);
+        let word = &line[start..pos];
+
+        match word.rsplit_once('.') {
+            None => {
+                let names = builtin_module_names()
+                    .map(str::to_string)
+                    .chain(self.past_def_idents.iter().cloned());
+
+                (start, matching(names, word))
+            }
+            Some((head, prefix)) => {
+                let members = if builtin_module_names().any(|name| name == head) {
+                    builtin_members(head)
+                } else {
+                    self.fields(arena, head, target)
+                };
+
+                (pos - prefix.len(), matching(members, prefix))
+            }
+        }
+    }
+
+    /// The fields of `expr`, like `user.address`, if it's a record or a tuple. Only exprs that start
+    /// with a def entered so far are checked, since anything else can't have fields in scope.
+    fn fields(&self, arena: &Bump, expr: &str, target: Target) -> Vec<String> {
+        let ident = expr.split('.').next().unwrap_or_default();
+
+        if !self.past_def_idents.contains(ident) {
+            return Vec::new();
+        }
+
+        let (opt_loaded, _) = check(
+            arena,
+            self.past_def_srcs(),
+            expr,
+            target,
+            DEFAULT_PALETTE,
+            self.opt_app_path.clone(),
+        );
+
+        opt_loaded
+            .map(|mut loaded| def_fields(&mut loaded, REPL_OUTPUT))
+            .unwrap_or_default()
+    }
+
+    /// The type of the name that ends at `pos` in `line`, like `List * -> U64` for `List.len`, if
+    /// the cursor is at the end of the line and it's a builtin or a def entered so far.
+    pub fn hint(&self, arena: &Bump, line: &str, pos: usize, target: Target) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let word = &line[word_start(line, pos)..pos];
+        let is_known = match word.split_once('.') {
+            None => self.past_def_idents.contains(word),
+            Some((module_name, member)) => builtin_members(module_name)
+                .iter()
+                .any(|name| name == member),
+        };
+
+        if !is_known {
+            return None;
+        }
+
+        let (opt_loaded, problems) = check(
+            arena,
+            self.past_def_srcs(),
+            word,
+            target,
+            DEFAULT_PALETTE,
+            self.opt_app_path.clone(),
+        );
+
+        if !problems.errors.is_empty() {
+            return None;
+        }
+
+        opt_loaded.and_then(|mut loaded| def_type(&mut loaded, REPL_OUTPUT))
+    }
+}
+
+/// The import that brings everything the module in `filename` exposes into scope, like
+/// `import Json.Decode exposing [decode, Decoder]` for `Json/Decode.roc`.
+fn load_import<'a>(arena: &Bump, filename: &Path) -> Result<String, ReplAction<'a>> {
+    let load_problem = |problem: &str| ReplAction::LoadProblem {
+        filename: filename.to_path_buf(),
+        problem: problem.to_string(),
+    };
+
+    if filename.extension().and_then(|ext| ext.to_str()) != Some("roc") {
+        return Err(ReplAction::FileProblem {
+            filename: filename.to_path_buf(),
+            error: io::ErrorKind::Unsupported,
+        });
+    }
+
+    // Modules are imported relative to the current directory, by a name made from their path
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|dir| filename.strip_prefix(dir).ok())
+        .unwrap_or(filename)
+        .with_extension("");
+    let mut parts = Vec::new();
+
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str().unwrap_or_default()),
+            Component::CurDir => {}
+            Component::Prefix(_) | Component::RootDir | Component::ParentDir => {
+                return Err(load_problem(
+                    "It has to be in the current directory, or in a directory inside it.",
+                ));
+            }
+        }
+    }
+
+    if !parts.iter().all(|part| is_module_name_part(part)) {
+        return Err(load_problem(
+            "Its path has to be a module name, like Json/Decode.roc for Json.Decode.",
+        ));
+    }
+
+    let src = fs::read_to_string(filename).map_err(|err| ReplAction::FileProblem {
+        filename: filename.to_path_buf(),
+        error: err.kind(),
+    })?;
+
+    let exposes: Vec<&str> = match parse_header(arena, State::new(src.as_bytes())) {
+        Ok((
+            SpacesBefore {
+                item: Header::Module(header),
+                ..
+            },
+            _,
+        )) => header
+            .exposes
+            .items
+            .iter()
+            .map(|name| name.value.item().as_str())
+            .collect(),
+        Ok(_) => {
+            return Err(load_problem(
+                "It has to be a module, not an app, package, or platform.",
+            ))
+        }
+        // The import will report the syntax error when it gets evaluated
+        Err(_) => Vec::new(),
+    };
+
+    Ok(format!(
+        "import {} exposing [{}]",
+        parts.join("."),
+        exposes.join(", ")
+    ))
+}
+
+fn is_annotation(arena: &Bump, src: &str) -> bool {
+    match parse_src(arena, src) {
+        ParseOutcome::DefsAndExpr(defs, None) => defs.loc_defs().all(|def| {
+            matches!(
+                def,
+                Err(Loc {
+                    value: ValueDef::Annotation(..),
+                    ..
+                })
+            )
+        }),
+        _ => false,
+    }
+}
+
+fn is_module_name_part(part: &str) -> bool {
+    part.starts_with(|c: char| c.is_ascii_uppercase())
+        && part.chars().all(|c| c.is_ascii_alphanumeric())
 }
 
 #[derive(Debug, PartialEq)]
@@ -292,6 +805,13 @@ pub enum ParseOutcome<'a> {
     Empty,
     Help,
     Exit,
+    Type(&'a str),
+    Load(&'a str),
+    Reload,
+    Save(&'a str),
+    Open(&'a str),
+    Defs,
+    Reset,
 }
 
 /// Special case some syntax errors to allow for multi-line inputs
@@ -307,6 +827,22 @@ fn parse_outcome_for_error(e: EExpr<'_>) -> ParseOutcome<'_> {
 }
 
 pub fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
+    if let Some(expr) = command_arg(line, ":type") {
+        return ParseOutcome::Type(expr);
+    }
+
+    if let Some(filename) = command_arg(line, ":load") {
+        return ParseOutcome::Load(filename);
+    }
+
+    if let Some(filename) = command_arg(line, ":save") {
+        return ParseOutcome::Save(filename);
+    }
+
+    if let Some(filename) = command_arg(line, ":open") {
+        return ParseOutcome::Open(filename);
+    }
+
     match line.trim().to_lowercase().as_str() {
         "" => ParseOutcome::Empty,
         ":help" => ParseOutcome::Help,
@@ -315,6 +851,9 @@ pub fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
         // If you really need to evaluate `exit` for some reason,
         // you can do `foo = exit` and then evaluate `foo` instead.
         ":exit" | ":quit" | ":q" | "exit" | "quit" | "exit()" | "quit()" => ParseOutcome::Exit,
+        ":reload" => ParseOutcome::Reload,
+        ":defs" => ParseOutcome::Defs,
+        ":reset" => ParseOutcome::Reset,
         _ => {
             let src_bytes = line.as_bytes();
 
@@ -331,3 +870,14 @@ pub fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
         }
     }
 }
+
+/// What comes after `command` in `line`, like `List.len` in `:type List.len`
+fn command_arg<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let arg = line.trim().strip_prefix(command)?;
+
+    if arg.is_empty() || arg.starts_with(char::is_whitespace) {
+        Some(arg.trim())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,68 @@
+//! `:save`, which writes the defs entered so far out as a module, and `:open`, which reads the defs
+//! of a module back in.
+
+use bumpalo::Bump;
+use roc_fmt::def::fmt_defs;
+use roc_fmt::header::fmt_header;
+use roc_fmt::{Buf, MigrationFlags};
+use roc_parse::ast::{Defs, Header, SpacesBefore};
+use roc_parse::header::{parse_header, parse_module_defs};
+use roc_parse::state::State;
+
+/// A module that exposes `exposes`, with `imports` and then `defs` after its header, formatted
+/// the way `roc format` would. If it doesn't parse, it's left as it was entered, so that nothing
+/// is lost.
+pub fn module_src(arena: &Bump, exposes: &[&str], imports: &[&str], defs: &[&str]) -> String {
+    let mut src = format!("module [{}]\n\n", exposes.join(", "));
+
+    if !imports.is_empty() {
+        src.push_str(&imports.join("\n"));
+        src.push_str("\n\n");
+    }
+
+    src.push_str(&defs.join("\n\n"));
+    src.push('\n');
+
+    let formatted = parse_module(arena, &src).map(|(header, defs)| {
+        let mut buf = Buf::new_in(
+            arena,
+            MigrationFlags {
+                snakify: false,
+                parens_and_commas: false,
+            },
+        );
+
+        fmt_header(&mut buf, &header);
+        fmt_defs(&mut buf, &defs, 0);
+        buf.fmt_end_of_file();
+
+        buf.as_str().to_string()
+    });
+
+    formatted.unwrap_or(src)
+}
+
+/// The defs of the module in `src`, or why they can't be read
+pub fn module_defs<'a>(arena: &'a Bump, src: &'a str) -> Result<Defs<'a>, &'static str> {
+    match parse_module(arena, src) {
+        Some((
+            SpacesBefore {
+                item: Header::Module(_),
+                ..
+            },
+            defs,
+        )) => Ok(defs),
+        Some(_) => Err("It has to be a module, not an app, package, or platform."),
+        None => Err("It has a syntax error, which roc check can show."),
+    }
+}
+
+fn parse_module<'a>(
+    arena: &'a Bump,
+    src: &'a str,
+) -> Option<(SpacesBefore<'a, Header<'a>>, Defs<'a>)> {
+    let (header, state) = parse_header(arena, State::new(src.as_bytes())).ok()?;
+    let defs = parse_module_defs(arena, state, Defs::default()).ok()?;
+
+    Some((header, defs))
+}
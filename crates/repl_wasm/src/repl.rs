@@ -12,7 +12,7 @@ use roc_repl_eval::{
     ReplApp, ReplAppMemory,
 };
 use roc_repl_ui::{
-    format_output,
+    format_defs, format_output,
     repl_state::{ReplAction, ReplState},
     TIPS,
 };
@@ -206,7 +206,15 @@ pub async fn entrypoint_from_js(src: String) -> String {
         ReplAction::FileProblem { .. } => {
             "The web version of the REPL cannot import files... for now!".to_string()
         }
+        ReplAction::LoadProblem { .. } => {
+            "The web version of the REPL cannot load files... for now!".to_string()
+        }
         ReplAction::Nothing => String::new(),
+        ReplAction::Type {
+            opt_output,
+            problems,
+        } => format_output(HTML_STYLE_CODES, opt_output, problems),
+        ReplAction::Defs(defs) => format_defs(HTML_STYLE_CODES, defs),
         ReplAction::Eval { opt_mono, problems } => {
             let opt_output = match opt_mono {
                 Some(mono) => eval_wasm(arena, target, mono).await,
@@ -3,10 +3,11 @@ use std::path::PathBuf;
 use roc_collections::MutMap;
 use roc_module::symbol::{Interns, ModuleId};
 use roc_problem::can::Problem;
-use roc_region::all::LineInfo;
+use roc_region::all::{LineInfo, Region};
 use roc_solve_problem::TypeError;
 
-use crate::report::ANSI_STYLE_CODES;
+use crate::diagnostic::{sarif_log, structured_report};
+use crate::report::{RenderTarget, ANSI_STYLE_CODES};
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Problems {
@@ -65,8 +66,59 @@ pub fn report_problems(
     interns: &Interns,
     can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
     type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    render: RenderTarget,
 ) -> Problems {
-    use crate::report::{can_problem, type_problem, Report, RocDocAllocator, DEFAULT_PALETTE};
+    use crate::report::{Report, DEFAULT_PALETTE};
+
+    let (problems, errors, warnings) =
+        render_problems(sources, interns, can_problems, type_problems, render);
+
+    if render.is_structured() {
+        print_structured(render, errors.iter().chain(warnings.iter()));
+
+        return problems;
+    }
+
+    let problems_reported;
+
+    // Only print warnings if there are no errors
+    if errors.is_empty() {
+        problems_reported = warnings.len();
+
+        for warning in warnings.iter() {
+            println!("\n{warning}\n");
+        }
+    } else {
+        problems_reported = errors.len();
+
+        for error in errors.iter() {
+            println!("\n{error}\n");
+        }
+    }
+
+    // If we printed any problems, print a horizontal rule at the end,
+    // and then clear any ANSI escape codes (e.g. colors) we've used.
+    //
+    // The horizontal rule is nice when running the program right after
+    // compiling it, as it lets you clearly see where the compiler
+    // errors/warnings end and the program output begins.
+    if problems_reported > 0 {
+        println!("{}\u{001B}[0m\n", Report::horizontal_rule(&DEFAULT_PALETTE));
+    }
+
+    problems
+}
+
+/// Renders the problems for `render` without printing them, so that they can be printed along
+/// with other reports. Gives the errors and the warnings separately.
+pub fn render_problems(
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    interns: &Interns,
+    can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
+    type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    render: RenderTarget,
+) -> (Problems, Vec<String>, Vec<String>) {
+    use crate::report::{can_problem, type_problem, RocDocAllocator, DEFAULT_PALETTE};
     use roc_problem::Severity::*;
 
     let palette = DEFAULT_PALETTE;
@@ -99,11 +151,13 @@ pub fn report_problems(
         let problems = type_problems.remove(home).unwrap_or_default();
 
         for problem in problems {
+            let region = problem.region().map(|region| lines.convert_region(region));
+
             if let Some(report) = type_problem(&alloc, &lines, module_path.clone(), problem) {
                 let severity = report.severity;
                 let mut buf = String::new();
 
-                report.render_color_terminal(&mut buf, &alloc, &palette);
+                report.render_with_regions(render, region, &[], &mut buf, &alloc, &palette);
 
                 match severity {
                     Warning => {
@@ -137,11 +191,18 @@ pub fn report_problems(
         ordered.extend(shadowing_errs);
 
         for problem in ordered.into_iter() {
+            let (region, related) = can_problem_regions(&problem);
+            let region = region.map(|region| lines.convert_region(region));
+            let related: Vec<_> = related
+                .into_iter()
+                .map(|region| lines.convert_region(region))
+                .collect();
+
             let report = can_problem(&alloc, &lines, module_path.clone(), problem);
             let severity = report.severity;
             let mut buf = String::new();
 
-            report.render_color_terminal(&mut buf, &alloc, &palette);
+            report.render_with_regions(render, region, &related, &mut buf, &alloc, &palette);
 
             match severity {
                 Warning => {
@@ -161,36 +222,39 @@ pub fn report_problems(
     debug_assert!(can_problems.is_empty() && type_problems.is_empty(), "After reporting problems, there were {:?} can_problems and {:?} type_problems that could not be reported because they did not have corresponding entries in `sources`.", can_problems.len(), type_problems.len());
     debug_assert_eq!(errors.len() + warnings.len(), total_problems);
 
-    let problems_reported;
+    let problems = Problems {
+        fatally_errored,
+        errors: errors.len(),
+        warnings: warnings.len(),
+    };
 
-    // Only print warnings if there are no errors
-    if errors.is_empty() {
-        problems_reported = warnings.len();
+    (problems, errors, warnings)
+}
 
-        for warning in warnings.iter() {
-            println!("\n{warning}\n");
+/// Prints reports rendered for tools: one per line for [RenderTarget::Json], or all of them in
+/// one log for [RenderTarget::Sarif], which is printed even if there aren't any.
+pub fn print_structured<'a>(render: RenderTarget, reports: impl IntoIterator<Item = &'a String>) {
+    match render {
+        RenderTarget::Sarif => {
+            println!("{}", sarif_log(reports.into_iter().map(String::as_str)));
         }
-    } else {
-        problems_reported = errors.len();
-
-        for error in errors.iter() {
-            println!("\n{error}\n");
+        _ => {
+            for report in reports {
+                println!("{}", structured_report(render, report));
+            }
         }
     }
+}
 
-    // If we printed any problems, print a horizontal rule at the end,
-    // and then clear any ANSI escape codes (e.g. colors) we've used.
-    //
-    // The horizontal rule is nice when running the program right after
-    // compiling it, as it lets you clearly see where the compiler
-    // errors/warnings end and the program output begins.
-    if problems_reported > 0 {
-        println!("{}\u{001B}[0m\n", Report::horizontal_rule(&palette));
-    }
-
-    Problems {
-        fatally_errored,
-        errors: errors.len(),
-        warnings: warnings.len(),
+/// The region a can problem is about, along with any others it mentions.
+fn can_problem_regions(problem: &Problem) -> (Option<Region>, Vec<Region>) {
+    match problem {
+        // `region` gives the original definition, but the problem is with the shadowing one
+        Problem::Shadowing {
+            original_region,
+            shadow,
+            ..
+        } => (Some(shadow.region), vec![*original_region]),
+        _ => (problem.region(), vec![]),
     }
 }
@@ -0,0 +1,232 @@
+//! Reports rendered as JSON, for tools like editors and code review bots that would otherwise
+//! have to scrape the text we print for people.
+//!
+//! [RenderTarget::Json](crate::report::RenderTarget::Json) renders each report as one line of
+//! JSON. [RenderTarget::Sarif](crate::report::RenderTarget::Sarif) renders each one as a SARIF
+//! 2.1.0 `result`, which [sarif_log] then wraps into a complete log.
+
+use std::path::{Path, PathBuf};
+
+use roc_problem::Severity;
+use roc_region::all::{LineColumn, LineColumnRegion};
+use serde_json::{json, Value};
+
+use crate::report::RenderTarget;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A report along with the regions it's about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub title: String,
+    pub file: PathBuf,
+    /// The region the report is about, if it's about a particular one.
+    pub region: Option<LineColumnRegion>,
+    /// Other regions the report mentions, e.g. the original definition of a shadowed name.
+    pub related: Vec<LineColumnRegion>,
+    /// The report's text, without its header.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// A single line of JSON, with 1-based lines and columns.
+    ///
+    /// ```json
+    /// {"severity":"warning","title":"UNUSED IMPORT","file":"main.roc","region":{"start":{"line":3,"column":1},"end":{"line":3,"column":11}},"related":[],"message":"..."}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let value = json!({
+            "severity": severity_name(self.severity),
+            "title": self.title,
+            "file": display_path(&self.file),
+            "region": self.region.map(region_json),
+            "related": self.related.iter().copied().map(region_json).collect::<Vec<_>>(),
+            "message": self.message,
+        });
+
+        value.to_string()
+    }
+
+    /// A SARIF `result` object, using the report's title as the rule.
+    pub fn to_sarif_result(&self) -> String {
+        let location = |region: Option<LineColumnRegion>| {
+            let mut physical_location = json!({
+                "artifactLocation": { "uri": display_path(&self.file) },
+            });
+
+            if let Some(region) = region {
+                physical_location["region"] = json!({
+                    "startLine": region.start.line + 1,
+                    "startColumn": region.start.column + 1,
+                    "endLine": region.end.line + 1,
+                    "endColumn": region.end.column + 1,
+                });
+            }
+
+            json!({ "physicalLocation": physical_location })
+        };
+
+        let related_locations: Vec<Value> = self
+            .related
+            .iter()
+            .enumerate()
+            .map(|(id, region)| {
+                let mut related = location(Some(*region));
+                related["id"] = json!(id);
+                related
+            })
+            .collect();
+
+        let value = json!({
+            "ruleId": self.title,
+            "level": match self.severity {
+                Severity::Warning => "warning",
+                Severity::RuntimeError | Severity::Fatal => "error",
+            },
+            "message": { "text": self.message },
+            "locations": [location(self.region)],
+            "relatedLocations": related_locations,
+        });
+
+        value.to_string()
+    }
+}
+
+/// Wraps the SARIF `result`s of reports rendered with
+/// [RenderTarget::Sarif](crate::report::RenderTarget::Sarif) into a complete SARIF log.
+pub fn sarif_log<'a>(results: impl IntoIterator<Item = &'a str>) -> String {
+    let results: Vec<Value> = results
+        .into_iter()
+        .map(|result| {
+            serde_json::from_str(result)
+                .unwrap_or_else(|_| text_report(RenderTarget::Sarif, result))
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "roc",
+                    "informationUri": "https://www.roc-lang.org",
+                },
+            },
+            "results": results,
+        }],
+    });
+
+    log.to_string()
+}
+
+/// Reports that are only ever rendered as text, like failing to read a file, have no title or
+/// region. This gives them the same shape as the reports rendered for `target`, with just a
+/// message. Reports that were rendered for `target` are left as they are.
+pub fn structured_report(target: RenderTarget, report: &str) -> String {
+    match serde_json::from_str::<Value>(report) {
+        Ok(_) => report.to_string(),
+        Err(_) => text_report(target, report).to_string(),
+    }
+}
+
+fn text_report(target: RenderTarget, text: &str) -> Value {
+    let message = text.trim();
+
+    match target {
+        RenderTarget::Sarif => json!({ "level": "error", "message": { "text": message } }),
+        _ => json!({
+            "severity": severity_name(Severity::Fatal),
+            "title": null,
+            "file": null,
+            "region": null,
+            "related": [],
+            "message": message,
+        }),
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::RuntimeError => "error",
+        Severity::Fatal => "fatal",
+    }
+}
+
+fn region_json(region: LineColumnRegion) -> Value {
+    let position = |position: LineColumn| {
+        json!({
+            "line": position.line + 1,
+            "column": position.column + 1,
+        })
+    };
+
+    json!({
+        "start": position(region.start),
+        "end": position(region.end),
+    })
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unused_import() -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            title: "UNUSED IMPORT".to_string(),
+            file: PathBuf::from("main.roc"),
+            region: Some(LineColumnRegion::new(
+                LineColumn { line: 2, column: 0 },
+                LineColumn {
+                    line: 2,
+                    column: 10,
+                },
+            )),
+            related: vec![],
+            message: "Nothing from Foo is used in this module.".to_string(),
+        }
+    }
+
+    #[test]
+    fn json_is_one_based() {
+        let json: Value = serde_json::from_str(&unused_import().to_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "severity": "warning",
+                "title": "UNUSED IMPORT",
+                "file": "main.roc",
+                "region": {
+                    "start": { "line": 3, "column": 1 },
+                    "end": { "line": 3, "column": 11 },
+                },
+                "related": [],
+                "message": "Nothing from Foo is used in this module.",
+            })
+        );
+    }
+
+    #[test]
+    fn sarif_log_wraps_results() {
+        let result = unused_import().to_sarif_result();
+        let log: Value = serde_json::from_str(&sarif_log([result.as_str()])).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+
+        let results = &log["runs"][0]["results"];
+        assert_eq!(results[0]["ruleId"], "UNUSED IMPORT");
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["region"]["endColumn"],
+            11
+        );
+    }
+}
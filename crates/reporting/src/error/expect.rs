@@ -140,6 +140,18 @@ impl<'a> Renderer<'a> {
         self.line_info.convert_region(display_region)
     }
 
+    fn write_report<W>(&self, writer: &mut W, buf: &str) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        if self.render_target.is_structured() {
+            // Each report is a line of its own
+            writeln!(writer, "{buf}")
+        } else {
+            write!(writer, "{buf}")
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn render_failure<W>(
         &self,
@@ -176,14 +188,16 @@ impl<'a> Renderer<'a> {
 
         let mut buf = String::new();
 
-        report.render(
+        report.render_with_regions(
             self.render_target,
+            Some(line_col_region),
+            &[],
             &mut buf,
             &self.alloc,
             &crate::report::DEFAULT_PALETTE,
         );
 
-        write!(writer, "{buf}")
+        self.write_report(writer, &buf)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -254,13 +268,157 @@ impl<'a> Renderer<'a> {
 
         let mut buf = String::new();
 
-        report.render(
+        report.render_with_regions(
+            self.render_target,
+            Some(line_col_region),
+            &[],
+            &mut buf,
+            &self.alloc,
+            &crate::report::DEFAULT_PALETTE,
+        );
+
+        self.write_report(writer, &buf)
+    }
+
+    /// Reports that the value of a snapshot expect isn't what its snapshot recorded, as a diff
+    /// of the two renderings.
+    pub fn render_snapshot_mismatch<W>(
+        &self,
+        writer: &mut W,
+        expected: &str,
+        actual: &str,
+        expect_region: Region,
+    ) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        use crate::report::{Annotation, Report};
+        use ven_pretty::DocAllocator;
+
+        let line_col_region = self.line_info.convert_region(expect_region);
+        let severity = Severity::RuntimeError;
+
+        let expected = self.arena.alloc_str(expected);
+        let actual = self.arena.alloc_str(actual);
+        let diff = diff_lines(expected, actual)
+            .into_iter()
+            .map(|line| match line {
+                DiffLine::Same(text) => self.alloc.text("  ").append(self.alloc.text(text)),
+                DiffLine::Removed(text) => self
+                    .alloc
+                    .text("- ")
+                    .append(self.alloc.text(text))
+                    .annotate(Annotation::Error),
+                DiffLine::Added(text) => self
+                    .alloc
+                    .text("+ ")
+                    .append(self.alloc.text(text))
+                    .annotate(Annotation::Structure),
+            });
+
+        let doc = self.alloc.stack([
+            self.alloc.text("This snapshot changed:"),
+            self.alloc.region(line_col_region, severity),
+            self.alloc.concat([
+                self.alloc
+                    .reflow("The lines that were in the snapshot start with "),
+                self.alloc.text("-").annotate(Annotation::Error),
+                self.alloc
+                    .reflow(", and the lines that are there now start with "),
+                self.alloc.text("+").annotate(Annotation::Structure),
+                self.alloc.text(":"),
+            ]),
+            self.alloc.vcat(diff).indent(4),
+            self.alloc.concat([
+                self.alloc.reflow("If the new value is right, run "),
+                self.alloc.keyword("roc test --update-snapshots"),
+                self.alloc.reflow(" to record it."),
+            ]),
+            self.alloc.text(""), // Blank line at the end
+        ]);
+
+        let report = Report {
+            title: "SNAPSHOT CHANGED".into(),
+            doc,
+            filename: self.filename.clone(),
+            severity,
+        };
+
+        let mut buf = String::new();
+
+        report.render_with_regions(
             self.render_target,
+            Some(line_col_region),
+            &[],
             &mut buf,
             &self.alloc,
             &crate::report::DEFAULT_PALETTE,
         );
 
-        write!(writer, "{buf}")
+        self.write_report(writer, &buf)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum DiffLine<'b> {
+    Same(&'b str),
+    Removed(&'b str),
+    Added(&'b str),
+}
+
+/// The lines of `old` and `new`, in order, marked by whether only one of them has the line.
+/// Lines they have in common are found by their longest common subsequence.
+fn diff_lines<'b>(old: &'b str, new: &'b str) -> Vec<DiffLine<'b>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is how many lines old[i..] and new[j..] have in common
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_keeps_common_lines() {
+        use DiffLine::*;
+
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            vec![Same("a"), Removed("b"), Same("c"), Added("d")]
+        );
+        assert_eq!(diff_lines("x", "y"), vec![Removed("x"), Added("y")]);
     }
 }
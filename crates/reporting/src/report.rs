@@ -1,3 +1,4 @@
+use crate::diagnostic::Diagnostic;
 use roc_module::ident::Ident;
 use roc_module::ident::{Lowercase, ModuleName, TagName, Uppercase};
 use roc_module::symbol::{Interns, ModuleId, ModuleIds, PQModuleName, PackageQualified, Symbol};
@@ -116,6 +117,17 @@ pub enum RenderTarget {
     ColorTerminal,
     Generic,
     LanguageServer,
+    /// One line of JSON per report, for tools to consume. See [crate::diagnostic].
+    Json,
+    /// A SARIF `result` per report, to be wrapped into a log by [crate::diagnostic::sarif_log].
+    Sarif,
+}
+
+impl RenderTarget {
+    /// Whether reports are rendered as JSON for tools, rather than as text for people.
+    pub fn is_structured(self) -> bool {
+        matches!(self, RenderTarget::Json | RenderTarget::Sarif)
+    }
 }
 
 /// A textual report.
@@ -138,6 +150,57 @@ impl<'b> Report<'b> {
             RenderTarget::Generic => self.render_ci(buf, alloc),
             RenderTarget::ColorTerminal => self.render_color_terminal(buf, alloc, palette),
             RenderTarget::LanguageServer => self.render_language_server(buf, alloc),
+            RenderTarget::Json | RenderTarget::Sarif => {
+                self.render_structured(target, None, &[], buf)
+            }
+        }
+    }
+
+    /// Render as JSON for tools to consume, along with the region the report is about and any
+    /// other regions it mentions. Other targets don't need the regions, so they render as usual.
+    pub fn render_with_regions(
+        self,
+        target: RenderTarget,
+        region: Option<LineColumnRegion>,
+        related: &[LineColumnRegion],
+        buf: &'b mut String,
+        alloc: &'b RocDocAllocator<'b>,
+        palette: &'b Palette,
+    ) {
+        if target.is_structured() {
+            self.render_structured(target, region, related, buf)
+        } else {
+            self.render(target, buf, alloc, palette)
+        }
+    }
+
+    fn render_structured(
+        self,
+        target: RenderTarget,
+        region: Option<LineColumnRegion>,
+        related: &[LineColumnRegion],
+        buf: &mut String,
+    ) {
+        let err_msg = "<buffer is not a utf-8 encoded string>";
+
+        let mut message = String::new();
+        self.doc
+            .1
+            .render_raw(70, &mut CiWrite::new(&mut message))
+            .expect(err_msg);
+
+        let diagnostic = Diagnostic {
+            severity: self.severity,
+            title: self.title,
+            file: self.filename,
+            region,
+            related: related.to_vec(),
+            message: message.trim_end().to_string(),
+        };
+
+        match target {
+            RenderTarget::Sarif => buf.push_str(&diagnostic.to_sarif_result()),
+            _ => buf.push_str(&diagnostic.to_json()),
         }
     }
 